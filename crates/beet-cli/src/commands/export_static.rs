@@ -10,6 +10,9 @@ const DIST_DIR: &str = "dist";
 struct ExportStaticParams {
 	/// Output directory for the rendered entry (default `<entry>/dist`).
 	out: Option<String>,
+	/// Also write a `.gz` and `.br` sibling beside every compressible file, for
+	/// a static host serving precompressed variants.
+	precompress: bool,
 }
 
 /// Statically exports a no-code entry: builds the entry world (loads its entry
@@ -22,6 +25,7 @@ struct ExportStaticParams {
 /// ```sh
 /// beet export-static examples/bsx_site             # writes examples/bsx_site/dist
 /// beet export-static examples/bsx_site --out=public # ..or a chosen output dir (cwd-relative)
+/// beet export-static examples/bsx_site --precompress # ..plus `.gz`/`.br` siblings
 /// ```
 #[action(route = "export-static/*entry", handler_only)]
 #[derive(Component, Reflect)]
@@ -64,11 +68,32 @@ pub async fn ExportStatic(cx: ActionContext<Request>) -> Result<Response> {
 	};
 	let out = BlobStore::new(FsStore::new(out_dir.clone()));
 	let written = StaticExport::export(&cx.world(), root, &out).await?;
-	Response::ok_text(format!(
-		"exported {} routes to {out_dir}\n",
-		written.len()
-	))
-	.xok()
+	let mut summary =
+		format!("exported {} routes to {out_dir}\n", written.len());
+	if params.precompress {
+		summary.push_str(&precompress(&out, &written).await?);
+	}
+	Response::ok_text(summary).xok()
+}
+
+/// Writes the precompressed siblings of the `written` export, returning the
+/// summary line.
+#[cfg(feature = "compression")]
+async fn precompress(out: &BlobStore, written: &[SmolPath]) -> Result<String> {
+	let siblings = StaticExport::precompress(out, written).await?;
+	format!("precompressed {} files\n", siblings.len()).xok()
+}
+
+/// Without the codecs `--precompress` errors with guidance rather than
+/// silently exporting uncompressed.
+#[cfg(not(feature = "compression"))]
+async fn precompress(
+	_out: &BlobStore,
+	_written: &[SmolPath],
+) -> Result<String> {
+	bevybail!(
+		"--precompress requires a binary built with the `compression` feature"
+	)
 }
 
 #[cfg(test)]
@@ -130,4 +155,20 @@ mod test {
 		// the index rendered into the chosen dir
 		out.path().join("index.html").exists().xpect_true();
 	}
+
+	/// `--precompress` writes a brotli sibling beside the exported index.
+	#[cfg(feature = "compression")]
+	#[beet::test]
+	async fn export_precompress() {
+		let out = TempDir::new().unwrap();
+		run(&format!(
+			"{} --out={} --precompress",
+			site_path(),
+			out.path()
+		))
+		.await
+		.as_str()
+		.xpect_contains("precompressed");
+		out.path().join("index.html.br").exists().xpect_true();
+	}
 }
@@ -0,0 +1,77 @@
+use beet::prelude::*;
+
+/// Request params for the [`Migrate`] command, surfaced in `--help`.
+#[derive(Reflect, Default)]
+#[reflect(Default)]
+struct MigrateParams {
+	/// Directory of a local table store (default: `target/<table>`).
+	dir: Option<String>,
+	/// A blob store uri holding the table instead of a local directory, ie
+	/// `s3://my-site--prod--analytics`.
+	store: Option<String>,
+	/// Migrate the remote (DynamoDB) table instead of a local directory.
+	remote: bool,
+	/// The remote table name, used with `--remote`.
+	bucket: Option<String>,
+}
+
+/// Rewrite every row of a table written at an older schema version at its
+/// row type's current one, the eager counterpart of the upgrade each read
+/// applies lazily.
+///
+/// The table is named as registered in [`MigratableTables`], ie `analytics`
+/// or an app's own. Rows are migrated in a local directory (an [`FsStore`])
+/// by default, any blob store with `--store` (ie an S3 bucket), or the
+/// DynamoDB table with `--remote`.
+///
+/// ```sh
+/// beet migrate analytics                            # local target/analytics
+/// beet migrate analytics --dir /data/analytics      # a specific directory
+/// beet migrate analytics --store=s3://my-site--prod--analytics
+/// beet migrate analytics --remote --bucket my-site--prod--analytics
+/// ```
+#[action(route = "migrate/*table", handler_only)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(ParamsPartial = ParamsPartial::new::<MigrateParams>())]
+pub async fn Migrate(cx: ActionContext<Request>) -> Result<Response> {
+	let parts = cx.input.request_parts();
+	let params = parts.params().parse_reflect::<MigrateParams>()?;
+	let cli = parts.to_cli_args();
+	let tables = cx
+		.caller
+		.with_world(|world, _| {
+			world.get_resource_or_init::<MigratableTables>().clone()
+		})
+		.await?;
+	let Some(table) = cli.path.get(1).cloned() else {
+		bevybail!(
+			"usage: beet migrate <table>, one of `{}`",
+			tables.names().join("`, `")
+		);
+	};
+	let store = table_store(&table, params)?;
+	let report = tables.migrate(&table, store).await?;
+	Response::ok_text(format!("{table}: {report}\n")).xok()
+}
+
+/// Resolve the store holding the table `name` from the params.
+fn table_store(name: &str, params: MigrateParams) -> Result<TableStore> {
+	if params.remote {
+		let Some(bucket) = params.bucket else {
+			bevybail!(
+				"`--remote` requires `--bucket <table-name>`, ie `my-app--prod--{name}`"
+			);
+		};
+		return TableStore::remote(&bucket);
+	}
+	let dir = match params.dir {
+		Some(dir) => AbsPathBuf::new(dir)?,
+		None => WorkspaceConfig::default().store_dir(name).into_abs(),
+	};
+	let store = match params.store {
+		Some(uri) => BlobStore::from_uri(&StoreUri::parse(&uri)?, dir)?,
+		None => BlobStore::new(FsStore::new(dir)),
+	};
+	TableStore::new(store).xok()
+}
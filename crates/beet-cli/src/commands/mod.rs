@@ -5,6 +5,8 @@ mod check;
 mod entry;
 mod export_pdf;
 mod export_static;
+mod migrate;
+mod openapi;
 #[cfg(feature = "pdf")]
 pub mod pdf_ext;
 #[cfg(feature = "qrcode")]
@@ -26,6 +28,8 @@ pub use check::*;
 pub(crate) use entry::*;
 pub use export_pdf::*;
 pub use export_static::*;
+pub use migrate::*;
+pub use openapi::*;
 #[cfg(feature = "qrcode")]
 pub use qrcode::*;
 pub use run_wasm::*;
@@ -46,6 +50,8 @@ impl Plugin for CliCommandsPlugin {
 		app.register_type::<AnalyticsReport>()
 			.register_type::<Check>()
 			.register_type::<ExportStatic>()
+			.register_type::<Migrate>()
+			.register_type::<OpenApi>()
 			// `serve <entry>` loads an entry and boots its servers (the only command
 			// that boots the workspace entry's server, via a direct boot call)
 			.register_type::<Serve>()
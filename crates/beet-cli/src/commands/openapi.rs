@@ -0,0 +1,90 @@
+use crate::prelude::*;
+use beet::prelude::*;
+
+/// Request params for the [`OpenApi`] command, surfaced in `--help`.
+#[derive(Reflect, Default)]
+#[reflect(Default)]
+struct OpenApiParams {
+	/// Write the document to this path instead of stdout. A `.yaml` or `.yml`
+	/// path writes YAML.
+	out: Option<String>,
+	/// The document format, `json` (default) or `yaml`.
+	format: Option<String>,
+}
+
+/// Generates an OpenAPI 3.1 document for an entry's routes: builds the entry
+/// world like [`Check`], then walks its [`RouteTree`] into an [`OpenApiDoc`],
+/// for a frontend to generate its clients from.
+///
+/// ```sh
+/// beet openapi examples/bsx_site                     # json to stdout
+/// beet openapi examples/bsx_site --format=yaml       # ..or yaml
+/// beet openapi examples/bsx_site --out=openapi.yaml  # written to a file
+/// ```
+#[action(route = "openapi/*entry", handler_only)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+#[require(ParamsPartial = ParamsPartial::new::<(OpenApiParams, EntryParams)>())]
+pub async fn OpenApi(cx: ActionContext<Request>) -> Result<Response> {
+	let parts = cx.input.request_parts();
+	let params = parts.params().parse_reflect::<OpenApiParams>()?;
+	let root = build_entry(
+		&cx.caller,
+		EntryParams::store(parts)?.as_ref(),
+		&entry_arg(parts)?,
+		Some(ONE_SHOT_SETTLE_DEADLINE),
+	)
+	.await?;
+	let doc = cx
+		.world()
+		.with(move |world: &mut World| OpenApiDoc::build(world, root))
+		.await?;
+
+	let yaml = match params.format.as_deref() {
+		Some("yaml" | "yml") => true,
+		Some("json") => false,
+		Some(other) => {
+			bevybail!("unknown --format `{other}`, expected `json` or `yaml`")
+		}
+		None => params
+			.out
+			.as_deref()
+			.is_some_and(|out| out.ends_with(".yaml") || out.ends_with(".yml")),
+	};
+	let (text, media_type) = if yaml {
+		(doc.to_yaml(), MediaType::Yaml)
+	} else {
+		(doc.to_json()?, MediaType::Json)
+	};
+	match params.out {
+		Some(out) => {
+			fs_ext::write(&out, &text)?;
+			Response::ok_text(format!("wrote openapi document to {out}\n"))
+				.xok()
+		}
+		None => Response::ok_body(text, media_type).xok(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[beet::test]
+	async fn documents_site_routes() {
+		let site = AbsPathBuf::new_workspace_rel("examples/bsx_site").unwrap();
+		let mut world = crate::commands::render_world();
+		let host = world.spawn((Router, children![OpenApi])).id();
+		let text = world
+			.entity_mut(host)
+			.call::<Request, Response>(Request::from_cli_args(CliArgs::parse(
+				&format!("openapi {site} --format=yaml"),
+			)))
+			.await
+			.unwrap()
+			.unwrap_str()
+			.await;
+		text.xpect_contains("\nopenapi: \"3.1.0\"\n")
+			.xpect_contains("\npaths:\n");
+	}
+}
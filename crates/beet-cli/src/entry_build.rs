@@ -31,6 +31,7 @@ pub fn cli_registration() -> CrateRegistration {
 		features: [
 			"aws_sdk",
 			"cloudflare",
+			"compression",
 			"extra",
 			"geoip",
 			"infra",
@@ -23,10 +23,36 @@ impl Plugin for ActionPlugin {
 			.register_type::<InfallibleSequence<(), ()>>()
 			.register_type::<Fallback<(), ()>>()
 			.register_type::<Parallel<(), ()>>()
+			.register_type::<ParallelWithPolicy<(), ()>>()
+			.register_type::<Race<(), ()>>()
 			.register_type::<HighestScore<(), ()>>()
 			.register_type::<Score>()
 			.register_type::<Repeat<()>>()
 			.register_type::<RepeatTimes<()>>()
+			.register_type::<Retry<(), ()>>()
+			.register_type::<Timeout<(), ()>>()
+			.register_type::<Cooldown<(), ()>>()
+			.register_type::<Throttle<(), ()>>()
+			// hierarchical state machines
+			.register_type::<StateMachine>()
+			.register_type::<MachineState>()
+			.register_type::<InitialState>()
+			.register_type::<FinalState>()
+			.register_type::<StateEnter>()
+			.register_type::<StateExit>()
+			.register_type::<ActiveState>()
+			.register_type::<Transition>()
+			.register_type::<TransitionTrigger>()
+			.register_type::<SendStateEvent>()
+			.add_observer(queue_state_events)
+			.add_systems(Update, release_abandoned_machines)
+			// goal-oriented action planning
+			.register_type::<GoapState>()
+			.register_type::<GoapAction>()
+			.register_type::<GoapPlanner>()
+			.register_type::<GoapPlan>()
+			.register_type::<GoapStep>()
+			.add_systems(Update, release_abandoned_plans)
 			// agent resolution types
 			.register_type::<ActionOf>()
 			.register_type::<Actions>()
@@ -55,6 +81,17 @@ impl Plugin for ActionPlugin {
 			// long-running action lifecycle
 			.add_systems(Update, tick_run_timers)
 			.add_plugins(running_plugin::<(), Outcome>);
+		// blackboards are documents, which need serde
+		#[cfg(feature = "serde")]
+		app.register_type::<Blackboard>()
+			.register_type::<BlackboardCondition>()
+			.register_type::<SetBlackboard>()
+			.register_type::<CheckBlackboard>()
+			.register_type::<WaitForBlackboard>()
+			.register_type::<BlackboardGuard>()
+			.add_systems(Update, (emit_blackboard_changes, wait_for_blackboard))
+			.add_observer(retrigger_blackboard_guards)
+			.add_observer(queue_blackboard_changes);
 		#[cfg(feature = "scripting")]
 		app.register_type::<Script<(), String>>();
 		// the external-process leaf needs the native `ChildProcess` to spawn, so it
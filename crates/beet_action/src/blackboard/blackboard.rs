@@ -0,0 +1,198 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Scoped key-value memory shared by the actions of a behavior tree subtree.
+///
+/// A blackboard is a [`Document`] on the subtree root: the blackboard actions
+/// address a key of the nearest ancestor blackboard, skipping any nearer
+/// document that is not one, and a `@doc:key` binding in the subtree reads and
+/// writes the same memory unless another document sits between them. A nested
+/// blackboard shadows an outer one for its own subtree.
+///
+/// Each change to the document is announced with a [`BlackboardChanged`],
+/// which [`BlackboardGuard`] observes to re-trigger the subtrees it guards.
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_action::prelude::*;
+/// # let mut world = AsyncPlugin::world();
+/// world.spawn((Blackboard::default(), Sequence::new(), children![
+/// 	SetBlackboard::new("target", "door"),
+/// 	CheckBlackboard::new("target", BlackboardCondition::IsSet),
+/// ]));
+/// ```
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(Document)]
+pub struct Blackboard {
+	/// The document as last announced, diffed against on the next change.
+	#[reflect(ignore)]
+	snapshot: Document,
+}
+
+/// Triggered on a [`Blackboard`] entity whenever its [`Document`] changes.
+#[derive(Debug, Clone, EntityEvent)]
+pub struct BlackboardChanged {
+	/// The blackboard entity.
+	pub entity: Entity,
+	/// The document before the change.
+	pub previous: Document,
+	/// The document after the change.
+	pub current: Document,
+}
+
+impl BlackboardChanged {
+	/// Whether the value at `key`, a dotted path like `target.x`, differs
+	/// between the previous and current document.
+	pub fn changed(&self, key: &str) -> bool {
+		let path = key_path(key);
+		self.previous.get_field_ref(&path).ok()
+			!= self.current.get_field_ref(&path).ok()
+	}
+}
+
+/// A test of the value at a blackboard key.
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub enum BlackboardCondition {
+	/// The key holds a value other than null.
+	#[default]
+	IsSet,
+	/// The key is missing or null.
+	IsUnset,
+	/// The key holds exactly this value.
+	Equals(Value),
+	/// The key holds anything but this value, including nothing.
+	NotEquals(Value),
+}
+
+impl BlackboardCondition {
+	/// Test the value at a key, `None` if the key is missing.
+	pub fn check(&self, value: Option<&Value>) -> bool {
+		let value = value.filter(|value| !value.is_null());
+		match self {
+			Self::IsSet => value.is_some(),
+			Self::IsUnset => value.is_none(),
+			Self::Equals(expected) => value == Some(expected),
+			Self::NotEquals(expected) => value != Some(expected),
+		}
+	}
+}
+
+/// Parse a dotted blackboard key like `target.x` into its [`FieldPath`].
+pub(crate) fn key_path(key: &str) -> FieldPath {
+	FieldPath::new(key.split('.'))
+}
+
+/// Resolves the [`Blackboard`] nearest an entity, itself or an ancestor.
+pub(crate) type BlackboardQuery<'w, 's> =
+	AncestorQuery<'w, 's, (), With<Blackboard>>;
+
+/// The [`FieldRef`] of `key` in the blackboard nearest `subject`, rather
+/// than in any nearer document.
+///
+/// ## Errors
+/// Errors if neither `subject` nor an ancestor has a [`Blackboard`].
+pub(crate) fn key_field(
+	blackboards: &BlackboardQuery,
+	subject: Entity,
+	key: &str,
+) -> Result<FieldRef> {
+	let Ok(blackboard) = blackboards.get_entity(subject) else {
+		bevybail!("no Blackboard on {subject} or its ancestors");
+	};
+	FieldRef {
+		document: DocumentPath::Entity(blackboard),
+		..FieldRef::new(key_path(key))
+	}
+	.xok()
+}
+
+/// The value at `key` in the blackboard nearest `subject`, `None` if there is
+/// no blackboard or the key is missing.
+pub(crate) fn read_key(
+	docs: &mut DocumentQuery,
+	blackboards: &BlackboardQuery,
+	subject: Entity,
+	key: &str,
+) -> Option<Value> {
+	let field = key_field(blackboards, subject, key).ok()?;
+	docs.get(subject, &field.document)
+		.ok()?
+		.get_field_ref(&field.field_path)
+		.ok()
+		.cloned()
+}
+
+/// Triggers a [`BlackboardChanged`] for each [`Blackboard`] whose document
+/// differs from its last snapshot.
+pub(crate) fn emit_blackboard_changes(
+	mut commands: Commands,
+	mut blackboards: Populated<
+		(Entity, &mut Blackboard, &Document),
+		Changed<Document>,
+	>,
+) {
+	for (entity, mut blackboard, document) in blackboards.iter_mut() {
+		// a write may leave the document as it was
+		if blackboard.snapshot == *document {
+			continue;
+		}
+		let previous =
+			core::mem::replace(&mut blackboard.snapshot, document.clone());
+		commands.trigger(BlackboardChanged {
+			entity,
+			previous,
+			current: document.clone(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::sync::Arc;
+	use core::sync::atomic::AtomicU32;
+	use core::sync::atomic::Ordering;
+
+	#[beet_core::test]
+	fn condition() {
+		let value = Value::from("door");
+		BlackboardCondition::IsSet.check(Some(&value)).xpect_true();
+		BlackboardCondition::IsSet
+			.check(Some(&Value::Null))
+			.xpect_false();
+		BlackboardCondition::IsUnset.check(None).xpect_true();
+		BlackboardCondition::Equals(value.clone())
+			.check(Some(&value))
+			.xpect_true();
+		BlackboardCondition::NotEquals(value)
+			.check(None)
+			.xpect_true();
+	}
+
+	#[beet_core::test]
+	fn announces_changes() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let count = Arc::new(AtomicU32::new(0));
+		let count_inner = count.clone();
+		let blackboard = world
+			.spawn(Blackboard::default())
+			.observe(move |ev: On<BlackboardChanged>| {
+				if ev.changed("target") {
+					count_inner.fetch_add(1, Ordering::SeqCst);
+				}
+			})
+			.id();
+		world
+			.entity_mut(blackboard)
+			.insert(Document::new(val!({ "target": "door" })));
+		world.update_local();
+		// rewriting the same document is not a change
+		world
+			.entity_mut(blackboard)
+			.insert(Document::new(val!({ "target": "door" })));
+		world.update_local();
+		count.load(Ordering::SeqCst).xpect_eq(1);
+	}
+}
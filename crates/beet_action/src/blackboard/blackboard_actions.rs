@@ -0,0 +1,226 @@
+use super::blackboard::BlackboardQuery;
+use super::blackboard::key_field;
+use super::blackboard::read_key;
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// `<SetBlackboard key=.. value=..>` writes a value to the nearest
+/// [`Blackboard`], then passes.
+///
+/// The key is a dotted path like `target.x`, resolved like a `@doc:` binding,
+/// and any missing parent is created as the path requires.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(SetBlackboardAction)]
+pub struct SetBlackboard {
+	/// The dotted path of the key to write.
+	pub key: SmolStr,
+	/// The value to write.
+	pub value: Value,
+}
+
+impl SetBlackboard {
+	/// Write `value` to `key` when called.
+	pub fn new(key: impl Into<SmolStr>, value: impl Into<Value>) -> Self {
+		Self {
+			key: key.into(),
+			value: value.into(),
+		}
+	}
+}
+
+/// The action behind [`SetBlackboard`].
+///
+/// ## Errors
+/// Errors if the caller has no [`SetBlackboard`] or [`Blackboard`], or the key
+/// passes through a value that is not a map.
+#[action(handler_only)]
+#[derive(Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub fn SetBlackboardAction(
+	cx: In<ActionContext>,
+	sets: Query<&SetBlackboard>,
+	blackboards: BlackboardQuery,
+	mut docs: DocumentQuery,
+) -> Result<Outcome> {
+	let set = sets.get(cx.id())?;
+	let field = key_field(&blackboards, cx.id(), &set.key)?;
+	let value = set.value.clone();
+	docs.with_field(cx.id(), &field, move |slot| *slot = value)?;
+	Outcome::PASS.xok()
+}
+
+/// `<CheckBlackboard key=.. condition=..>` is a condition leaf, passing if the
+/// value at a key of the nearest [`Blackboard`] meets its
+/// [`BlackboardCondition`] and failing otherwise.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(CheckBlackboardAction)]
+pub struct CheckBlackboard {
+	/// The dotted path of the key to test.
+	pub key: SmolStr,
+	/// The test to apply, by default that the key is set.
+	pub condition: BlackboardCondition,
+}
+
+impl CheckBlackboard {
+	/// Test the value at `key` against `condition` when called.
+	pub fn new(
+		key: impl Into<SmolStr>,
+		condition: BlackboardCondition,
+	) -> Self {
+		Self {
+			key: key.into(),
+			condition,
+		}
+	}
+}
+
+/// The action behind [`CheckBlackboard`].
+///
+/// ## Errors
+/// Errors if the caller has no [`CheckBlackboard`].
+#[action(handler_only)]
+#[derive(Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub fn CheckBlackboardAction(
+	cx: In<ActionContext>,
+	checks: Query<&CheckBlackboard>,
+	blackboards: BlackboardQuery,
+	mut docs: DocumentQuery,
+) -> Result<Outcome> {
+	let check = checks.get(cx.id())?;
+	let value = read_key(&mut docs, &blackboards, cx.id(), &check.key);
+	if check.condition.check(value.as_ref()) {
+		Outcome::PASS
+	} else {
+		Outcome::FAIL
+	}
+	.xok()
+}
+
+/// `<WaitForBlackboard key=.. condition=..>` runs until the value at a key of
+/// the nearest [`Blackboard`] meets its [`BlackboardCondition`], then passes.
+///
+/// A long-running action: [`ContinueRun`] keeps the call pending while the
+/// [`wait_for_blackboard`] system tests the key, so like any other [`Running`]
+/// action the wait can be interrupted.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(ContinueRun)]
+pub struct WaitForBlackboard {
+	/// The dotted path of the key to watch.
+	pub key: SmolStr,
+	/// The test to wait for, by default that the key is set.
+	pub condition: BlackboardCondition,
+}
+
+impl WaitForBlackboard {
+	/// Run until the value at `key` meets `condition`.
+	pub fn new(
+		key: impl Into<SmolStr>,
+		condition: BlackboardCondition,
+	) -> Self {
+		Self {
+			key: key.into(),
+			condition,
+		}
+	}
+}
+
+/// Ends each [`Running`] [`WaitForBlackboard`] whose condition is met.
+pub(crate) fn wait_for_blackboard(
+	mut commands: Commands,
+	waits: Populated<(Entity, &WaitForBlackboard), With<Running>>,
+	blackboards: BlackboardQuery,
+	mut docs: DocumentQuery,
+) {
+	for (entity, wait) in waits.iter() {
+		let value = read_key(&mut docs, &blackboards, entity, &wait.key);
+		if wait.condition.check(value.as_ref()) {
+			commands.entity(entity).queue(EndRun(Outcome::PASS));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[beet_core::test]
+	async fn set_then_check() {
+		let mut world = AsyncPlugin::world();
+		let blackboard = world
+			.spawn((Blackboard::default(), Sequence::new(), children![
+				SetBlackboard::new("target.name", "door"),
+				CheckBlackboard::new(
+					"target.name",
+					BlackboardCondition::Equals("door".into())
+				),
+			]))
+			.id();
+		world
+			.entity_mut(blackboard)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world
+			.get::<Document>(blackboard)
+			.unwrap()
+			.0
+			.xpect_eq(val!({ "target": { "name": "door" } }));
+	}
+
+	#[beet_core::test]
+	async fn writes_past_other_documents() {
+		let mut world = AsyncPlugin::world();
+		let blackboard = world
+			.spawn((Blackboard::default(), Sequence::new(), children![(
+				Document::default(),
+				Sequence::new(),
+				children![SetBlackboard::new("target", "door")]
+			)]))
+			.id();
+		world
+			.entity_mut(blackboard)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world
+			.get::<Document>(blackboard)
+			.unwrap()
+			.0
+			.xpect_eq(val!({ "target": "door" }));
+	}
+
+	#[beet_core::test]
+	async fn check_fails_when_unset() {
+		AsyncPlugin::world()
+			.spawn((Blackboard::default(), Sequence::new(), children![
+				CheckBlackboard::new("target", BlackboardCondition::IsSet)
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+	}
+
+	#[beet_core::test]
+	async fn waits_for_key() {
+		(MinimalPlugins, AsyncPlugin, ActionPlugin)
+			.into_world()
+			.spawn((Blackboard::default(), Parallel::new(), children![
+				WaitForBlackboard::new("ready", BlackboardCondition::IsSet),
+				(Sequence::new(), children![
+					EndInDuration::pass(Duration::from_millis(10)),
+					SetBlackboard::new("ready", true),
+				]),
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+	}
+}
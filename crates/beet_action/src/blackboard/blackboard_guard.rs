@@ -0,0 +1,250 @@
+use super::blackboard::BlackboardQuery;
+use super::blackboard::read_key;
+use crate::prelude::*;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use beet_core::prelude::*;
+
+/// `<BlackboardGuard key=.. condition=..>` calls its single child only while
+/// the value at a key of the nearest [`Blackboard`] meets its
+/// [`BlackboardCondition`], the reactive abort of a behavior tree.
+///
+/// The condition is checked before each call, failing without calling the
+/// child if it does not hold. While the child runs, any
+/// [`BlackboardChanged`] touching the key interrupts its [`Running`]
+/// descendants and re-triggers the guard: the condition is checked again,
+/// and the child restarts from scratch if it still holds, or the guard fails
+/// if it no longer does.
+///
+/// With no child, the guard acts as a [`CheckBlackboard`].
+///
+/// ```ignore
+/// <BlackboardGuard key="alarm" condition="IsUnset">
+/// 	<Patrol/>
+/// </BlackboardGuard>
+/// ```
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(BlackboardGuardAction)]
+pub struct BlackboardGuard {
+	/// The dotted path of the key to watch.
+	pub key: SmolStr,
+	/// The test guarding the child, by default that the key is set.
+	pub condition: BlackboardCondition,
+	/// Alive while the child runs, so a guard whose call is dropped stops
+	/// re-triggering.
+	#[reflect(ignore)]
+	running: Weak<()>,
+	/// Whether the running child was interrupted by a change to the key.
+	#[reflect(ignore)]
+	retriggered: bool,
+}
+
+impl BlackboardGuard {
+	/// Call the child only while the value at `key` meets `condition`.
+	pub fn new(
+		key: impl Into<SmolStr>,
+		condition: BlackboardCondition,
+	) -> Self {
+		Self {
+			key: key.into(),
+			condition,
+			..default()
+		}
+	}
+}
+
+/// Calls the single child while the [`BlackboardGuard`] condition holds,
+/// restarting it each time the guarded key changes.
+///
+/// ## Errors
+///
+/// Errors if the caller has no [`BlackboardGuard`], the child errors other
+/// than by a re-trigger, or depending on [`ChildError`] flags when the child
+/// has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn BlackboardGuardAction(cx: ActionContext) -> Result<Outcome> {
+	let child = concurrent_children::<(), ()>(&cx, "blackboard guard")
+		.await?
+		.into_iter()
+		.next();
+	loop {
+		// dropped with the call, so an abandoned guard reads as idle
+		let running = Arc::new(());
+		let token = Arc::downgrade(&running);
+		let entered = cx
+			.caller
+			.with_state::<(
+				Query<&mut BlackboardGuard>,
+				BlackboardQuery,
+				DocumentQuery,
+			), _>(
+				move |entity,
+				      (mut guards, blackboards, mut docs)|
+				      -> Result<bool> {
+					let mut guard = guards.get_mut(entity)?;
+					let value =
+						read_key(&mut docs, &blackboards, entity, &guard.key);
+					let entered = guard.condition.check(value.as_ref());
+					guard.running = if entered && child.is_some() {
+						token
+					} else {
+						Weak::new()
+					};
+					guard.retriggered = false;
+					entered.xok()
+				},
+			)
+			.await??;
+		if !entered {
+			return Ok(Outcome::FAIL);
+		}
+		let Some(child) = child else {
+			return Ok(Outcome::PASS);
+		};
+
+		let result = cx.world().entity(child).call::<(), Outcome>(()).await;
+		let retriggered = cx
+			.caller
+			.get_mut(|mut guard: Mut<BlackboardGuard>| {
+				guard.running = Weak::new();
+				core::mem::take(&mut guard.retriggered)
+			})
+			.await?;
+		drop(running);
+		// a re-triggered child was interrupted, its result is meaningless
+		if !retriggered {
+			return result;
+		}
+	}
+}
+
+/// Interrupts the child of each running [`BlackboardGuard`] whose key changed
+/// in the [`Blackboard`] it resolves to, flagging the guard to re-trigger.
+pub(crate) fn retrigger_blackboard_guards(
+	ev: On<BlackboardChanged>,
+	mut commands: Commands,
+	mut guards: Query<(Entity, &mut BlackboardGuard)>,
+	blackboards: BlackboardQuery,
+) {
+	for (entity, mut guard) in guards.iter_mut() {
+		if guard.running.strong_count() == 0
+			|| guard.retriggered
+			|| blackboards.get_entity(entity).ok() != Some(ev.entity)
+			|| !ev.changed(&guard.key)
+		{
+			continue;
+		}
+		guard.retriggered = true;
+		commands
+			.entity(entity)
+			.queue(InterruptRun::<Outcome>::new());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::sync::atomic::AtomicU32;
+	use core::sync::atomic::Ordering;
+
+	fn world() -> World {
+		(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world()
+	}
+
+	#[beet_core::test]
+	async fn fails_without_condition() {
+		world()
+			.spawn((
+				Blackboard::default(),
+				BlackboardGuard::new("ready", BlackboardCondition::IsSet),
+				children![EndWith(Outcome::PASS)],
+			))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+	}
+
+	#[beet_core::test]
+	async fn aborts_when_key_changes() {
+		world()
+			.spawn((Blackboard::default(), Parallel::new(), children![
+				(
+					BlackboardGuard::new("alarm", BlackboardCondition::IsUnset),
+					children![ContinueRun::<(), Outcome>::default()]
+				),
+				(Sequence::new(), children![
+					EndInDuration::pass(Duration::from_millis(10)),
+					SetBlackboard::new("alarm", true),
+				]),
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+	}
+
+	#[beet_core::test]
+	async fn idle_after_losing_a_race() {
+		let mut world = world();
+		world
+			.spawn((Blackboard::default(), Race::new(), children![
+				(
+					BlackboardGuard::new("alarm", BlackboardCondition::IsUnset),
+					children![ContinueRun::<(), Outcome>::default()]
+				),
+				EndInDuration::pass(Duration::from_millis(10)),
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world
+			.query::<&BlackboardGuard>()
+			.single(&world)
+			.unwrap()
+			.running
+			.strong_count()
+			.xpect_eq(0);
+	}
+
+	#[beet_core::test]
+	async fn restarts_while_condition_holds() {
+		let count = Arc::new(AtomicU32::new(0));
+		let count_inner = count.clone();
+		let child = (
+			ContinueRun::<(), Outcome>::default(),
+			OnSpawn::observe(move |_: On<StartRunning<()>>| {
+				count_inner.fetch_add(1, Ordering::SeqCst);
+			}),
+		);
+		world()
+			.spawn((Blackboard::default(), Sequence::new(), children![
+				SetBlackboard::new("mode", "patrol"),
+				(Race::new(), children![
+					(
+						BlackboardGuard::new(
+							"mode",
+							BlackboardCondition::IsSet
+						),
+						children![child]
+					),
+					(Sequence::new(), children![
+						EndInDuration::pass(Duration::from_millis(10)),
+						SetBlackboard::new("mode", "chase"),
+						EndInDuration::pass(Duration::from_millis(10)),
+					]),
+				]),
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		// restarted at least once, by the switch to `chase`
+		count.load(Ordering::SeqCst).xpect_greater_or_equal_to(2);
+	}
+}
@@ -0,0 +1,8 @@
+//! Scoped key-value memory for behavior trees, with leaf actions to read and
+//! write it and guards that react to its changes.
+mod blackboard;
+mod blackboard_actions;
+mod blackboard_guard;
+pub use blackboard::*;
+pub use blackboard_actions::*;
+pub use blackboard_guard::*;
@@ -0,0 +1,351 @@
+use crate::prelude::*;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use beet_core::prelude::*;
+
+/// Cooldown control-flow component.
+///
+/// Calls its single child with the input, then refuses re-entry with a
+/// default [`Outcome::Fail`] until [`Self::duration`] has elapsed since the
+/// child resolved. A call arriving while the child is still running is
+/// refused too. With no child, it passes with the input whenever it is out of
+/// its cooldown, gating the call alone.
+///
+/// For a window measured from entry instead, see [`Throttle`].
+#[derive(Debug, Component, Reflect)]
+#[require(CooldownAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct Cooldown<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	/// How long after the child resolves before it may be called again.
+	/// Defaults to one second.
+	pub duration: Duration,
+	/// The [`time_ext::now`] from which the next call is accepted.
+	#[reflect(ignore)]
+	ready_at: Option<Duration>,
+	/// Alive while a call is in flight, so a call that is dropped before its
+	/// child resolves leaves the cooldown open rather than closed for good.
+	#[reflect(ignore)]
+	in_flight: Weak<()>,
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Clone for Cooldown<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn clone(&self) -> Self {
+		Self {
+			duration: self.duration,
+			ready_at: self.ready_at,
+			in_flight: self.in_flight.clone(),
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Input, Output> Default for Cooldown<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn default() -> Self {
+		Self {
+			duration: Duration::from_secs(1),
+			ready_at: None,
+			in_flight: Weak::new(),
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl Cooldown {
+	/// Create a `Cooldown<(), ()>` refusing re-entry for `duration` after
+	/// the child resolves.
+	pub fn new(duration: Duration) -> Self {
+		Self {
+			duration,
+			..default()
+		}
+	}
+}
+
+/// Throttle control-flow component.
+///
+/// Calls its single child with the input at most once per
+/// [`Self::duration`], measured from each accepted call, refusing any call
+/// inside the window with a default [`Outcome::Fail`]. Unlike [`Cooldown`]
+/// the window may close while the child is still running. With no child, it
+/// passes with the input for each accepted call.
+#[derive(Debug, Component, Reflect)]
+#[require(ThrottleAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct Throttle<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	/// The minimum time between accepted calls. Defaults to one second.
+	pub duration: Duration,
+	/// The [`time_ext::now`] from which the next call is accepted.
+	#[reflect(ignore)]
+	ready_at: Option<Duration>,
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Clone for Throttle<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn clone(&self) -> Self {
+		Self {
+			duration: self.duration,
+			ready_at: self.ready_at,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Input, Output> Default for Throttle<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn default() -> Self {
+		Self {
+			duration: Duration::from_secs(1),
+			ready_at: None,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl Throttle {
+	/// Create a `Throttle<(), ()>` accepting at most one call per
+	/// `duration`.
+	pub fn new(duration: Duration) -> Self {
+		Self {
+			duration,
+			..default()
+		}
+	}
+}
+
+/// A component refusing calls inside a window, shared by [`Cooldown`] and
+/// [`Throttle`].
+trait EntryWindow: Component<Mutability = Mutable> {
+	/// Whether the window opens when the child resolves rather than when it
+	/// is called.
+	const FROM_EXIT: bool;
+	fn duration(&self) -> Duration;
+	fn ready_at(&mut self) -> &mut Option<Duration>;
+	/// The token of the call in flight, for a window opening on exit.
+	fn in_flight(&mut self) -> Option<&mut Weak<()>> { None }
+}
+
+impl<Input, Output> EntryWindow for Cooldown<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	const FROM_EXIT: bool = true;
+	fn duration(&self) -> Duration { self.duration }
+	fn ready_at(&mut self) -> &mut Option<Duration> { &mut self.ready_at }
+	fn in_flight(&mut self) -> Option<&mut Weak<()>> {
+		Some(&mut self.in_flight)
+	}
+}
+
+impl<Input, Output> EntryWindow for Throttle<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	const FROM_EXIT: bool = false;
+	fn duration(&self) -> Duration { self.duration }
+	fn ready_at(&mut self) -> &mut Option<Duration> { &mut self.ready_at }
+}
+
+/// Calls the single child unless it is inside its [`Cooldown`] window.
+///
+/// ## Errors
+///
+/// Errors if no clock is available, the child errors, or depending on
+/// [`ChildError`] flags when the child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn CooldownAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	call_in_window::<Input, Output, Cooldown<Input, Output>>(cx, "cooldown")
+		.await
+}
+
+/// Calls the single child unless it is inside its [`Throttle`] window.
+///
+/// ## Errors
+///
+/// Errors if no clock is available, the child errors, or depending on
+/// [`ChildError`] flags when the child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn ThrottleAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	call_in_window::<Input, Output, Throttle<Input, Output>>(cx, "throttle")
+		.await
+}
+
+/// Enter the caller's `Window`, failing if it is still closed, then call its
+/// single child and close the window behind it.
+async fn call_in_window<Input, Output, Window>(
+	cx: ActionContext<Input>,
+	label: &str,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+	Window: EntryWindow,
+{
+	let child = concurrent_children::<Input, Output>(&cx, label)
+		.await?
+		.into_iter()
+		.next();
+
+	let now = time_ext::try_now()?;
+	// dropped with this call, reopening a cooldown whose call never finished
+	let in_flight = Arc::new(());
+	let token = Arc::downgrade(&in_flight);
+	let entered = cx
+		.caller
+		.get_mut(move |mut window: Mut<Window>| {
+			if window.ready_at().is_some_and(|ready_at| now < ready_at)
+				|| window
+					.in_flight()
+					.is_some_and(|running| running.strong_count() > 0)
+			{
+				return false;
+			}
+			// a cooldown stays closed until the child resolves
+			if let Some(running) = window.in_flight() {
+				*running = token;
+			} else {
+				let duration = window.duration();
+				*window.ready_at() = Some(now.saturating_add(duration));
+			}
+			true
+		})
+		.await?;
+	if !entered {
+		return Ok(Outcome::Fail(Output::default()));
+	}
+
+	let result = match child {
+		Some(child) => {
+			cx.world()
+				.entity(child)
+				.call::<Input, Outcome<Input, Output>>(cx.input.clone())
+				.await
+		}
+		// with no child the window gates the call alone
+		None => Ok(Outcome::Pass(cx.input.clone())),
+	};
+	if Window::FROM_EXIT {
+		let now = time_ext::try_now()?;
+		cx.caller
+			.get_mut(move |mut window: Mut<Window>| {
+				let duration = window.duration();
+				*window.ready_at() = Some(now.saturating_add(duration));
+			})
+			.await?;
+	}
+	drop(in_flight);
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	async fn call(world: &mut World, entity: Entity) -> Outcome {
+		world
+			.entity_mut(entity)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+	}
+
+	#[beet_core::test]
+	async fn cooldown_refuses_reentry() {
+		let mut world = AsyncPlugin::world();
+		let cooldown = world
+			.spawn((Cooldown::new(Duration::from_millis(20)), children![
+				EndWith(Outcome::PASS)
+			]))
+			.id();
+		call(&mut world, cooldown).await.xpect_eq(Outcome::PASS);
+		call(&mut world, cooldown).await.xpect_eq(Outcome::FAIL);
+		time_ext::sleep(Duration::from_millis(30)).await;
+		call(&mut world, cooldown).await.xpect_eq(Outcome::PASS);
+	}
+
+	#[beet_core::test]
+	async fn cooldown_opens_after_child_resolves() {
+		// the child outlasts the window, which only opens once it resolves
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let cooldown = world
+			.spawn((Cooldown::new(Duration::from_millis(20)), children![
+				EndInDuration::pass(Duration::from_millis(30))
+			]))
+			.id();
+		call(&mut world, cooldown).await.xpect_eq(Outcome::PASS);
+		call(&mut world, cooldown).await.xpect_eq(Outcome::FAIL);
+	}
+
+	#[beet_core::test]
+	async fn cooldown_reopens_after_abandoned_call() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let timeout = world
+			.spawn((Timeout::new(Duration::from_millis(10)), children![(
+				Cooldown::new(Duration::from_millis(5)),
+				children![EndInDuration::pass(Duration::from_millis(50))]
+			)]))
+			.id();
+		call(&mut world, timeout).await.xpect_eq(Outcome::FAIL);
+		time_ext::sleep(Duration::from_millis(20)).await;
+		let cooldown = world.entity(timeout).get::<Children>().unwrap()[0];
+		call(&mut world, cooldown).await.xpect_eq(Outcome::PASS);
+	}
+
+	#[beet_core::test]
+	async fn throttle_without_child() {
+		let mut world = AsyncPlugin::world();
+		let throttle =
+			world.spawn(Throttle::new(Duration::from_millis(20))).id();
+		call(&mut world, throttle).await.xpect_eq(Outcome::PASS);
+		call(&mut world, throttle).await.xpect_eq(Outcome::FAIL);
+		time_ext::sleep(Duration::from_millis(30)).await;
+		call(&mut world, throttle).await.xpect_eq(Outcome::PASS);
+	}
+}
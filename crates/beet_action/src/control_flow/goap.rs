@@ -0,0 +1,697 @@
+//! Goal-oriented action planning.
+//!
+//! Instead of authoring the tree, a [`GoapPlanner`] is given a goal and a
+//! library of [`GoapAction`] children, each an ordinary action declaring the
+//! facts it needs and the facts it changes. When called, the planner searches
+//! for the cheapest chain of actions from the agent's current [`GoapState`] to
+//! the goal, and runs it as a [`Sequence`] of [`GoapStep`]s.
+//!
+//! A planner carrying a [`Score`] is a goal like any other utility AI option,
+//! so a [`HighestScore`] picks among several goals.
+//!
+//! ```text
+//! HighestScore
+//! ├── (GoapPlanner "fed", Score(0.8))
+//! │   ├── (GoapAction, ChopWood)    needs has_axe, makes has_wood
+//! │   ├── (GoapAction, GetAxe)      makes has_axe
+//! │   └── (GoapAction, Cook)        needs has_wood, makes fed
+//! └── (GoapPlanner "rested", Score(0.3))
+//! ```
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use beet_core::prelude::*;
+use core::cmp::Ordering;
+
+/// A set of named boolean facts about the world, like `has_axe = true`.
+///
+/// The agent's current state is a [`GoapState`] component on the agent, as
+/// resolved by [`AgentQuery`], and the same type describes goals,
+/// preconditions and effects, where a fact left out is a fact that does not
+/// matter.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct GoapState {
+	/// The facts by key.
+	facts: BTreeMap<SmolStr, bool>,
+}
+
+impl GoapState {
+	/// Set a fact, returning the state.
+	pub fn with(mut self, key: impl Into<SmolStr>, value: bool) -> Self {
+		self.set(key, value);
+		self
+	}
+
+	/// Set a fact.
+	pub fn set(&mut self, key: impl Into<SmolStr>, value: bool) {
+		self.facts.insert(key.into(), value);
+	}
+
+	/// The value of a fact, `None` if it is not set.
+	pub fn get(&self, key: &str) -> Option<bool> {
+		self.facts.get(key).copied()
+	}
+
+	/// Iterate over the facts, sorted by key.
+	pub fn iter(&self) -> impl Iterator<Item = (&SmolStr, bool)> {
+		self.facts.iter().map(|(key, value)| (key, *value))
+	}
+
+	/// Whether every fact of `conditions` holds in this state. A fact that is
+	/// not set here counts as `false`.
+	pub fn satisfies(&self, conditions: &GoapState) -> bool {
+		conditions.unmet(self) == 0
+	}
+
+	/// Set every fact of `effects`.
+	pub fn apply(&mut self, effects: &GoapState) {
+		for (key, value) in effects.iter() {
+			self.set(key.clone(), value);
+		}
+	}
+
+	/// The number of facts of this state that do not hold in `state`.
+	fn unmet(&self, state: &GoapState) -> usize {
+		self.iter()
+			.filter(|(key, value)| state.get(key).unwrap_or(false) != *value)
+			.count()
+	}
+}
+
+/// Declares an action as usable by its parent [`GoapPlanner`].
+///
+/// The entity also carries the action itself, an `Action<(), Outcome>`,
+/// which is called as a step of a plan once [`Self::preconditions`] hold.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct GoapAction {
+	/// The facts that must hold for the action to be planned.
+	pub preconditions: GoapState,
+	/// The facts the action makes hold once it passes.
+	pub effects: GoapState,
+	/// The cost the planner minimizes, by default `1.0`.
+	pub cost: f32,
+}
+
+impl Default for GoapAction {
+	fn default() -> Self {
+		Self {
+			preconditions: default(),
+			effects: default(),
+			cost: 1.0,
+		}
+	}
+}
+
+impl GoapAction {
+	/// An action making `effects` hold, with no preconditions and a cost of
+	/// `1.0`.
+	pub fn new(effects: GoapState) -> Self {
+		Self {
+			effects,
+			..default()
+		}
+	}
+
+	/// Set the facts that must hold for the action to be planned.
+	pub fn with_preconditions(mut self, preconditions: GoapState) -> Self {
+		self.preconditions = preconditions;
+		self
+	}
+
+	/// Set the cost the planner minimizes.
+	pub fn with_cost(mut self, cost: f32) -> Self {
+		self.cost = cost;
+		self
+	}
+}
+
+/// Goal-oriented action planner.
+///
+/// When called, plans from the agent's [`GoapState`] to [`Self::goal`] with
+/// the [`GoapAction`] children as the available actions, then runs the plan
+/// as a [`GoapPlan`] child: a [`Sequence`] with a [`GoapStep`] per action.
+/// Each step applies the effects of its action to the agent's state as it
+/// passes.
+///
+/// Passes once the plan passes, or immediately if the goal already holds.
+/// When a step fails the plan is discarded and a new one made from the state
+/// at that point, leaving out the failed action for the rest of the call, up
+/// to [`Self::max_replans`] times. Fails if no plan reaches the goal within
+/// [`Self::max_nodes`], or the replans are spent.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(GoapPlannerAction)]
+pub struct GoapPlanner {
+	/// The facts the planner makes hold.
+	pub goal: GoapState,
+	/// How many times to replan after a failing step, by default `3`.
+	pub max_replans: u32,
+	/// How many states a search may reach before giving up, see
+	/// [`goap_plan`], by default `10_000`.
+	pub max_nodes: usize,
+}
+
+impl Default for GoapPlanner {
+	fn default() -> Self {
+		Self {
+			goal: default(),
+			max_replans: 3,
+			max_nodes: 10_000,
+		}
+	}
+}
+
+impl GoapPlanner {
+	/// Plan toward `goal`.
+	pub fn new(goal: GoapState) -> Self { Self { goal, ..default() } }
+}
+
+/// Marks the [`Sequence`] a [`GoapPlanner`] spawns to run its current plan,
+/// despawned once the plan resolves or the planner's call is dropped.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct GoapPlan {
+	/// Alive while the planner call runs the plan.
+	#[reflect(ignore)]
+	call: Weak<()>,
+}
+
+/// A step of a [`GoapPlan`], calling a [`GoapAction`] and applying its
+/// [`GoapAction::effects`] to the agent's [`GoapState`] if it passes.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[require(GoapStepAction)]
+pub struct GoapStep {
+	/// The [`GoapAction`] entity to call.
+	pub action: Entity,
+	/// The facts to set once the action passes.
+	pub effects: GoapState,
+	/// Whether the action failed, so the planner leaves it out of the next
+	/// plan.
+	#[reflect(ignore)]
+	failed: bool,
+}
+
+/// The cheapest sequence of actions from one [`GoapState`] to a goal, as
+/// found by [`goap_plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoapPlanResult<T> {
+	/// The actions in order.
+	pub steps: Vec<T>,
+	/// The summed [`GoapAction::cost`] of the steps.
+	pub cost: f32,
+}
+
+/// A* search for the cheapest sequence of `actions` that makes `goal` hold,
+/// starting from `start`.
+///
+/// The heuristic is the cost of the cheapest action while the goal is unmet,
+/// which never overestimates, so the plan found is always a cheapest one.
+/// Returns `None` if no sequence reaches the goal, or none is found before
+/// `max_nodes` states have been reached, bounding the search when the
+/// actions can reach many states.
+pub fn goap_plan<T: Clone>(
+	start: &GoapState,
+	goal: &GoapState,
+	actions: &[(T, GoapAction)],
+	max_nodes: usize,
+) -> Option<GoapPlanResult<T>> {
+	let min_cost = actions
+		.iter()
+		.map(|(_, action)| action.cost.max(0.))
+		.fold(f32::INFINITY, f32::min);
+	let heuristic = |state: &GoapState| {
+		if state.satisfies(goal) { 0. } else { min_cost }
+	};
+
+	// every state reached, with its cost and the step reaching it
+	let mut nodes: Vec<(GoapState, f32, Option<(usize, usize)>)> =
+		vec![(start.clone(), 0., None)];
+	let mut visited = HashMap::<GoapState, usize>::default();
+	visited.insert(start.clone(), 0);
+	let mut frontier = BinaryHeap::new();
+	frontier.push(Frontier {
+		estimate: heuristic(start),
+		node: 0,
+	});
+
+	while let Some(Frontier { node, .. }) = frontier.pop() {
+		let (state, cost, _) = nodes[node].clone();
+		if state.satisfies(goal) {
+			let mut steps = Vec::new();
+			let mut current = node;
+			while let Some((parent, action)) = nodes[current].2 {
+				steps.push(actions[action].0.clone());
+				current = parent;
+			}
+			steps.reverse();
+			return Some(GoapPlanResult { steps, cost });
+		}
+		for (index, (_, action)) in actions.iter().enumerate() {
+			if !state.satisfies(&action.preconditions) {
+				continue;
+			}
+			let mut next = state.clone();
+			next.apply(&action.effects);
+			let next_cost = cost + action.cost.max(0.);
+			match visited.get(&next) {
+				Some(&seen) if nodes[seen].1 <= next_cost => continue,
+				Some(&seen) => {
+					nodes[seen].1 = next_cost;
+					nodes[seen].2 = Some((node, index));
+					frontier.push(Frontier {
+						estimate: next_cost + heuristic(&next),
+						node: seen,
+					});
+				}
+				None if nodes.len() >= max_nodes => return None,
+				None => {
+					let estimate = next_cost + heuristic(&next);
+					visited.insert(next.clone(), nodes.len());
+					frontier.push(Frontier {
+						estimate,
+						node: nodes.len(),
+					});
+					nodes.push((next, next_cost, Some((node, index))));
+				}
+			}
+		}
+	}
+	None
+}
+
+/// A node waiting in the A* frontier, ordered so the [`BinaryHeap`] pops the
+/// lowest estimate first.
+struct Frontier {
+	estimate: f32,
+	node: usize,
+}
+
+impl PartialEq for Frontier {
+	fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+impl Eq for Frontier {}
+impl PartialOrd for Frontier {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl Ord for Frontier {
+	fn cmp(&self, other: &Self) -> Ordering {
+		other.estimate.total_cmp(&self.estimate)
+	}
+}
+
+/// Despawns each [`GoapPlan`] whose planner call was dropped, ie by a
+/// [`Race`] or [`Timeout`].
+pub(crate) fn release_abandoned_plans(
+	plans: Query<(Entity, &GoapPlan)>,
+	mut commands: Commands,
+) {
+	for (entity, plan) in plans.iter() {
+		if plan.call.strong_count() == 0 {
+			commands.entity(entity).despawn();
+		}
+	}
+}
+
+/// Plans toward the [`GoapPlanner`] goal and runs the plan, replanning on
+/// failure.
+///
+/// If the call is dropped its plan is despawned on the next app update.
+///
+/// ## Errors
+///
+/// Errors if the agent has no [`GoapState`], a [`GoapAction`] child has no
+/// `Action<(), Outcome>`, or a step errors.
+#[action(default)]
+#[derive(Component)]
+pub async fn GoapPlannerAction(cx: ActionContext) -> Result<Outcome> {
+	let planner = cx.caller.get_cloned::<GoapPlanner>().await?;
+	// held for the call, so a dropped call leaves its plan to be released
+	let call = Arc::new(());
+	let mut replans = 0;
+	let mut failed = HashSet::<Entity>::default();
+	loop {
+		let excluded = failed.clone();
+		let (state, actions) = cx
+			.caller
+			.with_state::<(
+				AgentQuery<&GoapState>,
+				Query<&Children>,
+				Query<(&GoapAction, Option<&ActionMeta>)>,
+			), _>(move |planner, (agents, children, actions)| -> Result<_> {
+				let state = agents.get(planner).map_err(|_| {
+					bevyhow!("GoapPlanner agent has no GoapState")
+				})?;
+				let mut library = Vec::new();
+				let children = children
+					.get(planner)
+					.map(|children| children.to_vec())
+					.unwrap_or_default();
+				for child in children {
+					let Ok((action, meta)) = actions.get(child) else {
+						continue;
+					};
+					if excluded.contains(&child) {
+						continue;
+					}
+					meta.ok_or_else(|| {
+						bevyhow!("GoapAction {child:?} has no action")
+					})?
+					.assert_match::<(), Outcome>()?;
+					library.push((child, action.clone()));
+				}
+				(state.clone(), library).xok()
+			})
+			.await??;
+
+		if state.satisfies(&planner.goal) {
+			return Ok(Outcome::PASS);
+		}
+		let Some(plan) =
+			goap_plan(&state, &planner.goal, &actions, planner.max_nodes)
+		else {
+			return Ok(Outcome::FAIL);
+		};
+		let effects = actions
+			.iter()
+			.map(|(entity, action)| (*entity, action.effects.clone()))
+			.collect::<HashMap<_, _>>();
+		let steps = plan
+			.steps
+			.into_iter()
+			.map(|action| GoapStep {
+				action,
+				effects: effects[&action].clone(),
+				failed: false,
+			})
+			.collect::<Vec<_>>();
+
+		let plan = GoapPlan {
+			call: Arc::downgrade(&call),
+		};
+		let sequence = cx.caller.spawn_child((plan, Sequence::new())).await;
+		let world = cx.world();
+		for step in steps {
+			world.entity(sequence).spawn_child(step).await;
+		}
+		let outcome = world.entity(sequence).call::<(), Outcome>(()).await;
+		let failed_steps = world
+			.entity(sequence)
+			.with_state::<(Query<&Children>, Query<&GoapStep>), _>(
+				|sequence, (children, steps)| {
+					children
+						.get(sequence)
+						.map(|children| children.to_vec())
+						.unwrap_or_default()
+						.into_iter()
+						.filter_map(|child| steps.get(child).ok())
+						.filter(|step| step.failed)
+						.map(|step| step.action)
+						.collect::<Vec<_>>()
+				},
+			)
+			.await?;
+		failed.extend(failed_steps);
+		world.entity(sequence).despawn().await?;
+		match outcome? {
+			Outcome::Pass(_) => return Ok(Outcome::PASS),
+			Outcome::Fail(_) if replans < planner.max_replans => {
+				replans += 1;
+			}
+			Outcome::Fail(_) => return Ok(Outcome::FAIL),
+		}
+	}
+}
+
+/// Calls the [`GoapStep::action`], applying the [`GoapStep::effects`] to the
+/// agent's [`GoapState`] if it passes.
+///
+/// ## Errors
+///
+/// Errors if the caller has no [`GoapStep`], the action errors, or the agent
+/// has no [`GoapState`].
+#[action(default)]
+#[derive(Component)]
+pub async fn GoapStepAction(cx: ActionContext) -> Result<Outcome> {
+	let step = cx.caller.get_cloned::<GoapStep>().await?;
+	let outcome = cx
+		.world()
+		.entity(step.action)
+		.call::<(), Outcome>(())
+		.await?;
+	if outcome == Outcome::FAIL {
+		cx.caller
+			.get_mut(|mut step: Mut<GoapStep>| step.failed = true)
+			.await?;
+	} else {
+		cx.caller
+			.with_state::<AgentQuery<&mut GoapState>, _>(
+				move |entity, mut agents| -> Result {
+					agents
+						.get_mut(entity)
+						.map_err(|_| {
+							bevyhow!("GoapStep agent has no GoapState")
+						})?
+						.apply(&step.effects);
+					Ok(())
+				},
+			)
+			.await??;
+	}
+	Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::sync::Arc;
+	use core::sync::atomic::AtomicU32;
+
+	fn state() -> GoapState { GoapState::default() }
+
+	#[beet_core::test]
+	fn finds_cheapest_plan() {
+		let actions = [
+			(
+				"chop",
+				GoapAction::new(state().with("has_wood", true))
+					.with_preconditions(state().with("has_axe", true)),
+			),
+			("get_axe", GoapAction::new(state().with("has_axe", true))),
+			(
+				"buy_wood",
+				GoapAction::new(state().with("has_wood", true)).with_cost(5.),
+			),
+			(
+				"cook",
+				GoapAction::new(state().with("fed", true))
+					.with_preconditions(state().with("has_wood", true)),
+			),
+		];
+		let plan =
+			goap_plan(&state(), &state().with("fed", true), &actions, 100)
+				.unwrap();
+		plan.steps.xpect_eq(vec!["get_axe", "chop", "cook"]);
+		plan.cost.xpect_eq(3.);
+	}
+
+	#[beet_core::test]
+	fn no_plan() {
+		let actions = [(
+			"cook",
+			GoapAction::new(state().with("fed", true))
+				.with_preconditions(state().with("has_wood", true)),
+		)];
+		goap_plan(&state(), &state().with("fed", true), &actions, 100)
+			.xpect_none();
+	}
+
+	#[beet_core::test]
+	fn gives_up_past_max_nodes() {
+		// the costly key is only tried once the cheap, useless facts are
+		// exhausted, thousands of states later
+		let mut actions = (0..12)
+			.map(|index| {
+				let fact = format!("fact_{index}");
+				(fact.clone(), GoapAction::new(state().with(fact, true)))
+			})
+			.collect::<Vec<_>>();
+		actions.push((
+			"get_key".into(),
+			GoapAction::new(state().with("has_key", true)).with_cost(10.),
+		));
+		actions.push((
+			"open".into(),
+			GoapAction::new(state().with("open", true))
+				.with_preconditions(state().with("has_key", true)),
+		));
+		let goal = state().with("open", true);
+		goap_plan(&state(), &goal, &actions, 64).xpect_none();
+		goap_plan(&state(), &goal, &actions, 100_000)
+			.unwrap()
+			.steps
+			.xpect_eq(vec!["get_key".to_string(), "open".to_string()]);
+	}
+
+	/// A library action passing `passes` times, then failing.
+	fn pass_n_times(passes: u32) -> (Arc<AtomicU32>, Action<(), Outcome>) {
+		let calls = Arc::new(AtomicU32::new(0));
+		let calls_inner = calls.clone();
+		let action = Action::new_pure(move |_: ActionContext| {
+			let calls =
+				calls_inner.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+			if calls < passes {
+				Outcome::PASS.xok()
+			} else {
+				Outcome::FAIL.xok()
+			}
+		});
+		(calls, action)
+	}
+
+	#[beet_core::test]
+	async fn runs_plan() {
+		let mut world = AsyncPlugin::world();
+		let planner = world
+			.spawn((
+				GoapState::default(),
+				GoapPlanner::new(state().with("fed", true)),
+				children![
+					(
+						GoapAction::new(state().with("has_wood", true)),
+						EndWith(Outcome::PASS),
+					),
+					(
+						GoapAction::new(state().with("fed", true))
+							.with_preconditions(state().with("has_wood", true)),
+						EndWith(Outcome::PASS),
+					),
+				],
+			))
+			.id();
+
+		world
+			.entity_mut(planner)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world
+			.get::<GoapState>(planner)
+			.unwrap()
+			.get("fed")
+			.xpect_eq(Some(true));
+		world.query::<&GoapPlan>().iter(&world).count().xpect_eq(0);
+	}
+
+	#[beet_core::test]
+	async fn replans_around_failure() {
+		// the cheap route fails, so the costly one is planned next
+		let (calls, flaky) = pass_n_times(0);
+		AsyncPlugin::world()
+			.spawn((
+				GoapState::default(),
+				GoapPlanner::new(state().with("fed", true)),
+				children![
+					(GoapAction::new(state().with("fed", true)), flaky),
+					(
+						GoapAction::new(state().with("fed", true))
+							.with_cost(5.),
+						EndWith(Outcome::PASS),
+					),
+				],
+			))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		calls.load(core::sync::atomic::Ordering::SeqCst).xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn fails_once_replans_spent() {
+		let (calls, flaky) = pass_n_times(0);
+		AsyncPlugin::world()
+			.spawn((
+				GoapState::default(),
+				GoapPlanner {
+					max_replans: 0,
+					..GoapPlanner::new(state().with("fed", true))
+				},
+				children![
+					(GoapAction::new(state().with("fed", true)), flaky),
+					(
+						GoapAction::new(state().with("fed", true))
+							.with_cost(5.),
+						EndWith(Outcome::PASS),
+					),
+				],
+			))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+		calls.load(core::sync::atomic::Ordering::SeqCst).xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn releases_dropped_plans() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		world
+			.spawn((GoapState::default(), Race::new(), children![
+				(GoapPlanner::new(state().with("fed", true)), children![(
+					GoapAction::new(state().with("fed", true)),
+					ContinueRun::<(), Outcome>::default(),
+				)]),
+				EndInDuration::pass(Duration::from_millis(10)),
+			]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world.update();
+		world.query::<&GoapPlan>().iter(&world).count().xpect_eq(0);
+	}
+
+	#[beet_core::test]
+	async fn highest_score_picks_goal() {
+		let mut world = AsyncPlugin::world();
+		let agent = world
+			.spawn((GoapState::default(), HighestScore::new(), children![
+				(
+					Score(0.2),
+					GoapPlanner::new(state().with("rested", true)),
+					children![(
+						GoapAction::new(state().with("rested", true)),
+						EndWith(Outcome::PASS),
+					)],
+				),
+				(
+					Score(0.8),
+					GoapPlanner::new(state().with("fed", true)),
+					children![(
+						GoapAction::new(state().with("fed", true)),
+						EndWith(Outcome::PASS),
+					)],
+				),
+			]))
+			.id();
+
+		world
+			.entity_mut(agent)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		let state = world.get::<GoapState>(agent).unwrap();
+		state.get("fed").xpect_eq(Some(true));
+		state.get("rested").xpect_none();
+	}
+}
@@ -1,21 +1,33 @@
 mod call_on_spawn;
+mod cooldown;
 mod exclude_errors;
 mod fallback;
+mod goap;
 pub use call_on_spawn::*;
+pub use cooldown::*;
 pub use exclude_errors::*;
 pub use fallback::*;
+pub use goap::*;
 mod highest_score;
 pub use highest_score::*;
 mod parallel;
 pub use parallel::*;
+mod race;
+pub use race::*;
 mod score;
 pub use score::*;
 mod repeat;
 pub use repeat::*;
+mod retry;
+pub use retry::*;
 mod running;
 pub use running::*;
 mod sequence;
 pub use sequence::*;
+mod state_machine;
+pub use state_machine::*;
+mod timeout;
+pub use timeout::*;
 
 use beet_core::prelude::*;
 
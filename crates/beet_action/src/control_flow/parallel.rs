@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use beet_core::prelude::*;
+use core::task::Poll;
 
 /// Parallel control-flow component.
 ///
@@ -7,8 +8,9 @@ use beet_core::prelude::*;
 /// Returns the first [`Outcome::Fail`] if any child fails, otherwise
 /// [`Outcome::Pass`] with the original input once all children pass.
 ///
-/// Children are awaited together; cancellation of in-flight siblings on
-/// the first failure is deferred to the async runtime overhaul.
+/// Children are awaited together, so a failure still waits on its siblings.
+/// For settling early and cancelling the siblings still running see
+/// [`ParallelWithPolicy`], or [`Race`] for the first child to resolve.
 #[derive(Debug, Clone, Copy, Component, Reflect)]
 #[require(ParallelAction<Input,Output>)]
 #[reflect(Component, Default)]
@@ -38,7 +40,7 @@ impl Parallel {
 	pub fn new() -> Self { Self::default() }
 }
 
-/// Runs all children concurrently, failing fast if any child fails.
+/// Runs all children concurrently, failing if any child fails.
 ///
 /// Child error handling is controlled by [`ExcludeErrors`].
 ///
@@ -55,6 +57,185 @@ pub async fn ParallelAction<Input, Output>(
 where
 	Input: 'static + Send + Sync + Clone,
 	Output: 'static + Send + Sync,
+{
+	let children =
+		concurrent_children::<Input, Output>(&cx, "parallel").await?;
+	let world = cx.world().clone();
+	let input = cx.input;
+
+	let calls = children.into_iter().map(|child| {
+		let world = world.clone();
+		let input = input.clone();
+		async move {
+			world
+				.entity(child)
+				.call::<Input, Outcome<Input, Output>>(input)
+				.await
+		}
+	});
+
+	for outcome in async_ext::try_join_all(calls).await? {
+		if let Outcome::Fail(output) = outcome {
+			return Ok(Outcome::Fail(output));
+		}
+	}
+
+	Ok(Outcome::Pass(input))
+}
+
+/// Parallel variant that settles as soon as a policy is met, cancelling the
+/// children still running.
+///
+/// Runs all child actions concurrently with a clone of the same input,
+/// returning [`Outcome::Pass`] with the original input once [`Self::pass`]
+/// children have passed, or the [`Outcome::Fail`] of the child that brought
+/// the failures to [`Self::fail`]. It also fails as soon as too few children
+/// remain to reach the pass threshold. Both thresholds are clamped to the
+/// number of children, so the default passes once every child passes and
+/// fails on the first failure.
+///
+/// Once settled the remaining children are interrupted with [`InterruptRun`],
+/// resolving every [`Running`] descendant with
+/// [`ControlFlowError::Interrupted`].
+#[derive(Debug, Component, Reflect)]
+#[require(ParallelWithPolicyAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct ParallelWithPolicy<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	/// How many children must pass for this to pass.
+	/// Defaults to [`u32::MAX`], ie every child.
+	pub pass: u32,
+	/// How many children must fail for this to fail.
+	/// Defaults to `1`, ie the first failure.
+	pub fail: u32,
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Clone for ParallelWithPolicy<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	fn clone(&self) -> Self {
+		Self {
+			pass: self.pass,
+			fail: self.fail,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Input, Output> Default for ParallelWithPolicy<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	fn default() -> Self {
+		Self {
+			pass: u32::MAX,
+			fail: 1,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl ParallelWithPolicy {
+	/// Create a `ParallelWithPolicy<(), ()>` passing on `pass` passes and
+	/// failing on `fail` failures.
+	pub fn new(pass: u32, fail: u32) -> Self {
+		Self::default().with_pass(pass).with_fail(fail)
+	}
+}
+
+impl<Input, Output> ParallelWithPolicy<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	/// Pass once `pass` children have passed.
+	pub fn with_pass(mut self, pass: u32) -> Self {
+		self.pass = pass;
+		self
+	}
+
+	/// Fail once `fail` children have failed.
+	pub fn with_fail(mut self, fail: u32) -> Self {
+		self.fail = fail;
+		self
+	}
+}
+
+/// Runs all children concurrently until the [`ParallelWithPolicy`] pass or
+/// fail threshold is met, then interrupts the rest.
+///
+/// Child error handling is controlled by [`ExcludeErrors`].
+///
+/// ## Errors
+///
+/// Errors if a child errors, or depending on [`ChildError`] flags when a
+/// child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn ParallelWithPolicyAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	let policy = cx
+		.caller
+		.get_cloned::<ParallelWithPolicy<Input, Output>>()
+		.await
+		.unwrap_or_default();
+	let children =
+		concurrent_children::<Input, Output>(&cx, "parallel").await?;
+	if children.is_empty() {
+		return Ok(Outcome::Pass(cx.input));
+	}
+	let total = children.len();
+	let pass = (policy.pass as usize).clamp(1, total);
+	let fail = (policy.fail as usize).clamp(1, total);
+
+	let (mut passed, mut failed) = (0, 0);
+	let input = cx.input.clone();
+	let settled =
+		settle_children::<Input, Output, _>(&cx, children, |outcome| {
+			match outcome {
+				Outcome::Pass(_) => passed += 1,
+				Outcome::Fail(output) => {
+					failed += 1;
+					// settle on this failure if it meets the threshold, or
+					// leaves too few children to reach the pass threshold
+					if failed >= fail || total - failed < pass {
+						return Some(Outcome::Fail(output));
+					}
+				}
+			}
+			(passed >= pass).then(|| Outcome::Pass(input.clone()))
+		})
+		.await?;
+
+	settled
+		.ok_or_else(|| bevyhow!("parallel children settled without a policy"))
+}
+
+/// Resolves the children of the caller that can be called concurrently with
+/// `Action<Input, Outcome<Input, Output>>`, honouring [`ExcludeErrors`].
+/// `label` names the control flow in errors.
+pub(crate) async fn concurrent_children<Input, Output>(
+	cx: &ActionContext<Input>,
+	label: &str,
+) -> Result<Vec<Entity>>
+where
+	Input: 'static + Send + Sync,
+	Output: 'static + Send + Sync,
 {
 	let exclude_errors = cx
 		.caller
@@ -65,17 +246,12 @@ where
 	let children =
 		match cx.caller.get(|children: &Children| children.to_vec()).await {
 			Ok(children) => children,
-			Err(_) => {
-				// entity has no children, pass returning the input
-				return Ok(Outcome::Pass(cx.input));
-			}
+			// entity has no children
+			Err(_) => return Ok(Vec::new()),
 		};
 
-	let world = cx.world().clone();
-	let input = cx.input;
-
-	// resolve valid children up-front, then run them concurrently
-	let mut calls = Vec::new();
+	let world = cx.world();
+	let mut valid = Vec::with_capacity(children.len());
 	for child in children {
 		let action_meta_result = world
 			.entity(child)
@@ -89,7 +265,7 @@ where
 					continue;
 				}
 				bevybail!(
-					"parallel child has no action: {child:?}, error: {child_error}"
+					"{label} child has no action: {child:?}, error: {child_error}"
 				);
 			}
 		};
@@ -101,27 +277,82 @@ where
 				continue;
 			}
 			bevybail!(
-				"parallel child wrong action signature: {child:?}, error: {mismatch_error}"
+				"{label} child wrong action signature: {child:?}, error: {mismatch_error}"
 			);
 		}
-
-		let world = world.clone();
-		let input = input.clone();
-		calls.push(async move {
-			world
-				.entity(child)
-				.call::<Input, Outcome<Input, Output>>(input)
-				.await
-		});
+		valid.push(child);
 	}
+	Ok(valid)
+}
 
-	for outcome in async_ext::try_join_all(calls).await? {
-		if let Outcome::Fail(output) = outcome {
-			return Ok(Outcome::Fail(output));
+/// Calls every child concurrently with a clone of the input, handing each
+/// outcome to `settle` as it resolves until `settle` returns the outcome to
+/// settle on, or every child has resolved.
+///
+/// Whichever way it ends, the caller's [`Running`] descendants still in
+/// flight are then interrupted with [`InterruptRun`], so a losing branch never
+/// outlives the control flow that started it.
+///
+/// ## Errors
+///
+/// Errors with the first child error, after interrupting its siblings.
+pub(crate) async fn settle_children<Input, Output, Settle>(
+	cx: &ActionContext<Input>,
+	children: Vec<Entity>,
+	mut settle: Settle,
+) -> Result<Option<Outcome<Input, Output>>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+	Settle: FnMut(Outcome<Input, Output>) -> Option<Outcome<Input, Output>>,
+{
+	let world = cx.world();
+	let mut calls = children
+		.into_iter()
+		.map(|child| {
+			let world = world.clone();
+			let input = cx.input.clone();
+			Some(Box::pin(async move {
+				world
+					.entity(child)
+					.call::<Input, Outcome<Input, Output>>(input)
+					.await
+			}))
+		})
+		.collect::<Vec<_>>();
+
+	let settled = core::future::poll_fn(|task| {
+		let mut all_done = true;
+		for slot in calls.iter_mut() {
+			let Some(call) = slot else { continue };
+			match call.as_mut().poll(task) {
+				Poll::Ready(Ok(outcome)) => {
+					*slot = None;
+					if let Some(settled) = settle(outcome) {
+						return Poll::Ready(Ok(Some(settled)));
+					}
+				}
+				Poll::Ready(Err(err)) => {
+					*slot = None;
+					return Poll::Ready(Err(err));
+				}
+				Poll::Pending => all_done = false,
+			}
 		}
-	}
+		if all_done {
+			Poll::Ready(Ok(None))
+		} else {
+			Poll::Pending
+		}
+	})
+	.await;
+	// the losing calls are dropped here, their children interrupted below
+	drop(calls);
 
-	Ok(Outcome::Pass(input))
+	cx.caller
+		.queue(InterruptRun::<Outcome<Input, Output>>::new())
+		.await??;
+	settled
 }
 
 #[cfg(test)]
@@ -213,4 +444,55 @@ mod tests {
 			.unwrap()
 			.xpect_eq(Outcome::Pass(()));
 	}
+
+	#[beet_core::test]
+	async fn policy_passes_on_threshold() {
+		let mut world = AsyncPlugin::world();
+		let pending = world.spawn(ContinueRun::<(), Outcome>::default()).id();
+		let parallel = world
+			.spawn((ParallelWithPolicy::new(2, 2), children![
+				outcome_pass(),
+				outcome_fail(),
+				outcome_pass(),
+			]))
+			.add_child(pending)
+			.id();
+
+		world
+			.entity_mut(parallel)
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Pass(()));
+		// the child still running once the policy settled is interrupted
+		world.get::<Running<Outcome>>(pending).xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn policy_fails_on_threshold() {
+		AsyncPlugin::world()
+			.spawn((ParallelWithPolicy::new(1, 2), children![
+				outcome_fail(),
+				outcome_fail(),
+				ContinueRun::<(), Outcome>::default(),
+			]))
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Fail(()));
+	}
+
+	#[beet_core::test]
+	async fn policy_fails_when_pass_is_unreachable() {
+		AsyncPlugin::world()
+			.spawn((ParallelWithPolicy::new(2, 3), children![
+				outcome_fail(),
+				outcome_fail(),
+				outcome_pass(),
+			]))
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Fail(()));
+	}
 }
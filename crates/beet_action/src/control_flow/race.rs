@@ -0,0 +1,142 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Race control-flow component.
+///
+/// Runs all child actions concurrently with a clone of the same input, and
+/// returns the [`Outcome`] of the first child to resolve, pass or fail. Every
+/// sibling still running is then interrupted with [`InterruptRun`], resolving
+/// its [`Running`] descendants with [`ControlFlowError::Interrupted`].
+///
+/// Expresses a timeout as a race against a timer child, "first sensor to
+/// answer", or a speculative call across several models.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[require(RaceAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct Race<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Default for Race<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	fn default() -> Self {
+		Self {
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl Race {
+	/// Create a default `Race<(), ()>`.
+	pub fn new() -> Self { Self::default() }
+}
+
+/// Runs all children concurrently, settling on the first to resolve and
+/// interrupting the rest.
+///
+/// Child error handling is controlled by [`ExcludeErrors`].
+///
+/// ## Errors
+///
+/// Errors if the first child to resolve errors, or depending on
+/// [`ChildError`] flags when a child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn RaceAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	let children = concurrent_children::<Input, Output>(&cx, "race").await?;
+	if children.is_empty() {
+		// nothing to race, pass returning the input
+		return Ok(Outcome::Pass(cx.input));
+	}
+	settle_children::<Input, Output, _>(&cx, children, Some)
+		.await?
+		.ok_or_else(|| bevyhow!("race children settled without a winner"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn outcome_fail() -> Action<(), Outcome<(), ()>> {
+		Action::new_pure(|_: ActionContext| Outcome::Fail(()).xok())
+	}
+	fn outcome_pass() -> Action<(), Outcome<(), ()>> {
+		Action::new_pure(|_: ActionContext| Outcome::Pass(()).xok())
+	}
+
+	#[beet_core::test]
+	async fn no_children() {
+		AsyncPlugin::world()
+			.spawn(Race::new())
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Pass(()));
+	}
+
+	#[beet_core::test]
+	async fn first_to_resolve_wins() {
+		AsyncPlugin::world()
+			.spawn((Race::new(), children![
+				ContinueRun::<(), Outcome>::default(),
+				outcome_fail(),
+			]))
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Fail(()));
+	}
+
+	#[beet_core::test]
+	async fn interrupts_losing_branches() {
+		let mut world = AsyncPlugin::world();
+		let loser = world.spawn(ContinueRun::<(), Outcome>::default()).id();
+		let race = world
+			.spawn((Race::new(), children![outcome_pass()]))
+			.add_child(loser)
+			.id();
+
+		world
+			.entity_mut(race)
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Pass(()));
+		world.get::<Running<Outcome>>(loser).xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn no_interrupt_survives() {
+		let mut world = AsyncPlugin::world();
+		let loser = world
+			.spawn((ContinueRun::<(), Outcome>::default(), NoInterrupt))
+			.id();
+		let race = world
+			.spawn((Race::new(), children![outcome_pass()]))
+			.add_child(loser)
+			.id();
+
+		world
+			.entity_mut(race)
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap();
+		world.get::<Running<Outcome>>(loser).xpect_some();
+	}
+}
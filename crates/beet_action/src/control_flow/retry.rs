@@ -0,0 +1,205 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Retry control-flow component.
+///
+/// Calls its single child with a clone of the input, calling it again after
+/// each [`Outcome::Fail`] until it passes or [`Self::attempts`] calls have
+/// been made, sleeping between calls for the exponential delay of
+/// [`Self::backoff`]. Returns the first [`Outcome::Pass`], or the last
+/// [`Outcome::Fail`] once the attempts are spent.
+/// With no child, returns [`Outcome::Pass`] immediately.
+///
+/// ```ignore
+/// <Retry attempts=3>
+/// 	<FetchWeather/>
+/// </Retry>
+/// ```
+#[derive(Debug, Component, Reflect)]
+#[require(RetryAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct Retry<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	/// The total number of calls, including the first. Defaults to `3`, and
+	/// is never less than `1`.
+	pub attempts: u32,
+	/// The delay between calls. Its own
+	/// [`max_attempts`](Backoff::max_attempts) is ignored in favour of
+	/// [`Self::attempts`].
+	pub backoff: Backoff,
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Clone for Retry<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	fn clone(&self) -> Self {
+		Self {
+			attempts: self.attempts,
+			backoff: self.backoff.clone(),
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Input, Output> Default for Retry<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	fn default() -> Self {
+		let backoff = Backoff::default();
+		Self {
+			attempts: backoff.max_attempts(),
+			backoff,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<Input, Output> Retry<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	/// Set the delay between calls.
+	pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+		self.backoff = backoff;
+		self
+	}
+}
+
+impl Retry {
+	/// Create a `Retry<(), ()>` calling the child at most `attempts` times.
+	pub fn new(attempts: u32) -> Self {
+		Self {
+			attempts,
+			..default()
+		}
+	}
+}
+
+/// Calls the single child until it passes or the [`Retry::attempts`] are
+/// spent.
+///
+/// ## Errors
+///
+/// Errors if the child errors, which is not retried, or depending on
+/// [`ChildError`] flags when the child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn RetryAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync,
+{
+	let Some(child) = concurrent_children::<Input, Output>(&cx, "retry")
+		.await?
+		.into_iter()
+		.next()
+	else {
+		return Ok(Outcome::Pass(cx.input));
+	};
+	let backoff = cx
+		.caller
+		.get(|retry: &Retry<Input, Output>| {
+			retry
+				.backoff
+				.clone()
+				.with_max_attempts(retry.attempts.max(1))
+		})
+		.await?;
+
+	let world = cx.world();
+	let mut frames = backoff.iter();
+	loop {
+		// the iterator yields at least one frame for at least one attempt
+		let frame = frames
+			.next()
+			.ok_or_else(|| bevyhow!("retry ran out of attempts"))?;
+		let outcome = world
+			.entity(child)
+			.call::<Input, Outcome<Input, Output>>(cx.input.clone())
+			.await?;
+		match (outcome, frame.next_attempt) {
+			(Outcome::Fail(_), Some(delay)) => time_ext::sleep(delay).await,
+			(outcome, _) => return Ok(outcome),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloc::sync::Arc;
+	use core::sync::atomic::AtomicU32;
+	use core::sync::atomic::Ordering;
+
+	/// A child action that fails `n` times, then passes.
+	fn fail_n_then_pass(n: u32) -> (Arc<AtomicU32>, Action<(), Outcome>) {
+		let count = Arc::new(AtomicU32::new(0));
+		let count_inner = count.clone();
+		let action = Action::new_pure(move |_: ActionContext| {
+			let calls = count_inner.fetch_add(1, Ordering::SeqCst);
+			if calls < n {
+				Outcome::FAIL.xok()
+			} else {
+				Outcome::PASS.xok()
+			}
+		});
+		(count, action)
+	}
+
+	/// Retry `attempts` times with a negligible delay.
+	fn retry(attempts: u32) -> Retry {
+		Retry::new(attempts).with_backoff(Backoff::new(
+			attempts,
+			Duration::from_millis(1),
+			Duration::from_millis(2),
+		))
+	}
+
+	#[beet_core::test]
+	async fn no_child() {
+		AsyncPlugin::world()
+			.spawn(Retry::new(3))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+	}
+
+	#[beet_core::test]
+	async fn retries_until_pass() {
+		let (count, child) = fail_n_then_pass(2);
+		AsyncPlugin::world()
+			.spawn((retry(3), children![child]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		count.load(Ordering::SeqCst).xpect_eq(3);
+	}
+
+	#[beet_core::test]
+	async fn fails_once_attempts_spent() {
+		let (count, child) = fail_n_then_pass(5);
+		AsyncPlugin::world()
+			.spawn((retry(2), children![child]))
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+		count.load(Ordering::SeqCst).xpect_eq(2);
+	}
+}
@@ -0,0 +1,973 @@
+//! Hierarchical finite state machines.
+//!
+//! Where [`RunNext`] is a bare jump between entities, a [`StateMachine`]
+//! tracks which [`MachineState`] is active and moves between them through
+//! [`Transition`] children, each optionally guarded by its own action.
+//! The whole machine is plain components in the entity hierarchy, so it can
+//! be authored in `.bsx` and rendered with [`StateMachineQuery::mermaid`].
+//!
+//! ```text
+//! StateMachine
+//! ├── MachineState "active"
+//! │   ├── Transition(charging) on "low_battery"
+//! │   ├── MachineState "idle"
+//! │   │   ├── (StateEnter, Log)     entry action
+//! │   │   └── Transition(patrol)    on completion
+//! │   └── MachineState "patrol"
+//! │       └── Patrol                activity
+//! ├── MachineState "charging"
+//! │   ├── Charge                    activity
+//! │   └── Transition(active)        on completion
+//! └── FinalState
+//! ```
+use crate::prelude::*;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use beet_core::prelude::*;
+use core::task::Poll;
+
+/// Hierarchical state machine control-flow component.
+///
+/// When called, enters its initial [`MachineState`], then keeps moving between
+/// states as their [`Transition`]s fire, resolving with the outcome of the
+/// first [`FinalState`] entered. A machine with no states passes immediately.
+///
+/// The children of a state have the following roles:
+/// - [`MachineState`]: a nested state, entered through the initial substate
+///   whenever its parent is entered.
+/// - [`Transition`]: a way out of the state, see [`TransitionTrigger`].
+/// - [`StateEnter`] / [`StateExit`]: actions called in order as the state is
+///   entered or exited, their outcomes ignored.
+/// - any other action: the first is the activity of the state, called once
+///   the state is entered and interrupted when it is left.
+///
+/// While a nested state is active all of its ancestors are active too, and on
+/// each trigger the transitions of the innermost active state are tried first.
+/// The states along the active path are marked with [`ActiveState`].
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_action::prelude::*;
+/// # let mut world = AsyncPlugin::world();
+/// let machine = world.spawn(StateMachine).id();
+/// let done = world.spawn((FinalState::default(), ChildOf(machine))).id();
+/// world.spawn((MachineState, ChildOf(machine), children![
+/// 	Transition::new(done)
+/// ]));
+/// ```
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(StateMachineAction)]
+pub struct StateMachine;
+
+/// A state of a [`StateMachine`], a child of the machine or of another state.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct MachineState;
+
+/// Marks the [`MachineState`] entered first among its siblings, which is
+/// otherwise the first state child.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(MachineState)]
+pub struct InitialState;
+
+/// A [`MachineState`] that resolves its [`StateMachine`] with
+/// [`Self::outcome`] once entered, at whatever depth.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(MachineState)]
+pub struct FinalState {
+	/// The outcome the machine resolves with, by default [`Outcome::PASS`].
+	pub outcome: Outcome,
+}
+
+impl Default for FinalState {
+	fn default() -> Self {
+		Self {
+			outcome: Outcome::PASS,
+		}
+	}
+}
+
+impl FinalState {
+	/// A final state resolving the machine with `outcome`.
+	pub fn new(outcome: Outcome) -> Self { Self { outcome } }
+}
+
+/// Marks an action child of a [`MachineState`] to call when the state is
+/// entered.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct StateEnter;
+
+/// Marks an action child of a [`MachineState`] to call when the state is
+/// exited.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct StateExit;
+
+/// Added to each [`MachineState`] on the active path of a running
+/// [`StateMachine`], and removed as it is exited.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct ActiveState;
+
+/// A way out of the [`MachineState`] it is a child of, to [`Self::target`].
+///
+/// When its [`Self::trigger`] occurs while its state is active the
+/// transition is tried: if the entity carries an action, that is its guard
+/// and the transition fires only if it passes, otherwise it always fires.
+/// Firing exits every active state up to the one shared with the target,
+/// then enters the target and its initial substates. A transition to its own
+/// state, or an ancestor, exits and re-enters it.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct Transition {
+	/// The state to move to, at any depth of the same machine.
+	#[reflect(@RequiredField)]
+	pub target: Entity,
+	/// What makes the machine try this transition.
+	pub trigger: TransitionTrigger,
+}
+
+impl Default for Transition {
+	fn default() -> Self { Self::new(Entity::PLACEHOLDER) }
+}
+
+impl Transition {
+	/// Move to `target` once the activity of the state completes.
+	pub fn new(target: Entity) -> Self {
+		Self {
+			target,
+			trigger: TransitionTrigger::Completion,
+		}
+	}
+	/// Move to `target` on a [`StateEvent`] named `name`.
+	pub fn on_event(target: Entity, name: impl Into<SmolStr>) -> Self {
+		Self {
+			target,
+			trigger: TransitionTrigger::Event(name.into()),
+		}
+	}
+	/// Move to `target` when `key` of the machine's blackboard changes.
+	pub fn on_change(target: Entity, key: impl Into<SmolStr>) -> Self {
+		Self {
+			target,
+			trigger: TransitionTrigger::Change(key.into()),
+		}
+	}
+}
+
+/// What makes a [`StateMachine`] try a [`Transition`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+pub enum TransitionTrigger {
+	/// The activity of the state resolved, or the state was entered with no
+	/// activity. Only tried on the innermost active state.
+	#[default]
+	Completion,
+	/// A [`StateEvent`] with this name reached the machine.
+	Event(SmolStr),
+	/// The value at this dotted key changed in the blackboard nearest the
+	/// machine. Requires the `serde` feature.
+	Change(SmolStr),
+}
+
+/// Triggered on a running [`StateMachine`] to fire the transitions of its
+/// active states listening for [`Self::name`], once [`ActionPlugin`] is
+/// added. Ignored by a machine that is not running.
+#[derive(Debug, Clone, EntityEvent)]
+pub struct StateEvent {
+	/// The state machine entity.
+	pub entity: Entity,
+	/// The name transitions listen for.
+	pub name: SmolStr,
+}
+
+impl StateEvent {
+	/// Create a [`StateEvent`] for `machine`.
+	pub fn new(machine: Entity, name: impl Into<SmolStr>) -> Self {
+		Self {
+			entity: machine,
+			name: name.into(),
+		}
+	}
+}
+
+/// `<SendStateEvent("name")>` triggers a [`StateEvent`] on the nearest
+/// ancestor [`StateMachine`], then passes.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+#[require(SendStateEventAction)]
+pub struct SendStateEvent(pub SmolStr);
+
+impl SendStateEvent {
+	/// Send a [`StateEvent`] named `name` when called.
+	pub fn new(name: impl Into<SmolStr>) -> Self { Self(name.into()) }
+}
+
+/// The action behind [`SendStateEvent`].
+///
+/// ## Errors
+/// Errors if the caller has no [`SendStateEvent`] or no ancestor
+/// [`StateMachine`].
+#[action(handler_only)]
+#[derive(Default, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub fn SendStateEventAction(
+	cx: In<ActionContext>,
+	mut commands: Commands,
+	sends: Query<&SendStateEvent>,
+	machines: StateMachineQuery,
+) -> Result<Outcome> {
+	let SendStateEvent(name) = sends.get(cx.id())?;
+	let machine = machines.machine(cx.id()).ok_or_else(|| {
+		bevyhow!("SendStateEvent has no StateMachine ancestor")
+	})?;
+	commands.trigger(StateEvent::new(machine, name.clone()));
+	Outcome::PASS.xok()
+}
+
+/// Reads the structure of [`StateMachine`] hierarchies.
+#[derive(SystemParam)]
+pub struct StateMachineQuery<'w, 's> {
+	children: Query<'w, 's, &'static Children>,
+	parents: Query<'w, 's, &'static ChildOf>,
+	machines: Query<'w, 's, (), With<StateMachine>>,
+	states: Query<
+		'w,
+		's,
+		(Has<InitialState>, Option<&'static FinalState>),
+		With<MachineState>,
+	>,
+	transitions: Query<'w, 's, &'static Transition>,
+	hooks: Query<'w, 's, (Has<StateEnter>, Has<StateExit>)>,
+	actions: Query<'w, 's, (), With<ActionMeta>>,
+	names: Query<'w, 's, &'static Name>,
+}
+
+impl StateMachineQuery<'_, '_> {
+	/// The nearest [`StateMachine`] to `entity`, including itself.
+	pub fn machine(&self, entity: Entity) -> Option<Entity> {
+		let mut current = Some(entity);
+		while let Some(entity) = current {
+			if self.machines.contains(entity) {
+				return Some(entity);
+			}
+			current = self.parents.get(entity).ok().map(ChildOf::parent);
+		}
+		None
+	}
+
+	/// The [`MachineState`] children of a machine or state, in order.
+	pub fn states(&self, parent: Entity) -> Vec<Entity> {
+		self.child_vec(parent)
+			.into_iter()
+			.filter(|child| self.states.contains(*child))
+			.collect()
+	}
+
+	/// The state entered first among the children of a machine or state.
+	pub fn initial(&self, parent: Entity) -> Option<Entity> {
+		let states = self.states(parent);
+		states
+			.iter()
+			.find(|state| matches!(self.states.get(**state), Ok((true, _))))
+			.or(states.first())
+			.copied()
+	}
+
+	/// The [`Transition`] children of a state, with their entities.
+	pub fn transitions(&self, state: Entity) -> Vec<(Entity, Transition)> {
+		self.child_vec(state)
+			.into_iter()
+			.filter_map(|child| {
+				self.transitions
+					.get(child)
+					.ok()
+					.map(|transition| (child, transition.clone()))
+			})
+			.collect()
+	}
+
+	/// Render the machine as a Mermaid `stateDiagram-v2`, labelling states by
+	/// their [`Name`].
+	pub fn mermaid(&self, machine: Entity) -> String {
+		let mut out = String::from("stateDiagram-v2\n");
+		self.write_mermaid(&mut out, machine, 1);
+		out
+	}
+
+	fn write_mermaid(&self, out: &mut String, parent: Entity, depth: usize) {
+		let indent = "\t".repeat(depth);
+		if let Some(initial) = self.initial(parent) {
+			out.push_str(&format!("{indent}[*] --> {}\n", mermaid_id(initial)));
+		}
+		for state in self.states(parent) {
+			let id = mermaid_id(state);
+			let label = self
+				.names
+				.get(state)
+				.map(|name| name.to_string())
+				.unwrap_or_else(|_| id.clone());
+			out.push_str(&format!("{indent}state \"{label}\" as {id}"));
+			if self.states(state).is_empty() {
+				out.push('\n');
+			} else {
+				out.push_str(" {\n");
+				self.write_mermaid(out, state, depth + 1);
+				out.push_str(&format!("{indent}}}\n"));
+			}
+			if let Ok((_, Some(_))) = self.states.get(state) {
+				out.push_str(&format!("{indent}{id} --> [*]\n"));
+			}
+			for (_, transition) in self.transitions(state) {
+				let target = mermaid_id(transition.target);
+				let label = match &transition.trigger {
+					TransitionTrigger::Completion => String::new(),
+					TransitionTrigger::Event(name) => format!(" : {name}"),
+					TransitionTrigger::Change(key) => {
+						format!(" : {key} changed")
+					}
+				};
+				out.push_str(&format!("{indent}{id} --> {target}{label}\n"));
+			}
+		}
+	}
+
+	fn child_vec(&self, parent: Entity) -> Vec<Entity> {
+		self.children
+			.get(parent)
+			.map(|children| children.to_vec())
+			.unwrap_or_default()
+	}
+
+	/// The states from the top of `machine` down to `state`, inclusive.
+	fn path(&self, machine: Entity, state: Entity) -> Result<Vec<Entity>> {
+		if state == machine {
+			bevybail!(
+				"transition target {state:?} is the machine itself, not one of its states"
+			);
+		}
+		let mut path = Vec::new();
+		let mut current = state;
+		while current != machine {
+			if !self.states.contains(current) {
+				bevybail!(
+					"transition target {state:?} is not a state of machine {machine:?}"
+				);
+			}
+			path.push(current);
+			current = self.parents.get(current).map(ChildOf::parent).map_err(
+				|_| {
+					bevyhow!(
+						"transition target {state:?} is not in machine {machine:?}"
+					)
+				},
+			)?;
+		}
+		path.reverse();
+		path.xok()
+	}
+
+	/// The active states from the top of `machine` down, following the
+	/// [`ActiveState`] child at each level.
+	fn active_path(
+		&self,
+		machine: Entity,
+		active: &Query<(), With<ActiveState>>,
+	) -> Vec<Entity> {
+		let mut path = Vec::new();
+		let mut current = machine;
+		while let Some(state) = self
+			.states(current)
+			.into_iter()
+			.find(|state| active.contains(*state))
+		{
+			path.push(state);
+			current = state;
+		}
+		path
+	}
+
+	/// The hook children of a state, [`StateEnter`] if `enter` otherwise
+	/// [`StateExit`].
+	fn hooks(&self, state: Entity, enter: bool) -> Vec<Entity> {
+		self.child_vec(state)
+			.into_iter()
+			.filter(|child| {
+				self.actions.contains(*child)
+					&& self.hooks.get(*child).is_ok_and(
+						|(on_enter, on_exit)| {
+							if enter { on_enter } else { on_exit }
+						},
+					)
+			})
+			.collect()
+	}
+
+	/// The activity of a state, its first child action that has no other
+	/// role.
+	fn activity(&self, state: Entity) -> Option<Entity> {
+		self.child_vec(state).into_iter().find(|child| {
+			self.actions.contains(*child)
+				&& !self.states.contains(*child)
+				&& !self.transitions.contains(*child)
+				&& matches!(self.hooks.get(*child), Ok((false, false)))
+		})
+	}
+}
+
+fn mermaid_id(entity: Entity) -> String { format!("s{entity}") }
+
+/// Something that happened to a running [`StateMachine`], matched against
+/// the [`TransitionTrigger`]s of its active states.
+#[derive(Debug, Clone)]
+enum StateTrigger {
+	Completion,
+	Event(SmolStr),
+	#[cfg(feature = "serde")]
+	Change(BlackboardChanged),
+}
+
+impl StateTrigger {
+	fn matches(&self, trigger: &TransitionTrigger) -> bool {
+		match (self, trigger) {
+			(Self::Completion, TransitionTrigger::Completion) => true,
+			(Self::Event(name), TransitionTrigger::Event(expected)) => {
+				name == expected
+			}
+			#[cfg(feature = "serde")]
+			(Self::Change(changed), TransitionTrigger::Change(key)) => {
+				changed.changed(key)
+			}
+			_ => false,
+		}
+	}
+}
+
+/// The triggers received by a running [`StateMachine`], waiting for its
+/// action to try them.
+#[derive(Default, Component)]
+pub(crate) struct StateMachineRun {
+	pending: Vec<StateTrigger>,
+	/// Wakes the machine action waiting on a trigger.
+	wake: Option<OnceValue<()>>,
+	/// Alive while the machine action runs, so a run whose call was dropped,
+	/// ie by a [`Race`] or [`Timeout`], can be released.
+	call: Weak<()>,
+}
+
+impl StateMachineRun {
+	fn new(call: &Arc<()>) -> Self {
+		Self {
+			call: Arc::downgrade(call),
+			..default()
+		}
+	}
+
+	fn push(&mut self, trigger: StateTrigger) {
+		self.pending.push(trigger);
+		if let Some(wake) = self.wake.take() {
+			wake.signal(());
+		}
+	}
+}
+
+/// Queues each [`StateEvent`] on its machine, if running.
+pub(crate) fn queue_state_events(
+	ev: On<StateEvent>,
+	mut runs: Query<&mut StateMachineRun>,
+) {
+	if let Ok(mut run) = runs.get_mut(ev.entity) {
+		run.push(StateTrigger::Event(ev.name.clone()));
+	}
+}
+
+/// Queues each [`BlackboardChanged`] on the running machines resolving to
+/// that blackboard.
+#[cfg(feature = "serde")]
+pub(crate) fn queue_blackboard_changes(
+	ev: On<BlackboardChanged>,
+	mut runs: Query<(Entity, &mut StateMachineRun)>,
+	blackboards: BlackboardQuery,
+) {
+	for (machine, mut run) in runs.iter_mut() {
+		if blackboards.get_entity(machine).ok() == Some(ev.entity) {
+			run.push(StateTrigger::Change(ev.event().clone()));
+		}
+	}
+}
+
+/// Releases each machine whose [`StateMachineAction`] was dropped mid-run,
+/// clearing its [`ActiveState`]s and calling their [`StateExit`] hooks.
+pub(crate) fn release_abandoned_machines(
+	runs: Query<(Entity, &StateMachineRun)>,
+	mut commands: Commands,
+) {
+	for (machine, run) in runs.iter() {
+		if run.call.strong_count() > 0 {
+			continue;
+		}
+		commands.queue(move |world: &mut World| {
+			if let Some(path) = take_abandoned(world, machine) {
+				world.run_async(async move |world: AsyncWorld| -> Result {
+					exit_abandoned(&world, path).await
+				});
+			}
+		});
+	}
+}
+
+/// Take the active path of a machine whose last call was dropped, removing
+/// its [`ActiveState`]s and run, or `None` if no run was abandoned.
+fn take_abandoned(world: &mut World, machine: Entity) -> Option<Vec<Entity>> {
+	if world.get::<StateMachineRun>(machine)?.call.strong_count() > 0 {
+		return None;
+	}
+	let path = world
+		.with_state::<(StateMachineQuery, Query<(), With<ActiveState>>), _>(
+			|(query, active)| query.active_path(machine, &active),
+		);
+	world.entity_mut(machine).remove::<StateMachineRun>();
+	for state in &path {
+		world.entity_mut(*state).remove::<ActiveState>();
+	}
+	Some(path)
+}
+
+/// Call the [`StateExit`] hooks of an abandoned active path, innermost first.
+async fn exit_abandoned(world: &AsyncWorld, path: Vec<Entity>) -> Result {
+	for state in path.into_iter().rev() {
+		call_hooks(world, state, false).await?;
+	}
+	Ok(())
+}
+
+/// Runs the caller's [`StateMachine`] until it enters a [`FinalState`].
+///
+/// If the call is dropped, ie by a [`Race`] or [`Timeout`], its active states
+/// are released and exited on the next app update, or when it is called again.
+///
+/// ## Errors
+///
+/// Errors if a transition targets an entity that is not a state of this
+/// machine, or an activity, guard or hook errors.
+#[action(default)]
+#[derive(Component)]
+pub async fn StateMachineAction(cx: ActionContext) -> Result<Outcome> {
+	// exit whatever a dropped call left active before starting over
+	if let Some(path) = cx.caller.with_world(take_abandoned).await? {
+		exit_abandoned(&cx.world(), path).await?;
+	}
+	// a fresh run, discarding triggers left over from the last
+	let call = Arc::new(());
+	cx.caller.insert(StateMachineRun::new(&call)).await?;
+	let mut active = Vec::new();
+	let result = run_machine(&cx, &mut active).await;
+	cx.caller
+		.with_world(move |world, machine| {
+			for state in active {
+				world.entity_mut(state).remove::<ActiveState>();
+			}
+			world.entity_mut(machine).remove::<StateMachineRun>();
+		})
+		.await?;
+	drop(call);
+	result
+}
+
+async fn run_machine(
+	cx: &ActionContext,
+	active: &mut Vec<Entity>,
+) -> Result<Outcome> {
+	let Some(initial) = cx
+		.caller
+		.with_state::<StateMachineQuery, _>(|machine, query| {
+			query.initial(machine)
+		})
+		.await?
+	else {
+		return Ok(Outcome::PASS);
+	};
+	move_to(cx, active, initial).await?;
+
+	loop {
+		let leaf = *active.last().ok_or_else(|| bevyhow!("no active state"))?;
+		let (activity, final_state) = cx
+			.world()
+			.entity(leaf)
+			.with_state::<(StateMachineQuery, Query<&FinalState>), _>(
+				|leaf, (query, finals)| {
+					(query.activity(leaf), finals.get(leaf).ok().copied())
+				},
+			)
+			.await?;
+		if let Some(final_state) = final_state {
+			exit_to(cx, active, 0).await?;
+			return Ok(final_state.outcome);
+		}
+
+		let mut call = activity.map(|activity| {
+			let world = cx.world().clone();
+			Box::pin(async move {
+				world.entity(activity).call::<(), Outcome>(()).await
+			})
+		});
+		// a state with no activity completes as it is entered
+		let mut completed = call.is_none();
+		let target = loop {
+			let (mut triggers, wake) = cx
+				.caller
+				.get_mut(|mut run: Mut<StateMachineRun>| {
+					let (send, recv) = OnceValue::oneshot();
+					run.wake = Some(send);
+					(core::mem::take(&mut run.pending), recv)
+				})
+				.await?;
+			if core::mem::take(&mut completed) {
+				triggers.push(StateTrigger::Completion);
+			}
+			if let Some(target) =
+				select_transition(cx, active, triggers).await?
+			{
+				break target;
+			}
+			let mut wake = Box::pin(wake.wait());
+			let Some(running) = call.as_mut() else {
+				wake.await;
+				continue;
+			};
+			let settled = core::future::poll_fn(|task| {
+				if let Poll::Ready(result) = running.as_mut().poll(task) {
+					return Poll::Ready(Some(result));
+				}
+				wake.as_mut().poll(task).map(|_| None)
+			})
+			.await;
+			if let Some(result) = settled {
+				result?;
+				call = None;
+				completed = true;
+			}
+		};
+		if let Some(call) = call {
+			// drop the abandoned activity, then interrupt what it left running
+			drop(call);
+			cx.world()
+				.entity(leaf)
+				.queue(InterruptRun::<Outcome>::new())
+				.await??;
+		}
+		move_to(cx, active, target).await?;
+	}
+}
+
+/// Try the transitions of the active states against each trigger in turn,
+/// innermost state first, returning the target of the first to fire.
+async fn select_transition(
+	cx: &ActionContext,
+	active: &[Entity],
+	triggers: Vec<StateTrigger>,
+) -> Result<Option<Entity>> {
+	if triggers.is_empty() {
+		return Ok(None);
+	}
+	let active = active.to_vec();
+	let candidates = cx
+		.caller
+		.with_state::<StateMachineQuery, _>(move |_, query| {
+			let mut candidates = Vec::new();
+			for trigger in triggers {
+				for (depth, state) in active.iter().enumerate().rev() {
+					let innermost = depth + 1 == active.len();
+					for (entity, transition) in query.transitions(*state) {
+						if trigger.matches(&transition.trigger)
+							&& (innermost
+								|| transition.trigger
+									!= TransitionTrigger::Completion)
+						{
+							let guarded = query.actions.contains(entity);
+							candidates.push((
+								entity,
+								transition.target,
+								guarded,
+							));
+						}
+					}
+				}
+			}
+			candidates
+		})
+		.await?;
+
+	for (entity, target, guarded) in candidates {
+		if !guarded
+			|| cx.world().entity(entity).call::<(), Outcome>(()).await?
+				== Outcome::PASS
+		{
+			return Ok(Some(target));
+		}
+	}
+	Ok(None)
+}
+
+/// Exit active states up to the one shared with `target`, then enter
+/// `target` and its initial substates.
+async fn move_to(
+	cx: &ActionContext,
+	active: &mut Vec<Entity>,
+	target: Entity,
+) -> Result {
+	let (path, entering) = cx
+		.caller
+		.with_state::<StateMachineQuery, _>(
+			move |machine, query| -> Result<_> {
+				let path = query.path(machine, target)?;
+				let mut entering = Vec::new();
+				let mut current = target;
+				while let Some(initial) = query.initial(current) {
+					entering.push(initial);
+					current = initial;
+				}
+				(path, entering).xok()
+			},
+		)
+		.await??;
+	// the target itself is always re-entered, even when already active
+	let shared = active
+		.iter()
+		.zip(&path)
+		.take_while(|(active, path)| active == path)
+		.count()
+		.min(path.len() - 1);
+	exit_to(cx, active, shared).await?;
+	for state in path[shared..].iter().chain(&entering) {
+		active.push(*state);
+		cx.world().entity(*state).insert(ActiveState).await?;
+		call_hooks(&cx.world(), *state, true).await?;
+	}
+	Ok(())
+}
+
+/// Exit active states, innermost first, until `depth` remain.
+async fn exit_to(
+	cx: &ActionContext,
+	active: &mut Vec<Entity>,
+	depth: usize,
+) -> Result {
+	while active.len() > depth {
+		let Some(state) = active.pop() else { break };
+		call_hooks(&cx.world(), state, false).await?;
+		cx.world().entity(state).take::<ActiveState>().await?;
+	}
+	Ok(())
+}
+
+/// Call the [`StateEnter`] or [`StateExit`] hooks of a state in order.
+async fn call_hooks(world: &AsyncWorld, state: Entity, enter: bool) -> Result {
+	let hooks = world
+		.entity(state)
+		.with_state::<StateMachineQuery, _>(move |state, query| {
+			query.hooks(state, enter)
+		})
+		.await?;
+	for hook in hooks {
+		world.entity(hook).call::<(), Outcome>(()).await?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::sync::atomic::AtomicU32;
+	use core::sync::atomic::Ordering;
+
+	fn counter() -> (Arc<AtomicU32>, Action<(), Outcome>) {
+		let count = Arc::new(AtomicU32::new(0));
+		let count_inner = count.clone();
+		let action = Action::new_pure(move |_: ActionContext| {
+			count_inner.fetch_add(1, Ordering::SeqCst);
+			Outcome::PASS.xok()
+		});
+		(count, action)
+	}
+
+	#[beet_core::test]
+	async fn no_states() {
+		AsyncPlugin::world()
+			.spawn(StateMachine)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+	}
+
+	#[beet_core::test]
+	async fn completes_into_final_state() {
+		let mut world = AsyncPlugin::world();
+		let (entered, on_enter) = counter();
+		let (exited, on_exit) = counter();
+		let machine = world.spawn(StateMachine).id();
+		let done = world
+			.spawn((FinalState::new(Outcome::FAIL), ChildOf(machine)))
+			.id();
+		world.spawn((MachineState, ChildOf(machine), children![
+			(StateEnter, on_enter),
+			(StateExit, on_exit),
+			EndWith(Outcome::PASS),
+			Transition::new(done),
+		]));
+
+		world
+			.entity_mut(machine)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::FAIL);
+		entered.load(Ordering::SeqCst).xpect_eq(1);
+		exited.load(Ordering::SeqCst).xpect_eq(1);
+		world
+			.query::<&ActiveState>()
+			.iter(&world)
+			.count()
+			.xpect_eq(0);
+	}
+
+	#[beet_core::test]
+	async fn events_fire_outer_transitions() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let (exited, on_exit) = counter();
+		let machine = world.spawn(StateMachine).id();
+		let active = world.spawn((MachineState, ChildOf(machine))).id();
+		let charging =
+			world.spawn((FinalState::default(), ChildOf(machine))).id();
+		let rejected = world
+			.spawn((FinalState::new(Outcome::FAIL), ChildOf(machine)))
+			.id();
+		// the guarded transition is tried first and refuses
+		world.spawn((
+			Transition::on_event(rejected, "low_battery"),
+			EndWith(Outcome::FAIL),
+			ChildOf(active),
+		));
+		world.spawn((
+			Transition::on_event(charging, "low_battery"),
+			ChildOf(active),
+		));
+		let patrol = world
+			.spawn((MachineState, ChildOf(active), children![
+				(StateExit, on_exit),
+				SendStateEvent::new("low_battery"),
+			]))
+			.id();
+		world.spawn((MachineState, InitialState, ChildOf(active), children![
+			Transition::new(patrol)
+		]));
+
+		world
+			.entity_mut(machine)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		exited.load(Ordering::SeqCst).xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn rejects_machine_as_target() {
+		let mut world = AsyncPlugin::world();
+		let machine = world.spawn(StateMachine).id();
+		world.spawn((MachineState, ChildOf(machine), children![
+			Transition::new(machine)
+		]));
+		world
+			.entity_mut(machine)
+			.call::<(), Outcome>(())
+			.await
+			.xpect_err();
+	}
+
+	#[beet_core::test]
+	async fn releases_dropped_runs() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let root = world.spawn(Race::new()).id();
+		let machine = world.spawn((StateMachine, ChildOf(root))).id();
+		world.spawn((MachineState, ChildOf(machine), children![
+			ContinueRun::<(), Outcome>::default()
+		]));
+		world.spawn((
+			EndInDuration::pass(Duration::from_millis(10)),
+			ChildOf(root),
+		));
+
+		world
+			.entity_mut(root)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+		world.update();
+		world
+			.query::<&ActiveState>()
+			.iter(&world)
+			.count()
+			.xpect_eq(0);
+		world
+			.entity(machine)
+			.contains::<StateMachineRun>()
+			.xpect_false();
+	}
+
+	#[cfg(feature = "serde")]
+	#[beet_core::test]
+	async fn blackboard_changes_fire_transitions() {
+		let mut world =
+			(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world();
+		let machine = world.spawn((Blackboard::default(), StateMachine)).id();
+		let alarmed =
+			world.spawn((FinalState::default(), ChildOf(machine))).id();
+		world.spawn((MachineState, ChildOf(machine), children![
+			Transition::on_change(alarmed, "alarm"),
+			(Sequence::new(), children![
+				EndInDuration::pass(Duration::from_millis(10)),
+				SetBlackboard::new("alarm", true),
+			]),
+		]));
+
+		world
+			.entity_mut(machine)
+			.call::<(), Outcome>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::PASS);
+	}
+
+	#[beet_core::test]
+	fn mermaid() {
+		let mut world = World::new();
+		let machine = world.spawn(StateMachine).id();
+		let done = world
+			.spawn((Name::new("done"), FinalState::default(), ChildOf(machine)))
+			.id();
+		world.spawn((
+			Name::new("idle"),
+			MachineState,
+			ChildOf(machine),
+			children![Transition::on_event(done, "finish")],
+		));
+
+		let diagram = world
+			.run_system_once(move |query: StateMachineQuery| {
+				query.mermaid(machine)
+			})
+			.unwrap();
+		diagram.as_str().xpect_contains("[*] --> ");
+		diagram.as_str().xpect_contains("state \"idle\" as ");
+		diagram.as_str().xpect_contains(" : finish");
+		diagram.as_str().xpect_contains(" --> [*]");
+	}
+}
@@ -0,0 +1,187 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+use core::task::Poll;
+
+/// Timeout control-flow component.
+///
+/// Calls its single child with the input, returning its [`Outcome`] if it
+/// resolves within [`Self::duration`]. Once the deadline passes the child is
+/// abandoned and its [`Running`] descendants are interrupted with
+/// [`InterruptRun`], and this fails with a default output.
+/// With no child, returns [`Outcome::Pass`] immediately.
+///
+/// # Example
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_action::prelude::*;
+/// # let mut world = AsyncPlugin::world();
+/// world.spawn((Timeout::new(Duration::from_secs(5)), children![
+/// 	EndInDuration::pass(Duration::from_secs(1))
+/// ]));
+/// ```
+#[derive(Debug, Component, Reflect)]
+#[require(TimeoutAction<Input,Output>)]
+#[reflect(Component, Default)]
+pub struct Timeout<Input = (), Output = ()>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	/// How long the child may run before it is interrupted.
+	/// Defaults to one second.
+	pub duration: Duration,
+	#[reflect(ignore)]
+	_marker: PhantomData<fn() -> (Input, Output)>,
+}
+
+impl<Input, Output> Clone for Timeout<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn clone(&self) -> Self {
+		Self {
+			duration: self.duration,
+			_marker: PhantomData,
+		}
+	}
+}
+impl<Input, Output> Copy for Timeout<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+}
+
+impl<Input, Output> Default for Timeout<Input, Output>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	fn default() -> Self {
+		Self {
+			duration: Duration::from_secs(1),
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl Timeout {
+	/// Create a `Timeout<(), ()>` interrupting the child once it has run for
+	/// `duration`.
+	pub fn new(duration: Duration) -> Self {
+		Self {
+			duration,
+			_marker: PhantomData,
+		}
+	}
+}
+
+/// Calls the single child, failing and interrupting it once the
+/// [`Timeout::duration`] elapses.
+///
+/// ## Errors
+///
+/// Errors if the child errors before the deadline, or depending on
+/// [`ChildError`] flags when the child has:
+/// - no [`ActionMeta`]
+/// - incompatible [`ActionMeta`] signature
+#[action(default)]
+#[derive(Component)]
+pub async fn TimeoutAction<Input, Output>(
+	cx: ActionContext<Input>,
+) -> Result<Outcome<Input, Output>>
+where
+	Input: 'static + Send + Sync + Clone,
+	Output: 'static + Send + Sync + Default,
+{
+	let Some(child) = concurrent_children::<Input, Output>(&cx, "timeout")
+		.await?
+		.into_iter()
+		.next()
+	else {
+		return Ok(Outcome::Pass(cx.input));
+	};
+	let duration = cx
+		.caller
+		.get(|timeout: &Timeout<Input, Output>| timeout.duration)
+		.await?;
+
+	let world = cx.world().clone();
+	let input = cx.input.clone();
+	let mut call = Box::pin(async move {
+		world
+			.entity(child)
+			.call::<Input, Outcome<Input, Output>>(input)
+			.await
+	});
+	let mut deadline = Box::pin(time_ext::sleep(duration));
+
+	let settled = core::future::poll_fn(|task| {
+		if let Poll::Ready(result) = call.as_mut().poll(task) {
+			return Poll::Ready(Some(result));
+		}
+		deadline.as_mut().poll(task).map(|_| None)
+	})
+	.await;
+	match settled {
+		Some(result) => result,
+		None => {
+			// drop the abandoned call, then interrupt its running descendants
+			drop(call);
+			cx.caller
+				.queue(InterruptRun::<Outcome<Input, Output>>::new())
+				.await??;
+			Ok(Outcome::Fail(Output::default()))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn world() -> World {
+		(MinimalPlugins, AsyncPlugin, ActionPlugin).into_world()
+	}
+
+	#[beet_core::test]
+	async fn no_child() {
+		AsyncPlugin::world()
+			.spawn(Timeout::new(Duration::from_millis(10)))
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Pass(()));
+	}
+
+	#[beet_core::test]
+	async fn child_within_deadline() {
+		world()
+			.spawn((Timeout::new(Duration::from_secs(5)), children![
+				EndInDuration::pass(Duration::from_millis(1))
+			]))
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Pass(()));
+	}
+
+	#[beet_core::test]
+	async fn interrupts_after_deadline() {
+		let mut world = world();
+		let child = world.spawn(ContinueRun::<(), Outcome>::default()).id();
+		let timeout = world
+			.spawn(Timeout::new(Duration::from_millis(10)))
+			.add_child(child)
+			.id();
+
+		world
+			.entity_mut(timeout)
+			.call::<(), Outcome<(), ()>>(())
+			.await
+			.unwrap()
+			.xpect_eq(Outcome::Fail(()));
+		world.get::<Running<Outcome>>(child).xpect_none();
+	}
+}
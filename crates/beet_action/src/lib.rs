@@ -7,6 +7,8 @@ beet_core::test_main!();
 
 mod action_plugin;
 mod actions;
+#[cfg(feature = "serde")]
+mod blackboard;
 mod control_flow;
 #[cfg(feature = "scripting")]
 mod scripting;
@@ -18,6 +20,8 @@ pub mod prelude {
 	pub const PASS: Outcome<(), ()> = crate::control_flow::Outcome::PASS;
 	pub use crate::action_plugin::*;
 	pub use crate::actions::*;
+	#[cfg(feature = "serde")]
+	pub use crate::blackboard::*;
 	pub use crate::control_flow::Outcome::Fail;
 	pub use crate::control_flow::Outcome::Pass;
 	pub use crate::control_flow::*;
@@ -11,6 +11,7 @@ mod mdx;
 #[cfg(feature = "rsx")]
 mod rsx;
 mod sendit;
+mod table_store_row;
 #[cfg(feature = "rsx")]
 mod template;
 mod test_attr;
@@ -129,6 +130,30 @@ pub fn entity_target_event(
 	entity_target_event::impl_entity_target_event(input).into()
 }
 
+/// Implements `TableStoreRow` for a struct carrying its uuid as an `id`
+/// field, with an optional schema version and the migrations upgrading a row
+/// to it.
+///
+/// ```ignore
+/// #[derive(Clone, Serialize, Deserialize, TableStoreRow)]
+/// /// The schema version rows are written at, default 0
+/// #[table(version = 2)]
+/// /// The upgrades from each prior version, required from version 1
+/// #[table(migrations = Post::upgrades())]
+/// /// The field holding the row id, default `id`
+/// #[table(id = post_id)]
+/// struct Post {
+/// 	post_id: Uuid,
+/// 	title: String,
+/// }
+/// ```
+#[proc_macro_derive(TableStoreRow, attributes(table))]
+pub fn table_store_row(
+	input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	table_store_row::impl_table_store_row(input).into()
+}
+
 /// A unified macro for handling all test cases:
 /// - sync native
 /// - sync wasm
@@ -0,0 +1,94 @@
+use beet_core_shared::prelude::*;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn;
+use syn::DeriveInput;
+use syn::parse_macro_input;
+
+pub(crate) fn impl_table_store_row(
+	input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	parse_table_store_row(input)
+		.unwrap_or_else(|err| err.into_compile_error())
+		.into()
+}
+
+fn parse_table_store_row(input: DeriveInput) -> syn::Result<TokenStream> {
+	let (impl_generics, type_generics, where_clause) =
+		input.generics.split_for_impl();
+	let input_ident = &input.ident;
+
+	let attrs = AttributeGroup::parse(&input.attrs, "table")?;
+	attrs.validate_allowed_keys(&["id", "version", "migrations"])?;
+	let beet_net = pkg_ext::internal_or_beet("beet_net");
+
+	let id = attrs
+		.get_value_parsed::<syn::Member>("id")?
+		.unwrap_or_else(|| syn::parse_quote!(id));
+	let version = attrs.get_value_parsed::<syn::LitInt>("version")?;
+	// a versioned row read at an older version has nothing to upgrade it by
+	if let Some(version) = &version
+		&& version.base10_parse::<u32>()? > 0
+		&& attrs.get_value("migrations").is_none()
+	{
+		return Err(syn::Error::new_spanned(
+			version,
+			"a `version` above 0 requires `#[table(migrations = ..)]`",
+		));
+	}
+	let version =
+		version.map(|version| quote! { const VERSION: u32 = #version; });
+	let migrations = attrs.get_value("migrations").map(|migrations| {
+		quote! {
+			fn migrations() -> #beet_net::prelude::TableMigrations {
+				#migrations
+			}
+		}
+	});
+
+	Ok(quote! {
+		impl #impl_generics #beet_net::prelude::TableStoreRow
+			for #input_ident #type_generics #where_clause
+		{
+			#version
+			fn id(&self) -> #beet_net::prelude::Uuid { self.#id.into() }
+			#migrations
+		}
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use alloc::string::ToString;
+
+	#[test]
+	fn works() {
+		let result = parse_table_store_row(syn::parse_quote! {
+			#[table(version = 2)]
+			#[table(migrations = upgrades())]
+			struct Post {
+				id: Uuid,
+			}
+		})
+		.unwrap()
+		.to_string();
+
+		let expected = "impl beet_net :: prelude :: TableStoreRow for Post { const VERSION : u32 = 2 ; fn id (& self) -> beet_net :: prelude :: Uuid { self . id . into () } fn migrations () -> beet_net :: prelude :: TableMigrations { upgrades () } }";
+		assert_eq!(expected, result);
+	}
+
+	#[test]
+	fn version_requires_migrations() {
+		let err = parse_table_store_row(syn::parse_quote! {
+			#[table(version = 1)]
+			struct Post {
+				id: Uuid,
+			}
+		})
+		.unwrap_err()
+		.to_string();
+		assert!(err.contains("requires `#[table(migrations = ..)]`"));
+	}
+}
@@ -63,7 +63,8 @@ use core::str::FromStr;
 ///
 /// Secrets are deliberately absent: the type has no secret field, so no renderer
 /// can put one on an argv line, a `CMD` array or a systemd `ExecStart`. Secrets
-/// stay env on their existing channels.
+/// stay env on their existing channels; a knob may *name* one, as
+/// [`encryption_keys`](Self::encryption_keys) names the key env vars.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(default))]
@@ -115,6 +116,11 @@ pub struct BootstrapConfig {
 	/// The frame the screenshot harness captures on. `--screenshot-frame` /
 	/// `BEET_SCREENSHOT_FRAME`.
 	pub screenshot_frame: Option<u32>,
+	/// The ids of the store encryption keys, the first encrypting new objects
+	/// and every one decrypting, so a rotated-out key still reads. Each key's
+	/// material stays a secret in `BEET_ENCRYPTION_KEY_<ID>`.
+	/// `--encryption-keys` / `BEET_ENCRYPTION_KEYS`.
+	pub encryption_keys: Vec<SmolStr>,
 }
 
 /// Every field's static default, ie what an unset knob resolves to. Also the
@@ -142,6 +148,7 @@ impl Default for BootstrapConfig {
 			headless: false,
 			screenshot: None,
 			screenshot_frame: None,
+			encryption_keys: default(),
 		}
 	}
 }
@@ -236,6 +243,10 @@ impl BootstrapConfig {
 		arg: "screenshot-frame",
 		env: "BEET_SCREENSHOT_FRAME",
 	};
+	const ENCRYPTION_KEYS: Knob = Knob {
+		arg: "encryption-keys",
+		env: "BEET_ENCRYPTION_KEYS",
+	};
 
 	/// Parse this process's argv, with the `BEET_*` environment as the fallback for
 	/// every field argv did not set. Strict: a malformed value errors.
@@ -298,7 +309,7 @@ impl BootstrapConfig {
 
 	/// Every knob, in field order. The one enumeration of the table, so a new
 	/// field is either listed here or is not a knob at all.
-	const KNOBS: [Knob; 18] = [
+	const KNOBS: [Knob; 19] = [
 		Self::MAIN,
 		Self::STORE,
 		Self::WATCH,
@@ -317,6 +328,7 @@ impl BootstrapConfig {
 		Self::HEADLESS,
 		Self::SCREENSHOT,
 		Self::SCREENSHOT_FRAME,
+		Self::ENCRYPTION_KEYS,
 	];
 
 	/// The one parse. `env` resolves a `BEET_*` name, and is consulted only for a
@@ -372,6 +384,7 @@ impl BootstrapConfig {
 			headless: reader.flag(Self::HEADLESS),
 			screenshot: reader.value(Self::SCREENSHOT),
 			screenshot_frame: reader.parsed(Self::SCREENSHOT_FRAME)?,
+			encryption_keys: reader.list(Self::ENCRYPTION_KEYS),
 		}
 		.xok()
 	}
@@ -439,6 +452,11 @@ impl BootstrapConfig {
 			Self::FEATURES,
 			(!self.features.is_empty()).then(|| self.features.join(",")),
 		);
+		push(
+			Self::ENCRYPTION_KEYS,
+			(!self.encryption_keys.is_empty())
+				.then(|| self.encryption_keys.join(",")),
+		);
 		// bare flags: `--watch` parses back as a flag, so no value is rendered.
 		if self.watch {
 			argv.push(SmolStr::from(format!("--{}", Self::WATCH.arg)));
@@ -493,6 +511,11 @@ impl BootstrapConfig {
 			Self::FEATURES,
 			(!self.features.is_empty()).then(|| self.features.join(",")),
 		);
+		push(
+			Self::ENCRYPTION_KEYS,
+			(!self.encryption_keys.is_empty())
+				.then(|| self.encryption_keys.join(",")),
+		);
 		// presence is the signal for a flag, so any value parses back as `true`.
 		push(Self::WATCH, self.watch.then(|| "1".to_string()));
 		push(Self::HEADLESS, self.headless.then(|| "1".to_string()));
@@ -518,7 +541,8 @@ impl BootstrapConfig {
 	/// Split into the `(argv, env)` pair a platform encoder emits, each field on
 	/// its documented default channel: boot selection (the store, the server
 	/// selection, the opening path) is visible on argv, ambient service config
-	/// (the bind address, the ports, the stage, the deploy identity) rides env.
+	/// (the bind address, the ports, the stage, the deploy identity, the
+	/// encryption key ids) rides env.
 	///
 	/// The dev-harness fields (`main`, `watch`, `features`, `remote_url`,
 	/// `tls_dir`, `headless`, `screenshot*`) belong to neither deploy channel and
@@ -540,6 +564,7 @@ impl BootstrapConfig {
 			deploy_id: self.deploy_id,
 			deploy_timestamp: self.deploy_timestamp,
 			tls: self.tls,
+			encryption_keys: self.encryption_keys,
 			..default()
 		};
 		(argv, env)
@@ -787,6 +812,7 @@ mod test {
 			headless: true,
 			screenshot: Some("/tmp/shot.png".into()),
 			screenshot_frame: Some(30),
+			encryption_keys: vec!["k2".into(), "k1".into()],
 		}
 	}
 
@@ -913,6 +939,7 @@ mod test {
 				"BEET_DEPLOY_ID",
 				"BEET_DEPLOY_TIMESTAMP",
 				"BEET_TLS",
+				"BEET_ENCRYPTION_KEYS",
 			]);
 	}
 
@@ -20,7 +20,11 @@ use core::str::FromStr;
 ///    bucket. With an `endpoint` (eg a Cloudflare R2 account endpoint) the region
 ///    defaults to `auto`; without one it falls back to `AWS_REGION`, then
 ///    `us-west-2`.
-/// 4. `local-storage` / `indexed-db`: browser storage (wasm).
+/// 4. `sqlite://<path>`: a single-file SQLite database, relative paths
+///    resolved against the context dir (native, `sqlite` feature).
+/// 5. `local-storage` / `indexed-db`: browser storage (wasm).
+/// 6. `cache+<uri>`: any of the above behind an in-memory read-through cache,
+///    eg `cache+s3://my-site`.
 ///
 /// ## Example
 ///
@@ -54,10 +58,24 @@ pub enum StoreUri {
 		/// The bucket region, resolved at construction when absent.
 		region: Option<SmolStr>,
 	},
+	/// A single-file SQLite database, the store of a single-node deployment.
+	Sqlite {
+		/// The database file, absolute or relative to the resolution context
+		/// dir.
+		path: SmolStr,
+	},
 	/// Browser `localStorage` (wasm).
 	LocalStorage,
 	/// Browser IndexedDB (wasm).
 	IndexedDb,
+	/// Another store behind an in-memory read-through, write-through cache
+	/// of at most 64MiB, each entry refetched after a minute.
+	Cached {
+		/// The store the cache fronts. Opaque to reflection, which cannot
+		/// recurse into the boxed uri.
+		#[reflect(ignore)]
+		origin: Box<StoreUri>,
+	},
 }
 
 impl Default for StoreUri {
@@ -68,9 +86,24 @@ impl StoreUri {
 	/// Parse a store uri, erroring with the supported list on an unknown kind.
 	pub fn parse(value: &str) -> Result<Self> {
 		let value = value.trim();
+		if let Some(origin) = value.strip_prefix("cache+") {
+			if origin.is_empty() {
+				bevybail!("store `cache+` is missing an origin store");
+			}
+			return Self::Cached {
+				origin: Box::new(Self::parse(origin)?),
+			}
+			.xok();
+		}
 		if let Some(rest) = value.strip_prefix("s3://") {
 			return Self::parse_s3(rest);
 		}
+		if let Some(path) = value.strip_prefix("sqlite://") {
+			if path.is_empty() {
+				bevybail!("store `sqlite://` is missing a database path");
+			}
+			return Self::Sqlite { path: path.into() }.xok();
+		}
 		if let Some(path) = value.strip_prefix("fs:") {
 			let path = path.trim();
 			return Self::Fs {
@@ -85,8 +118,9 @@ impl StoreUri {
 			"indexed-db" => Self::IndexedDb,
 			other => bevybail!(
 				"unknown store `{other}`, supported kinds: fs, fs:<path>, \
-				memory, s3://<bucket>[?endpoint=..][&region=..], local-storage \
-				(wasm), indexed-db (wasm)"
+				memory, s3://<bucket>[?endpoint=..][&region=..], \
+				sqlite://<path>, local-storage (wasm), indexed-db (wasm), \
+				cache+<store>"
 			),
 		}
 		.xok()
@@ -119,11 +153,21 @@ impl StoreUri {
 		.xok()
 	}
 
-	/// Whether this store roots itself (a bucket or browser storage), needing no
-	/// local directory or filesystem walk. For these an entry name addresses the
-	/// document *within* the store and there is no live-reload watch dir.
+	/// Whether this store roots itself (a bucket, database or browser storage),
+	/// needing no local directory or filesystem walk. For these an entry name
+	/// addresses the document *within* the store and there is no live-reload
+	/// watch dir. A cached store is as rooted as its origin.
 	pub fn is_self_rooted(&self) -> bool {
-		matches!(self, Self::S3 { .. } | Self::LocalStorage | Self::IndexedDb)
+		match self {
+			Self::Cached { origin } => origin.is_self_rooted(),
+			other => matches!(
+				other,
+				Self::S3 { .. }
+					| Self::Sqlite { .. }
+					| Self::LocalStorage
+					| Self::IndexedDb
+			),
+		}
 	}
 }
 
@@ -138,8 +182,10 @@ impl fmt::Display for StoreUri {
 			Self::Fs { path: None } => write!(f, "fs"),
 			Self::Fs { path: Some(path) } => write!(f, "fs:{path}"),
 			Self::Memory => write!(f, "memory"),
+			Self::Sqlite { path } => write!(f, "sqlite://{path}"),
 			Self::LocalStorage => write!(f, "local-storage"),
 			Self::IndexedDb => write!(f, "indexed-db"),
+			Self::Cached { origin } => write!(f, "cache+{origin}"),
 			Self::S3 {
 				bucket,
 				endpoint,
@@ -177,9 +223,13 @@ mod test {
 			"local-storage",
 			"indexed-db",
 			"s3://my-bucket",
+			"sqlite://site.db",
+			"sqlite:///var/lib/beet/site.db",
 			"s3://my-bucket?region=us-east-1",
 			"s3://my-bucket?endpoint=https://acc.r2.cloudflarestorage.com",
 			"s3://my-bucket?endpoint=https://acc.r2.cloudflarestorage.com&region=auto",
+			"cache+s3://my-bucket?region=us-east-1",
+			"cache+fs:../site",
 		] {
 			StoreUri::parse(uri).unwrap().to_string().xpect_eq(uri);
 		}
@@ -198,13 +248,27 @@ mod test {
 				endpoint: Some("http://e".into()),
 				region: Some("r".into()),
 			});
+		StoreUri::parse("sqlite://data/site.db").unwrap().xpect_eq(
+			StoreUri::Sqlite {
+				path: "data/site.db".into(),
+			},
+		);
+		StoreUri::parse("cache+memory")
+			.unwrap()
+			.xpect_eq(StoreUri::Cached {
+				origin: Box::new(StoreUri::Memory),
+			});
 	}
 
-	/// Only a bucket or browser storage is self-rooted; a filesystem store needs
-	/// its resolution context dir.
+	/// Only a bucket, database or browser storage is self-rooted; a filesystem
+	/// store needs its resolution context dir.
 	#[crate::test]
 	fn self_rooted_kinds() {
 		StoreUri::parse("s3://b").unwrap().is_self_rooted().xpect_true();
+		StoreUri::parse("sqlite://site.db")
+			.unwrap()
+			.is_self_rooted()
+			.xpect_true();
 		StoreUri::parse("local-storage")
 			.unwrap()
 			.is_self_rooted()
@@ -215,6 +279,14 @@ mod test {
 			.xpect_true();
 		StoreUri::parse("fs").unwrap().is_self_rooted().xpect_false();
 		StoreUri::parse("memory").unwrap().is_self_rooted().xpect_false();
+		StoreUri::parse("cache+s3://b")
+			.unwrap()
+			.is_self_rooted()
+			.xpect_true();
+		StoreUri::parse("cache+fs")
+			.unwrap()
+			.is_self_rooted()
+			.xpect_false();
 	}
 
 	#[crate::test]
@@ -227,9 +299,17 @@ mod test {
 			.unwrap_err()
 			.to_string()
 			.xpect_contains("missing a bucket name");
+		StoreUri::parse("sqlite://")
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("missing a database path");
 		StoreUri::parse("s3://b?nope=1")
 			.unwrap_err()
 			.to_string()
 			.xpect_contains("unknown s3 store query param");
+		StoreUri::parse("cache+")
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("missing an origin store");
 	}
 }
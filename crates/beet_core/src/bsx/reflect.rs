@@ -183,6 +183,15 @@ fn scalar_to_reflect(
 	value: &Value,
 	field_info: Option<&'static TypeInfo>,
 ) -> Result<Box<dyn PartialReflect>> {
+	// a scalar targeting a `Value` field is kept as authored, so a markup
+	// `<SetBlackboard key="count" value=3/>` carries the literal itself rather
+	// than its natural reflect type, which cannot apply to the `Value` enum.
+	if let Some(info) = field_info
+		&& info.type_id() == TypeId::of::<Value>()
+	{
+		return Ok(Box::new(value.clone()));
+	}
+
 	// numeric coercion: read as f64 then cast to the field's concrete type id.
 	// A numeric string parses too (the quoted twin of the bare-number form), so
 	// a markup `port="0"` authors a numeric field directly.
@@ -803,6 +812,16 @@ mod test {
 		.xpect_eq(alloc::borrow::Cow::Borrowed("hi"));
 	}
 
+	/// A scalar targeting a `Value` field keeps the authored literal, so a markup
+	/// `value=3` or `value="idle"` lands as that value.
+	#[beet_core::test]
+	fn keeps_scalar_as_value() {
+		resolve::<Value>(DataLiteral::Scalar(Value::Uint(3)))
+			.xpect_eq(Value::Uint(3));
+		resolve::<Value>(DataLiteral::Scalar(Value::str("idle")))
+			.xpect_eq(Value::str("idle"));
+	}
+
 	#[beet_core::test]
 	fn wraps_scalar_into_option() {
 		resolve::<Option<String>>(DataLiteral::Scalar(Value::str("beet")))
@@ -292,6 +292,116 @@ pub async fn read_async(path: impl AsRef<Path>) -> FsResult<Vec<u8>> {
 	}
 }
 
+/// The size of a file in bytes, asynchronously.
+///
+/// Off native `fs` (eg the wasm runner) there is no metadata call, so the file
+/// is read to measure it.
+pub async fn file_size_async(path: impl AsRef<Path>) -> FsResult<u64> {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		fs_ext::read(path).map(|bytes| bytes.len() as u64)
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		async_fs::metadata(&path)
+			.await
+			.map(|meta| meta.len())
+			.map_err(|e| FsError::io(path, e))
+	}
+}
+
+/// When a file was last modified, asynchronously.
+///
+/// `None` where the platform does not record it, or off native `fs` (eg the
+/// wasm runner) where there is no metadata call.
+pub async fn modified_async(
+	path: impl AsRef<Path>,
+) -> FsResult<Option<Timestamp>> {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		fs_ext::exists(&path).map(|_| None)
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		let meta = async_fs::metadata(&path)
+			.await
+			.map_err(|e| FsError::io(&path, e))?;
+		meta.modified()
+			.ok()
+			.and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+			.map(Timestamp::from_unix_epoch_elapsed)
+			.xok()
+	}
+}
+
+/// A version of a file that changes whenever it is rewritten or replaced,
+/// asynchronously: its length, modified time in nanoseconds and, on unix, its
+/// inode, which a write renamed into place always changes.
+///
+/// `None` off native `fs` (eg the wasm runner) where there is no metadata call.
+pub async fn file_version_async(
+	path: impl AsRef<Path>,
+) -> FsResult<Option<String>> {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		fs_ext::exists(&path).map(|_| None)
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		let meta = async_fs::metadata(&path)
+			.await
+			.map_err(|e| FsError::io(&path, e))?;
+		let modified = meta
+			.modified()
+			.ok()
+			.and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+			.unwrap_or_default()
+			.as_nanos();
+		#[cfg(unix)]
+		let inode = std::os::unix::fs::MetadataExt::ino(&meta);
+		#[cfg(not(unix))]
+		let inode = 0u64;
+		Some(format!("{inode:x}-{modified:x}-{:x}", meta.len())).xok()
+	}
+}
+
+/// Reads the half-open byte `range` of a file, fewer bytes if the file ends
+/// first.
+///
+/// On native with the `fs` feature, seeks to the start and reads only the
+/// range. Otherwise falls back to reading the entire file and slicing it.
+pub async fn read_range_async(
+	path: impl AsRef<Path>,
+	range: core::ops::Range<u64>,
+) -> FsResult<Vec<u8>> {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		let bytes = fs_ext::read(path)?;
+		let end = (range.end as usize).min(bytes.len());
+		let start = (range.start as usize).min(end);
+		Ok(bytes[start..end].to_vec())
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		use futures_lite::io::AsyncReadExt;
+		use futures_lite::io::AsyncSeekExt;
+		let path = path.as_ref();
+		let len = range.end.saturating_sub(range.start);
+		let mut file = async_fs::File::open(path)
+			.await
+			.map_err(|e| FsError::io(path, e))?;
+		file.seek(std::io::SeekFrom::Start(range.start))
+			.await
+			.map_err(|e| FsError::io(path, e))?;
+		let mut buf = Vec::with_capacity(len as usize);
+		file.take(len)
+			.read_to_end(&mut buf)
+			.await
+			.map_err(|e| FsError::io(path, e))?;
+		Ok(buf)
+	}
+}
+
 /// Reads a file as a UTF-8 string.
 pub fn read_to_string(path: impl AsRef<Path>) -> FsResult<String> {
 	fs_ext::read(path.as_ref()).and_then(|bytes| {
@@ -484,6 +594,74 @@ pub async fn write_async(
 	}
 }
 
+/// Create a file holding `data` only if none exists at `path`, returning
+/// whether it was created, ensuring the parent exists.
+///
+/// On native with the `fs` feature this is one `create_new` open, so of two
+/// racing callers exactly one creates the file, which makes it usable as a
+/// lock file. Otherwise falls back to an existence check then a write.
+pub async fn create_new_async(
+	path: impl AsRef<Path>,
+	data: impl AsRef<[u8]>,
+) -> FsResult<bool> {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		if fs_ext::exists(&path)? {
+			return Ok(false);
+		}
+		fs_ext::write(path, data).map(|_| true)
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		use futures_lite::io::AsyncWriteExt;
+		let path = path.as_ref();
+		if let Some(parent) = path.parent() {
+			fs_ext::create_dir_all_async(parent).await?;
+		}
+		let mut file = match async_fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(path)
+			.await
+		{
+			Ok(file) => file,
+			Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+				return Ok(false);
+			}
+			Err(err) => return Err(FsError::io(path, err)),
+		};
+		file.write_all(data.as_ref())
+			.await
+			.map_err(|err| FsError::io(path, err))?;
+		// an async_fs file must be flushed before drop to finish the write
+		file.flush().await.map_err(|err| FsError::io(path, err))?;
+		Ok(true)
+	}
+}
+
+/// Rename a file, replacing any file at `to`.
+///
+/// On native with the `fs` feature the replace is atomic, so a reader sees
+/// the old file or the new one, never a partial write. Otherwise falls back
+/// to a copy then a remove.
+pub async fn rename_async(
+	from: impl AsRef<Path>,
+	to: impl AsRef<Path>,
+) -> FsResult {
+	#[cfg(not(all(feature = "fs", not(target_arch = "wasm32"))))]
+	{
+		fs_ext::write(&to, fs_ext::read(&from)?)?;
+		fs_ext::remove(from)
+	}
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	{
+		let from = from.as_ref();
+		async_fs::rename(from, to)
+			.await
+			.map_err(|err| FsError::io(from, err))
+	}
+}
+
 /// Write a file only if the data is different from the existing file.
 /// If the file does not exist it will be created.
 pub fn write_if_diff(
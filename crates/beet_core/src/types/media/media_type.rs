@@ -592,6 +592,32 @@ impl MediaType {
 	pub fn is_media(&self) -> bool {
 		self.is_image() || self.is_video() || self.is_audio()
 	}
+
+	/// Whether this format is already compressed, so a content coding
+	/// (gzip, brotli) would spend cpu for little or negative gain.
+	pub fn is_compressed(&self) -> bool {
+		matches!(
+			self,
+			MediaType::Png
+				| MediaType::Jpeg
+				| MediaType::Gif
+				| MediaType::Webp
+				| MediaType::Avif
+				| MediaType::Zip
+				| MediaType::Gzip
+				| MediaType::Pdf
+				| MediaType::Woff
+				| MediaType::Woff2
+				| MediaType::Mp3
+				| MediaType::Ogg
+				| MediaType::Flac
+				| MediaType::Aac
+				| MediaType::AudioWebm
+				| MediaType::Mp4
+				| MediaType::VideoWebm
+				| MediaType::VideoOgg
+		)
+	}
 }
 
 impl core::str::FromStr for MediaType {
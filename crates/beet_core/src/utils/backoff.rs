@@ -58,7 +58,11 @@ impl Default for Backoff {
 /// ```
 ///
 /// If you need lower-level control, you can also use [`Self::iter()`] or [`Self::stream()`].
-#[derive(Debug, Clone)]
+///
+/// Reflectable so a policy can be authored in markup, ie the `backoff` of a
+/// `Retry` control-flow component.
+#[derive(Debug, Clone, Reflect)]
+#[reflect(Default)]
 pub struct Backoff {
 	max_attempts: u32,
 	min: Duration,
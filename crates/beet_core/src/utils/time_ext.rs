@@ -86,8 +86,83 @@ pub fn format_iso8601(unix: Duration) -> String {
 	let millis = unix.subsec_millis();
 	let (hour, min, sec) =
 		((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
-	// days-to-civil (Howard Hinnant), valid for the unsigned epoch range
-	let days = secs / 86_400 + 719_468;
+	let (year, month, day) = civil_from_days(secs / 86_400);
+	format!(
+		"{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{millis:03}Z"
+	)
+}
+
+/// The day names of an HTTP date, Monday first (the Unix epoch was a Thursday).
+const HTTP_DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+/// The month names of an HTTP date.
+const HTTP_MONTHS: [&str; 12] = [
+	"Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+	"Nov", "Dec",
+];
+
+/// Formats a unix epoch [`Duration`] as an HTTP date (the RFC 9110 IMF-fixdate
+/// used by `Last-Modified`, `Expires` etc), eg `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// Second precision: the subsecond part is dropped, as the format has none.
+pub fn format_http_date(unix: Duration) -> String {
+	let secs = unix.as_secs();
+	let (hour, min, sec) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+	let days = secs / 86_400;
+	// day 0 (1970-01-01) was a Thursday
+	let weekday = HTTP_DAYS[((days + 3) % 7) as usize];
+	let (year, month, day) = civil_from_days(days);
+	let month = HTTP_MONTHS[(month - 1) as usize];
+	format!(
+		"{weekday}, {day:02} {month} {year:04} {hour:02}:{min:02}:{sec:02} GMT"
+	)
+}
+
+/// Parses an HTTP date in the IMF-fixdate form [`format_http_date`] writes
+/// back into a unix epoch [`Duration`].
+///
+/// The obsolete RFC 850 and asctime forms are rejected rather than guessed at:
+/// every current client sends IMF-fixdate, and a caller treating a date as a
+/// validator (`If-Modified-Since`) should ignore one it cannot read.
+pub fn parse_http_date(value: &str) -> Result<Duration> {
+	let invalid = || bevyhow!("invalid http date: {value}");
+	// `Sun, 06 Nov 1994 08:49:37 GMT`; the weekday is redundant and unchecked
+	let (_weekday, rest) = value.trim().split_once(", ").ok_or_else(invalid)?;
+	let mut parts = rest.split(' ');
+	let (Some(day), Some(month), Some(year), Some(time), Some("GMT"), None) = (
+		parts.next(),
+		parts.next(),
+		parts.next(),
+		parts.next(),
+		parts.next(),
+		parts.next(),
+	) else {
+		return Err(invalid());
+	};
+	let day: u64 = day.parse().map_err(|_| invalid())?;
+	let month = HTTP_MONTHS
+		.iter()
+		.position(|name| *name == month)
+		.ok_or_else(invalid)? as u64
+		+ 1;
+	let year: u64 = year.parse().map_err(|_| invalid())?;
+	let mut clock = time.split(':').map(|part| part.parse::<u64>());
+	let (Some(Ok(hour)), Some(Ok(min)), Some(Ok(sec)), None) =
+		(clock.next(), clock.next(), clock.next(), clock.next())
+	else {
+		return Err(invalid());
+	};
+	if year < 1970 || day == 0 || day > 31 || hour > 23 || min > 59 || sec > 60
+	{
+		return Err(invalid());
+	}
+	let days = days_from_civil(year, month, day);
+	Duration::from_secs(days * 86_400 + hour * 3600 + min * 60 + sec).xok()
+}
+
+/// Days since the Unix epoch to a `(year, month, day)` civil date
+/// (days-to-civil, Howard Hinnant), valid for the unsigned epoch range.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+	let days = days + 719_468;
 	let era = days / 146_097;
 	let doe = days - era * 146_097;
 	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
@@ -96,9 +171,19 @@ pub fn format_iso8601(unix: Duration) -> String {
 	let day = doy - (153 * mp + 2) / 5 + 1;
 	let month = if mp < 10 { mp + 3 } else { mp - 9 };
 	let year = yoe + era * 400 + (month <= 2) as u64;
-	format!(
-		"{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}.{millis:03}Z"
-	)
+	(year, month, day)
+}
+
+/// A post-1970 `(year, month, day)` civil date to days since the Unix epoch,
+/// the inverse of [`civil_from_days`].
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year / 400;
+	let yoe = year - era * 400;
+	let mp = if month > 2 { month - 3 } else { month + 9 };
+	let doy = (153 * mp + 2) / 5 + day - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	era * 146_097 + doe - 719_468
 }
 
 /// Formats a duration as a human-readable string with appropriate units.
@@ -263,6 +348,26 @@ mod test {
 			.xpect_eq("2024-02-29T00:00:00.000Z");
 	}
 
+	#[crate::test]
+	fn http_date_roundtrip() {
+		// the RFC 9110 example date
+		let date = Duration::from_secs(784_111_777);
+		time_ext::format_http_date(date)
+			.xpect_eq("Sun, 06 Nov 1994 08:49:37 GMT");
+		time_ext::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+			.unwrap()
+			.xpect_eq(date);
+		// leap year day, subseconds dropped
+		let leap = Duration::from_millis(1_709_164_800_500);
+		time_ext::format_http_date(leap)
+			.xpect_eq("Thu, 29 Feb 2024 00:00:00 GMT");
+		time_ext::parse_http_date(&time_ext::format_http_date(leap))
+			.unwrap()
+			.xpect_eq(Duration::from_secs(1_709_164_800));
+		// the obsolete RFC 850 form is rejected
+		time_ext::parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT").xpect_err();
+	}
+
 	#[cfg(not(target_arch = "wasm32"))]
 	#[crate::test]
 	fn timeout_sync_completes() {
@@ -7,7 +7,8 @@ use crate::terra::ResourceDef;
 use beet_core::prelude::*;
 use beet_net::prelude::*;
 
-/// A DynamoDB table with a single string hash key, provisioned pay-per-request.
+/// A DynamoDB table with a single string hash key, provisioned pay-per-request,
+/// and a global secondary index per declared [`TableIndex`].
 ///
 /// Mirrors [`S3BucketBlock`](crate::prelude::S3BucketBlock): the declaration
 /// carries only its `label`, and the `<app>--<stage>--<label>` name composes at
@@ -35,6 +36,12 @@ pub struct DynamoTableBlock {
 	/// crosses to the task env as a literal, not a field ref), so the layer is
 	/// what makes it exist before the service that names it rolls.
 	layer: SmolStr,
+	/// Global secondary indexes, emitted as the table's
+	/// `global_secondary_index` blocks and declared on the runtime
+	/// [`DynamoStore`](beet_net::prelude::DynamoStore) so a [`TableQuery`]
+	/// keyed on one is served by it rather than a scan.
+	#[serde(default)]
+	indexes: Vec<TableIndex>,
 }
 
 impl Default for DynamoTableBlock {
@@ -50,9 +57,40 @@ impl DynamoTableBlock {
 			hash_key: "id".into(),
 			region: crate::bindings::aws::region::DEFAULT.into(),
 			layer: terra::Config::STORAGE_LAYER.into(),
+			indexes: Vec::new(),
 		}
 	}
 
+	/// Declare a global secondary index on the table.
+	pub fn with_index(mut self, index: TableIndex) -> Self {
+		self.indexes.push(index);
+		self
+	}
+
+	/// The attribute definitions: the hash key then each distinct index key,
+	/// the only attributes DynamoDB types up front.
+	fn attributes(&self) -> Vec<AwsDynamodbTableResourceBlockTypeAttribute> {
+		let mut attributes = vec![AwsDynamodbTableResourceBlockTypeAttribute {
+			name: self.hash_key.clone(),
+			r#type: "S".into(),
+		}];
+		for key in self.indexes.iter().flat_map(|index| {
+			core::iter::once(&index.partition_key).chain(&index.sort_key)
+		}) {
+			if !attributes.iter().any(|attr| attr.name == key.field) {
+				attributes.push(AwsDynamodbTableResourceBlockTypeAttribute {
+					name: key.field.clone(),
+					r#type: match key.kind {
+						IndexKeyKind::String => "S",
+						IndexKeyKind::Number => "N",
+					}
+					.into(),
+				});
+			}
+		}
+		attributes
+	}
+
 	/// The composed table name this block declares, ie `beet-site--prod--analytics`.
 	pub fn table_name(&self, scope: &ResourceScope) -> String {
 		scope.resource_name(self.label.clone())
@@ -90,10 +128,19 @@ pub(crate) fn attach_table_store(
 				ServiceAccess::Remote => {
 					cfg_if! {
 						if #[cfg(feature = "aws_sdk")] {
-							entity.insert(beet_net::prelude::DynamoStore::new(
+							let store = beet_net::prelude::DynamoStore::new(
 								block.table_name(&scope),
 								block.region().clone(),
-							));
+							);
+							entity.insert(
+								block
+									.indexes()
+									.iter()
+									.cloned()
+									.fold(store, |store, index| {
+										store.with_index(index)
+									}),
+							);
 						} else {
 							bevybail!(
 								"the table declared as `{}` resolves to the remote `{}`, but this binary has no `aws_sdk` backend to reach it",
@@ -129,12 +176,25 @@ impl Block for DynamoTableBlock {
 			AwsDynamodbTableDetails {
 				billing_mode: Some("PAY_PER_REQUEST".into()),
 				hash_key: Some(self.hash_key.clone()),
-				attribute: Some(vec![
-					AwsDynamodbTableResourceBlockTypeAttribute {
-						name: self.hash_key.clone(),
-						r#type: "S".into(),
-					},
-				]),
+				attribute: Some(self.attributes()),
+				// pay-per-request, so the indexes carry no throughput
+				global_secondary_index: (!self.indexes.is_empty()).then(|| {
+					self.indexes
+						.iter()
+						.map(|index| {
+							AwsDynamodbTableResourceBlockTypeGlobalSecondaryIndex {
+								name: index.name.clone(),
+								hash_key: Some(index.partition_key.field.clone()),
+								range_key: index
+									.sort_key
+									.as_ref()
+									.map(|key| key.field.clone()),
+								projection_type: "ALL".into(),
+								..default()
+							}
+						})
+						.collect()
+				}),
 				region: Some(self.region.clone()),
 				..default()
 			},
@@ -183,4 +243,34 @@ mod test {
 			.xpect_contains("hash_key")
 			.xpect_contains("\"id\"");
 	}
+
+	/// A declared index emits a `global_secondary_index` and types its keys.
+	#[beet_core::test]
+	fn emits_global_secondary_index() {
+		let (stack, _dir) = Stack::default_local();
+		let mut config = stack.create_config();
+		let mut world = World::new();
+		DynamoTableBlock::new("users")
+			.with_index(
+				TableIndex::new("by_email", "email")
+					.with_sort_key(IndexKey::number("created")),
+			)
+			.apply_to_config(
+				&world.spawn(()).as_readonly(),
+				&stack,
+				&default(),
+				&mut config,
+			)
+			.unwrap();
+		config
+			.to_json()
+			.to_string()
+			.as_str()
+			.xpect_contains("global_secondary_index")
+			.xpect_contains("by_email")
+			.xpect_contains("\"email\"")
+			.xpect_contains("\"range_key\":\"created\"")
+			.xpect_contains("\"N\"")
+			.xpect_contains("\"ALL\"");
+	}
 }
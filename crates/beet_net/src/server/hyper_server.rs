@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use beet_core::prelude::*;
+use bevy::tasks::IoTaskPool;
 use bytes::Bytes;
 use futures::ready;
 use http_body_util::BodyExt;
@@ -9,6 +10,7 @@ use hyper::body::Frame;
 use hyper::rt::Sleep;
 use hyper::rt::Timer;
 use hyper::server::conn::http1;
+use hyper::server::conn::http2;
 use hyper::service::service_fn;
 use pin_project::pin_project;
 use std::convert::Infallible;
@@ -52,7 +54,7 @@ impl HttpServer {
 			.local_addr()
 			.map_err(|err| bevyhow!("Failed to get local address: {}", err))?;
 		// build the TLS acceptor (if any) before logging so the printed scheme is real
-		let tls = MaybeTls::resolve(&entity).await?;
+		let tls = MaybeTls::resolve(&entity).await?.with_alpn(&HTTP_ALPN);
 		info!("Server listening on {}://{}", tls.http_scheme(), addr);
 		// register the resolved port as the process loopback port when canonical (the
 		// mini server does the same), so an authority-less request loops back here. An
@@ -77,6 +79,18 @@ impl HttpServer {
 		.await
 	}
 }
+/// The ALPN protocols the hyper server negotiates over TLS, preferring h2.
+const HTTP_ALPN: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// The http version a connection speaks, decided before hyper sees it: by
+/// ALPN for TLS, by the connection preface for plaintext (prior-knowledge
+/// `h2c`, no `Upgrade: h2c` dance).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnVersion {
+	Http1,
+	Http2,
+}
+
 /// The hyper accept loop: serve each connection on its own spawned task. Diverges
 /// (only the shutdown race in [`HttpServer::start_hyper_with_tcp`] ends it).
 async fn hyper_accept_loop(
@@ -107,7 +121,9 @@ async fn hyper_accept_loop(
 /// Classify the connection's first bytes and dispatch, mirroring the mini
 /// server: TLS is accepted onto the hyper handler, plaintext is served for
 /// loopback peers and `307`-redirected to https for remote peers. Without
-/// [`Tls`] every connection takes the plaintext path untouched.
+/// [`Tls`] every connection takes the plaintext path untouched. Either path
+/// is served as http2 when the peer asked for it (ALPN `h2`, or the `h2c`
+/// preface), otherwise http1.
 async fn serve_sniffed(
 	entity: AsyncEntity,
 	tcp: async_io::Async<std::net::TcpStream>,
@@ -125,13 +141,23 @@ async fn serve_sniffed(
 						.unwrap_or_else(stream_sniff::tls_required_response);
 				return stream_sniff::write_and_close(replay, response).await;
 			}
-			serve_connection(entity, replay, addr).await
+			let version =
+				match stream_sniff::head_is_h2_preface(replay.prefix()) {
+					true => ConnVersion::Http2,
+					false => ConnVersion::Http1,
+				};
+			serve_connection(entity, replay, addr, version).await
 		}
 		SecureProtocol::Tls => {
 			#[cfg(feature = "secure")]
 			if let Some(server_tls) = tls.get() {
 				let tls_stream = server_tls.accept(replay).await?;
-				return serve_connection(entity, tls_stream, addr).await;
+				let version = match tls_stream.get_ref().1.alpn_protocol() {
+					Some(b"h2") => ConnVersion::Http2,
+					_ => ConnVersion::Http1,
+				};
+				return serve_connection(entity, tls_stream, addr, version)
+					.await;
 			}
 			debug!("TLS ClientHello on a plaintext listener, dropping");
 			Ok(())
@@ -139,11 +165,15 @@ async fn serve_sniffed(
 	}
 }
 
-/// Drive one (possibly TLS) connection through hyper's http1 machinery.
+/// Drive one (possibly TLS) connection through hyper's http1 or http2
+/// machinery. Every http2 stream is dispatched into the same
+/// [`exchange_child`](AsyncEntity::exchange_child) pipeline as an http1
+/// request, concurrently on the connection's thread.
 async fn serve_connection<S>(
 	entity: AsyncEntity,
 	stream: S,
 	addr: SocketAddr,
+	version: ConnVersion,
 ) -> Result
 where
 	S: 'static + Send + Unpin + futures::AsyncRead + futures::AsyncWrite,
@@ -169,16 +199,28 @@ where
 		}
 	});
 
-	// `.with_upgrades()`: keep the connection alive past the `101` so
-	// hyper can yield the upgraded IO to `hyper::upgrade::on`.
-	if let Err(err) = http1::Builder::new()
-		.timer(BevyTimer)
-		.header_read_timeout(Duration::from_secs(2))
-		// .keep_alive(false)
-		.serve_connection(io, service)
-		.with_upgrades()
-		.await
-	{
+	let result = match version {
+		// `.with_upgrades()`: keep the connection alive past the `101` so
+		// hyper can yield the upgraded IO to `hyper::upgrade::on`.
+		ConnVersion::Http1 => {
+			http1::Builder::new()
+				.timer(BevyTimer)
+				.header_read_timeout(Duration::from_secs(2))
+				// .keep_alive(false)
+				.serve_connection(io, service)
+				.with_upgrades()
+				.await
+		}
+		// websockets over h2 (RFC 8441) are not supported: an h2 request
+		// carries no `upgrade` header, so no route answers it with a `101`.
+		ConnVersion::Http2 => {
+			http2::Builder::new(LocalExecutor)
+				.timer(BevyTimer)
+				.serve_connection(io, service)
+				.await
+		}
+	};
+	if let Err(err) = result {
 		if err.is_timeout() && err.xfmt_debug() == "hyper::Error(HeaderTimeout)"
 		{
 			trace!("Connection closed due to header timeout (normal behavior)");
@@ -245,7 +287,7 @@ async fn hyper_to_request(
 	// tag the direct peer address so a router middleware (eg analytics) can read
 	// the client address (a proxy's `x-forwarded-for` takes precedence downstream).
 	Request::from_parts(RequestParts::from(parts), body)
-		.with_header_raw(PEER_ADDR_HEADER, &peer_addr.to_string())
+		.with_peer_addr(peer_addr)
 }
 
 async fn response_to_hyper(
@@ -404,6 +446,19 @@ where
 	}
 }
 
+/// The hyper executor for http2 streams: each stream's future is spawned on
+/// the [`IoTaskPool`] thread-local executor, the thread the connection already
+/// runs on, so the (`!Send`) service futures need not cross threads.
+#[derive(Clone, Copy, Debug)]
+struct LocalExecutor;
+
+impl<F> hyper::rt::Executor<F> for LocalExecutor
+where
+	F: 'static + Future,
+{
+	fn execute(&self, fut: F) { IoTaskPool::get().spawn_local(fut).detach(); }
+}
+
 #[derive(Clone, Debug)]
 struct BevyTimer;
 
@@ -667,4 +722,157 @@ mod upgrade_test {
 		landed.get().len().xpect_eq(1usize);
 		client.close(None).await.ok();
 	}
+
+	/// A plaintext peer opening with the http2 preface is served h2c: the
+	/// server answers the preface with its own `SETTINGS` frame.
+	#[beet_core::test]
+	async fn h2c_prior_knowledge() {
+		use futures::AsyncReadExt;
+		use futures::AsyncWriteExt;
+		let server = HttpServer::new_test(HttpServer::start_hyper_with_tcp);
+		let port = server.0.port.unwrap();
+		let _handle = std::thread::spawn(|| {
+			App::new()
+				.add_plugins((MinimalPlugins, ServerPlugin))
+				.spawn((server, children![exchange_ext::mirror()]))
+				.run();
+		});
+		time_ext::sleep_millis(100).await;
+		let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+		let mut stream = async_io::Async::<std::net::TcpStream>::connect(addr)
+			.await
+			.unwrap();
+		// the preface, then an empty client `SETTINGS` frame
+		let mut preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+		preface.extend_from_slice(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0]);
+		stream.write_all(&preface).await.unwrap();
+		let mut frame_head = [0u8; 9];
+		stream.read_exact(&mut frame_head).await.unwrap();
+		// frame type `0x4`: SETTINGS, on the connection stream
+		frame_head[3].xpect_eq(0x4);
+		frame_head[5..9].xpect_eq([0, 0, 0, 0]);
+	}
+}
+
+/// http2 end to end: a request sent through hyper's own h2 client reaches the
+/// handler pipeline, in plaintext by prior knowledge and over TLS by ALPN.
+#[cfg(test)]
+mod http2_test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use bytes::Bytes;
+	use http_body_util::BodyExt;
+	use http_body_util::Empty;
+	use std::net::SocketAddr;
+
+	/// Runs the hyper client's futures on threads of their own, the test
+	/// thread having no bevy executor to tick them.
+	#[derive(Clone, Copy)]
+	struct ThreadExecutor;
+
+	impl<F> hyper::rt::Executor<F> for ThreadExecutor
+	where
+		F: 'static + Send + Future,
+		F::Output: 'static + Send,
+	{
+		fn execute(&self, fut: F) {
+			std::thread::spawn(move || futures_lite::future::block_on(fut));
+		}
+	}
+
+	/// Spawn a hyper server answering every request with `h2 hello`, with the
+	/// extra `bundle` on the server entity.
+	fn serve(bundle: impl Bundle) -> SocketAddr {
+		let server = HttpServer::new_test(HttpServer::start_hyper_with_tcp);
+		let port = server.0.port.unwrap();
+		std::thread::spawn(move || {
+			App::new()
+				.add_plugins((MinimalPlugins, ServerPlugin))
+				.spawn((server, bundle, children![exchange_ext::handler(
+					|_| { Response::ok().with_body("h2 hello") }
+				)]))
+				.run();
+		});
+		([127, 0, 0, 1], port).into()
+	}
+
+	/// Send one h2 `GET /` over `stream`, returning the response status,
+	/// version and body.
+	async fn h2_get<S>(
+		stream: S,
+		uri: String,
+	) -> (hyper::StatusCode, hyper::Version, String)
+	where
+		S: 'static + Send + Unpin + futures::AsyncRead + futures::AsyncWrite,
+	{
+		let (mut sender, connection) = hyper::client::conn::http2::handshake(
+			ThreadExecutor,
+			super::BevyIo::new(stream),
+		)
+		.await
+		.unwrap();
+		hyper::rt::Executor::execute(&ThreadExecutor, async move {
+			connection.await.ok();
+		});
+		let request = hyper::Request::get(uri)
+			.body(Empty::<Bytes>::new())
+			.unwrap();
+		let response = sender.send_request(request).await.unwrap();
+		let status = response.status();
+		let version = response.version();
+		let body = response.into_body().collect().await.unwrap().to_bytes();
+		(status, version, String::from_utf8(body.to_vec()).unwrap())
+	}
+
+	#[beet_core::test]
+	async fn h2c_request_reaches_handler() {
+		let addr = serve(());
+		time_ext::sleep_millis(100).await;
+		let stream = async_io::Async::<std::net::TcpStream>::connect(addr)
+			.await
+			.unwrap();
+		let (status, version, body) =
+			h2_get(stream, format!("http://{addr}/")).await;
+		status.xpect_eq(hyper::StatusCode::OK);
+		version.xpect_eq(hyper::Version::HTTP_2);
+		body.xpect_eq("h2 hello");
+	}
+
+	#[cfg(feature = "secure")]
+	#[beet_core::test]
+	async fn tls_negotiates_h2() {
+		use crate::tls::test_client;
+		let addr = serve(Tls::default());
+		time_ext::sleep_millis(300).await;
+
+		// offered both, the server picks h2 and serves the request over it
+		let stream =
+			test_client::connect_with_alpn(addr, &[b"http/1.1", b"h2"])
+				.await
+				.unwrap();
+		stream
+			.get_ref()
+			.1
+			.alpn_protocol()
+			.xpect_eq(Some(&b"h2"[..]));
+		let (status, version, body) =
+			h2_get(stream, format!("https://{addr}/")).await;
+		status.xpect_eq(hyper::StatusCode::OK);
+		version.xpect_eq(hyper::Version::HTTP_2);
+		body.xpect_eq("h2 hello");
+
+		// a client without h2 is served http/1.1
+		let stream = test_client::connect_with_alpn(addr, &[b"http/1.1"])
+			.await
+			.unwrap();
+		stream
+			.get_ref()
+			.1
+			.alpn_protocol()
+			.xpect_eq(Some(&b"http/1.1"[..]));
+		test_client::raw_get(stream, "/")
+			.await
+			.unwrap()
+			.xpect_contains("h2 hello");
+	}
 }
@@ -204,8 +204,7 @@ where
 
 	// Parse the raw HTTP request into our Request type, tagging the direct peer
 	// address so a router middleware (eg analytics) can read the client address.
-	let request = http_ext::parse_http_request(&buf)?
-		.with_header_raw(PEER_ADDR_HEADER, &peer_addr.to_string());
+	let request = http_ext::parse_http_request(&buf)?.with_peer_addr(peer_addr);
 
 	// Dispatch through the router child
 	let response: Response = entity.exchange_child(request).await;
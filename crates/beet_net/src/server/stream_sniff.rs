@@ -144,6 +144,17 @@ pub fn head_is_websocket_upgrade(head: &[u8]) -> bool {
 		.unwrap_or(false)
 }
 
+/// The first line of the HTTP/2 connection preface (RFC 9113 §3.4). A
+/// plaintext peer opening with it speaks prior-knowledge `h2c`; the sniff
+/// stops at its blank line, so only this much is guaranteed to be buffered.
+pub const H2_PREFACE_HEAD: &[u8] = b"PRI * HTTP/2.0\r\n\r\n";
+
+/// Whether a sniffed plaintext head is the HTTP/2 connection preface, so the
+/// listener hands the connection to an http2 server rather than http1.
+pub fn head_is_h2_preface(head: &[u8]) -> bool {
+	head.starts_with(H2_PREFACE_HEAD)
+}
+
 /// The `307` redirecting a plaintext request to the same authority over
 /// https, serialized and ready to write. `None` when the head has no `host`
 /// header to redirect to (the caller answers 400 instead). Temporary (not
@@ -298,6 +309,12 @@ mod tests {
 		head_is_websocket_upgrade(GET_HEAD).xpect_false();
 	}
 
+	#[beet_core::test]
+	async fn detects_h2_preface() {
+		head_is_h2_preface(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").xpect_true();
+		head_is_h2_preface(GET_HEAD).xpect_false();
+	}
+
 	#[beet_core::test]
 	async fn builds_redirect() {
 		let redirect = https_redirect_response(GET_HEAD).unwrap();
@@ -28,6 +28,49 @@ pub fn client_ip(headers: &HeaderMap) -> Option<std::net::IpAddr> {
 	}
 }
 
+/// The client ip for enforcement, ie a rate limit: the direct peer address,
+/// trusting a proxy's forwarded headers only when that peer is one of
+/// `trusted_proxies`. Any client can send `x-forwarded-for` itself, so
+/// [`client_ip`] suits attribution but not a quota.
+///
+/// Behind a trusted proxy the client is its `cf-connecting-ip`, else the
+/// right-most `x-forwarded-for` hop that is not itself a trusted proxy: hops
+/// further left were appended by whoever sent the request, so are only as
+/// trustworthy as that client. `None` without a parseable peer address.
+pub fn trusted_client_ip(
+	headers: &HeaderMap,
+	trusted_proxies: &[std::net::IpAddr],
+) -> Option<std::net::IpAddr> {
+	let peer = headers
+		.first_raw(PEER_ADDR_HEADER)
+		.and_then(|peer| peer.parse::<std::net::SocketAddr>().ok())
+		.map(|peer| peer.ip())?;
+	if !trusted_proxies.contains(&peer) {
+		return Some(peer);
+	}
+	if let Some(connecting) = headers
+		.first_raw("cf-connecting-ip")
+		.and_then(|ip| ip.trim().parse().ok())
+	{
+		return Some(connecting);
+	}
+	let hops = headers
+		.get_raw("x-forwarded-for")
+		.into_iter()
+		.flatten()
+		.flat_map(|line| line.split(','))
+		.collect::<Vec<_>>();
+	for hop in hops.into_iter().rev() {
+		match hop.trim().parse::<std::net::IpAddr>() {
+			Ok(ip) if trusted_proxies.contains(&ip) => continue,
+			Ok(ip) => return Some(ip),
+			// a malformed hop was not appended by a proxy, stop trusting here
+			Err(_) => break,
+		}
+	}
+	Some(peer)
+}
+
 /// The [`SESSION_COOKIE`] value parsed as a session id, searching every `Cookie`
 /// header line's `key=value` pairs.
 pub fn session_from_cookies(headers: &HeaderMap) -> Option<Uuid> {
@@ -70,4 +113,32 @@ mod test {
 			.as_str()
 			.xpect_eq("198.51.100.4");
 	}
+
+	#[beet_core::test]
+	fn trusted_client_ip_ignores_untrusted_headers() {
+		let proxy: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+		let mut headers = HeaderMap::default();
+		headers.set_raw(PEER_ADDR_HEADER, "192.0.2.9:443");
+		headers.set_raw("x-forwarded-for", "203.0.113.7");
+		headers.set_raw("cf-connecting-ip", "198.51.100.4");
+		// a direct client's forwarded headers are its own invention
+		trusted_client_ip(&headers, &[proxy])
+			.unwrap()
+			.to_string()
+			.xpect_eq("192.0.2.9");
+
+		let mut headers = HeaderMap::default();
+		headers.set_raw(PEER_ADDR_HEADER, "10.0.0.1:443");
+		headers.set_raw("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.1");
+		// the right-most hop the trusted proxy did not add itself
+		trusted_client_ip(&headers, &[proxy])
+			.unwrap()
+			.to_string()
+			.xpect_eq("203.0.113.7");
+		headers.set_raw("cf-connecting-ip", "198.51.100.4");
+		trusted_client_ip(&headers, &[proxy])
+			.unwrap()
+			.to_string()
+			.xpect_eq("198.51.100.4");
+	}
 }
@@ -44,18 +44,7 @@ impl AnalyticsStore {
 	/// else the default region. Used by `beet analytics --remote` to query
 	/// without a scene; errors without the `aws_sdk` backend.
 	pub fn remote(table_name: &str) -> Result<Self> {
-		cfg_if! {
-			if #[cfg(all(feature = "aws_sdk", not(target_arch = "wasm32")))] {
-				Self::new(Table::new(DynamoStore::new(
-					table_name,
-					DynamoStore::env_region(),
-				)))
-				.xok()
-			} else {
-				let _ = table_name;
-				bevybail!("a remote analytics store requires the `aws_sdk` feature")
-			}
-		}
+		Self::new(TableStore::remote(table_name)?.table()).xok()
 	}
 
 	/// Record `event`, raising exactly once for a store that will not answer.
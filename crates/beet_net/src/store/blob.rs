@@ -68,13 +68,10 @@ impl Blob {
 		self.store.insert(&self.path, body.into()).await
 	}
 
-	/// Insert the blob's content, failing if it already exists.
+	/// Insert the blob's content, failing if it already exists. Atomic where
+	/// the backend supports it, see [`BlobStoreProvider::insert_if_absent`].
 	pub async fn try_insert(&self, body: impl Into<Bytes>) -> Result {
-		if self.exists().await? {
-			bevybail!("Object already exists: {}", self.path)
-		} else {
-			self.insert(body).await
-		}
+		self.store.insert_if_absent(&self.path, body.into()).await
 	}
 
 	/// Retrieve the blob's content.
@@ -237,13 +234,10 @@ where
 		self.store.insert(&self.path, body.into()).await
 	}
 
-	/// Insert the blob's content, failing if it already exists.
+	/// Insert the blob's content, failing if it already exists. Atomic where
+	/// the backend supports it, see [`BlobStoreProvider::insert_if_absent`].
 	pub async fn try_insert(&self, body: impl Into<Bytes>) -> Result {
-		if self.exists().await? {
-			bevybail!("Object already exists: {}", self.path)
-		} else {
-			self.insert(body).await
-		}
+		self.store.insert_if_absent(&self.path, body.into()).await
 	}
 
 	/// Retrieve the blob's content.
@@ -87,6 +87,18 @@ impl BlobStore {
 				endpoint,
 				region,
 			} => Self::s3_from_uri(bucket, endpoint.as_deref(), region.as_deref()),
+			StoreUri::Sqlite { path } => {
+				Self::sqlite_from_uri(dir.join(path.as_str()))
+			}
+			StoreUri::Cached { origin } => BlobStore::new(
+				CachedStore::new(
+					Self::from_uri(origin, dir)?,
+					InMemoryStore::new(),
+				)
+				.with_max_bytes(CachedStore::DEFAULT_MAX_BYTES)
+				.with_ttl(CachedStore::DEFAULT_TTL),
+			)
+			.xok(),
 			#[cfg(target_arch = "wasm32")]
 			StoreUri::LocalStorage => {
 				BlobStore::new(LocalStorageStore::new("beet")).xok()
@@ -148,6 +160,27 @@ impl BlobStore {
 		)
 	}
 
+	/// The [`SqliteStore`]-backed store for a `sqlite://` uri, the database file
+	/// created on first use.
+	#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+	fn sqlite_from_uri(path: AbsPathBuf) -> Result<BlobStore> {
+		info!("entry store: sqlite database `{path}`");
+		BlobStore::new(SqliteStore::new(path)).xok()
+	}
+
+	/// Without a compiled SQLite backend the request errors with guidance
+	/// rather than degrading.
+	#[cfg(all(
+		feature = "std",
+		not(all(feature = "sqlite", not(target_arch = "wasm32")))
+	))]
+	fn sqlite_from_uri(_path: AbsPathBuf) -> Result<BlobStore> {
+		bevybail!(
+			"a sqlite:// store requires a compiled SQLite backend (enable the \
+			`sqlite` feature, native only)"
+		)
+	}
+
 	/// Returns a new store scoped to the given subdirectory.
 	pub fn with_subdir(&self, path: SmolPath) -> BlobStore {
 		BlobStore::from_arc(Arc::from(self.provider.with_subdir(path)))
@@ -230,7 +263,8 @@ impl BlobStore {
 		self.provider.insert(path, body.into()).await
 	}
 
-	/// Insert object, failing if it already exists.
+	/// Insert object, failing if it already exists. Atomic where the backend
+	/// supports it, see [`BlobStoreProvider::insert_if_absent`].
 	///
 	/// # Example
 	/// ```
@@ -247,11 +281,7 @@ impl BlobStore {
 		path: &SmolPath,
 		body: impl Into<Bytes>,
 	) -> Result {
-		if self.exists(path).await? {
-			bevybail!("Object already exists: {}", path)
-		} else {
-			self.insert(path, body).await
-		}
+		self.provider.insert_if_absent(path, body.into()).await
 	}
 
 	/// Get all objects and their data.
@@ -312,18 +342,96 @@ impl BlobStoreProvider for BlobStore {
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
 		self.provider.insert(path, body)
 	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.provider.insert_with_meta(path, body, meta)
+	}
 	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
 		self.provider.list()
 	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		self.provider.list_prefix(prefix, delimiter, cursor, limit)
+	}
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		self.provider.get(path)
 	}
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		self.provider.head(path)
+	}
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		self.provider.insert_if_absent(path, body)
+	}
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.provider.insert_with_meta_if_absent(path, body, meta)
+	}
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		self.provider.insert_if_match(path, body, etag)
+	}
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		self.provider.get_range(path, range)
+	}
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		self.provider.exists(path)
 	}
 	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
 		self.provider.remove(path)
 	}
+	fn keeps_versions(&self) -> bool { self.provider.keeps_versions() }
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		self.provider.list_versions(path)
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		self.provider.get_version(path, version)
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.provider.remove_version(path, version)
+	}
+	fn restore(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.provider.restore(path, version)
+	}
 	fn public_url(
 		&self,
 		path: &SmolPath,
@@ -354,6 +462,110 @@ pub mod store_test {
 		store.list().await.unwrap().xpect_eq(vec![path.clone()]);
 		store.get(&path).await.unwrap().xpect_eq(body.clone());
 		store.get(&path).await.unwrap().xpect_eq(body);
+		// "test_body": a native ranged read agrees with slicing the whole object
+		let part = store
+			.get_range(&path, header::ByteRange::FromTo(5, 99))
+			.await
+			.unwrap();
+		part.total.xpect_eq(9);
+		part.range.xpect_eq(Some(5..9));
+		part.bytes.xpect_eq(bytes::Bytes::from("body"));
+		store
+			.get_range(&path, header::ByteRange::From(9))
+			.await
+			.unwrap()
+			.range
+			.xpect_none();
+		// a prefix listing groups `dir/sub/` and pages by the cursor
+		let nested = ["dir/a.txt", "dir/b.txt", "dir/sub/c.txt"];
+		for key in nested {
+			store.insert(&SmolPath::from(key), "nested").await.unwrap();
+		}
+		let root = store.list_prefix("", Some("/"), None, None).await.unwrap();
+		root.paths.xpect_eq(vec![path.clone()]);
+		root.common_prefixes.xpect_eq(vec![SmolStr::from("dir/")]);
+		root.cursor.xpect_none();
+		let first = store
+			.list_prefix("dir/", Some("/"), None, Some(2))
+			.await
+			.unwrap();
+		first.paths.xpect_eq(vec![
+			SmolPath::from("dir/a.txt"),
+			SmolPath::from("dir/b.txt"),
+		]);
+		let last = store
+			.list_prefix("dir/", Some("/"), first.cursor.as_deref(), Some(2))
+			.await
+			.unwrap();
+		last.paths.xpect_empty();
+		last.common_prefixes
+			.xpect_eq(vec![SmolStr::from("dir/sub/")]);
+		last.cursor.xpect_none();
+		store
+			.list_prefix("dir/", None, None, None)
+			.await
+			.unwrap()
+			.paths
+			.len()
+			.xpect_eq(3);
+		for key in nested {
+			store.remove(&SmolPath::from(key)).await.unwrap();
+		}
+
+		// a head reads the size, content type and an etag that tracks the body
+		let meta_path = SmolPath::from("meta.json");
+		store
+			.insert_with_meta(
+				&meta_path,
+				"{}".into(),
+				InsertMeta::default().with_content_type(MediaType::Json),
+			)
+			.await
+			.unwrap();
+		let meta = store.head(&meta_path).await.unwrap();
+		meta.size.xpect_eq(2);
+		meta.content_type.xpect_eq(MediaType::Json);
+		store
+			.insert(&meta_path, "{\"changed\":true}".into())
+			.await
+			.unwrap();
+		let changed = store.head(&meta_path).await.unwrap();
+		changed.size.xpect_eq(16);
+		changed.etag.xpect_some();
+		(changed.etag != meta.etag).xpect_true();
+		// conditional writes refuse an existing object and a stale etag
+		store
+			.insert_if_absent(&meta_path, "{}".into())
+			.await
+			.unwrap_err()
+			.xmap(|err| HttpError::status_of(&err))
+			.xpect_eq(Some(StatusCode::PRECONDITION_FAILED));
+		let stale = meta.etag.unwrap_or_default();
+		store
+			.insert_if_match(&meta_path, "[]".into(), &stale)
+			.await
+			.xpect_err();
+		let current = changed.etag.unwrap_or_default();
+		store
+			.insert_if_match(&meta_path, "[]".into(), &current)
+			.await
+			.unwrap();
+		store
+			.get(&meta_path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("[]"));
+		store.remove(&meta_path).await.unwrap();
+		store.head(&meta_path).await.xpect_err();
+		store
+			.insert_if_match(&meta_path, "[]".into(), &current)
+			.await
+			.xpect_err();
+		store
+			.insert_if_absent(&meta_path, "{}".into())
+			.await
+			.unwrap();
+		store.remove(&meta_path).await.unwrap();
 
 		store.remove(&path).await.unwrap();
 		store.get(&path).await.xpect_err();
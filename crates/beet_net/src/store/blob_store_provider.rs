@@ -1,6 +1,216 @@
 use crate::prelude::*;
+use alloc::collections::BTreeMap;
 use beet_core::prelude::*;
 use bytes::Bytes;
+use core::ops::Bound;
+
+/// The result of a ranged read ([`BlobStoreProvider::get_range`]): the
+/// object's full length and the bytes of the range resolved against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobRange {
+	/// The full object length.
+	pub total: u64,
+	/// The half-open byte range read, or `None` if the requested range
+	/// selects nothing from a `total`-byte object (unsatisfiable).
+	pub range: Option<core::ops::Range<u64>>,
+	/// The bytes of `range`, empty when unsatisfiable.
+	pub bytes: Bytes,
+}
+
+impl BlobRange {
+	/// Slices `range` out of a whole object, the read a provider without a
+	/// native ranged get falls back to.
+	pub fn slice(object: Bytes, range: header::ByteRange) -> Self {
+		let total = object.len() as u64;
+		let range = range.resolve(total);
+		let bytes = range
+			.clone()
+			.map(|range| object.slice(range.start as usize..range.end as usize))
+			.unwrap_or_default();
+		Self {
+			total,
+			range,
+			bytes,
+		}
+	}
+
+	/// The unsatisfiable result for a `total`-byte object.
+	pub fn unsatisfiable(total: u64) -> Self {
+		Self {
+			total,
+			range: None,
+			bytes: Bytes::new(),
+		}
+	}
+}
+
+/// Metadata of a stored object, read without its body
+/// ([`BlobStoreProvider::head`]).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlobMeta {
+	/// The object length in bytes.
+	pub size: u64,
+	/// The content type stored on insert, else inferred from the path
+	/// extension.
+	pub content_type: MediaType,
+	/// An opaque tag that changes whenever the body does, ie an S3 `ETag`, a
+	/// content hash, or a filesystem stat fingerprint. Only comparable between
+	/// reads of the same backend.
+	pub etag: Option<SmolStr>,
+	/// When the object was last written, if the backend records it.
+	pub modified: Option<Timestamp>,
+	/// User metadata stored on insert, empty for a backend without a metadata
+	/// model (ie the filesystem).
+	pub metadata: BTreeMap<SmolStr, SmolStr>,
+}
+
+impl BlobMeta {
+	/// The metadata a backend without a native `head` derives from the whole
+	/// object: its length, the content type of its path and a content hash.
+	pub fn from_bytes(path: &SmolPath, bytes: &[u8]) -> Self {
+		Self {
+			size: bytes.len() as u64,
+			content_type: path.media_type().unwrap_or(MediaType::Bytes),
+			etag: Some(content_etag(bytes)),
+			..default()
+		}
+	}
+}
+
+/// An etag for a backend that has none of its own: the 64 bit FNV-1a hash of
+/// the body, stable across processes and platforms.
+pub fn content_etag(bytes: &[u8]) -> SmolStr {
+	let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+	});
+	format!("{hash:016x}").into()
+}
+
+/// What to store alongside a body on
+/// [`insert_with_meta`](BlobStoreProvider::insert_with_meta).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InsertMeta {
+	/// The content type, served back by [`BlobStoreProvider::head`] in place
+	/// of one inferred from the path extension.
+	pub content_type: Option<MediaType>,
+	/// User metadata, ie S3 `x-amz-meta-*` headers.
+	pub metadata: BTreeMap<SmolStr, SmolStr>,
+}
+
+impl InsertMeta {
+	/// Store `content_type` with the body.
+	pub fn with_content_type(mut self, content_type: MediaType) -> Self {
+		self.content_type = Some(content_type);
+		self
+	}
+
+	/// Store a user metadata entry with the body.
+	pub fn with_metadata(
+		mut self,
+		key: impl Into<SmolStr>,
+		value: impl Into<SmolStr>,
+	) -> Self {
+		self.metadata.insert(key.into(), value.into());
+		self
+	}
+}
+
+/// A prior version of an object ([`BlobStoreProvider::list_versions`]).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlobVersion {
+	/// Pass to [`get_version`](BlobStoreProvider::get_version) or
+	/// [`restore`](BlobStoreProvider::restore), ie an S3 `VersionId`.
+	pub id: SmolStr,
+	/// The version length in bytes.
+	pub size: u64,
+	/// When the version was superseded or written, if the backend records it.
+	pub modified: Option<Timestamp>,
+}
+
+/// One page of a prefix listing ([`BlobStoreProvider::list_prefix`]).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlobListing {
+	/// The objects of this page, relative to the store like
+	/// [`BlobStoreProvider::list`].
+	pub paths: Vec<SmolPath>,
+	/// The "directories" of this page: each distinct key prefix up to and
+	/// including the delimiter, standing in for every key continuing past it,
+	/// ie `photos/2024/`. Always empty without a delimiter.
+	pub common_prefixes: Vec<SmolStr>,
+	/// Pass back as the `cursor` for the next page, `None` on the last.
+	pub cursor: Option<SmolStr>,
+}
+
+impl BlobListing {
+	/// Answer a listing from every key in the store, the emulation a provider
+	/// without a native prefix listing falls back to.
+	///
+	/// Keys under `prefix` are grouped at the first `delimiter` past it into
+	/// common prefixes, then paged in key order: the cursor is the last key or
+	/// prefix returned, and the next page resumes strictly after it.
+	pub fn from_keys(
+		keys: impl IntoIterator<Item = SmolPath>,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> Self {
+		let mut entries = BTreeMap::<SmolStr, bool>::new();
+		for key in keys {
+			let key = key.to_string();
+			let Some(rest) = key.strip_prefix(prefix) else {
+				continue;
+			};
+			match delimiter
+				.filter(|delimiter| !delimiter.is_empty())
+				.and_then(|delimiter| {
+					rest.find(delimiter).map(|idx| idx + delimiter.len())
+				}) {
+				Some(end) => {
+					entries.insert(key[..prefix.len() + end].into(), true);
+				}
+				None => {
+					entries.insert(key.into(), false);
+				}
+			}
+		}
+		Self::from_entries(entries, cursor, limit)
+	}
+
+	/// Page sorted entries, each a key and whether it is a common prefix,
+	/// resuming strictly after `cursor` and holding at most `limit` (at least
+	/// one) entries.
+	pub(crate) fn from_entries(
+		entries: BTreeMap<SmolStr, bool>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> Self {
+		let start = match cursor {
+			Some(cursor) => Bound::Excluded(cursor),
+			None => Bound::Unbounded,
+		};
+		let mut remaining = entries.range::<str, _>((start, Bound::Unbounded));
+		let mut listing = Self::default();
+		let mut last = None;
+		for (key, is_prefix) in
+			remaining.by_ref().take(limit.unwrap_or(usize::MAX).max(1))
+		{
+			match is_prefix {
+				true => listing.common_prefixes.push(key.clone()),
+				false => listing.paths.push(SmolPath::new(key.as_str())),
+			}
+			last = Some(key.clone());
+		}
+		if remaining.next().is_some() {
+			listing.cursor = last;
+		}
+		listing
+	}
+}
 
 /// Trait for store storage backends (S3, filesystem, memory, etc.).
 ///
@@ -153,6 +363,38 @@ pub trait BlobStoreProvider: 'static + Send + Sync {
 	/// ```
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result>;
 
+	/// Insert object into store with a content type and user metadata, read
+	/// back by [`head`](Self::head).
+	///
+	/// The default drops the metadata and inserts the body alone, for a
+	/// backend with nowhere to keep it; its [`head`](Self::head) then infers
+	/// the content type from the path extension.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::temp();
+	/// let meta = InsertMeta::default()
+	/// 	.with_content_type(MediaType::Json)
+	/// 	.with_metadata("author", "beet");
+	/// store
+	/// 	.insert_with_meta(&SmolPath::from("data"), "{}".into(), meta)
+	/// 	.await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let _ = meta;
+		self.insert(path, body)
+	}
+
 	/// List all objects in store.
 	///
 	/// # Example
@@ -167,6 +409,56 @@ pub trait BlobStoreProvider: 'static + Send + Sync {
 	/// ```
 	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>>;
 
+	/// List one page of the objects whose path starts with `prefix`, grouping
+	/// those continuing past the next `delimiter` into common prefixes (the
+	/// "directories" of an S3 `ListObjectsV2`).
+	///
+	/// Pass the returned [`BlobListing::cursor`] back as `cursor` for the next
+	/// page; `limit` caps the paths plus prefixes of a page, which a backend
+	/// may cap further. The cursor is opaque and only valid for the same
+	/// `prefix` and `delimiter`.
+	///
+	/// The default pages [`BlobListing::from_keys`] over a full
+	/// [`list`](Self::list), fine for in-memory and browser stores; a backend
+	/// with a native listing (S3, R2, the filesystem) overrides it so a page
+	/// never reads the whole store.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::temp();
+	/// let page = store.list_prefix("photos/", Some("/"), None, Some(100)).await?;
+	/// for dir in page.common_prefixes {
+	/// 	println!("{dir}");
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		let list = self.list();
+		let prefix = prefix.to_string();
+		let delimiter = delimiter.map(str::to_string);
+		let cursor = cursor.map(str::to_string);
+		Box::pin(async move {
+			BlobListing::from_keys(
+				list.await?,
+				&prefix,
+				delimiter.as_deref(),
+				cursor.as_deref(),
+				limit,
+			)
+			.xok()
+		})
+	}
+
 	/// Get object from store.
 	///
 	/// # Example
@@ -181,6 +473,158 @@ pub trait BlobStoreProvider: 'static + Send + Sync {
 	/// ```
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>>;
 
+	/// Get a byte range of an object, along with the object's full length.
+	///
+	/// The default reads the whole object and slices it; backends with a
+	/// native ranged read (a file seek, an http `Range` get) override it so a
+	/// media player seeking a large file never downloads the rest.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::temp();
+	/// let range = header::ByteRange::FromTo(0, 99);
+	/// let part = store.get_range(&SmolPath::from("video.mp4"), range).await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		let get = self.get(path);
+		Box::pin(async move { BlobRange::slice(get.await?, range).xok() })
+	}
+
+	/// Get an object's [`BlobMeta`] without its body, ie to show sizes in a
+	/// listing or skip syncing an unchanged object.
+	///
+	/// The default reads the whole object and derives it with
+	/// [`BlobMeta::from_bytes`]; a backend with a native stat or `HEAD`
+	/// overrides it.
+	///
+	/// # Errors
+	/// Returns error if the object doesn't exist.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::temp();
+	/// let meta = store.head(&SmolPath::from("file.txt")).await?;
+	/// println!("{} bytes of {}", meta.size, meta.content_type);
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let get = self.get(path);
+		let path = path.clone();
+		Box::pin(async move { BlobMeta::from_bytes(&path, &get.await?).xok() })
+	}
+
+	/// Insert object only if none exists at `path`.
+	///
+	/// Atomic where the backend supports it (an S3 `If-None-Match` put, a
+	/// DynamoDB condition expression, an exclusive file create), so of two
+	/// racing writers exactly one succeeds. The default checks
+	/// [`exists`](Self::exists) then inserts, which two writers can race.
+	///
+	/// # Errors
+	/// Returns a [`StatusCode::PRECONDITION_FAILED`] [`HttpError`] if the
+	/// object already exists, see [`HttpError::status_of`].
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let exists_fut = self.exists(path);
+		let insert_fut = self.insert(path, body);
+		let path = path.clone();
+		Box::pin(async move {
+			if exists_fut.await? {
+				Err(HttpError::precondition_failed(format!(
+					"object already exists: {path}"
+				))
+				.into())
+			} else {
+				insert_fut.await
+			}
+		})
+	}
+
+	/// Insert object with a content type and user metadata only if none
+	/// exists at `path`, the [`insert_with_meta`](Self::insert_with_meta) of
+	/// [`insert_if_absent`](Self::insert_if_absent).
+	///
+	/// The default drops the metadata, as [`insert_with_meta`](Self::insert_with_meta)
+	/// does, and inserts through [`insert_if_absent`](Self::insert_if_absent),
+	/// so it is exactly as atomic as that.
+	///
+	/// # Errors
+	/// Returns a [`StatusCode::PRECONDITION_FAILED`] [`HttpError`] if the
+	/// object already exists, see [`HttpError::status_of`].
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let _ = meta;
+		self.insert_if_absent(path, body)
+	}
+
+	/// Replace object only if its current [`etag`](BlobMeta::etag) is `etag`,
+	/// the compare-and-swap half of a read-modify-write: [`head`](Self::head)
+	/// the object, read and change it, then write it back only if nobody wrote
+	/// in between.
+	///
+	/// Atomic where the backend supports it (an S3 `If-Match` put, a DynamoDB
+	/// condition expression, a lock file around the write). The default
+	/// compares [`head`](Self::head) then inserts, which two writers can race.
+	///
+	/// # Errors
+	/// Returns a [`StatusCode::PRECONDITION_FAILED`] [`HttpError`] if the
+	/// object changed or was removed since `etag` was read.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::temp();
+	/// let path = SmolPath::from("counter");
+	/// let etag = store.head(&path).await?.etag.unwrap_or_default();
+	/// store.insert_if_match(&path, "1".into(), &etag).await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let head_fut = self.head(path);
+		let insert_fut = self.insert(path, body);
+		let path = path.clone();
+		let etag = SmolStr::from(etag);
+		Box::pin(async move {
+			match head_fut.await {
+				Ok(meta) if meta.etag.as_ref() == Some(&etag) => {
+					insert_fut.await
+				}
+				_ => Err(HttpError::precondition_failed(format!(
+					"object changed since read: {path}"
+				))
+				.into()),
+			}
+		})
+	}
+
 	/// Check if object exists in store.
 	///
 	/// # Example
@@ -209,6 +653,88 @@ pub trait BlobStoreProvider: 'static + Send + Sync {
 	/// ```
 	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result>;
 
+	/// True if this store keeps prior versions of an object on every write,
+	/// ie a `VersionedStore` or a versioned S3 bucket.
+	fn keeps_versions(&self) -> bool { false }
+
+	/// The prior versions of `path`, newest first, excluding its current
+	/// content. A removed object keeps its versions, so it can be restored.
+	///
+	/// # Errors
+	/// Errors if the store keeps no versions, see
+	/// [`keeps_versions`](Self::keeps_versions).
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let store = BlobStore::new(VersionedStore::new(InMemoryStore::new()));
+	/// let path = SmolPath::from("notes.md");
+	/// store.insert(&path, "first").await?;
+	/// store.insert(&path, "second").await?;
+	/// let versions = store.list_versions(&path).await?;
+	/// store.restore(&path, &versions[0].id).await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		let _ = path;
+		let id = self.id();
+		Box::pin(async move { bevybail!("store `{id}` keeps no versions") })
+	}
+
+	/// Get the bytes of a prior version of `path`, by its
+	/// [`BlobVersion::id`].
+	///
+	/// # Errors
+	/// Errors if the store keeps no versions, or a [`StatusCode::NOT_FOUND`]
+	/// [`HttpError`] if there is no such version.
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		let _ = (path, version);
+		let id = self.id();
+		Box::pin(async move { bevybail!("store `{id}` keeps no versions") })
+	}
+
+	/// Discard a prior version of `path`, ie to prune the oldest.
+	///
+	/// # Errors
+	/// Errors if the store keeps no versions.
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		let _ = (path, version);
+		let id = self.id();
+		Box::pin(async move { bevybail!("store `{id}` keeps no versions") })
+	}
+
+	/// Make a prior version the current content of `path`. This is itself a
+	/// write, so on a store keeping versions the content it replaces becomes
+	/// a version in turn, and the restore can be undone.
+	///
+	/// # Errors
+	/// Errors if the version cannot be read, see
+	/// [`get_version`](Self::get_version).
+	fn restore(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		let get_fut = self.get_version(path, version);
+		let store = self.box_clone();
+		let path = path.clone();
+		Box::pin(async move { store.insert(&path, get_fut.await?).await })
+	}
+
 	/// Get public URL of object.
 	/// - fs: `file:///data/stores/my-store/key`
 	/// - s3: `https://my-store.s3.us-west-2.amazonaws.com/key`
@@ -259,18 +785,96 @@ impl BlobStoreProvider for Box<dyn BlobStoreProvider> {
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
 		self.as_ref().insert(path, body)
 	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().insert_with_meta(path, body, meta)
+	}
 	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
 		self.as_ref().list()
 	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		self.as_ref().list_prefix(prefix, delimiter, cursor, limit)
+	}
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		self.as_ref().get(path)
 	}
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		self.as_ref().get_range(path, range)
+	}
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		self.as_ref().head(path)
+	}
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().insert_if_absent(path, body)
+	}
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().insert_with_meta_if_absent(path, body, meta)
+	}
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().insert_if_match(path, body, etag)
+	}
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		self.as_ref().exists(path)
 	}
 	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
 		self.as_ref().remove(path)
 	}
+	fn keeps_versions(&self) -> bool { self.as_ref().keeps_versions() }
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		self.as_ref().list_versions(path)
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		self.as_ref().get_version(path, version)
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().remove_version(path, version)
+	}
+	fn restore(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.as_ref().restore(path, version)
+	}
 	fn public_url(
 		&self,
 		path: &SmolPath,
@@ -0,0 +1,1280 @@
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use bevy::platform::sync::Arc;
+use bevy::platform::sync::Mutex;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+/// The hidden directory a synced store keeps its [`BlobSync`] state in, never
+/// itself synced.
+pub const SYNC_DIR: &str = ".sync";
+
+/// True if `path` is sync bookkeeping rather than user data.
+fn is_sync_path(path: &SmolPath) -> bool {
+	path.as_str() == SYNC_DIR
+		|| path
+			.as_str()
+			.strip_prefix(SYNC_DIR)
+			.is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Offline-first replication of this entity's [`BlobStore`] against a server
+/// store, ie a browser `IndexedDbStore` mirroring a server's [`BlobStore`].
+///
+/// - **Change log:** every [`BlobEvent`] inside the local store records its
+///   path as pending, so writes made offline are kept until they can be
+///   pushed. The log persists in the local store under [`SYNC_DIR`], so it
+///   survives a reload.
+/// - **Push and pull:** a [`sync`](Self::sync) sends the pending changes, each
+///   conditional on the server version it was based on, along with every
+///   server version the client already holds. The server applies what it can
+///   and answers with the objects that changed or vanished since.
+/// - **Conflicts:** a change based on a stale server version is resolved by
+///   the [`ConflictPolicy`].
+/// - **Status:** progress surfaces as the [`SyncStatus`] and [`SyncStats`]
+///   components. An unreachable server is [`SyncStatus::Offline`], not an
+///   error, and the pending changes wait for the next attempt.
+///
+/// The local store emits events only while it is watched, ie spawned as its
+/// own component, so the entity holds the concrete store next to this
+/// component. Pair it with a server exposing the `blob_sync` route.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # fn run(world: &mut World) {
+/// world.spawn((
+/// 	InMemoryStore::new(),
+/// 	BlobSync::new(SyncEndpoint::Remote("https://example.com/sync".into()))
+/// 		.with_policy(ConflictPolicy::ServerWins)
+/// 		.with_interval(Duration::from_secs(30)),
+/// ));
+/// # }
+/// ```
+#[derive(Debug, Clone, Component)]
+#[require(SyncStatus, SyncStats)]
+pub struct BlobSync {
+	/// Where changes are pushed and pulled.
+	endpoint: SyncEndpoint,
+	/// How a change based on a stale server version is resolved.
+	policy: ConflictPolicy,
+	/// How often the [`StorePlugin`] syncs on its own, if at all.
+	interval: Option<Duration>,
+	/// The change log and run bookkeeping, shared by every clone.
+	state: Arc<Mutex<SyncState>>,
+}
+
+/// Where a [`BlobSync`] pushes and pulls.
+#[derive(Debug, Clone)]
+pub enum SyncEndpoint {
+	/// A `blob_sync` route, exchanged as json over http.
+	Remote(Url),
+	/// A store in this process, applied directly, ie a replica held in memory.
+	Store(BlobStore),
+}
+
+/// How a [`BlobSync`] resolves a change whose server version moved on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+	/// Keep whichever write is newer: the local change's time against the
+	/// server's last modified time. A server without modified times loses.
+	#[default]
+	LastWriterWins,
+	/// Keep the server version, discarding the local change.
+	ServerWins,
+	/// Keep the local change, overwriting the server version.
+	ClientWins,
+	/// Call the entity's `Action<SyncConflict, Option<Bytes>>`, writing its
+	/// output locally and pushing it, or removing the object on `None`.
+	Merge,
+}
+
+/// The input of a [`ConflictPolicy::Merge`] action.
+#[derive(Debug, Clone)]
+pub struct SyncConflict {
+	/// The conflicting path, relative to the local store.
+	pub path: SmolPath,
+	/// The local bytes, or `None` if removed locally.
+	pub local: Option<Bytes>,
+	/// The server version, or `None` if removed on the server.
+	pub server: Option<SyncVersion>,
+}
+
+/// Where a [`BlobSync`] stands, updated by each [`sync`](BlobSync::sync).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub enum SyncStatus {
+	/// Not syncing, and the last sync (if any) succeeded.
+	#[default]
+	Idle,
+	/// A sync is in flight.
+	Syncing,
+	/// The server was unreachable, the pending changes are kept.
+	Offline,
+	/// The last sync failed for a reason other than connectivity.
+	Error(String),
+}
+
+/// Counters describing a [`BlobSync`], updated by each
+/// [`sync`](BlobSync::sync) and each recorded change.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub struct SyncStats {
+	/// Local changes not yet accepted by the server.
+	pub pending: usize,
+	/// Conflicts resolved since the entity was spawned.
+	pub conflicts: u32,
+	/// When a sync last reached the server.
+	pub last_synced: Option<Timestamp>,
+}
+
+/// What a single [`sync`](BlobSync::sync) did.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+	/// Local changes the server accepted.
+	pub pushed: usize,
+	/// Server changes written to, or removed from, the local store.
+	pub pulled: usize,
+	/// Conflicts resolved by the [`ConflictPolicy`].
+	pub conflicts: usize,
+	/// The server was unreachable, nothing was exchanged.
+	pub offline: bool,
+}
+
+/// The [`BlobSync`] change log and run bookkeeping.
+#[derive(Debug, Default)]
+struct SyncState {
+	log: SyncLog,
+	/// Whether the persisted log was merged in yet.
+	loaded: bool,
+	/// Whether a sync is in flight, so runs never overlap.
+	running: bool,
+	/// When the interval last started a sync.
+	last_attempt: Option<Instant>,
+}
+
+/// A claimed [`BlobSync`] run, releasing the claim when dropped so a sync
+/// that fails or is cancelled midway never blocks the next.
+#[derive(Debug)]
+struct SyncRun(Arc<Mutex<SyncState>>);
+
+impl Drop for SyncRun {
+	fn drop(&mut self) {
+		if let Ok(mut state) = self.0.lock() {
+			state.running = false;
+		}
+	}
+}
+
+/// The persisted half of [`SyncState`].
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+struct SyncLog {
+	/// Locally changed paths, with the time of their latest change.
+	pending: BTreeMap<SmolPath, Timestamp>,
+	/// The last version each path was in sync at.
+	synced: BTreeMap<SmolPath, SyncedVersion>,
+}
+
+/// A path's version at its last successful sync.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct SyncedVersion {
+	/// The server's etag.
+	server: SmolStr,
+	/// The [`content_etag`] of the local bytes, empty once a conflict is
+	/// resolved in the client's favour so the local bytes are pushed again.
+	local: SmolStr,
+}
+
+/// How a conflict resolves.
+enum Resolution {
+	/// Adopt the server version locally.
+	Server,
+	/// Push the local version again, based on the server's.
+	Client,
+}
+
+impl BlobSync {
+	/// The path of the persisted change log in the local store.
+	pub const STATE_PATH: &str = ".sync/state.json";
+	/// How many passes over the server listing a [`sync`](Self::sync) runs
+	/// before leaving the remaining conflicts to the next one.
+	const MAX_ROUNDS: usize = 3;
+
+	/// Sync with `endpoint` on demand, resolving conflicts by
+	/// [`ConflictPolicy::LastWriterWins`].
+	pub fn new(endpoint: SyncEndpoint) -> Self {
+		Self {
+			endpoint,
+			policy: default(),
+			interval: None,
+			state: default(),
+		}
+	}
+
+	/// Resolve conflicts with `policy`.
+	pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+		self.policy = policy;
+		self
+	}
+
+	/// Sync on spawn and then every `interval`, retrying an offline server.
+	pub fn with_interval(mut self, interval: Duration) -> Self {
+		self.interval = Some(interval);
+		self
+	}
+
+	/// The endpoint changes are pushed to and pulled from.
+	pub fn endpoint(&self) -> &SyncEndpoint { &self.endpoint }
+
+	/// The conflict policy.
+	pub fn policy(&self) -> ConflictPolicy { self.policy }
+
+	/// The locally changed paths not yet accepted by the server.
+	pub fn pending(&self) -> Vec<SmolPath> {
+		self.state
+			.lock()
+			.unwrap()
+			.log
+			.pending
+			.keys()
+			.cloned()
+			.collect()
+	}
+
+	/// Record a local change to `path`, as the [`StorePlugin`] does for each
+	/// [`BlobEvent`] in the local store.
+	pub fn record(&self, path: SmolPath) {
+		if !is_sync_path(&path) {
+			self.state
+				.lock()
+				.unwrap()
+				.log
+				.pending
+				.insert(path, Timestamp::now());
+		}
+	}
+
+	/// Push the pending changes of `entity`'s [`BlobStore`] and pull the
+	/// server's, updating its [`SyncStatus`] and [`SyncStats`]. Returns an
+	/// empty report if a sync is already in flight.
+	///
+	/// # Errors
+	/// Errors if the entity has no [`BlobSync`] or [`BlobStore`], a store
+	/// fails, or the server rejects the exchange. An unreachable server is
+	/// not an error, see [`SyncReport::offline`].
+	pub async fn sync(entity: AsyncEntity) -> Result<SyncReport> {
+		let (sync, local) = entity.get_cloned2::<BlobSync, BlobStore>().await?;
+		let Some(run) = sync.begin() else {
+			return SyncReport::default().xok();
+		};
+		entity.insert(SyncStatus::Syncing).await?;
+		let result = sync.run(&entity, &local).await;
+		drop(run);
+		let pending = sync.pending().len();
+		let status = match &result {
+			Ok(report) if report.offline => SyncStatus::Offline,
+			Ok(_) => SyncStatus::Idle,
+			Err(err) => SyncStatus::Error(err.to_string()),
+		};
+		let report = result.as_ref().ok().cloned();
+		entity
+			.with(move |mut entity| {
+				entity.insert(status);
+				let mut stats = entity.get_mut::<SyncStats>().unwrap();
+				stats.pending = pending;
+				if let Some(report) = report {
+					stats.conflicts += report.conflicts as u32;
+					if !report.offline {
+						stats.last_synced = Some(Timestamp::now());
+					}
+				}
+			})
+			.await?;
+		result
+	}
+
+	/// Claim the run, `None` if one is already in flight.
+	fn begin(&self) -> Option<SyncRun> {
+		let mut state = self.state.lock().unwrap();
+		(!core::mem::replace(&mut state.running, true))
+			.then(|| SyncRun(self.state.clone()))
+	}
+
+	/// True once the interval has elapsed since the last attempt, restarting
+	/// it.
+	fn poll_due(&self) -> bool {
+		let Some(interval) = self.interval else {
+			return false;
+		};
+		let mut state = self.state.lock().unwrap();
+		let due = !state.running
+			&& state
+				.last_attempt
+				.is_none_or(|last| last.elapsed() >= interval);
+		if due {
+			state.last_attempt = Some(Instant::now());
+		}
+		due
+	}
+
+	async fn run(
+		&self,
+		entity: &AsyncEntity,
+		local: &BlobStore,
+	) -> Result<SyncReport> {
+		self.load(local).await?;
+		let mut report = SyncReport::default();
+		let mut cursor = None;
+		let mut rounds = 0;
+		loop {
+			let (changes, pushed) = self.collect_changes(local).await?;
+			let request = SyncRequest {
+				changes,
+				known: self.known(cursor.as_ref()),
+				cursor: cursor.take(),
+			};
+			let response = match self.endpoint.exchange(&request).await {
+				Ok(response) => response,
+				Err(err) if self.endpoint.is_unreachable(&err) => {
+					report.offline = true;
+					break;
+				}
+				Err(err) => {
+					self.save(local).await?;
+					return Err(err);
+				}
+			};
+			let next = response.cursor.clone();
+			let retry = self
+				.apply_response(entity, local, &pushed, response, &mut report)
+				.await?;
+			// a retry rides along with the next page, or starts another pass
+			// once the listing is done
+			match next {
+				Some(next) => cursor = Some(next),
+				None if retry && rounds + 1 < Self::MAX_ROUNDS => rounds += 1,
+				None => break,
+			}
+		}
+		self.save(local).await?;
+		report.xok()
+	}
+
+	/// Merge the persisted log into the in-memory one, once.
+	async fn load(&self, local: &BlobStore) -> Result {
+		if self.state.lock().unwrap().loaded {
+			return Ok(());
+		}
+		let path = SmolPath::new(Self::STATE_PATH);
+		let persisted = if local.exists(&path).await? {
+			serde_json::from_slice::<SyncLog>(&local.get(&path).await?)?
+		} else {
+			SyncLog::default()
+		};
+		let mut state = self.state.lock().unwrap();
+		for (path, modified) in persisted.pending {
+			state.log.pending.entry(path).or_insert(modified);
+		}
+		for (path, version) in persisted.synced {
+			state.log.synced.entry(path).or_insert(version);
+		}
+		state.loaded = true;
+		Ok(())
+	}
+
+	async fn save(&self, local: &BlobStore) -> Result {
+		let bytes = serde_json::to_vec(&self.state.lock().unwrap().log)?;
+		BlobStoreProvider::insert(
+			local,
+			&SmolPath::new(Self::STATE_PATH),
+			bytes.into(),
+		)
+		.await
+	}
+
+	/// The server etag of each synced path the rest of the listing from
+	/// `cursor` covers.
+	fn known(
+		&self,
+		cursor: Option<&SyncCursor>,
+	) -> BTreeMap<SmolPath, SmolStr> {
+		self.state
+			.lock()
+			.unwrap()
+			.log
+			.synced
+			.iter()
+			.filter(|(path, _)| {
+				cursor.is_none_or(|cursor| **path > cursor.after)
+			})
+			.map(|(path, version)| (path.clone(), version.server.clone()))
+			.collect()
+	}
+
+	fn synced(&self, path: &SmolPath) -> Option<SyncedVersion> {
+		self.state.lock().unwrap().log.synced.get(path).cloned()
+	}
+
+	fn set_synced(&self, path: SmolPath, version: Option<SyncedVersion>) {
+		let mut state = self.state.lock().unwrap();
+		match version {
+			Some(version) => state.log.synced.insert(path, version),
+			None => state.log.synced.remove(&path),
+		};
+	}
+
+	fn is_pending(&self, path: &SmolPath) -> bool {
+		self.state.lock().unwrap().log.pending.contains_key(path)
+	}
+
+	/// Drop `path` from the change log, unless it changed again since
+	/// `modified`.
+	fn settle(&self, path: &SmolPath, modified: Timestamp) {
+		let mut state = self.state.lock().unwrap();
+		if state.log.pending.get(path) == Some(&modified) {
+			state.log.pending.remove(path);
+		}
+	}
+
+	/// Read each pending path into a change, settling those that match their
+	/// synced version, ie the echo of a pulled write. Returns the changes and
+	/// the time and local etag each was pushed at.
+	async fn collect_changes(
+		&self,
+		local: &BlobStore,
+	) -> Result<(
+		Vec<SyncChange>,
+		BTreeMap<SmolPath, (Timestamp, Option<SmolStr>)>,
+	)> {
+		let pending = self.state.lock().unwrap().log.pending.clone();
+		let mut changes = Vec::new();
+		let mut pushed = BTreeMap::new();
+		for (path, modified) in pending {
+			let body = read_optional(local, &path).await?;
+			let local_etag = body.as_deref().map(content_etag);
+			let synced = self.synced(&path);
+			match (&local_etag, &synced) {
+				(Some(etag), Some(synced)) if *etag == synced.local => {
+					self.settle(&path, modified);
+					continue;
+				}
+				(None, None) => {
+					self.settle(&path, modified);
+					continue;
+				}
+				_ => {}
+			}
+			pushed.insert(path.clone(), (modified, local_etag));
+			changes.push(SyncChange {
+				path,
+				body,
+				base: synced.map(|synced| synced.server),
+				modified,
+			});
+		}
+		(changes, pushed).xok()
+	}
+
+	/// Record the server's answer, returning true if a conflict resolved in
+	/// the client's favour needs another push.
+	async fn apply_response(
+		&self,
+		entity: &AsyncEntity,
+		local: &BlobStore,
+		pushed: &BTreeMap<SmolPath, (Timestamp, Option<SmolStr>)>,
+		response: SyncResponse,
+		report: &mut SyncReport,
+	) -> Result<bool> {
+		let mut retry = false;
+		for result in response.results {
+			match result {
+				SyncResult::Applied { path, etag } => {
+					let Some((modified, local_etag)) = pushed.get(&path) else {
+						bevybail!("sync server applied unsent change `{path}`");
+					};
+					self.set_synced(
+						path.clone(),
+						local_etag.clone().map(|local| SyncedVersion {
+							server: etag.unwrap_or_default(),
+							local,
+						}),
+					);
+					self.settle(&path, *modified);
+					report.pushed += 1;
+				}
+				SyncResult::Conflict { path, server } => {
+					let Some((modified, _)) = pushed.get(&path) else {
+						bevybail!(
+							"sync server rejected unsent change `{path}`"
+						);
+					};
+					report.conflicts += 1;
+					retry |= self
+						.resolve(entity, local, path, *modified, server)
+						.await?;
+				}
+			}
+		}
+		// a path changed locally during the exchange keeps its change, which
+		// the next push will conflict on and resolve.
+		for version in response.pulled {
+			if self.is_pending(&version.path) {
+				continue;
+			}
+			BlobStoreProvider::insert(
+				local,
+				&version.path,
+				version.body.clone(),
+			)
+			.await?;
+			self.set_synced(
+				version.path.clone(),
+				Some(SyncedVersion {
+					local: content_etag(&version.body),
+					server: version.etag,
+				}),
+			);
+			report.pulled += 1;
+		}
+		for path in response.removed {
+			if self.is_pending(&path) {
+				continue;
+			}
+			if local.exists(&path).await? {
+				local.remove(&path).await?;
+			}
+			self.set_synced(path, None);
+			report.pulled += 1;
+		}
+		retry.xok()
+	}
+
+	/// Resolve a conflict by the [`ConflictPolicy`], returning true if the
+	/// local version is to be pushed again.
+	async fn resolve(
+		&self,
+		entity: &AsyncEntity,
+		local: &BlobStore,
+		path: SmolPath,
+		modified: Timestamp,
+		server: Option<SyncVersion>,
+	) -> Result<bool> {
+		let resolution = match self.policy {
+			ConflictPolicy::ServerWins => Resolution::Server,
+			ConflictPolicy::ClientWins => Resolution::Client,
+			ConflictPolicy::LastWriterWins => {
+				match server.as_ref().and_then(|server| server.modified) {
+					Some(server_modified) if server_modified >= modified => {
+						Resolution::Server
+					}
+					_ => Resolution::Client,
+				}
+			}
+			ConflictPolicy::Merge => {
+				let merged = entity
+					.call::<SyncConflict, Option<Bytes>>(SyncConflict {
+						path: path.clone(),
+						local: read_optional(local, &path).await?,
+						server: server.clone(),
+					})
+					.await?;
+				write_optional(local, &path, merged).await?;
+				Resolution::Client
+			}
+		};
+		let retry = match resolution {
+			Resolution::Server => {
+				let body = server.as_ref().map(|server| server.body.clone());
+				write_optional(local, &path, body).await?;
+				self.set_synced(
+					path.clone(),
+					server.map(|server| SyncedVersion {
+						local: content_etag(&server.body),
+						server: server.etag,
+					}),
+				);
+				self.settle(&path, modified);
+				false
+			}
+			Resolution::Client => {
+				self.set_synced(
+					path,
+					server.map(|server| SyncedVersion {
+						server: server.etag,
+						local: default(),
+					}),
+				);
+				true
+			}
+		};
+		retry.xok()
+	}
+}
+
+impl SyncEndpoint {
+	async fn exchange(&self, request: &SyncRequest) -> Result<SyncResponse> {
+		match self {
+			Self::Remote(url) => {
+				Request::with_json(url.clone(), request)?
+					.send()
+					.await?
+					.into_result()
+					.await?
+					.json::<SyncResponse>()
+					.await
+			}
+			Self::Store(store) => request.apply(store).await,
+		}
+	}
+
+	/// True if `err` means the server could not be reached, as opposed to a
+	/// server or store that failed.
+	fn is_unreachable(&self, err: &BevyError) -> bool {
+		matches!(self, Self::Remote(_)) && HttpError::status_of(err).is_none()
+	}
+}
+
+/// The bytes at `path`, or `None` if there is no such object.
+async fn read_optional(
+	store: &BlobStore,
+	path: &SmolPath,
+) -> Result<Option<Bytes>> {
+	if store.exists(path).await? {
+		Some(store.get(path).await?).xok()
+	} else {
+		None.xok()
+	}
+}
+
+/// Write `body` to `path`, or remove `path` on `None`.
+async fn write_optional(
+	store: &BlobStore,
+	path: &SmolPath,
+	body: Option<Bytes>,
+) -> Result {
+	match body {
+		Some(body) => BlobStoreProvider::insert(store, path, body).await,
+		None if store.exists(path).await? => store.remove(path).await,
+		None => Ok(()),
+	}
+}
+
+/// A push from a [`BlobSync`], answered by a [`SyncResponse`].
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SyncRequest {
+	/// The local changes, applied in order.
+	pub changes: Vec<SyncChange>,
+	/// The server etag of every path the client holds past the cursor, so the
+	/// server sends only what moved on.
+	pub known: BTreeMap<SmolPath, SmolStr>,
+	/// Where the previous response's page of the diff ended, `None` to start
+	/// from the top of the listing.
+	#[serde(default)]
+	pub cursor: Option<SyncCursor>,
+}
+
+/// Resumes the diff of a [`SyncRequest`] after the page a [`SyncResponse`]
+/// covered.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SyncCursor {
+	/// The store's [`BlobListing::cursor`].
+	pub listing: SmolStr,
+	/// The last path the page listed, every known path up to it is settled.
+	pub after: SmolPath,
+}
+
+/// A single local change in a [`SyncRequest`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SyncChange {
+	/// The changed path.
+	pub path: SmolPath,
+	/// The new bytes, or `None` for a removal.
+	#[serde(with = "base64_body")]
+	pub body: Option<Bytes>,
+	/// The server etag the change was made against, `None` for a path the
+	/// client has never synced.
+	pub base: Option<SmolStr>,
+	/// When the change was made.
+	pub modified: Timestamp,
+}
+
+/// The server's answer to a [`SyncRequest`].
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SyncResponse {
+	/// The outcome of each change, in request order.
+	pub results: Vec<SyncResult>,
+	/// The objects that are new or changed since the client's known versions.
+	pub pulled: Vec<SyncVersion>,
+	/// The known paths in this page the server no longer holds.
+	pub removed: Vec<SmolPath>,
+	/// Send back with the next request to diff the following page, `None` once
+	/// the whole store was diffed.
+	#[serde(default)]
+	pub cursor: Option<SyncCursor>,
+}
+
+/// The outcome of a [`SyncChange`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SyncResult {
+	/// The change was written, now at `etag` (`None` once removed).
+	Applied {
+		/// The changed path.
+		path: SmolPath,
+		/// The server etag of the written object.
+		etag: Option<SmolStr>,
+	},
+	/// The server version moved on since the change's base.
+	Conflict {
+		/// The conflicting path.
+		path: SmolPath,
+		/// The current server version, `None` if removed.
+		server: Option<SyncVersion>,
+	},
+}
+
+/// An object as the server holds it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SyncVersion {
+	/// The object path.
+	pub path: SmolPath,
+	/// The server etag.
+	pub etag: SmolStr,
+	/// When the server last wrote it, if the backend records it.
+	pub modified: Option<Timestamp>,
+	/// The object bytes.
+	#[serde(with = "base64_body::required")]
+	pub body: Bytes,
+}
+
+impl SyncVersion {
+	/// Read `path` from `store`, or `None` if there is no such object.
+	pub async fn read(
+		store: &BlobStore,
+		path: &SmolPath,
+	) -> Result<Option<Self>> {
+		if !store.exists(path).await? {
+			return None.xok();
+		}
+		let meta = store.head(path).await?;
+		let body = store.get(path).await?;
+		Some(Self {
+			path: path.clone(),
+			etag: meta.etag.unwrap_or_else(|| content_etag(&body)),
+			modified: meta.modified,
+			body,
+		})
+		.xok()
+	}
+}
+
+impl SyncRequest {
+	/// The most objects a single response diffs, and so pulls.
+	pub const PAGE_SIZE: usize = 100;
+
+	/// Apply the changes to `store` as conditional writes, then diff a page
+	/// of its listing against the client's known versions.
+	///
+	/// The diff reads the metadata of each object in the page, at most
+	/// [`PAGE_SIZE`](Self::PAGE_SIZE), answering a [`SyncResponse::cursor`]
+	/// while more of the store remains. Paths under [`SYNC_DIR`] are never
+	/// sent.
+	///
+	/// # Errors
+	/// Errors if the store fails for any reason other than a conflict.
+	pub async fn apply(&self, store: &BlobStore) -> Result<SyncResponse> {
+		let mut known = self.known.clone();
+		let mut conflicted = BTreeSet::new();
+		let mut response = SyncResponse::default();
+		for change in &self.changes {
+			let path = change.path.clone();
+			match Self::apply_change(store, change).await {
+				Ok(etag) => {
+					match &etag {
+						Some(etag) => known.insert(path.clone(), etag.clone()),
+						None => known.remove(&path),
+					};
+					response.results.push(SyncResult::Applied { path, etag });
+				}
+				Err(err)
+					if HttpError::status_of(&err)
+						== Some(StatusCode::PRECONDITION_FAILED) =>
+				{
+					let server = SyncVersion::read(store, &path).await?;
+					conflicted.insert(path.clone());
+					response
+						.results
+						.push(SyncResult::Conflict { path, server });
+				}
+				Err(err) => return Err(err),
+			}
+		}
+		let after = self.cursor.as_ref().map(|cursor| cursor.after.clone());
+		let page = store
+			.list_prefix(
+				"",
+				None,
+				self.cursor.as_ref().map(|cursor| cursor.listing.as_str()),
+				Some(Self::PAGE_SIZE),
+			)
+			.await?;
+		// the page covers the known paths after the previous one, up to its
+		// last path or, on the last page, to the end of the store
+		let last = page.paths.last().cloned().or_else(|| after.clone());
+		response.cursor = page.cursor.map(|listing| SyncCursor {
+			listing,
+			after: last.clone().unwrap_or_default(),
+		});
+		known.retain(|path, _| {
+			after.as_ref().is_none_or(|after| path > after)
+				&& (response.cursor.is_none()
+					|| last.as_ref().is_some_and(|last| path <= last))
+		});
+		for path in page.paths {
+			if is_sync_path(&path) || conflicted.contains(&path) {
+				continue;
+			}
+			let etag = store.head(&path).await?.etag;
+			if known.remove(&path).is_some_and(|known| Some(known) == etag) {
+				continue;
+			}
+			if let Some(version) = SyncVersion::read(store, &path).await? {
+				response.pulled.push(version);
+			}
+		}
+		response.removed = known
+			.into_keys()
+			.filter(|path| !conflicted.contains(path))
+			.collect();
+		response.xok()
+	}
+
+	/// Write a single change, returning the new server etag.
+	///
+	/// A write is conditional on the change's base, but a removal heads the
+	/// object and then removes it, as stores have no conditional remove: a
+	/// write landing between the two is removed with it.
+	async fn apply_change(
+		store: &BlobStore,
+		change: &SyncChange,
+	) -> Result<Option<SmolStr>> {
+		let path = &change.path;
+		if is_sync_path(path) {
+			return Err(HttpError::bad_request(format!(
+				"`{path}` is reserved for sync state"
+			))
+			.into());
+		}
+		match (&change.body, &change.base) {
+			(Some(body), None) => {
+				store.insert_if_absent(path, body.clone()).await?;
+			}
+			(Some(body), Some(base)) => {
+				store.insert_if_match(path, body.clone(), base).await?;
+			}
+			(None, base) => {
+				if !store.exists(path).await? {
+					return None.xok();
+				}
+				let etag = store.head(path).await?.etag;
+				if base.is_none() || etag.as_ref() != base.as_ref() {
+					return Err(HttpError::precondition_failed(format!(
+						"`{path}` changed since it was removed"
+					))
+					.into());
+				}
+				store.remove(path).await?;
+				return None.xok();
+			}
+		}
+		store.head(path).await?.etag.xok()
+	}
+}
+
+/// Records each [`BlobEvent`] inside a synced store in its [`BlobSync`]
+/// change log.
+pub(crate) fn record_blob_sync_changes(
+	ev: On<BlobEvent>,
+	mut syncs: Query<(&BlobStore, &BlobSync, &mut SyncStats)>,
+) {
+	for (store, sync, mut stats) in syncs.iter_mut() {
+		if !store.did_change(&ev) {
+			continue;
+		}
+		let key = ev.root_relative_path();
+		let Some(path) = key
+			.as_str()
+			.strip_prefix(store.subdir().as_str())
+			.map(|path| path.trim_start_matches('/'))
+			.filter(|path| !path.is_empty())
+		else {
+			continue;
+		};
+		let path = SmolPath::new(path);
+		if !is_sync_path(&path) {
+			sync.record(path);
+			stats.pending = sync.pending().len();
+		}
+	}
+}
+
+/// Starts a [`sync`](BlobSync::sync) for each [`BlobSync`] whose interval
+/// elapsed.
+pub(crate) fn drive_blob_sync(
+	mut commands: Commands,
+	syncs: Query<(Entity, &BlobSync)>,
+) {
+	for (entity, sync) in syncs.iter() {
+		if sync.poll_due() {
+			commands
+				.entity(entity)
+				.run_async(async |entity: AsyncEntity| {
+					// the outcome is surfaced through the `SyncStatus`
+					BlobSync::sync(entity).await.ok();
+				});
+		}
+	}
+}
+
+/// Serializes blob bodies as base64 strings, keeping the json wire compact.
+mod base64_body {
+	use base64::Engine as _;
+	use base64::engine::general_purpose::STANDARD;
+	use bytes::Bytes;
+	use serde::Deserialize;
+	use serde::Deserializer;
+	use serde::Serializer;
+
+	pub fn serialize<S: Serializer>(
+		body: &Option<Bytes>,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		match body {
+			Some(body) => serializer.serialize_some(&STANDARD.encode(body)),
+			None => serializer.serialize_none(),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Option<Bytes>, D::Error> {
+		Option::<String>::deserialize(deserializer)?
+			.map(|body| {
+				STANDARD
+					.decode(body)
+					.map(Bytes::from)
+					.map_err(serde::de::Error::custom)
+			})
+			.transpose()
+	}
+
+	/// The same encoding for a body that is always present.
+	pub mod required {
+		use super::*;
+
+		pub fn serialize<S: Serializer>(
+			body: &Bytes,
+			serializer: S,
+		) -> Result<S::Ok, S::Error> {
+			serializer.serialize_str(&STANDARD.encode(body))
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(
+			deserializer: D,
+		) -> Result<Bytes, D::Error> {
+			STANDARD
+				.decode(String::deserialize(deserializer)?)
+				.map(Bytes::from)
+				.map_err(serde::de::Error::custom)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_action::prelude::*;
+	use beet_core::prelude::*;
+	use bytes::Bytes;
+
+	fn path(path: &str) -> SmolPath { SmolPath::new(path) }
+
+	async fn sync(world: &mut World, entity: Entity) -> SyncReport {
+		world
+			.entity_mut(entity)
+			.run_async_then(BlobSync::sync)
+			.await
+			.unwrap()
+	}
+
+	fn client(world: &mut World, server: &BlobStore) -> Entity {
+		world
+			.spawn((
+				InMemoryStore::new(),
+				BlobSync::new(SyncEndpoint::Store(server.clone())),
+			))
+			.id()
+	}
+
+	fn store(world: &World, entity: Entity) -> BlobStore {
+		world.entity(entity).get::<BlobStore>().unwrap().clone()
+	}
+
+	#[beet_core::test]
+	async fn pushes_and_pulls() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		server
+			.insert(&path("remote.txt"), "from server")
+			.await
+			.unwrap();
+		let client = client(&mut world, &server);
+		let local = store(&world, client);
+
+		let sync_state =
+			world.entity(client).get::<BlobSync>().unwrap().clone();
+		local
+			.insert(&path("local.txt"), "from client")
+			.await
+			.unwrap();
+		sync_state.record(path("local.txt"));
+
+		let report = sync(&mut world, client).await;
+		report.pushed.xpect_eq(1);
+		report.pulled.xpect_eq(1);
+		server
+			.get(&path("local.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("from client"));
+		local
+			.get(&path("remote.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("from server"));
+		sync_state.pending().xpect_empty();
+		world
+			.entity(client)
+			.get::<SyncStatus>()
+			.unwrap()
+			.clone()
+			.xpect_eq(SyncStatus::Idle);
+
+		// nothing moved, so nothing is exchanged
+		sync(&mut world, client)
+			.await
+			.xpect_eq(SyncReport::default());
+
+		server.remove(&path("remote.txt")).await.unwrap();
+		sync(&mut world, client).await.pulled.xpect_eq(1);
+		local
+			.exists(&path("remote.txt"))
+			.await
+			.unwrap()
+			.xpect_false();
+	}
+
+	#[beet_core::test]
+	async fn pages_the_diff() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		let count = SyncRequest::PAGE_SIZE * 2 + 10;
+		for index in 0..count {
+			server
+				.insert(&path(&format!("{index:03}.txt")), "server")
+				.await
+				.unwrap();
+		}
+		let client = client(&mut world, &server);
+		let local = store(&world, client);
+
+		sync(&mut world, client).await.pulled.xpect_eq(count);
+		local.list().await.unwrap().len().xpect_eq(count + 1);
+
+		// removals are found in the first and last pages alike
+		server.remove(&path("000.txt")).await.unwrap();
+		server.remove(&path("205.txt")).await.unwrap();
+		sync(&mut world, client).await.pulled.xpect_eq(2);
+		local.exists(&path("205.txt")).await.unwrap().xpect_false();
+		sync(&mut world, client)
+			.await
+			.xpect_eq(SyncReport::default());
+	}
+
+	#[beet_core::test]
+	async fn records_changes_from_events() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		let client = client(&mut world, &server);
+		let local = store(&world, client);
+
+		local.insert(&path("a.txt"), "a").await.unwrap();
+		world.update();
+		let sync_state =
+			world.entity(client).get::<BlobSync>().unwrap().clone();
+		sync_state.pending().xpect_eq(vec![path("a.txt")]);
+		world
+			.entity(client)
+			.get::<SyncStats>()
+			.unwrap()
+			.pending
+			.xpect_eq(1);
+
+		sync(&mut world, client).await.pushed.xpect_eq(1);
+		// the persisted log is bookkeeping, never a change
+		world.update();
+		sync_state.pending().xpect_empty();
+		server
+			.exists(&path(BlobSync::STATE_PATH))
+			.await
+			.unwrap()
+			.xpect_false();
+	}
+
+	#[beet_core::test]
+	async fn server_wins() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		server.insert(&path("doc.txt"), "v1").await.unwrap();
+		let client = world
+			.spawn((
+				InMemoryStore::new(),
+				BlobSync::new(SyncEndpoint::Store(server.clone()))
+					.with_policy(ConflictPolicy::ServerWins),
+			))
+			.id();
+		let local = store(&world, client);
+		sync(&mut world, client).await;
+
+		server.insert(&path("doc.txt"), "server v2").await.unwrap();
+		local.insert(&path("doc.txt"), "client v2").await.unwrap();
+		let sync_state =
+			world.entity(client).get::<BlobSync>().unwrap().clone();
+		sync_state.record(path("doc.txt"));
+
+		sync(&mut world, client).await.conflicts.xpect_eq(1);
+		local
+			.get(&path("doc.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("server v2"));
+		world
+			.entity(client)
+			.get::<SyncStats>()
+			.unwrap()
+			.conflicts
+			.xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn client_wins() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		server.insert(&path("doc.txt"), "v1").await.unwrap();
+		let client = world
+			.spawn((
+				InMemoryStore::new(),
+				BlobSync::new(SyncEndpoint::Store(server.clone()))
+					.with_policy(ConflictPolicy::ClientWins),
+			))
+			.id();
+		let local = store(&world, client);
+		sync(&mut world, client).await;
+
+		server.insert(&path("doc.txt"), "server v2").await.unwrap();
+		local.insert(&path("doc.txt"), "client v2").await.unwrap();
+		world
+			.entity(client)
+			.get::<BlobSync>()
+			.unwrap()
+			.record(path("doc.txt"));
+
+		let report = sync(&mut world, client).await;
+		report.conflicts.xpect_eq(1);
+		report.pushed.xpect_eq(1);
+		server
+			.get(&path("doc.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("client v2"));
+	}
+
+	/// A merge appending the local bytes to the server's.
+	fn append(cx: ActionContext<SyncConflict>) -> Result<Option<Bytes>> {
+		let local = cx.input.local.clone().unwrap_or_default();
+		let server = cx.input.server.clone().unwrap().body;
+		Ok(Some([server, local].concat().into()))
+	}
+
+	#[beet_core::test]
+	async fn merges_with_action() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let server = BlobStore::temp();
+		server.insert(&path("list.txt"), "a").await.unwrap();
+		let client = world
+			.spawn((
+				InMemoryStore::new(),
+				BlobSync::new(SyncEndpoint::Store(server.clone()))
+					.with_policy(ConflictPolicy::Merge),
+				Action::new_pure(append),
+			))
+			.id();
+		let local = store(&world, client);
+		sync(&mut world, client).await;
+
+		server.insert(&path("list.txt"), "a+s").await.unwrap();
+		local.insert(&path("list.txt"), "+c").await.unwrap();
+		world
+			.entity(client)
+			.get::<BlobSync>()
+			.unwrap()
+			.record(path("list.txt"));
+
+		sync(&mut world, client).await.conflicts.xpect_eq(1);
+		server
+			.get(&path("list.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("a+s+c"));
+	}
+
+	#[beet_core::test]
+	fn dropped_run_releases_claim() {
+		let sync = BlobSync::new(SyncEndpoint::Store(BlobStore::temp()));
+		let run = sync.begin().unwrap();
+		sync.begin().xpect_none();
+		// ie a sync that returned early with an error
+		drop(run);
+		sync.begin().xpect_some();
+	}
+
+	#[beet_core::test]
+	async fn offline_keeps_pending() {
+		let mut world = (AsyncPlugin, StorePlugin).into_world();
+		let client = world
+			.spawn((
+				InMemoryStore::new(),
+				BlobSync::new(SyncEndpoint::Remote(
+					"http://127.0.0.1:9/sync".into(),
+				)),
+			))
+			.id();
+		let local = store(&world, client);
+		local.insert(&path("a.txt"), "a").await.unwrap();
+		let sync_state =
+			world.entity(client).get::<BlobSync>().unwrap().clone();
+		sync_state.record(path("a.txt"));
+
+		sync(&mut world, client).await.offline.xpect_true();
+		sync_state.pending().xpect_eq(vec![path("a.txt")]);
+		world
+			.entity(client)
+			.get::<SyncStatus>()
+			.unwrap()
+			.clone()
+			.xpect_eq(SyncStatus::Offline);
+	}
+}
@@ -0,0 +1,654 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+use bevy::platform::sync::Arc;
+use bevy::platform::sync::Mutex;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+
+/// A layered store: reads go through a fast `cache` provider in front of a
+/// slower `origin`, and writes go through to both.
+///
+/// - **Read-through:** a `get` serves the cached copy while it is fresh, else
+///   fetches from the origin and caches the result, unless the path was
+///   written or invalidated while it fetched, so an older body never replaces
+///   a newer one.
+/// - **Write-through:** `insert` and the conditional writes land on the origin
+///   first, caching the body only once the origin accepts it. `remove`
+///   drops both copies.
+/// - **Budgets:** an entry older than the [`ttl`](Self::with_ttl) is refetched,
+///   and the least recently used entries are evicted once the cached bytes
+///   exceed [`max_bytes`](Self::with_max_bytes).
+///
+/// The origin stays the authority: listings, `head` and `public_url` go
+/// straight to it, and the store's `root_key` is the origin's, so a
+/// [`BlobEvent`] from the origin routes to this store. Spawned, the
+/// [`StorePlugin`] drops the entry such an event names, so writes that bypass
+/// the cache are not served stale. The origin emits
+/// events only while it is itself watched, ie spawned as its own component.
+///
+/// The cache is keyed by the origin's root-relative path, so every
+/// [`with_subdir`](BlobStoreProvider::with_subdir) view shares one cache and
+/// one byte budget.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn run() -> Result<()> {
+/// let store = CachedStore::new(InMemoryStore::new(), InMemoryStore::new())
+/// 	.with_max_bytes(1024 * 1024)
+/// 	.with_ttl(Duration::from_secs(60));
+/// store.insert(&SmolPath::new("hello.txt"), "world".into()).await?;
+/// store.get(&SmolPath::new("hello.txt")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Component)]
+#[component(on_add = BlobStore::on_add::<Self>)]
+pub struct CachedStore {
+	/// The authoritative store, scoped by each subdir view.
+	origin: BlobStore,
+	/// The fast store, never scoped: it mirrors the origin's root keyspace.
+	cache: BlobStore,
+	/// How long a cached entry is served before it is refetched.
+	ttl: Option<Duration>,
+	/// Ceiling on the cached bytes, evicting least recently used entries.
+	max_bytes: Option<u64>,
+	/// Which cached entries are fresh, shared by every clone and subdir view.
+	index: Arc<Mutex<CacheIndex>>,
+}
+
+impl CachedStore {
+	/// The cache budget of a `cache+` [`StoreUri`], 64MiB.
+	pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+	/// The ttl of a `cache+` [`StoreUri`], bounding how long a write that
+	/// bypasses the cache and emits no [`BlobEvent`], ie from another task on
+	/// a shared bucket, is served stale.
+	pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+	/// Compose `origin` behind `cache`, with no ttl and no byte budget.
+	pub fn new(
+		origin: impl BlobStoreProvider,
+		cache: impl BlobStoreProvider,
+	) -> Self {
+		Self {
+			origin: BlobStore::new(origin),
+			cache: BlobStore::new(cache),
+			ttl: None,
+			max_bytes: None,
+			index: default(),
+		}
+	}
+
+	/// Refetch entries cached longer than `ttl`.
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = Some(ttl);
+		self
+	}
+
+	/// Evict least recently used entries once the cache holds more than
+	/// `max_bytes`. An object larger than the budget is never cached.
+	pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+		self.max_bytes = Some(max_bytes);
+		self
+	}
+
+	/// The authoritative store.
+	pub fn origin(&self) -> &BlobStore { &self.origin }
+
+	/// The bytes currently counted against the budget.
+	pub fn cached_bytes(&self) -> u64 { self.index.lock().unwrap().bytes }
+
+	/// Drop the cached copy of `path`, so the next read refetches it.
+	pub fn invalidate(&self, path: &SmolPath) {
+		self.index.lock().unwrap().evict(&self.key(path));
+	}
+
+	/// Drop the cached copy an origin [`BlobEvent`] names, ignoring events from
+	/// any other store.
+	pub fn invalidate_event(&self, event: &BlobEvent) {
+		if self.origin.root_key() == event.store.root_key() {
+			self.index
+				.lock()
+				.unwrap()
+				.evict(&event.root_relative_path());
+		}
+	}
+
+	/// The cache key of `path`, its location relative to the origin root.
+	fn key(&self, path: &SmolPath) -> SmolPath {
+		self.origin.subdir().join(path)
+	}
+
+	/// Whether `key` is cached and within the ttl, marking it recently used.
+	fn is_fresh(&self, key: &SmolPath) -> bool {
+		self.index.lock().unwrap().touch(key, self.ttl)
+	}
+
+	/// Cache `body` under `key` if it fits the budget, then remove whatever the
+	/// budget or an invalidation evicted. The cache is best-effort: a failed
+	/// cache write only forgets the entry.
+	///
+	/// A read-through passes the `fetch` token of its
+	/// [`CacheIndex::begin_fetch`], caching nothing if the key was written or
+	/// invalidated since. Two cache writes to one key may land out of order,
+	/// so one admitted over by another is evicted rather than trusted.
+	async fn store(&self, key: SmolPath, body: Bytes, fetch: Option<u64>) {
+		let admitted = self.index.lock().unwrap().admit(
+			&key,
+			body.len() as u64,
+			self.max_bytes,
+			fetch,
+		);
+		if let Some(generation) = admitted {
+			let stored = self.cache.insert(&key, body).await.is_ok();
+			let mut index = self.index.lock().unwrap();
+			let current = index.entries.get(&key).map(|entry| entry.generation);
+			if !stored {
+				index.forget(&key);
+			} else if current != Some(generation) {
+				index.evict(&key);
+			}
+		}
+		self.purge().await;
+	}
+
+	/// Remove evicted entries from the cache provider. Eviction happens under
+	/// the index lock, so the removals are queued and drained here.
+	async fn purge(&self) {
+		let evicted = core::mem::take(&mut self.index.lock().unwrap().evicted);
+		for key in evicted {
+			// an entry re-admitted since its eviction keeps its fresh body
+			if !self.index.lock().unwrap().entries.contains_key(&key) {
+				self.cache.remove(&key).await.ok();
+			}
+		}
+	}
+}
+
+/// Freshness and recency of the entries a [`CachedStore`] has cached.
+///
+/// Only entries cached through this index are served, so a persistent cache
+/// provider never serves bytes left over from a previous process.
+#[derive(Debug, Default)]
+struct CacheIndex {
+	entries: HashMap<SmolPath, CacheEntry>,
+	/// Entries by last use, the first being the next to evict.
+	recency: BTreeMap<u64, SmolPath>,
+	/// Monotonic use counter ordering `recency`.
+	tick: u64,
+	/// Total size of `entries`.
+	bytes: u64,
+	/// Keys dropped from the index but not yet removed from the cache.
+	evicted: Vec<SmolPath>,
+	/// The token of the latest read-through in flight per key, removed by
+	/// any write or invalidation of the key.
+	fetching: HashMap<SmolPath, u64>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+	size: u64,
+	cached_at: Instant,
+	/// The entry's key in `recency`.
+	tick: u64,
+	/// Identifies the admission that cached this entry.
+	generation: u64,
+}
+
+impl CacheIndex {
+	fn next_tick(&mut self) -> u64 {
+		self.tick += 1;
+		self.tick
+	}
+
+	/// Whether `key` is cached and younger than `ttl`, bumping its recency. A
+	/// stale entry is evicted.
+	fn touch(&mut self, key: &SmolPath, ttl: Option<Duration>) -> bool {
+		let Some(entry) = self.entries.get(key) else {
+			return false;
+		};
+		if ttl.is_some_and(|ttl| entry.cached_at.elapsed() > ttl) {
+			self.evict(key);
+			return false;
+		}
+		let tick = self.next_tick();
+		let entry = self.entries.get_mut(key).unwrap();
+		self.recency.remove(&entry.tick);
+		entry.tick = tick;
+		self.recency.insert(tick, key.clone());
+		true
+	}
+
+	/// Start a read-through of `key`, returning the token to admit its body
+	/// with.
+	fn begin_fetch(&mut self, key: &SmolPath) -> u64 {
+		let token = self.next_tick();
+		self.fetching.insert(key.clone(), token);
+		token
+	}
+
+	/// End a read-through of `key` that fetched nothing.
+	fn cancel_fetch(&mut self, key: &SmolPath, token: u64) {
+		if self.fetching.get(key) == Some(&token) {
+			self.fetching.remove(key);
+		}
+	}
+
+	/// Record `key` as cached with `size` bytes, evicting least recently used
+	/// entries to fit `max_bytes`, and return the admission's generation.
+	/// Returns `None`, caching nothing, when the object alone exceeds the
+	/// budget or the `fetch` it was read by is no longer the latest.
+	fn admit(
+		&mut self,
+		key: &SmolPath,
+		size: u64,
+		max_bytes: Option<u64>,
+		fetch: Option<u64>,
+	) -> Option<u64> {
+		if fetch.is_some() && self.fetching.get(key) != fetch.as_ref() {
+			return None;
+		}
+		self.forget(key);
+		self.fetching.remove(key);
+		if max_bytes.is_some_and(|max| size > max) {
+			self.evicted.push(key.clone());
+			return None;
+		}
+		if let Some(max) = max_bytes {
+			while self.bytes + size > max {
+				let Some((_, oldest)) = self.recency.first_key_value() else {
+					break;
+				};
+				let oldest = oldest.clone();
+				self.evict(&oldest);
+			}
+		}
+		let tick = self.next_tick();
+		self.recency.insert(tick, key.clone());
+		self.entries.insert(key.clone(), CacheEntry {
+			size,
+			cached_at: Instant::now(),
+			tick,
+			generation: tick,
+		});
+		self.bytes += size;
+		Some(tick)
+	}
+
+	/// Drop `key` from the index, queueing its cached bytes for removal. A
+	/// read-through of it in flight is no longer cached.
+	fn evict(&mut self, key: &SmolPath) {
+		self.fetching.remove(key);
+		if self.forget(key) {
+			self.evicted.push(key.clone());
+		}
+	}
+
+	/// Drop every entry, ie after the origin itself is removed.
+	fn evict_all(&mut self) {
+		let keys = self.entries.keys().cloned().collect::<Vec<_>>();
+		keys.iter().for_each(|key| self.evict(key));
+	}
+
+	/// Drop `key` from the index only, returning whether it was present.
+	fn forget(&mut self, key: &SmolPath) -> bool {
+		match self.entries.remove(key) {
+			Some(entry) => {
+				self.recency.remove(&entry.tick);
+				self.bytes -= entry.size;
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+impl BlobStoreProvider for CachedStore {
+	fn box_clone(&self) -> Box<dyn BlobStoreProvider> { Box::new(self.clone()) }
+	fn with_subdir(&self, path: SmolPath) -> Box<dyn BlobStoreProvider> {
+		Box::new(Self {
+			origin: self.origin.with_subdir(path),
+			..self.clone()
+		})
+	}
+	fn id(&self) -> &'static str { "cached" }
+	fn root_key(&self) -> SmolStr { self.origin.root_key() }
+	fn subdir(&self) -> SmolPath { self.origin.subdir() }
+	fn watch_dir(&self) -> Option<AbsPathBuf> { self.origin.watch_dir() }
+	fn base_dir(&self) -> Option<AbsPathBuf> { self.origin.base_dir() }
+	fn region(&self) -> Option<String> { self.origin.region() }
+	fn store_exists(&self) -> SendBoxedFuture<Result<bool>> {
+		self.origin.store_exists()
+	}
+	fn store_create(&self) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		Box::pin(async move {
+			this.origin.store_create().await?;
+			this.cache.store_try_create().await.ok();
+			Ok(())
+		})
+	}
+	fn store_remove(&self) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		Box::pin(async move {
+			this.origin.store_remove().await?;
+			this.index.lock().unwrap().evict_all();
+			this.purge().await;
+			Ok(())
+		})
+	}
+	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.origin.insert(&path, body.clone()).await?;
+			this.store(this.key(&path), body, None).await;
+			Ok(())
+		})
+	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.origin
+				.insert_with_meta(&path, body.clone(), meta)
+				.await?;
+			this.store(this.key(&path), body, None).await;
+			Ok(())
+		})
+	}
+	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
+		self.origin.list()
+	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		self.origin.list_prefix(prefix, delimiter, cursor, limit)
+	}
+	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			let key = this.key(&path);
+			if this.is_fresh(&key) {
+				match this.cache.get(&key).await {
+					Ok(body) => return body.xok(),
+					// evicted from under us, ie by a size-capped provider
+					Err(_) => {
+						this.index.lock().unwrap().forget(&key);
+					}
+				}
+			}
+			let fetch = this.index.lock().unwrap().begin_fetch(&key);
+			let body = match this.origin.get(&path).await {
+				Ok(body) => body,
+				Err(err) => {
+					this.index.lock().unwrap().cancel_fetch(&key, fetch);
+					return Err(err);
+				}
+			};
+			this.store(key, body.clone(), Some(fetch)).await;
+			body.xok()
+		})
+	}
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		self.origin.head(path)
+	}
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.origin.insert_if_absent(&path, body.clone()).await?;
+			this.store(this.key(&path), body, None).await;
+			Ok(())
+		})
+	}
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.origin
+				.insert_with_meta_if_absent(&path, body.clone(), meta)
+				.await?;
+			this.store(this.key(&path), body, None).await;
+			Ok(())
+		})
+	}
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		let etag = etag.to_owned();
+		Box::pin(async move {
+			this.origin
+				.insert_if_match(&path, body.clone(), &etag)
+				.await?;
+			this.store(this.key(&path), body, None).await;
+			Ok(())
+		})
+	}
+	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
+		if self.is_fresh(&self.key(path)) {
+			Box::pin(async { true.xok() })
+		} else {
+			self.origin.exists(path)
+		}
+	}
+	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			// invalidate even when the origin errors, ie it was already gone
+			let result = this.origin.remove(&path).await;
+			this.invalidate(&path);
+			this.purge().await;
+			result
+		})
+	}
+	fn keeps_versions(&self) -> bool { self.origin.keeps_versions() }
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		self.origin.list_versions(path)
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		self.origin.get_version(path, version)
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.origin.remove_version(path, version)
+	}
+	fn public_url(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Option<String>>> {
+		self.origin.public_url(path)
+	}
+}
+
+/// Drops the cached copy each origin [`BlobEvent`] names from every spawned
+/// [`CachedStore`]. A write through the store itself echoes back too, so its
+/// next read refetches once.
+pub(crate) fn invalidate_cached_stores(
+	ev: On<BlobEvent>,
+	stores: Query<&CachedStore>,
+) {
+	stores.iter().for_each(|store| store.invalidate_event(&ev));
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[beet_core::test]
+	async fn works() {
+		store_test::run(CachedStore::new(
+			InMemoryStore::new_empty(),
+			InMemoryStore::new(),
+		))
+		.await;
+	}
+
+	#[beet_core::test]
+	async fn reads_through_and_serves_cached() {
+		let origin = BlobStore::new(InMemoryStore::new());
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new());
+		let path = SmolPath::new("a.txt");
+		origin.insert(&path, "first").await.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("first"));
+		// an unobserved origin write is not seen until invalidated
+		origin.insert(&path, "second").await.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("first"));
+		store.invalidate(&path);
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("second"));
+	}
+
+	#[beet_core::test]
+	async fn ttl_expires() {
+		let origin = BlobStore::new(InMemoryStore::new());
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new())
+			.with_ttl(Duration::from_millis(10));
+		let path = SmolPath::new("a.txt");
+		store.insert(&path, "first".into()).await.unwrap();
+		origin.insert(&path, "second").await.unwrap();
+		time_ext::sleep(Duration::from_millis(20)).await;
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("second"));
+	}
+
+	#[beet_core::test]
+	async fn read_through_yields_to_newer_write() {
+		let origin = BlobStore::new(InMemoryStore::new());
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new());
+		let path = SmolPath::new("a.txt");
+		origin.insert(&path, "old").await.unwrap();
+		// a read fetched `old`, then a write landed before it cached
+		let fetch = store.index.lock().unwrap().begin_fetch(&path);
+		store.insert(&path, "new".into()).await.unwrap();
+		store.store(path.clone(), "old".into(), Some(fetch)).await;
+		store.get(&path).await.unwrap().xpect_eq(Bytes::from("new"));
+	}
+
+	#[beet_core::test]
+	async fn evicts_least_recently_used() {
+		let origin = BlobStore::new(InMemoryStore::new());
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new())
+			.with_max_bytes(8);
+		let (a, b, c) =
+			(SmolPath::new("a"), SmolPath::new("b"), SmolPath::new("c"));
+		store.insert(&a, "aaaa".into()).await.unwrap();
+		store.insert(&b, "bbbb".into()).await.unwrap();
+		// reading `a` leaves `b` the least recently used
+		store.get(&a).await.unwrap();
+		store.insert(&c, "cccc".into()).await.unwrap();
+		store.cached_bytes().xpect_eq(8);
+		origin.insert(&a, "AAAA").await.unwrap();
+		origin.insert(&b, "BBBB").await.unwrap();
+		store
+			.get(&a)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("aaaa"));
+		store
+			.get(&b)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("BBBB"));
+		// an object over the whole budget is never cached
+		store.insert(&a, "too large".into()).await.unwrap();
+		(store.cached_bytes() <= 8).xpect_true();
+	}
+
+	#[beet_core::test]
+	async fn subdir_views_share_the_cache() {
+		let origin = BlobStore::new(InMemoryStore::new());
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new());
+		let sub =
+			BlobStore::new(store.clone()).with_subdir(SmolPath::new("dir"));
+		sub.insert(&SmolPath::new("a.txt"), "a").await.unwrap();
+		origin
+			.get(&SmolPath::new("dir/a.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("a"));
+		store.cached_bytes().xpect_eq(1);
+		store.invalidate(&SmolPath::new("dir/a.txt"));
+		store.cached_bytes().xpect_eq(0);
+	}
+
+	/// A write straight to a watched origin emits a [`BlobEvent`] that drops the
+	/// stale cached copy.
+	#[beet_core::test]
+	async fn origin_events_invalidate() {
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, AsyncPlugin, StorePlugin));
+		let origin = InMemoryStore::new();
+		let store = CachedStore::new(origin.clone(), InMemoryStore::new());
+		app.world_mut().spawn(origin.clone());
+		app.world_mut().spawn(store.clone());
+		app.update();
+
+		let path = SmolPath::new("a.txt");
+		let origin = BlobStore::new(origin);
+		origin.insert(&path, "first").await.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("first"));
+		origin.insert(&path, "second").await.unwrap();
+		app.update();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("second"));
+	}
+}
@@ -4,10 +4,24 @@ use aws_config::meta::region::RegionProviderChain;
 use aws_sdk_dynamodb::Client;
 use aws_sdk_dynamodb::error::SdkError;
 use aws_sdk_dynamodb::operation;
+use aws_sdk_dynamodb::types::AttributeDefinition;
 use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::types::GlobalSecondaryIndex;
+use aws_sdk_dynamodb::types::KeySchemaElement;
+use aws_sdk_dynamodb::types::KeyType;
+use aws_sdk_dynamodb::types::Projection;
+use aws_sdk_dynamodb::types::ProjectionType;
+use aws_sdk_dynamodb::types::ScalarAttributeType;
 use aws_sdk_dynamodb::types::TableStatus;
 use beet_core::prelude::*;
 use bytes::Bytes;
+use core::ops::Bound;
+use std::collections::HashMap as ItemMap;
+
+/// The attribute stamping each row with a fresh version on every write,
+/// checked by [`TableProvider::insert_row_if_match`] and stripped from the row
+/// document on read.
+const ETAG_ATTR: &str = "_etag";
 
 /// AWS DynamoDB provider storing its configuration as serializable fields.
 /// The DynamoDB client is lazily constructed and cached by region using a [`LazyPool`].
@@ -21,6 +35,9 @@ pub struct DynamoStore {
 	region: SmolStr,
 	/// Optional subdirectory prefix for all keys.
 	subdir: Option<SmolPath>,
+	/// Global secondary indexes created with the table and queried by
+	/// [`Table::query`].
+	indexes: Vec<TableIndex>,
 }
 
 impl DynamoStore {
@@ -33,6 +50,7 @@ impl DynamoStore {
 			table_name: table_name.into(),
 			region: region.into(),
 			subdir: None,
+			indexes: Vec::new(),
 		}
 	}
 
@@ -42,6 +60,13 @@ impl DynamoStore {
 		self
 	}
 
+	/// Declare a global secondary index, created with the table and serving
+	/// any [`TableQuery`] with an equality filter on its partition key.
+	pub fn with_index(mut self, index: TableIndex) -> Self {
+		self.indexes.push(index);
+		self
+	}
+
 	/// The region the convention resolves when none is configured: the sdk's
 	/// `AWS_REGION` env, else `us-west-2` (mirroring the `s3://` store uri).
 	pub fn env_region() -> SmolStr {
@@ -139,10 +164,94 @@ impl DynamoStore {
 		bevybail!("Table did not delete in time");
 	}
 
+	/// Every key attribute the table declares: the `id` primary key then each
+	/// distinct index key, in declaration order.
+	fn key_attributes(&self) -> Vec<(SmolStr, IndexKeyKind)> {
+		let mut keys = vec![(SmolStr::new("id"), IndexKeyKind::String)];
+		for key in self.indexes.iter().flat_map(|index| {
+			core::iter::once(&index.partition_key).chain(&index.sort_key)
+		}) {
+			if !keys.iter().any(|(field, _)| *field == key.field) {
+				keys.push((key.field.clone(), key.kind));
+			}
+		}
+		keys
+	}
+
+	/// The index to serve `query` from: the one it names, else the first
+	/// declared index that [serves](TableIndex::serves) it.
+	///
+	/// # Errors
+	/// Returns error if the named index is undeclared or cannot serve the query.
+	fn query_index(&self, query: &TableQuery) -> Result<Option<&TableIndex>> {
+		match &query.index {
+			Some(name) => {
+				let Some(index) =
+					self.indexes.iter().find(|index| index.name == *name)
+				else {
+					bevybail!(
+						"index `{name}` is not declared on table {}",
+						self.table_name
+					);
+				};
+				if !index.serves(query) {
+					bevybail!(
+						"index `{name}` needs an equality filter on `{}`",
+						index.partition_key.field
+					);
+				}
+				Some(index).xok()
+			}
+			None => self.indexes.iter().find(|index| index.serves(query)).xok(),
+		}
+	}
+
 	/// Create a [`TypedBlob`] handle for a single object in this store.
 	pub fn blob(&self, path: SmolPath) -> TypedBlob<Self> {
 		TypedBlob::new(self.clone(), path)
 	}
+
+	/// `PutItem` the `item` if the condition expression `condition` holds,
+	/// with its `#` attribute names and `:` attribute values. A failed
+	/// condition is surfaced as [`HttpError::precondition_failed`].
+	fn put_item_if(
+		&self,
+		item: ItemMap<String, AttributeValue>,
+		condition: &'static str,
+		names: Vec<(&'static str, &'static str)>,
+		values: Vec<(&'static str, AttributeValue)>,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let mut request = client
+				.put_item()
+				.table_name(this.table_name.as_str())
+				.set_item(Some(item))
+				.condition_expression(condition);
+			for (name, attribute) in names {
+				request = request.expression_attribute_names(name, attribute);
+			}
+			for (name, value) in values {
+				request = request.expression_attribute_values(name, value);
+			}
+			match request.send().await {
+				Ok(_) => Ok(()),
+				Err(err)
+					if matches!(
+						err.as_service_error(),
+						Some(operation::put_item::PutItemError::ConditionalCheckFailedException(_))
+					) =>
+				{
+					Err(HttpError::precondition_failed(format!(
+						"condition failed: {condition}"
+					))
+					.into())
+				}
+				Err(err) => Err(sdk_err(err)),
+			}
+		})
+	}
 }
 
 /// Insert both erased store currencies: the [`BlobStore`] every provider gets,
@@ -154,6 +263,68 @@ fn on_add_dynamo(mut world: DeferredWorld, cx: HookContext) {
 	TableStore::on_add::<DynamoStore>(world, cx);
 }
 
+/// The global secondary index declaring `index`, projecting every attribute
+/// so a query against it returns whole rows.
+fn gsi(index: &TableIndex) -> Result<GlobalSecondaryIndex> {
+	let mut builder = GlobalSecondaryIndex::builder()
+		.index_name(index.name.as_str())
+		.key_schema(
+			KeySchemaElement::builder()
+				.attribute_name(index.partition_key.field.as_str())
+				.key_type(KeyType::Hash)
+				.build()?,
+		)
+		.projection(
+			Projection::builder()
+				.projection_type(ProjectionType::All)
+				.build(),
+		)
+		.provisioned_throughput(
+			aws_sdk_dynamodb::types::ProvisionedThroughput::builder()
+				.read_capacity_units(1)
+				.write_capacity_units(1)
+				.build()?,
+		);
+	if let Some(sort_key) = &index.sort_key {
+		builder = builder.key_schema(
+			KeySchemaElement::builder()
+				.attribute_name(sort_key.field.as_str())
+				.key_type(KeyType::Range)
+				.build()?,
+		);
+	}
+	builder.build()?.xok()
+}
+
+/// The item storing `row`, stamped with a fresh [`ETAG_ATTR`].
+fn row_item(row: Value) -> Result<ItemMap<String, AttributeValue>> {
+	let mut item: ItemMap<String, AttributeValue> = serde_dynamo::to_item(row)?;
+	item.insert(
+		ETAG_ATTR.into(),
+		AttributeValue::S(uuid_ext::now_v7().to_string()),
+	);
+	item.xok()
+}
+
+/// The row document stored in `item`, without its [`ETAG_ATTR`].
+fn row_from_item(mut item: ItemMap<String, AttributeValue>) -> Result<Value> {
+	item.remove(ETAG_ATTR);
+	serde_dynamo::from_item(item).map_err(Into::into)
+}
+
+/// Convert a row field value to the attribute it is stored as.
+fn to_attribute(value: &Value) -> Result<AttributeValue> {
+	serde_dynamo::to_attribute_value(value).map_err(Into::into)
+}
+
+/// The value a bound is drawn at, if any.
+fn bound_value(bound: &Bound<Value>) -> Option<&Value> {
+	match bound {
+		Bound::Included(value) | Bound::Excluded(value) => Some(value),
+		Bound::Unbounded => None,
+	}
+}
+
 /// Convert an SDK error to a [`BevyError`] carrying the full error chain.
 /// A plain `?` loses it: `SdkError`'s bare `Display` is just "service error",
 /// hiding eg an `AccessDeniedException` behind an opaque message.
@@ -172,6 +343,7 @@ impl BlobStoreProvider for DynamoStore {
 				Some(existing) => existing.join(&path),
 				None => path,
 			}),
+			indexes: self.indexes.clone(),
 		})
 	}
 
@@ -198,21 +370,27 @@ impl BlobStoreProvider for DynamoStore {
 		let this = self.clone();
 		async_ext::pin_tokio(async move {
 			let client = this.client().await;
-			let result = client
-				.create_table()
-				.table_name(this.table_name.as_str())
-				.attribute_definitions(
-					aws_sdk_dynamodb::types::AttributeDefinition::builder()
-						.attribute_name("id")
-						.attribute_type(
-							aws_sdk_dynamodb::types::ScalarAttributeType::S,
-						)
+			let mut create =
+				client.create_table().table_name(this.table_name.as_str());
+			for (field, kind) in this.key_attributes() {
+				create = create.attribute_definitions(
+					AttributeDefinition::builder()
+						.attribute_name(field.as_str())
+						.attribute_type(match kind {
+							IndexKeyKind::String => ScalarAttributeType::S,
+							IndexKeyKind::Number => ScalarAttributeType::N,
+						})
 						.build()?,
-				)
+				);
+			}
+			for index in &this.indexes {
+				create = create.global_secondary_indexes(gsi(index)?);
+			}
+			let result = create
 				.key_schema(
-					aws_sdk_dynamodb::types::KeySchemaElement::builder()
+					KeySchemaElement::builder()
 						.attribute_name("id")
-						.key_type(aws_sdk_dynamodb::types::KeyType::Hash)
+						.key_type(KeyType::Hash)
 						.build()?,
 				)
 				.provisioned_throughput(
@@ -338,6 +516,67 @@ impl BlobStoreProvider for DynamoStore {
 		})
 	}
 
+	/// A `PutItem` conditional on no item existing at the key.
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let item = ItemMap::from([
+			("id".to_string(), self.resolve_key(path)),
+			("data".to_string(), AttributeValue::B(body.to_vec().into())),
+		]);
+		self.put_item_if(item, "attribute_not_exists(id)", vec![], vec![])
+	}
+
+	/// The etag of a blob is the default content hash, so this reads the
+	/// stored bytes, checks they hash to `etag`, then puts conditional on the
+	/// stored bytes still being those.
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		let path = path.clone();
+		let etag = SmolStr::from(etag);
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let out = client
+				.get_item()
+				.table_name(this.table_name.as_str())
+				.key("id", key.clone())
+				.send()
+				.await
+				.map_err(sdk_err)?;
+			let current =
+				match out.item.as_ref().and_then(|item| item.get("data")) {
+					Some(AttributeValue::B(data))
+						if content_etag(data.as_ref()) == etag =>
+					{
+						data.clone()
+					}
+					_ => {
+						return Err(HttpError::precondition_failed(format!(
+							"object changed since read: {path}"
+						))
+						.into());
+					}
+				};
+			let item = ItemMap::from([
+				("id".to_string(), key),
+				("data".to_string(), AttributeValue::B(body.to_vec().into())),
+			]);
+			this.put_item_if(item, "#d = :d", vec![("#d", "data")], vec![(
+				":d",
+				AttributeValue::B(current),
+			)])
+			.await
+		})
+	}
+
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		let this = self.clone();
 		let key = self.resolve_key(path);
@@ -404,7 +643,7 @@ impl TableProvider for DynamoStore {
 
 	fn insert_row(&self, _id: Uuid, row: Value) -> SendBoxedFuture<Result> {
 		let this = self.clone();
-		let Ok(item) = serde_dynamo::to_item(row) else {
+		let Ok(item) = row_item(row) else {
 			return Box::pin(async move {
 				bevybail!("Failed to serialize item for dynamo");
 			});
@@ -436,11 +675,85 @@ impl TableProvider for DynamoStore {
 			let Some(item) = out.item else {
 				bevybail!("Item not found");
 			};
-			let row: Value = serde_dynamo::from_item(item)?;
-			row.xok()
+			row_from_item(item)
 		})
 	}
 
+	/// The version is the row's [`ETAG_ATTR`], empty for a row written before
+	/// rows were versioned.
+	fn get_row_versioned(
+		&self,
+		id: Uuid,
+	) -> SendBoxedFuture<Result<VersionedRow>> {
+		let this = self.clone();
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let out = client
+				.get_item()
+				.table_name(this.table_name.as_str())
+				.key("id", AttributeValue::S(id.to_string()))
+				.send()
+				.await
+				.map_err(sdk_err)?;
+			let Some(item) = out.item else {
+				bevybail!("Item not found");
+			};
+			let etag = match item.get(ETAG_ATTR) {
+				Some(AttributeValue::S(etag)) => SmolStr::from(etag.as_str()),
+				_ => SmolStr::default(),
+			};
+			VersionedRow {
+				row: row_from_item(item)?,
+				etag,
+			}
+			.xok()
+		})
+	}
+
+	/// A `PutItem` conditional on no item existing at the id.
+	fn insert_row_if_absent(
+		&self,
+		_id: Uuid,
+		row: Value,
+	) -> SendBoxedFuture<Result> {
+		match row_item(row) {
+			Ok(item) => self.put_item_if(
+				item,
+				"attribute_not_exists(id)",
+				vec![],
+				vec![],
+			),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+
+	/// A `PutItem` conditional on the stored [`ETAG_ATTR`] being `etag`, or
+	/// for an empty `etag` on the row existing without one.
+	fn insert_row_if_match(
+		&self,
+		_id: Uuid,
+		row: Value,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let item = match row_item(row) {
+			Ok(item) => item,
+			Err(err) => return Box::pin(async move { Err(err) }),
+		};
+		if etag.is_empty() {
+			self.put_item_if(
+				item,
+				"attribute_exists(id) AND attribute_not_exists(#e)",
+				vec![("#e", ETAG_ATTR)],
+				vec![],
+			)
+		} else {
+			self.put_item_if(item, "#e = :e", vec![("#e", ETAG_ATTR)], vec![(
+				":e",
+				AttributeValue::S(etag.to_string()),
+			)])
+		}
+	}
+
 	/// Read every row with a paginated `Scan`, deserializing the items the scan
 	/// already returned.
 	///
@@ -475,11 +788,7 @@ impl TableProvider for DynamoStore {
 						},
 						None => SmolPath::new(id.as_str()),
 					};
-					rows.push((
-						path,
-						serde_dynamo::from_item::<_, Value>(item)
-							.map_err(Into::into),
-					));
+					rows.push((path, row_from_item(item)));
 				}
 				// an absent (or empty) last evaluated key ends the scan
 				start_key = out.last_evaluated_key.filter(|key| !key.is_empty());
@@ -490,6 +799,138 @@ impl TableProvider for DynamoStore {
 			rows.xok()
 		})
 	}
+
+	/// Serve a query keyed on a declared [`TableIndex`] with a DynamoDB `Query`
+	/// against its global secondary index, ordered by the index sort key.
+	/// Anything else falls back to the filtered scan.
+	///
+	/// The partition key equality and the first filter on the sort key become
+	/// the key condition, every filter is then applied to the returned rows.
+	/// DynamoDB applies the limit before that, so a page may hold fewer rows
+	/// than the limit while its cursor still points at more.
+	fn query_rows(
+		&self,
+		query: TableQuery,
+	) -> SendBoxedFuture<Result<TablePage<Value>>> {
+		let index = match self.query_index(&query) {
+			Ok(Some(index)) => index.clone(),
+			Ok(None) => {
+				let rows = self.get_all_rows();
+				return Box::pin(async move { query.scan(rows.await?) });
+			}
+			Err(err) => return Box::pin(async move { Err(err) }),
+		};
+		let this = self.clone();
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let mut request = client
+				.query()
+				.table_name(this.table_name.as_str())
+				.index_name(index.name.as_str())
+				.scan_index_forward(query.order == SortOrder::Ascending)
+				.expression_attribute_names(
+					"#p",
+					index.partition_key.field.as_str(),
+				);
+			let mut condition = String::from("#p = :p");
+			// present, as the index only serves queries keyed on it
+			if let Some(value) =
+				query.filters.iter().find_map(|filter| match filter {
+					TableFilter::Eq { field, value }
+						if *field == index.partition_key.field =>
+					{
+						Some(value)
+					}
+					_ => None,
+				}) {
+				request = request
+					.expression_attribute_values(":p", to_attribute(value)?);
+			}
+			let sort_filter = index.sort_key.as_ref().and_then(|key| {
+				query
+					.filters
+					.iter()
+					.find(|filter| *filter.field() == key.field)
+			});
+			if let Some(filter) = sort_filter {
+				request = request
+					.expression_attribute_names("#s", filter.field().as_str());
+				// one condition per sort key, so an exclusive bound is widened
+				// to an inclusive one here and narrowed by the row filter.
+				let (sort_condition, start, end) = match filter {
+					TableFilter::Eq { value, .. } => {
+						("#s = :s", Some(value), None)
+					}
+					TableFilter::Range { start, end, .. } => {
+						match (bound_value(start), bound_value(end)) {
+							(Some(start), Some(end)) => {
+								("#s BETWEEN :s AND :e", Some(start), Some(end))
+							}
+							(Some(start), None) => {
+								("#s >= :s", Some(start), None)
+							}
+							(None, Some(end)) => ("#s <= :e", None, Some(end)),
+							(None, None) => ("", None, None),
+						}
+					}
+				};
+				if !sort_condition.is_empty() {
+					condition = format!("{condition} AND {sort_condition}");
+				}
+				if let Some(start) = start {
+					request = request.expression_attribute_values(
+						":s",
+						to_attribute(start)?,
+					);
+				}
+				if let Some(end) = end {
+					request = request
+						.expression_attribute_values(":e", to_attribute(end)?);
+				}
+			}
+			request = request.key_condition_expression(condition);
+			if let Some(limit) = query.limit {
+				request = request.limit(limit.min(i32::MAX as usize) as i32);
+			}
+			if let Some(cursor) = &query.cursor {
+				request = request.set_exclusive_start_key(Some(
+					serde_dynamo::to_item(&cursor.0)?,
+				));
+			}
+			let out = request.send().await.map_err(sdk_err)?;
+			// the index spans the whole table, so keep to this subdir as the
+			// scan fallback does
+			let prefix = this.subdir.as_ref().map(|sub| format!("{}/", sub));
+			let mut rows = Vec::new();
+			for item in out.items.unwrap_or_default() {
+				let Some(AttributeValue::S(id)) = item.get("id") else {
+					continue;
+				};
+				let key = match &prefix {
+					Some(prefix) => match id.strip_prefix(prefix.as_str()) {
+						Some(stripped) => stripped.to_string(),
+						None => continue,
+					},
+					None => id.clone(),
+				};
+				match row_from_item(item) {
+					Ok(row) if query.matches(&key, &row) => rows.push(row),
+					Ok(_) => {}
+					Err(err) if query.lossy => {
+						warn!("skipping unreadable row: {err}");
+					}
+					Err(err) => return Err(err),
+				}
+			}
+			let cursor = out
+				.last_evaluated_key
+				.filter(|key| !key.is_empty())
+				.map(serde_dynamo::from_item::<_, Value>)
+				.transpose()?
+				.map(TableCursor);
+			TablePage { rows, cursor }.xok()
+		})
+	}
 }
 
 #[cfg(test)]
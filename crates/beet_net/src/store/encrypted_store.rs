@@ -0,0 +1,613 @@
+use crate::prelude::*;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::Aead;
+use aes_gcm::aead::KeyInit;
+use aes_gcm::aead::Nonce;
+use aes_gcm::aead::Payload;
+use beet_core::prelude::*;
+use bevy::platform::sync::Arc;
+use bytes::Bytes;
+use uuid::Uuid;
+
+/// A named set of AES-256-GCM keys: the first encrypts, every one decrypts.
+///
+/// Rotating is prepending a new key: new objects encrypt under it while the
+/// objects written under an older key still read, each naming its key in its
+/// header. Key material never prints, the [`Debug`] impl lists ids only.
+#[derive(Clone, Default)]
+pub struct KeyRing {
+	keys: Vec<(SmolStr, Arc<[u8; 32]>)>,
+}
+
+impl core::fmt::Debug for KeyRing {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("KeyRing")
+			.field(
+				"ids",
+				&self.keys.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+			)
+			.finish()
+	}
+}
+
+impl KeyRing {
+	/// The env var prefix of a key's base64 material, ie
+	/// `BEET_ENCRYPTION_KEY_PRIMARY` for the key `primary`.
+	pub const ENV_PREFIX: &'static str = "BEET_ENCRYPTION_KEY_";
+
+	/// An empty ring, ie to add keys to with [`with_key`](Self::with_key).
+	pub fn new() -> Self { Self::default() }
+
+	/// Add a key, the first added being the one new objects encrypt under.
+	///
+	/// ## Panics
+	/// Panics if `id` is not 1 to 255 ascii alphanumeric, `-` or `_`
+	/// characters, the charset a header and an env var name both carry, or if
+	/// it shares its [`env_name`](Self::env_name) with a key already added.
+	pub fn with_key(mut self, id: impl Into<SmolStr>, key: [u8; 32]) -> Self {
+		let id = id.into();
+		self.validate_id(&id).unwrap();
+		self.keys.push((id, Arc::new(key)));
+		self
+	}
+
+	/// The keys [`BootstrapConfig::encryption_keys`] names, each read from its
+	/// `BEET_ENCRYPTION_KEY_<ID>` env var.
+	pub fn from_env() -> Result<Self> {
+		Self::from_lookup(&BootstrapConfig::get().encryption_keys, |name| {
+			env_ext::var(name).ok()
+		})
+	}
+
+	/// The keys `ids` names, each resolved by `lookup` from its
+	/// `BEET_ENCRYPTION_KEY_<ID>` name (the id uppercased, `-` as `_`) to 32
+	/// base64-encoded bytes.
+	pub fn from_lookup(
+		ids: &[SmolStr],
+		lookup: impl Fn(&str) -> Option<SmolStr>,
+	) -> Result<Self> {
+		use base64::Engine as _;
+		if ids.is_empty() {
+			bevybail!(
+				"no encryption keys configured, set --encryption-keys / \
+				BEET_ENCRYPTION_KEYS"
+			);
+		}
+		let mut ring = Self::new();
+		for id in ids {
+			ring.validate_id(id)?;
+			let name = Self::env_name(id);
+			let Some(value) = lookup(&name) else {
+				bevybail!("encryption key `{id}` is missing, set `{name}`");
+			};
+			let key: [u8; 32] = base64::engine::general_purpose::STANDARD
+				.decode(value.trim())
+				.map_err(|err| bevyhow!("`{name}` is not base64: {err}"))?
+				.try_into()
+				.map_err(|_| bevyhow!("`{name}` must decode to 32 bytes"))?;
+			ring = ring.with_key(id.clone(), key);
+		}
+		ring.xok()
+	}
+
+	/// The env var holding the key `id`. Ids differing only in case or in `-`
+	/// against `_` share a name, so a ring holds at most one of them.
+	pub fn env_name(id: &str) -> String {
+		format!(
+			"{}{}",
+			Self::ENV_PREFIX,
+			id.to_uppercase().replace('-', "_")
+		)
+	}
+
+	fn validate_id(&self, id: &str) -> Result {
+		if id.is_empty()
+			|| id.len() > u8::MAX as usize
+			|| !id
+				.chars()
+				.all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+		{
+			bevybail!(
+				"invalid encryption key id `{id}`, expected 1-255 of \
+				[A-Za-z0-9_-]"
+			);
+		}
+		let name = Self::env_name(id);
+		if let Some((other, _)) = self
+			.keys
+			.iter()
+			.find(|(other, _)| Self::env_name(other) == name)
+		{
+			bevybail!(
+				"encryption key ids `{other}` and `{id}` would both be read \
+				from `{name}`"
+			);
+		}
+		Ok(())
+	}
+
+	/// The id and cipher new objects encrypt under.
+	fn active(&self) -> Result<(&SmolStr, Aes256Gcm)> {
+		let Some((id, key)) = self.keys.first() else {
+			bevybail!("the encryption key ring is empty");
+		};
+		(id, Self::cipher(key)).xok()
+	}
+
+	/// The cipher for the key an object header names.
+	fn find(&self, id: &str) -> Result<Aes256Gcm> {
+		self.keys
+			.iter()
+			.find(|(key_id, _)| key_id == id)
+			.map(|(_, key)| Self::cipher(key))
+			.ok_or_else(|| bevyhow!("no encryption key `{id}` in the key ring"))
+	}
+
+	fn cipher(key: &[u8; 32]) -> Aes256Gcm {
+		// a 32 byte slice is always a valid AES-256 key
+		Aes256Gcm::new_from_slice(key).unwrap()
+	}
+}
+
+/// Wraps any provider with client-side authenticated encryption, so the
+/// backing store (S3, R2, localStorage, IndexedDB) only ever holds ciphertext.
+///
+/// Each object is sealed with AES-256-GCM under a fresh random nonce, and
+/// prefixed by a header naming the key it was sealed with:
+///
+/// ```text
+/// "BENC" | version: u8 | id len: u8 | key id | nonce: [u8; 12] | ciphertext + tag
+/// ```
+///
+/// The header and the object's path from the store root are the authenticated
+/// data, so a swapped key id or nonce fails to open just as tampered ciphertext
+/// does, and so does a sealed body copied to another path. Paths, listings,
+/// sizes on the wire and [`InsertMeta::metadata`] stay plaintext: only bodies
+/// are sealed.
+///
+/// Etags are the backing store's, ie of the ciphertext, so the conditional
+/// writes compare against what [`head`](BlobStoreProvider::head) returned as
+/// usual. A `public_url` is never served, the bucket's bytes being unreadable.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn run() -> Result<()> {
+/// let keys = KeyRing::new().with_key("primary", [7; 32]);
+/// let store = EncryptedStore::new(InMemoryStore::new(), keys);
+/// store.insert(&SmolPath::new("secret.txt"), "hi".into()).await?;
+/// store.get(&SmolPath::new("secret.txt")).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Component)]
+#[component(on_add = BlobStore::on_add::<Self>)]
+pub struct EncryptedStore {
+	/// The store holding the ciphertext.
+	inner: BlobStore,
+	keys: KeyRing,
+	/// Serve an object without a header as-is, ie while a plaintext store is
+	/// migrated.
+	plaintext_reads: bool,
+}
+
+impl EncryptedStore {
+	/// Leading bytes of every sealed object.
+	const MAGIC: &'static [u8; 4] = b"BENC";
+	/// The header layout version.
+	const VERSION: u8 = 1;
+	const NONCE_LEN: usize = 12;
+	const TAG_LEN: usize = 16;
+
+	/// Wrap `inner`, sealing every body under `keys`.
+	pub fn new(inner: impl BlobStoreProvider, keys: KeyRing) -> Self {
+		Self {
+			inner: BlobStore::new(inner),
+			keys,
+			plaintext_reads: false,
+		}
+	}
+
+	/// Wrap `inner` with the keys the process config names, see
+	/// [`KeyRing::from_env`].
+	pub fn from_env(inner: impl BlobStoreProvider) -> Result<Self> {
+		Self::new(inner, KeyRing::from_env()?).xok()
+	}
+
+	/// Read an object lacking the header as plaintext rather than erroring, so
+	/// an existing store is encrypted in place as its objects are rewritten.
+	pub fn with_plaintext_reads(mut self) -> Self {
+		self.plaintext_reads = true;
+		self
+	}
+
+	/// The store holding the ciphertext.
+	pub fn inner(&self) -> &BlobStore { &self.inner }
+
+	/// Seal `body` under the active key, bound to `path`.
+	pub fn seal(&self, path: &SmolPath, body: &[u8]) -> Result<Bytes> {
+		let (id, cipher) = self.keys.active()?;
+		let nonce = random_nonce();
+		let mut header = Vec::with_capacity(
+			Self::MAGIC.len() + 2 + id.len() + Self::NONCE_LEN,
+		);
+		header.extend_from_slice(Self::MAGIC);
+		header.push(Self::VERSION);
+		header.push(id.len() as u8);
+		header.extend_from_slice(id.as_bytes());
+		header.extend_from_slice(&nonce);
+		let sealed = cipher
+			.encrypt(&Nonce::<Aes256Gcm>::from(nonce), Payload {
+				msg: body,
+				aad: &self.aad(&header, path),
+			})
+			.map_err(|_| bevyhow!("failed to encrypt object"))?;
+		header.extend_from_slice(&sealed);
+		Bytes::from(header).xok()
+	}
+
+	/// Open an object sealed for `path`, erroring if it was tampered with,
+	/// moved from another path or names a key the ring lacks.
+	pub fn open(&self, path: &SmolPath, object: Bytes) -> Result<Bytes> {
+		let Some(header_len) = Self::header_len(&object) else {
+			if self.plaintext_reads {
+				return object.xok();
+			}
+			bevybail!("object is not encrypted, it has no header");
+		};
+		if object.len() < header_len + Self::TAG_LEN {
+			bevybail!("encrypted object is truncated");
+		}
+		let (header, sealed) = object.split_at(header_len);
+		let id_end =
+			Self::MAGIC.len() + 2 + header[Self::MAGIC.len() + 1] as usize;
+		let id = core::str::from_utf8(&header[Self::MAGIC.len() + 2..id_end])?;
+		let nonce: [u8; 12] = header[id_end..].try_into()?;
+		self.keys
+			.find(id)?
+			.decrypt(&Nonce::<Aes256Gcm>::from(nonce), Payload {
+				msg: sealed,
+				aad: &self.aad(header, path),
+			})
+			.map_err(|_| {
+				bevyhow!(
+					"failed to decrypt object, it was altered or the key is wrong"
+				)
+			})?
+			.xmap(Bytes::from)
+			.xok()
+	}
+
+	/// The authenticated data of an object at `path`: its header then its path
+	/// from the store root, so every subdir view of the object agrees.
+	fn aad(&self, header: &[u8], path: &SmolPath) -> Vec<u8> {
+		let path = self.inner.subdir().join(path);
+		[header, path.as_str().as_bytes()].concat()
+	}
+
+	/// The header length of a sealed object, read from its first six bytes, or
+	/// `None` when it does not start with a header.
+	fn header_len(object: &[u8]) -> Option<usize> {
+		match object {
+			[m0, m1, m2, m3, Self::VERSION, id_len, ..]
+				if [*m0, *m1, *m2, *m3] == *Self::MAGIC =>
+			{
+				Some(Self::MAGIC.len() + 2 + *id_len as usize + Self::NONCE_LEN)
+			}
+			_ => None,
+		}
+	}
+}
+
+/// A fresh 96-bit GCM nonce. Drawn from uuid's v4 rng, the one random source
+/// every target here has (WebCrypto on wasm): the first six bytes of a v4 uuid
+/// are all random, so two uuids give the twelve.
+fn random_nonce() -> [u8; 12] {
+	let mut nonce = [0; 12];
+	nonce[..6].copy_from_slice(&Uuid::new_v4().as_bytes()[..6]);
+	nonce[6..].copy_from_slice(&Uuid::new_v4().as_bytes()[..6]);
+	nonce
+}
+
+impl BlobStoreProvider for EncryptedStore {
+	fn box_clone(&self) -> Box<dyn BlobStoreProvider> { Box::new(self.clone()) }
+	fn with_subdir(&self, path: SmolPath) -> Box<dyn BlobStoreProvider> {
+		Box::new(Self {
+			inner: self.inner.with_subdir(path),
+			..self.clone()
+		})
+	}
+	fn id(&self) -> &'static str { "encrypted" }
+	fn root_key(&self) -> SmolStr { self.inner.root_key() }
+	fn subdir(&self) -> SmolPath { self.inner.subdir() }
+	fn watch_dir(&self) -> Option<AbsPathBuf> { self.inner.watch_dir() }
+	fn base_dir(&self) -> Option<AbsPathBuf> { self.inner.base_dir() }
+	fn region(&self) -> Option<String> { self.inner.region() }
+	fn store_exists(&self) -> SendBoxedFuture<Result<bool>> {
+		self.inner.store_exists()
+	}
+	fn store_create(&self) -> SendBoxedFuture<Result> {
+		self.inner.store_create()
+	}
+	fn store_remove(&self) -> SendBoxedFuture<Result> {
+		self.inner.store_remove()
+	}
+	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		match self.seal(path, &body) {
+			Ok(sealed) => BlobStoreProvider::insert(&self.inner, path, sealed),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		match self.seal(path, &body) {
+			Ok(sealed) => self.inner.insert_with_meta(path, sealed, meta),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
+		self.inner.list()
+	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		self.inner.list_prefix(prefix, delimiter, cursor, limit)
+	}
+	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
+		let this = self.clone();
+		let path = path.clone();
+		let get = self.inner.get(&path);
+		Box::pin(async move { this.open(&path, get.await?) })
+	}
+	/// The backing object's metadata, its size reduced by the header and tag
+	/// read from the object's first bytes.
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let this = self.clone();
+		let head = self.inner.head(path);
+		let prefix =
+			self.inner.get_range(path, header::ByteRange::FromTo(0, 5));
+		Box::pin(async move {
+			let mut meta = head.await?;
+			let prefix = prefix.await?;
+			match Self::header_len(&prefix.bytes) {
+				Some(header_len) => {
+					meta.size = meta
+						.size
+						.saturating_sub((header_len + Self::TAG_LEN) as u64);
+				}
+				None if this.plaintext_reads => {}
+				None => bevybail!("object is not encrypted, it has no header"),
+			}
+			meta.xok()
+		})
+	}
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		match self.seal(path, &body) {
+			Ok(sealed) => self.inner.insert_if_absent(path, sealed),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		match self.seal(path, &body) {
+			Ok(sealed) => {
+				self.inner.insert_with_meta_if_absent(path, sealed, meta)
+			}
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		match self.seal(path, &body) {
+			Ok(sealed) => self.inner.insert_if_match(path, sealed, etag),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
+		self.inner.exists(path)
+	}
+	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
+		self.inner.remove(path)
+	}
+	fn keeps_versions(&self) -> bool { self.inner.keeps_versions() }
+	/// The backing versions, their sizes those of the ciphertext.
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		self.inner.list_versions(path)
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		let this = self.clone();
+		let path = path.clone();
+		let get = self.inner.get_version(&path, version);
+		Box::pin(async move { this.open(&path, get.await?) })
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.inner.remove_version(path, version)
+	}
+	fn public_url(
+		&self,
+		_path: &SmolPath,
+	) -> SendBoxedFuture<Result<Option<String>>> {
+		Box::pin(async { None.xok() })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+
+	fn keys() -> KeyRing { KeyRing::new().with_key("k1", [1; 32]) }
+
+	#[beet_core::test]
+	async fn works() {
+		store_test::run(EncryptedStore::new(
+			InMemoryStore::new_empty(),
+			keys(),
+		))
+		.await;
+	}
+
+	#[beet_core::test]
+	async fn backing_store_holds_ciphertext() {
+		let store = EncryptedStore::new(InMemoryStore::new(), keys());
+		let path = SmolPath::new("secret.txt");
+		store
+			.insert(&path, "plaintext secret".into())
+			.await
+			.unwrap();
+		let sealed = store.inner().get(&path).await.unwrap();
+		sealed.starts_with(b"BENC").xpect_true();
+		sealed
+			.windows(6)
+			.any(|window| window == b"secret")
+			.xpect_false();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("plaintext secret"));
+	}
+
+	#[beet_core::test]
+	async fn rotated_keys_still_read() {
+		let inner = InMemoryStore::new();
+		let (old, new) = (SmolPath::new("old"), SmolPath::new("new"));
+		EncryptedStore::new(inner.clone(), keys())
+			.insert(&old, "old".into())
+			.await
+			.unwrap();
+		let rotated = EncryptedStore::new(
+			inner.clone(),
+			KeyRing::new()
+				.with_key("k2", [2; 32])
+				.with_key("k1", [1; 32]),
+		);
+		rotated.insert(&new, "new".into()).await.unwrap();
+		rotated
+			.get(&old)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("old"));
+		// the retired ring cannot read what the new key sealed
+		EncryptedStore::new(inner, keys())
+			.get(&new)
+			.await
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("no encryption key `k2`");
+	}
+
+	#[beet_core::test]
+	async fn rejects_tampering() {
+		let store = EncryptedStore::new(InMemoryStore::new(), keys());
+		let path = SmolPath::new("a");
+		store.insert(&path, "hello".into()).await.unwrap();
+		let mut sealed = store.inner().get(&path).await.unwrap().to_vec();
+		*sealed.last_mut().unwrap() ^= 1;
+		store.inner().insert(&path, sealed).await.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("failed to decrypt");
+	}
+
+	#[beet_core::test]
+	async fn rejects_moved_bodies() {
+		let store = EncryptedStore::new(InMemoryStore::new(), keys());
+		let (from, to) = (SmolPath::new("a"), SmolPath::new("b"));
+		store.insert(&from, "hello".into()).await.unwrap();
+		let sealed = store.inner().get(&from).await.unwrap();
+		store.inner().insert(&to, sealed).await.unwrap();
+		store
+			.get(&to)
+			.await
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("failed to decrypt");
+		// a subdir view opens what the root sealed
+		store
+			.with_subdir(SmolPath::new("dir"))
+			.insert(&from, "nested".into())
+			.await
+			.unwrap();
+		store
+			.get(&SmolPath::new("dir/a"))
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("nested"));
+	}
+
+	#[beet_core::test]
+	async fn plaintext_reads_opt_in() {
+		let inner = BlobStore::new(InMemoryStore::new());
+		let path = SmolPath::new("legacy.txt");
+		inner.insert(&path, "legacy").await.unwrap();
+		EncryptedStore::new(inner.clone(), keys())
+			.get(&path)
+			.await
+			.xpect_err();
+		EncryptedStore::new(inner, keys())
+			.with_plaintext_reads()
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(bytes::Bytes::from("legacy"));
+	}
+
+	#[beet_core::test]
+	fn key_ring_from_lookup() {
+		let lookup = |name: &str| {
+			(name == "BEET_ENCRYPTION_KEY_MY_KEY").then(|| {
+				SmolStr::from("AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=")
+			})
+		};
+		KeyRing::from_lookup(&["my-key".into()], lookup)
+			.unwrap()
+			.xmap(|ring| format!("{ring:?}"))
+			.xpect_contains("my-key");
+		KeyRing::from_lookup(&["other".into()], lookup)
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("BEET_ENCRYPTION_KEY_OTHER");
+		KeyRing::from_lookup(&[], lookup).xpect_err();
+		KeyRing::from_lookup(&["my-key".into(), "MY_KEY".into()], lookup)
+			.unwrap_err()
+			.to_string()
+			.xpect_contains("would both be read from");
+	}
+}
@@ -162,8 +162,11 @@ mod native {
 			let Ok(rel) = path_event.path.strip_prefix(base.as_ref()) else {
 				return;
 			};
-			// directories are not objects
-			if path_event.path.is_dir() {
+			// directories are not objects, nor the lock and temporary files of
+			// an `FsStore` write
+			if path_event.path.is_dir()
+				|| FsStore::is_write_file(&path_event.path)
+			{
 				return;
 			}
 			let kind = match path_event.kind {
@@ -1,6 +1,7 @@
 use crate::prelude::*;
 use beet_core::prelude::*;
 use bytes::Bytes;
+use std::collections::BTreeMap;
 
 /// Filesystem-backed store for local storage.
 ///
@@ -57,6 +58,149 @@ impl FsStore {
 	pub fn blob(&self, path: SmolPath) -> TypedBlob<Self> {
 		TypedBlob::new(self.clone(), path)
 	}
+
+	/// How many times a conditional write tries another writer's lock file,
+	/// [`Self::LOCK_RETRY`] apart, before giving up.
+	const LOCK_ATTEMPTS: usize = 500;
+	/// The wait between tries of a held lock file.
+	const LOCK_RETRY: Duration = Duration::from_millis(10);
+	/// The age past which a lock file is taken to be left by a crashed writer
+	/// and removed, well over any write holding it.
+	const LOCK_STALE: Duration = Duration::from_secs(30);
+	/// The suffix of a conditional write's lock file.
+	const LOCK_SUFFIX: &str = ".beet-lock";
+	/// The suffix of a written file before it is renamed into place.
+	const TMP_SUFFIX: &str = ".beet-tmp";
+
+	/// Whether `path` is a conditional write's lock or a temporary file, which
+	/// listings leave out as they are no object.
+	pub(crate) fn is_write_file(path: &std::path::Path) -> bool {
+		path.file_name()
+			.and_then(|name| name.to_str())
+			.is_some_and(|name| {
+				name.ends_with(Self::LOCK_SUFFIX)
+					|| name.ends_with(Self::TMP_SUFFIX)
+			})
+	}
+
+	/// Whether the lock file at `lock` is older than [`Self::LOCK_STALE`].
+	async fn is_stale(lock: &AbsPathBuf) -> bool {
+		match fs_ext::modified_async(lock).await {
+			Ok(Some(modified)) => {
+				Timestamp::now()
+					.unix_epoch_elapsed()
+					.saturating_sub(modified.unix_epoch_elapsed())
+					> Self::LOCK_STALE
+			}
+			_ => false,
+		}
+	}
+
+	/// Remove the stale `lock` of a write to `path`, by first renaming it to a
+	/// tombstone of this writer's own: of several writers finding it stale,
+	/// only the one whose rename wins removes it, so none removes a lock
+	/// another has since taken. A tombstone found fresh after all was a lock
+	/// taken between the staleness check and the rename, so is put back.
+	async fn take_stale(path: &AbsPathBuf, lock: &AbsPathBuf) {
+		let tombstone = AbsPathBuf::new_unchecked(format!(
+			"{path}.{}{}",
+			uuid_ext::now_v7(),
+			Self::LOCK_SUFFIX
+		));
+		if fs_ext::rename_async(lock, &tombstone).await.is_err() {
+			// another writer won the rename
+			return;
+		}
+		if Self::is_stale(&tombstone).await {
+			warn!("removing the stale lock file {lock}");
+			fs_ext::remove_async(&tombstone).await.ok();
+		} else {
+			fs_ext::rename_async(&tombstone, lock).await.ok();
+		}
+	}
+
+	/// The [`BlobMeta`] of the file at `path`, a 404 if absent.
+	async fn stat(path: &AbsPathBuf, route: &SmolPath) -> Result<BlobMeta> {
+		let size = fs_ext::file_size_async(path)
+			.await
+			.map_err(|_| HttpError::not_found())?;
+		let modified = fs_ext::modified_async(path).await?;
+		let etag = match fs_ext::file_version_async(path).await? {
+			Some(version) => version.into(),
+			// no metadata to version by, ie on wasm, which read the file to
+			// size it anyway
+			None => content_etag(&fs_ext::read_async(path).await?),
+		};
+		BlobMeta {
+			size,
+			content_type: route.media_type().unwrap_or(MediaType::Bytes),
+			etag: Some(etag),
+			modified,
+			..default()
+		}
+		.xok()
+	}
+
+	/// Write `body` to a fresh `{path}.{uuid}.beet-tmp` file renamed over
+	/// `path`, so a reader never sees a partial write and every write gives
+	/// the file a new inode, and so a new [`fs_ext::file_version_async`].
+	async fn replace(path: &AbsPathBuf, body: Bytes) -> Result {
+		let tmp = AbsPathBuf::new_unchecked(format!(
+			"{path}.{}{}",
+			uuid_ext::now_v7(),
+			Self::TMP_SUFFIX
+		));
+		fs_ext::write_async(&tmp, body).await?;
+		if let Err(err) = fs_ext::rename_async(&tmp, path).await {
+			fs_ext::remove_async(&tmp).await.ok();
+			return Err(err.into());
+		}
+		Ok(())
+	}
+
+	/// Write `body` at `route` if `condition` accepts the current file's
+	/// [`BlobMeta`] (`None` when absent).
+	///
+	/// Conditional writers to one path take turns on a `{path}.beet-lock`
+	/// file created exclusively, then [replace](Self::replace) the file as any
+	/// write does. Neither the lock nor the temporary file is listed as an
+	/// object. A plain [`insert`](BlobStoreProvider::insert)
+	/// takes no lock. A lock file older than [`Self::LOCK_STALE`] was left by
+	/// a writer that crashed mid-write, so is [taken](Self::take_stale) rather
+	/// than waited on.
+	async fn write_locked(
+		&self,
+		route: &SmolPath,
+		body: Bytes,
+		condition: impl FnOnce(Option<BlobMeta>) -> Result,
+	) -> Result {
+		let path = self.resolve_path(route);
+		let lock =
+			AbsPathBuf::new_unchecked(format!("{path}{}", Self::LOCK_SUFFIX));
+		let mut attempts = 0;
+		while !fs_ext::create_new_async(&lock, b"").await? {
+			if Self::is_stale(&lock).await {
+				Self::take_stale(&path, &lock).await;
+				continue;
+			}
+			attempts += 1;
+			if attempts == Self::LOCK_ATTEMPTS {
+				bevybail!("timed out waiting for the lock file {lock}");
+			}
+			time_ext::sleep(Self::LOCK_RETRY).await;
+		}
+		let result = async {
+			let current = match fs_ext::exists_async(&path).await? {
+				true => Some(Self::stat(&path, route).await?),
+				false => None,
+			};
+			condition(current)?;
+			Self::replace(&path, body).await
+		}
+		.await;
+		fs_ext::remove_async(&lock).await?;
+		result
+	}
 }
 
 impl BlobStoreProvider for FsStore {
@@ -107,10 +251,7 @@ impl BlobStoreProvider for FsStore {
 
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
 		let path = self.resolve_path(path);
-		Box::pin(async move {
-			fs_ext::write_async(path, body).await?;
-			().xok()
-		})
+		Box::pin(async move { Self::replace(&path, body).await })
 	}
 
 	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
@@ -119,6 +260,7 @@ impl BlobStoreProvider for FsStore {
 			ReadDir::files_recursive_async(&root)
 				.await?
 				.into_iter()
+				.filter(|path| !Self::is_write_file(path))
 				.map(|path| {
 					let path = path
 						.strip_prefix(&root)
@@ -130,6 +272,64 @@ impl BlobStoreProvider for FsStore {
 		})
 	}
 
+	/// Reads only the directory `prefix` falls in: its entries when listing by
+	/// `/`, the filesystem's own delimiter, otherwise the files beneath it.
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		let root = self.effective_root();
+		let prefix = prefix.to_string();
+		let delimiter = delimiter.map(str::to_string);
+		let cursor = cursor.map(str::to_string);
+		Box::pin(async move {
+			let dir = prefix.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+			if dir.split('/').any(|segment| segment == "..") {
+				bevybail!("list prefix `{prefix}` escapes the store");
+			}
+			let dir = root.join(dir);
+			if !fs_ext::exists_async(&dir).await? {
+				return BlobListing::default().xok();
+			}
+			let key = |path: &std::path::Path| {
+				SmolPath::from(path.strip_prefix(&root).unwrap_or(path))
+			};
+			let listing = if delimiter.as_deref() == Some("/") {
+				let mut entries = BTreeMap::new();
+				for path in ReadDir::all_async(&dir).await? {
+					if Self::is_write_file(&path) {
+						continue;
+					}
+					let key = key(&path).to_string();
+					if !key.starts_with(prefix.as_str()) {
+						continue;
+					}
+					match fs_ext::is_dir(&path) {
+						true => entries.insert(format!("{key}/").into(), true),
+						false => entries.insert(key.into(), false),
+					};
+				}
+				BlobListing::from_entries(entries, cursor.as_deref(), limit)
+			} else {
+				BlobListing::from_keys(
+					ReadDir::files_recursive_async(&dir)
+						.await?
+						.iter()
+						.filter(|path| !Self::is_write_file(path))
+						.map(|path| key(path)),
+					&prefix,
+					delimiter.as_deref(),
+					cursor.as_deref(),
+					limit,
+				)
+			};
+			listing.xok()
+		})
+	}
+
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		let path = self.resolve_path(path);
 		Box::pin(async move {
@@ -141,6 +341,88 @@ impl BlobStoreProvider for FsStore {
 		})
 	}
 
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		let path = self.resolve_path(path);
+		Box::pin(async move {
+			let total = fs_ext::file_size_async(&path)
+				.await
+				.map_err(|_| HttpError::not_found())?;
+			let Some(range) = range.resolve(total) else {
+				return BlobRange::unsatisfiable(total).xok();
+			};
+			let bytes = fs_ext::read_range_async(&path, range.clone()).await?;
+			BlobRange {
+				total,
+				range: Some(range),
+				bytes: bytes.into(),
+			}
+			.xok()
+		})
+	}
+
+	/// Stats the file, with an etag of its
+	/// [version](fs_ext::file_version_async) rather than a hash of its
+	/// content, so a head never reads the file. A modified time and size alone
+	/// would match two same-size writes within the clock's resolution, letting
+	/// a stale conditional write through, but every write renames a new file
+	/// into place, changing its inode. The filesystem has nowhere to keep user
+	/// metadata or a content type, so these are dropped on insert and the
+	/// content type is inferred from the extension.
+	///
+	/// Off native `fs` (ie wasm) there is no metadata, so the etag hashes the
+	/// content.
+	fn head(&self, route: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let path = self.resolve_path(route);
+		let route = route.clone();
+		Box::pin(async move { Self::stat(&path, &route).await })
+	}
+
+	fn insert_if_absent(
+		&self,
+		route: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let route = route.clone();
+		Box::pin(async move {
+			let target = route.clone();
+			this.write_locked(&route, body, move |current| match current {
+				Some(_) => Err(HttpError::precondition_failed(format!(
+					"object already exists: {target}"
+				))
+				.into()),
+				None => Ok(()),
+			})
+			.await
+		})
+	}
+
+	fn insert_if_match(
+		&self,
+		route: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let route = route.clone();
+		let etag = SmolStr::from(etag);
+		Box::pin(async move {
+			let target = route.clone();
+			this.write_locked(&route, body, move |current| match current {
+				Some(current) if current.etag == Some(etag) => Ok(()),
+				_ => Err(HttpError::precondition_failed(format!(
+					"object changed since read: {target}"
+				))
+				.into()),
+			})
+			.await
+		})
+	}
+
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		let path = self.resolve_path(path);
 		Box::pin(async move { fs_ext::exists_async(path).await?.xok() })
@@ -175,4 +457,67 @@ mod test {
 			FsStore::new(AbsPathBuf::new_workspace_rel(dir).unwrap());
 		store_test::run(provider).await;
 	}
+
+	#[beet_core::test]
+	async fn etag_tracks_content() {
+		let dir = "target/tests/beet_net/test-store-etag";
+		let store = FsStore::new(AbsPathBuf::new_workspace_rel(dir).unwrap());
+		let path = SmolPath::new("same_size.txt");
+		store.insert(&path, "aaaa".into()).await.unwrap();
+		let etag = store.head(&path).await.unwrap().etag;
+		// stable while the file is untouched
+		store.head(&path).await.unwrap().etag.xpect_eq(etag.clone());
+		// same size, within the same clock tick
+		store.insert(&path, "bbbb".into()).await.unwrap();
+		store.head(&path).await.unwrap().etag.xpect_not_eq(etag);
+		store.remove(&path).await.unwrap();
+	}
+
+	#[beet_core::test]
+	async fn lock_files_are_not_listed() {
+		let dir = "target/tests/beet_net/test-store-lock";
+		let root = AbsPathBuf::new_workspace_rel(dir).unwrap();
+		let store = FsStore::new(root.clone());
+		let path = SmolPath::new("doc.txt");
+		store.insert_if_absent(&path, "a".into()).await.unwrap();
+		// a crashed writer's lock
+		fs_ext::write(root.join("doc.txt.beet-lock"), b"").unwrap();
+		store.list().await.unwrap().xpect_eq(vec![path.clone()]);
+		store
+			.list_prefix("", Some("/"), None, None)
+			.await
+			.unwrap()
+			.paths
+			.xpect_eq(vec![path.clone()]);
+		store.remove(&path).await.unwrap();
+		fs_ext::remove(root.join("doc.txt.beet-lock")).unwrap();
+	}
+
+	// staleness reads the lock's modified time, a native `fs` metadata call
+	#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+	#[beet_core::test]
+	async fn takes_stale_lock() {
+		let dir = "target/tests/beet_net/test-store-stale-lock";
+		let root = AbsPathBuf::new_workspace_rel(dir).unwrap();
+		let store = FsStore::new(root.clone());
+		let path = SmolPath::new("doc.txt");
+		let lock = root.join("doc.txt.beet-lock");
+		fs_ext::write(&lock, b"").unwrap();
+		std::fs::File::options()
+			.write(true)
+			.open(&lock)
+			.unwrap()
+			.set_modified(
+				std::time::SystemTime::now() - FsStore::LOCK_STALE * 2,
+			)
+			.unwrap();
+		store.insert_if_absent(&path, "a".into()).await.unwrap();
+		// neither the lock nor its tombstone is left behind
+		ReadDir::files_recursive_async(&root)
+			.await
+			.unwrap()
+			.len()
+			.xpect_eq(1);
+		store.remove(&path).await.unwrap();
+	}
 }
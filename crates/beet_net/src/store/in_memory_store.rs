@@ -40,7 +40,7 @@ pub struct InMemoryStore {
 #[derive(Debug)]
 struct InMemoryInner {
 	/// Shared storage state, `None` until the store is created.
-	map: RwLock<Option<HashMap<SmolPath, Bytes>>>,
+	map: RwLock<Option<HashMap<SmolPath, MemoryObject>>>,
 	/// Stable identity used by `root_key`, unique per `new` / `new_empty`.
 	instance_id: usize,
 	/// Bus to emit [`BlobEvent`]s on, set while at least one watcher subscribes.
@@ -52,6 +52,43 @@ struct InMemoryInner {
 	subscribers: AtomicUsize,
 }
 
+/// A stored body with the metadata [`head`](BlobStoreProvider::head) serves.
+#[derive(Debug, Clone)]
+struct MemoryObject {
+	body: Bytes,
+	/// Content type and user metadata passed on insert.
+	meta: InsertMeta,
+	/// Content hash of `body`, computed once on insert.
+	etag: SmolStr,
+	modified: Timestamp,
+}
+
+impl MemoryObject {
+	fn new(body: Bytes, meta: InsertMeta) -> Self {
+		Self {
+			etag: content_etag(&body),
+			body,
+			meta,
+			modified: Timestamp::now(),
+		}
+	}
+
+	fn head(&self, key: &SmolPath) -> BlobMeta {
+		BlobMeta {
+			size: self.body.len() as u64,
+			content_type: self
+				.meta
+				.content_type
+				.clone()
+				.or_else(|| key.media_type())
+				.unwrap_or(MediaType::Bytes),
+			etag: Some(self.etag.clone()),
+			modified: Some(self.modified),
+			metadata: self.meta.metadata.clone(),
+		}
+	}
+}
+
 impl Default for InMemoryStore {
 	fn default() -> Self { Self::new() }
 }
@@ -86,11 +123,18 @@ impl InMemoryStore {
 	pub fn new_seeded(
 		entries: impl IntoIterator<Item = (SmolPath, Bytes)>,
 	) -> Self {
-		Self::with_map(Some(entries.into_iter().collect()))
+		Self::with_map(Some(
+			entries
+				.into_iter()
+				.map(|(path, body)| {
+					(path, MemoryObject::new(body, InsertMeta::default()))
+				})
+				.collect(),
+		))
 	}
 
 	/// Build a store with the given initial map and a fresh `instance_id`.
-	fn with_map(map: Option<HashMap<SmolPath, Bytes>>) -> Self {
+	fn with_map(map: Option<HashMap<SmolPath, MemoryObject>>) -> Self {
 		Self {
 			inner: Arc::new(InMemoryInner {
 				map: RwLock::new(map),
@@ -112,6 +156,37 @@ impl InMemoryStore {
 		}
 	}
 
+	/// Write `object` at `path` if `condition` accepts the current object,
+	/// checked and written under one lock so a conditional write is atomic.
+	fn write_if(
+		&self,
+		path: &SmolPath,
+		object: MemoryObject,
+		condition: impl 'static + Send + FnOnce(Option<&MemoryObject>) -> Result,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		#[cfg(feature = "std")]
+		let path = path.clone();
+		Box::pin(async move {
+			let existed = {
+				let mut guard = this.inner.map.write().unwrap();
+				let map = guard
+					.as_mut()
+					.ok_or_else(|| bevyhow!("store not created"))?;
+				condition(map.get(&key))?;
+				map.insert(key, object).is_some()
+			};
+			#[cfg(feature = "std")]
+			this.emit(&path, match existed {
+				true => BlobEventKind::Changed,
+				false => BlobEventKind::Created,
+			});
+			let _ = existed;
+			Ok(())
+		})
+	}
+
 	/// Subscribe a watcher, setting the bus on the first subscriber.
 	#[cfg(feature = "std")]
 	pub(crate) fn subscribe(&self, sender: async_channel::Sender<BlobEvent>) {
@@ -195,25 +270,58 @@ impl BlobStoreProvider for InMemoryStore {
 	}
 
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
-		let this = self.clone();
-		let key = self.resolve_key(path);
-		#[cfg(feature = "std")]
-		let path = path.clone();
-		Box::pin(async move {
-			let existed = {
-				let mut guard = this.inner.map.write().unwrap();
-				let map = guard
-					.as_mut()
-					.ok_or_else(|| bevyhow!("store not created"))?;
-				map.insert(key, body).is_some()
-			};
-			#[cfg(feature = "std")]
-			this.emit(&path, match existed {
-				true => BlobEventKind::Changed,
-				false => BlobEventKind::Created,
-			});
-			let _ = existed;
-			Ok(())
+		self.insert_with_meta(path, body, InsertMeta::default())
+	}
+
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.write_if(path, MemoryObject::new(body, meta), |_| Ok(()))
+	}
+
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		self.insert_with_meta_if_absent(path, body, InsertMeta::default())
+	}
+
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let object = MemoryObject::new(body, meta);
+		let target = path.clone();
+		self.write_if(path, object, move |current| match current {
+			Some(_) => Err(HttpError::precondition_failed(format!(
+				"object already exists: {target}"
+			))
+			.into()),
+			None => Ok(()),
+		})
+	}
+
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let object = MemoryObject::new(body, InsertMeta::default());
+		let target = path.clone();
+		let etag = SmolStr::from(etag);
+		self.write_if(path, object, move |current| match current {
+			Some(current) if current.etag == etag => Ok(()),
+			_ => Err(HttpError::precondition_failed(format!(
+				"object changed since read: {target}"
+			))
+			.into()),
 		})
 	}
 
@@ -263,13 +371,60 @@ impl BlobStoreProvider for InMemoryStore {
 				.ok_or_else(|| bevyhow!("store not created"))?;
 			// a miss is a 404, matching every other backend, so a served route
 			// distinguishes an absent file from a broken store.
-			map.get(&key).cloned().ok_or_else(|| {
-				HttpError::new(
-					StatusCode::NOT_FOUND,
-					format!("object not found: {key}"),
-				)
-				.into()
-			})
+			map.get(&key)
+				.map(|object| object.body.clone())
+				.ok_or_else(|| {
+					HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("object not found: {key}"),
+					)
+					.into()
+				})
+		})
+	}
+
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		Box::pin(async move {
+			let guard = this.inner.map.read().unwrap();
+			let map = guard
+				.as_ref()
+				.ok_or_else(|| bevyhow!("store not created"))?;
+			// `Bytes::slice` shares the stored buffer, so no copy is made
+			map.get(&key)
+				.map(|object| BlobRange::slice(object.body.clone(), range))
+				.ok_or_else(|| {
+					HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("object not found: {key}"),
+					)
+					.into()
+				})
+		})
+	}
+
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		Box::pin(async move {
+			let guard = this.inner.map.read().unwrap();
+			let map = guard
+				.as_ref()
+				.ok_or_else(|| bevyhow!("store not created"))?;
+			map.get(&key)
+				.map(|object| object.head(&key))
+				.ok_or_else(|| {
+					HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("object not found: {key}"),
+					)
+					.into()
+				})
 		})
 	}
 
@@ -338,6 +493,57 @@ mod test {
 		store_test::run(provider).await;
 	}
 
+	#[beet_core::test]
+	async fn head_returns_user_metadata() {
+		let store = InMemoryStore::new();
+		let path = SmolPath::new("data");
+		store
+			.insert_with_meta(
+				&path,
+				"{}".into(),
+				InsertMeta::default()
+					.with_content_type(MediaType::Json)
+					.with_metadata("author", "beet"),
+			)
+			.await
+			.unwrap();
+		let meta = store.head(&path).await.unwrap();
+		meta.content_type.xpect_eq(MediaType::Json);
+		meta.metadata
+			.get("author")
+			.map(SmolStr::as_str)
+			.xpect_eq(Some("beet"));
+		meta.modified.is_some().xpect_true();
+		// a plain insert clears the previous metadata
+		store.insert(&path, "{}".into()).await.unwrap();
+		let meta = store.head(&path).await.unwrap();
+		meta.content_type.xpect_eq(MediaType::Bytes);
+		meta.metadata.is_empty().xpect_true();
+	}
+
+	#[beet_core::test]
+	async fn insert_with_meta_if_absent() {
+		let store = InMemoryStore::new();
+		let path = SmolPath::new("data");
+		let meta = InsertMeta::default().with_content_type(MediaType::Json);
+		store
+			.insert_with_meta_if_absent(&path, "{}".into(), meta.clone())
+			.await
+			.unwrap();
+		store
+			.head(&path)
+			.await
+			.unwrap()
+			.content_type
+			.xpect_eq(MediaType::Json);
+		store
+			.insert_with_meta_if_absent(&path, "[]".into(), meta)
+			.await
+			.unwrap_err()
+			.xmap(|err| HttpError::status_of(&err))
+			.xpect_eq(Some(StatusCode::PRECONDITION_FAILED));
+	}
+
 	#[beet_core::test]
 	fn distinct_instances_have_distinct_root_keys() {
 		let a = InMemoryStore::new();
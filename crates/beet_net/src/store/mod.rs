@@ -9,6 +9,16 @@
 //! - [`LocalStorageStore`]: Browser localStorage (WASM only)
 //! - [`S3Store`]: AWS S3 storage (requires `aws_sdk` feature)
 //! - [`DynamoStore`]: AWS DynamoDB storage (requires `aws_sdk` feature)
+//! - [`SqliteStore`]: Single-file SQLite storage with native table rows
+//!   (requires `sqlite` feature, native only)
+//! - [`CachedStore`]: A read-through, write-through cache layering any two
+//!   providers
+//! - [`EncryptedStore`]: Client-side encryption at rest over any provider
+//!   (requires `encryption` feature)
+//! - [`VersionedStore`]: Prior versions of each object, restorable, over any
+//!   provider
+//! - [`BlobSync`]: Offline-first replication of a local store against a server
+//!   store (requires `json` feature)
 //!
 //! Use [`StorePlugin`] to register store types for world serialization.
 //! Concrete store types (like [`FsStore`], [`S3Store`]) are Components whose
@@ -52,6 +62,12 @@ mod analytics;
 mod aws_cli;
 #[cfg(feature = "std")]
 mod table;
+// versioned row schemas, upgraded on read and rewritten by `Table::migrate`.
+#[cfg(feature = "std")]
+mod table_migration;
+// filters, time ranges and cursor pages over a table, and its declared indexes.
+#[cfg(feature = "std")]
+mod table_query;
 #[cfg(feature = "std")]
 mod store_ref;
 #[cfg(feature = "template_serde")]
@@ -63,6 +79,10 @@ pub use aws_cli::*;
 #[cfg(feature = "std")]
 pub use table::*;
 #[cfg(feature = "std")]
+pub use table_migration::*;
+#[cfg(feature = "std")]
+pub use table_query::*;
+#[cfg(feature = "std")]
 pub use store_ref::*;
 #[cfg(feature = "template_serde")]
 pub use template_store::*;
@@ -105,6 +125,35 @@ pub use dynamo_store::*;
 pub use s3_fs_store::*;
 #[cfg(all(feature = "aws_sdk", not(target_arch = "wasm32")))]
 mod dynamo_store;
+// the single-node blob + table backend, its rows stored as real columns.
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+mod sqlite_store;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub use sqlite_store::*;
+
+// a fast provider layered in front of a slower origin, invalidated by the
+// origin's blob events.
+#[cfg(feature = "std")]
+mod cached_store;
+#[cfg(feature = "std")]
+pub use cached_store::*;
+// authenticated encryption of every body, over any provider on any target.
+#[cfg(feature = "encryption")]
+mod encrypted_store;
+#[cfg(feature = "encryption")]
+pub use encrypted_store::*;
+// prior versions per object: S3 object versioning natively, a hidden
+// `.versions/` subtree elsewhere.
+#[cfg(feature = "std")]
+mod versioned_store;
+#[cfg(feature = "std")]
+pub use versioned_store::*;
+// the offline-first change log, replicating a local store through a server's
+// sync route as json.
+#[cfg(all(feature = "std", feature = "json"))]
+mod blob_sync;
+#[cfg(all(feature = "std", feature = "json"))]
+pub use blob_sync::*;
 
 #[cfg(feature = "std")]
 use beet_core::prelude::*;
@@ -134,12 +183,14 @@ impl Plugin for StorePlugin {
 			.add_observer(on_insert_store)
 			.add_observer(on_remove_store);
 
-		// reactive substrate: global event bus, drain, and the two propagation
-		// observers marking matching BlobStore / Blob components Changed.
+		// reactive substrate: global event bus, drain, the two propagation
+		// observers marking matching BlobStore / Blob components Changed, and
+		// the CachedStore invalidation.
 		app.init_resource::<BlobEventBus>()
 			.add_systems(PreUpdate, drain_blob_events)
 			.add_observer(propagate_blob_store_changes)
 			.add_observer(propagate_blob_changes)
+			.add_observer(invalidate_cached_stores)
 			.add_observer(add_memory_store_watcher)
 			.add_observer(remove_memory_store_watcher);
 
@@ -155,6 +206,20 @@ impl Plugin for StorePlugin {
 		#[cfg(feature = "template_serde")]
 		app.add_systems(PostUpdate, load_template_on_insert);
 
+		// the sync change log records each local write, and the interval
+		// drives any `BlobSync` that syncs on its own.
+		#[cfg(feature = "json")]
+		app.register_type::<SyncStatus>()
+			.register_type::<SyncStats>()
+			.add_systems(Update, drive_blob_sync)
+			.add_observer(record_blob_sync_changes);
+
+		// the tables `beet migrate` can name, an app adding its own.
+		#[cfg(feature = "json")]
+		app.world_mut()
+			.get_resource_or_init::<MigratableTables>()
+			.register::<AnalyticsEvent>("analytics");
+
 		// wasm localStorage watcher lifecycle (NonSend, owns the JS closure)
 		#[cfg(target_arch = "wasm32")]
 		app.register_type::<LocalStorageStore>()
@@ -176,5 +241,13 @@ impl Plugin for StorePlugin {
 			.register_type::<DynamoStore>()
 			.register_type::<TypedBlob<S3Store>>()
 			.register_type::<TypedBlob<DynamoStore>>();
+
+		// the sqlite store is reactive like the in-memory one, its writes
+		// emitting on the bus while spawned.
+		#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+		app.register_type::<SqliteStore>()
+			.register_type::<TypedBlob<SqliteStore>>()
+			.add_observer(add_sqlite_store_watcher)
+			.add_observer(remove_sqlite_store_watcher);
 	}
 }
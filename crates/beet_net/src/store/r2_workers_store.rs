@@ -143,14 +143,98 @@ impl BlobStoreProvider for R2WorkersStore {
 	}
 
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		self.insert_with_meta(path, body, InsertMeta::default())
+	}
+
+	/// Stores the content type in the object's http metadata, inferred from
+	/// the path extension when unset, and user metadata as custom metadata.
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
 		let bucket = self.bucket();
 		let key = self.effective_key(path);
+		let http_metadata = worker::HttpMetadata {
+			content_type: meta
+				.content_type
+				.or_else(|| path.media_type())
+				.map(|media_type| media_type.as_str().to_string()),
+			..Default::default()
+		};
+		let custom_metadata = meta
+			.metadata
+			.into_iter()
+			.map(|(key, value)| (key.to_string(), value.to_string()))
+			.collect::<std::collections::HashMap<_, _>>();
 		Box::pin(SendWrapper::new(async move {
-			bucket?.put(key, body.to_vec()).execute().await?;
+			bucket?
+				.put(key, body.to_vec())
+				.http_metadata(http_metadata)
+				.custom_metadata(custom_metadata)
+				.execute()
+				.await?;
 			().xok()
 		}))
 	}
 
+	/// Checks [`exists`](BlobStoreProvider::exists) then inserts with the
+	/// metadata, as the default [`insert_if_absent`](BlobStoreProvider::insert_if_absent)
+	/// does, so two writers can race.
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let exists_fut = self.exists(path);
+		let insert_fut = self.insert_with_meta(path, body, meta);
+		let path = path.clone();
+		Box::pin(async move {
+			if exists_fut.await? {
+				Err(HttpError::precondition_failed(format!(
+					"object already exists: {path}"
+				))
+				.into())
+			} else {
+				insert_fut.await
+			}
+		})
+	}
+
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let bucket = self.bucket();
+		let key = self.effective_key(path);
+		Box::pin(SendWrapper::new(async move {
+			let object = bucket?.head(&key).await?.ok_or_else(|| {
+				HttpError::new(
+					StatusCode::NOT_FOUND,
+					format!("object not found: {key}"),
+				)
+			})?;
+			BlobMeta {
+				size: object.size(),
+				content_type: object
+					.http_metadata()
+					.content_type
+					.as_deref()
+					.map(MediaType::from_content_type)
+					.unwrap_or_default(),
+				etag: Some(object.etag().into()),
+				modified: Some(Timestamp::from_unix_epoch_elapsed(
+					Duration::from_millis(object.uploaded().as_millis()),
+				)),
+				metadata: object
+					.custom_metadata()?
+					.into_iter()
+					.map(|(key, value)| (key.into(), value.into()))
+					.collect(),
+			}
+			.xok()
+		}))
+	}
+
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		let bucket = self.bucket();
 		let key = self.effective_key(path);
@@ -200,6 +284,63 @@ impl BlobStoreProvider for R2WorkersStore {
 		}))
 	}
 
+	/// One R2 list call, its delimiter, cursor and limit passed straight
+	/// through. R2 caps a page at 1000 objects.
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		let bucket = self.bucket();
+		let subdir = self.subdir.as_ref().map(|sub| format!("{sub}/"));
+		let prefix = format!("{}{prefix}", subdir.as_deref().unwrap_or(""));
+		let delimiter = delimiter.map(str::to_string);
+		let cursor = cursor.map(str::to_string);
+		Box::pin(SendWrapper::new(async move {
+			let strip = |key: String| -> Option<String> {
+				match &subdir {
+					Some(subdir) => {
+						key.strip_prefix(subdir.as_str()).map(str::to_string)
+					}
+					None => Some(key),
+				}
+			};
+			let bucket = bucket?;
+			let mut req = bucket.list().prefix(prefix);
+			if let Some(delimiter) = delimiter {
+				req = req.delimiter(delimiter);
+			}
+			if let Some(cursor) = cursor {
+				req = req.cursor(cursor);
+			}
+			if let Some(limit) = limit {
+				req = req.limit(limit.min(u32::MAX as usize) as u32);
+			}
+			let objects = req.execute().await?;
+			BlobListing {
+				paths: objects
+					.objects()
+					.into_iter()
+					.filter_map(|object| strip(object.key()))
+					.map(SmolPath::new)
+					.collect(),
+				common_prefixes: objects
+					.delimited_prefixes()
+					.into_iter()
+					.filter_map(strip)
+					.map(SmolStr::from)
+					.collect(),
+				cursor: match objects.truncated() {
+					true => objects.cursor().map(SmolStr::from),
+					false => None,
+				},
+			}
+			.xok()
+		}))
+	}
+
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		let bucket = self.bucket();
 		let key = self.effective_key(path);
@@ -216,6 +357,46 @@ impl BlobStoreProvider for R2WorkersStore {
 		}))
 	}
 
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		let bucket = self.bucket();
+		let key = self.effective_key(path);
+		Box::pin(SendWrapper::new(async move {
+			let bucket = bucket?;
+			// resolve against the head's size first, so an unsatisfiable range
+			// is answered without a body read
+			let total = bucket
+				.head(&key)
+				.await?
+				.ok_or_else(|| bevyhow!("Object not found: {key}"))?
+				.size();
+			let Some(resolved) = range.resolve(total) else {
+				return BlobRange::unsatisfiable(total).xok();
+			};
+			let object = bucket
+				.get(&key)
+				.range(worker::Range::OffsetWithLength {
+					offset: resolved.start,
+					length: resolved.end - resolved.start,
+				})
+				.execute()
+				.await?
+				.ok_or_else(|| bevyhow!("Object not found: {key}"))?;
+			let body = object
+				.body()
+				.ok_or_else(|| bevyhow!("Object has no body: {key}"))?;
+			BlobRange {
+				total,
+				range: Some(resolved),
+				bytes: Bytes::from(body.bytes().await?),
+			}
+			.xok()
+		}))
+	}
+
 	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
 		let bucket = self.bucket();
 		let key = self.effective_key(path);
@@ -62,18 +62,89 @@ impl BlobStoreProvider for S3FsStore {
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
 		self.active().insert(path, body)
 	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.active().insert_with_meta(path, body, meta)
+	}
 	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
 		self.active().list()
 	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		self.active().list_prefix(prefix, delimiter, cursor, limit)
+	}
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		self.active().get(path)
 	}
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		self.active().head(path)
+	}
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		self.active().insert_if_absent(path, body)
+	}
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.active().insert_with_meta_if_absent(path, body, meta)
+	}
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		self.active().insert_if_match(path, body, etag)
+	}
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		self.active().exists(path)
 	}
 	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
 		self.active().remove(path)
 	}
+	fn keeps_versions(&self) -> bool { self.active().keeps_versions() }
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		self.active().list_versions(path)
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		self.active().get_version(path, version)
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.active().remove_version(path, version)
+	}
+	fn restore(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		self.active().restore(path, version)
+	}
 	fn public_url(
 		&self,
 		path: &SmolPath,
@@ -30,6 +30,9 @@ pub struct S3Store {
 	/// so this is `false` by default: claiming a url the bucket will not serve
 	/// turns every static file into a redirect to a 403.
 	public: bool,
+	/// Whether the bucket keeps every version of an object, serving
+	/// [`BlobStoreProvider::list_versions`] from S3 object versioning.
+	versioned: bool,
 }
 
 impl S3Store {
@@ -44,6 +47,7 @@ impl S3Store {
 			subdir: None,
 			endpoint: None,
 			public: false,
+			versioned: false,
 		}
 	}
 
@@ -83,6 +87,16 @@ impl S3Store {
 		self
 	}
 
+	/// Declare the bucket versioned, so prior versions of an object are listed,
+	/// read and restored through S3 object versioning. A created bucket has
+	/// versioning enabled; an existing one must already have it. Pruning old
+	/// versions is left to a bucket lifecycle rule, or a [`VersionedStore`]
+	/// wrapping this store.
+	pub fn with_versioning(mut self, versioned: bool) -> Self {
+		self.versioned = versioned;
+		self
+	}
+
 	/// Construct the full S3 URI including optional subdir.
 	pub fn s3_uri(&self) -> String {
 		match &self.subdir {
@@ -128,6 +142,61 @@ impl S3Store {
 		}
 	}
 
+	/// A `PutObject` of `body` with `meta`, conditional on the current `ETag`
+	/// being `etag`, or on no object existing when `None`. S3 answers a failed
+	/// condition with a 412, or a 409 when a concurrent conditional write won
+	/// the race, both surfaced as [`HttpError::precondition_failed`].
+	fn put_if(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+		etag: Option<String>,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		let content_type = meta
+			.content_type
+			.or_else(|| path.media_type())
+			.map(|media_type| media_type.as_str().to_string());
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let request = client
+				.put_object()
+				.bucket(this.bucket_name.as_str())
+				.key(&key)
+				.body(body.to_vec().into())
+				.set_content_type(content_type)
+				.set_metadata((!meta.metadata.is_empty()).then(|| {
+					meta.metadata
+						.into_iter()
+						.map(|(key, value)| {
+							(key.to_string(), value.to_string())
+						})
+						.collect()
+				}));
+			let request = match etag {
+				Some(etag) => request.if_match(etag),
+				None => request.if_none_match("*"),
+			};
+			match request.send().await {
+				Ok(_) => ().xok(),
+				Err(SdkError::ServiceError(service_err))
+					if matches!(
+						service_err.raw().status().as_u16(),
+						409 | 412
+					) =>
+				{
+					Err(HttpError::precondition_failed(format!(
+						"conditional write failed: {key}"
+					))
+					.into())
+				}
+				Err(err) => Err(err.into()),
+			}
+		})
+	}
+
 	/// Create a [`TypedBlob`] handle for a single object in this store.
 	pub fn blob(&self, path: SmolPath) -> TypedBlob<Self> {
 		TypedBlob::new(self.clone(), path)
@@ -147,6 +216,7 @@ impl BlobStoreProvider for S3Store {
 			}),
 			endpoint: self.endpoint.clone(),
 			public: self.public,
+			versioned: self.versioned,
 		})
 	}
 
@@ -196,6 +266,20 @@ impl BlobStoreProvider for S3Store {
 				req = req.create_bucket_configuration(bucket_config);
 			}
 			req.send().await?;
+			if this.versioned {
+				use aws_sdk_s3::types::BucketVersioningStatus;
+				use aws_sdk_s3::types::VersioningConfiguration;
+				client
+					.put_bucket_versioning()
+					.bucket(this.bucket_name.as_str())
+					.versioning_configuration(
+						VersioningConfiguration::builder()
+							.status(BucketVersioningStatus::Enabled)
+							.build(),
+					)
+					.send()
+					.await?;
+			}
 			().xok()
 		})
 	}
@@ -256,8 +340,53 @@ impl BlobStoreProvider for S3Store {
 	}
 
 	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		self.insert_with_meta(path, body, InsertMeta::default())
+	}
+
+	/// A conditional `PutObject` with `If-None-Match: *`.
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		self.put_if(path, body, InsertMeta::default(), None)
+	}
+
+	/// A conditional `PutObject` with `If-None-Match: *`, keeping `meta` as
+	/// [`insert_with_meta`](BlobStoreProvider::insert_with_meta) does.
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		self.put_if(path, body, meta, None)
+	}
+
+	/// A conditional `PutObject` with `If-Match`.
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		self.put_if(path, body, InsertMeta::default(), Some(etag.to_string()))
+	}
+
+	/// Stores the content type as the object's `Content-Type`, inferred from
+	/// the path extension when unset, and user metadata as `x-amz-meta-*`.
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
 		let this = self.clone();
 		let key = self.resolve_key(path);
+		let content_type = meta
+			.content_type
+			.or_else(|| path.media_type())
+			.map(|media_type| media_type.as_str().to_string());
 		async_ext::pin_tokio(async move {
 			let client = this.client().await;
 			client
@@ -265,6 +394,15 @@ impl BlobStoreProvider for S3Store {
 				.bucket(this.bucket_name.as_str())
 				.key(&key)
 				.body(body.to_vec().into())
+				.set_content_type(content_type)
+				.set_metadata((!meta.metadata.is_empty()).then(|| {
+					meta.metadata
+						.into_iter()
+						.map(|(key, value)| {
+							(key.to_string(), value.to_string())
+						})
+						.collect()
+				}))
 				.send()
 				.await?;
 			().xok()
@@ -313,6 +451,67 @@ impl BlobStoreProvider for S3Store {
 		})
 	}
 
+	/// One `ListObjectsV2` call, its delimiter, continuation token and
+	/// `MaxKeys` passed straight through. S3 caps a page at 1000 keys.
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		let this = self.clone();
+		let subdir = self.subdir.as_ref().map(|s| format!("{}/", s));
+		let prefix = format!("{}{prefix}", subdir.as_deref().unwrap_or(""));
+		let delimiter = delimiter.map(str::to_string);
+		let cursor = cursor.map(str::to_string);
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let strip = |key: String| -> Option<String> {
+				match &subdir {
+					Some(subdir) => {
+						key.strip_prefix(subdir.as_str()).map(str::to_string)
+					}
+					None => Some(key),
+				}
+			};
+			let out = client
+				.list_objects_v2()
+				.bucket(this.bucket_name.as_str())
+				.prefix(prefix)
+				.set_delimiter(delimiter)
+				.set_continuation_token(cursor)
+				.set_max_keys(
+					limit.map(|limit| limit.min(i32::MAX as usize) as i32),
+				)
+				.send()
+				.await?;
+			BlobListing {
+				paths: out
+					.contents
+					.unwrap_or_default()
+					.into_iter()
+					.filter_map(|obj| strip(obj.key?))
+					.map(SmolPath::new)
+					.collect(),
+				common_prefixes: out
+					.common_prefixes
+					.unwrap_or_default()
+					.into_iter()
+					.filter_map(|common| strip(common.prefix?))
+					.map(SmolStr::from)
+					.collect(),
+				cursor: match out.is_truncated {
+					Some(true) => {
+						out.next_continuation_token.map(SmolStr::from)
+					}
+					_ => None,
+				},
+			}
+			.xok()
+		})
+	}
+
 	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
 		let this = self.clone();
 		let key = self.resolve_key(path);
@@ -343,6 +542,127 @@ impl BlobStoreProvider for S3Store {
 		})
 	}
 
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			// S3 resolves the range itself and reports it back in the
+			// `Content-Range`, so one request serves every range form.
+			let get_result = match client
+				.get_object()
+				.bucket(this.bucket_name.as_str())
+				.key(&key)
+				.range(format!("bytes={range}"))
+				.send()
+				.await
+			{
+				Ok(get_result) => get_result,
+				Err(SdkError::ServiceError(service_err))
+					if let GetObjectError::NoSuchKey(_) = service_err.err() =>
+				{
+					return Err(HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("object not found: {key}"),
+					)
+					.into());
+				}
+				// an unsatisfiable range is a 416 with no length, so ask for it
+				Err(SdkError::ServiceError(service_err))
+					if service_err.raw().status().as_u16() == 416 =>
+				{
+					let head = client
+						.head_object()
+						.bucket(this.bucket_name.as_str())
+						.key(&key)
+						.send()
+						.await?;
+					let total =
+						head.content_length().unwrap_or_default() as u64;
+					return BlobRange::unsatisfiable(total).xok();
+				}
+				Err(err) => return Err(err.into()),
+			};
+			let content_range = get_result
+				.content_range()
+				.ok_or_else(|| {
+					bevyhow!("ranged get has no content-range: {key}")
+				})?
+				.to_string();
+			let bytes = get_result.body.collect().await?.into_bytes();
+			match header::ContentRange::parse(&vec![content_range])? {
+				header::ContentRangeValue::Satisfied { range, total } => {
+					BlobRange {
+						total,
+						range: Some(range),
+						bytes,
+					}
+				}
+				header::ContentRangeValue::Unsatisfied { total } => {
+					BlobRange::unsatisfiable(total)
+				}
+			}
+			.xok()
+		})
+	}
+
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let head = match client
+				.head_object()
+				.bucket(this.bucket_name.as_str())
+				.key(&key)
+				.send()
+				.await
+			{
+				Ok(head) => head,
+				Err(SdkError::ServiceError(service_err))
+					if let HeadObjectError::NotFound(_) = service_err.err() =>
+				{
+					return Err(HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("object not found: {key}"),
+					)
+					.into());
+				}
+				Err(err) => return Err(err.into()),
+			};
+			BlobMeta {
+				size: head.content_length().unwrap_or_default() as u64,
+				content_type: head
+					.content_type()
+					.map(MediaType::from_content_type)
+					.unwrap_or_default(),
+				etag: head.e_tag().map(SmolStr::from),
+				modified: head.last_modified().map(|time| {
+					Timestamp::from_unix_epoch_elapsed(Duration::new(
+						time.secs().max(0) as u64,
+						time.subsec_nanos(),
+					))
+				}),
+				metadata: head
+					.metadata()
+					.into_iter()
+					.flatten()
+					.map(|(key, value)| {
+						(
+							SmolStr::from(key.as_str()),
+							SmolStr::from(value.as_str()),
+						)
+					})
+					.collect(),
+			}
+			.xok()
+		})
+	}
+
 	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
 		let this = self.clone();
 		let key = self.resolve_key(path);
@@ -389,6 +709,126 @@ impl BlobStoreProvider for S3Store {
 		})
 	}
 
+	fn keeps_versions(&self) -> bool { self.versioned }
+
+	/// A `ListObjectVersions` of the key, skipping the latest and any delete
+	/// markers. Reads a single page, ie the newest thousand versions.
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		if !self.versioned {
+			return Box::pin(async move {
+				bevybail!("s3 store is not versioned, see `with_versioning`")
+			});
+		}
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let mut versions = Vec::new();
+			let mut key_marker = None;
+			let mut version_id_marker = None;
+			loop {
+				let listing = client
+					.list_object_versions()
+					.bucket(this.bucket_name.as_str())
+					.prefix(&key)
+					.set_key_marker(key_marker)
+					.set_version_id_marker(version_id_marker)
+					.send()
+					.await?;
+				versions.extend(
+					listing
+						.versions()
+						.iter()
+						.filter(|version| {
+							version.key() == Some(key.as_str())
+								&& version.is_latest() != Some(true)
+						})
+						.filter_map(|version| {
+							Some(BlobVersion {
+								id: version.version_id()?.into(),
+								size: version.size().unwrap_or_default() as u64,
+								modified: version.last_modified().map(|time| {
+									Timestamp::from_unix_epoch_elapsed(
+										Duration::new(
+											time.secs().max(0) as u64,
+											time.subsec_nanos(),
+										),
+									)
+								}),
+							})
+						}),
+				);
+				if listing.is_truncated != Some(true) {
+					break;
+				}
+				key_marker = listing.next_key_marker;
+				version_id_marker = listing.next_version_id_marker;
+				if key_marker.is_none() {
+					break;
+				}
+			}
+			versions.xok()
+		})
+	}
+
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		let version = version.to_string();
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			let get_result = match client
+				.get_object()
+				.bucket(this.bucket_name.as_str())
+				.key(&key)
+				.version_id(&version)
+				.send()
+				.await
+			{
+				Ok(get_result) => get_result,
+				Err(SdkError::ServiceError(service_err))
+					if let GetObjectError::NoSuchKey(_) = service_err.err() =>
+				{
+					return Err(HttpError::new(
+						StatusCode::NOT_FOUND,
+						format!("version not found: {key}@{version}"),
+					)
+					.into());
+				}
+				Err(err) => return Err(err.into()),
+			};
+			get_result.body.collect().await?.into_bytes().xok()
+		})
+	}
+
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let key = self.resolve_key(path);
+		let version = version.to_string();
+		async_ext::pin_tokio(async move {
+			let client = this.client().await;
+			client
+				.delete_object()
+				.bucket(this.bucket_name.as_str())
+				.key(&key)
+				.version_id(&version)
+				.send()
+				.await?;
+			().xok()
+		})
+	}
+
 	fn public_url(
 		&self,
 		path: &SmolPath,
@@ -0,0 +1,1357 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+use bytes::Bytes;
+use core::ops::Bound;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use rusqlite::Transaction;
+use rusqlite::TransactionBehavior;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::types::ValueRef;
+use std::sync::Arc;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Process-global counter assigning a unique `instance_id` per backing store,
+/// distinguishing one private `:memory:` database from another.
+static INSTANCE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The primary key column, holding the subdir-resolved object path.
+const KEY_COLUMN: &str = "_key";
+/// The column holding an object's bytes, `NULL` for a table row.
+const BLOB_COLUMN: &str = "_blob";
+/// The condition keeping to keys under the store's prefix, bound as `?1`.
+const IN_PREFIX: &str = "substr(_key, 1, length(?1)) = ?1";
+/// The declared type of a column holding nested (map or list) values as JSON
+/// text, which also accepts any value kind a typed column would reject.
+const JSON_TYPE: &str = "JSON";
+
+/// SQLite provider for single-node deployments, one database file holding both
+/// the blob and table surfaces.
+///
+/// The store is a single SQL table (`beet_store` by default) keyed by object
+/// path. A blob is a `_blob` bytes cell, while a [`TableProvider`] row is
+/// stored natively: each top-level field of the row document is a real column,
+/// added on first write and typed by that first value (`BOOLEAN`, `INTEGER`,
+/// `REAL`, `TEXT`, `BLOB`, or `JSON` for nested maps and lists), so the table
+/// is directly queryable with plain SQL. A field later written as another kind,
+/// ie the unit and data variants of one enum, widens its column to `JSON`. A
+/// `null` field is stored as `NULL` and read back as absent.
+///
+/// Calls run on the `blocking` pool against one connection shared by every
+/// clone, and like [`InMemoryStore`] a spawned store is reactive: `insert`,
+/// `insert_row` and `remove` emit a [`BlobEvent`] on the subscribed bus.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_sqlite)]
+pub struct SqliteStore {
+	/// The database file, or `:memory:` for a private in-memory database.
+	path: SmolStr,
+	/// The SQL table holding this store's blobs and rows.
+	table_name: SmolStr,
+	/// Optional subdirectory prefix for all keys.
+	subdir: Option<SmolPath>,
+	/// Shared connection and bus, opaque to reflection.
+	#[reflect(ignore)]
+	inner: Arc<SqliteInner>,
+}
+
+/// Backing state shared across all clones of a [`SqliteStore`].
+#[derive(Debug)]
+struct SqliteInner {
+	/// The connection, opened on first use.
+	conn: Mutex<Option<Connection>>,
+	/// Stable identity used by `root_key` for a `:memory:` database.
+	instance_id: usize,
+	/// Bus to emit [`BlobEvent`]s on, set while at least one watcher subscribes.
+	bus: Mutex<Option<async_channel::Sender<BlobEvent>>>,
+	/// Number of watcher subscribers; the bus is set on the first, cleared on
+	/// the last.
+	subscribers: AtomicUsize,
+}
+
+impl Default for SqliteInner {
+	fn default() -> Self {
+		Self {
+			conn: Mutex::new(None),
+			instance_id: INSTANCE_COUNTER.fetch_add(1, Ordering::Relaxed),
+			bus: Mutex::new(None),
+			subscribers: AtomicUsize::new(0),
+		}
+	}
+}
+
+impl SqliteStore {
+	/// The SQL table used when none is configured.
+	pub const DEFAULT_TABLE: &'static str = "beet_store";
+	/// The path of a private in-memory database.
+	const MEMORY_PATH: &'static str = ":memory:";
+
+	/// Creates a new provider for the database file at `path`, created with its
+	/// parent directories on first use.
+	pub fn new(path: impl AsRef<std::path::Path>) -> Self {
+		Self {
+			path: path.as_ref().to_string_lossy().into(),
+			table_name: Self::DEFAULT_TABLE.into(),
+			subdir: None,
+			inner: Arc::default(),
+		}
+	}
+
+	/// Creates a new provider over a private in-memory database, shared by
+	/// every clone and dropped with the last one.
+	pub fn new_in_memory() -> Self { Self::new(Self::MEMORY_PATH) }
+
+	/// Set the SQL table holding this store's blobs and rows, so one database
+	/// file can hold several stores.
+	pub fn with_table(mut self, table_name: impl Into<SmolStr>) -> Self {
+		self.table_name = table_name.into();
+		self
+	}
+
+	/// Set the subdirectory prefix for all keys.
+	pub fn with_subdir(mut self, subdir: impl Into<SmolPath>) -> Self {
+		self.subdir = Some(subdir.into());
+		self
+	}
+
+	/// Create a [`TypedBlob`] handle for a single object in this store.
+	pub fn blob(&self, path: SmolPath) -> TypedBlob<Self> {
+		TypedBlob::new(self.clone(), path)
+	}
+
+	/// Resolve an external path to the internal key by prepending the subdir.
+	fn resolve_key(&self, path: &SmolPath) -> SmolPath {
+		match &self.subdir {
+			Some(sub) => sub.join(path),
+			None => path.clone(),
+		}
+	}
+
+	/// The key prefix of this store's subdir, empty at the root.
+	fn key_prefix(&self) -> String {
+		match &self.subdir {
+			Some(sub) => format!("{sub}/"),
+			None => String::new(),
+		}
+	}
+
+	/// The quoted table name, safe to splice into a statement.
+	fn table(&self) -> String { quote(&self.table_name) }
+
+	/// Run `func` against the shared connection on the `blocking` pool, opening
+	/// the database on first use.
+	fn with_conn<O: 'static + Send>(
+		&self,
+		func: impl 'static + Send + FnOnce(&Self, &Connection) -> Result<O>,
+	) -> SendBoxedFuture<Result<O>> {
+		let this = self.clone();
+		Box::pin(blocking::unblock(move || {
+			let mut guard = this.inner.conn.lock().unwrap();
+			if guard.is_none() {
+				*guard = Some(this.open()?);
+			}
+			func(&this, guard.as_ref().unwrap())
+		}))
+	}
+
+	/// Open the database, creating a file's parent directories.
+	fn open(&self) -> Result<Connection> {
+		if self.path == Self::MEMORY_PATH {
+			return Connection::open_in_memory()?.xok();
+		}
+		if let Some(parent) = std::path::Path::new(self.path.as_str()).parent()
+		{
+			std::fs::create_dir_all(parent)?;
+		}
+		let conn = Connection::open(self.path.as_str())?;
+		// write-ahead logging lets readers in other processes (ie a backup or a
+		// `sqlite3` shell) proceed while this one writes.
+		conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+		conn.busy_timeout(Duration::from_secs(5))?;
+		conn.xok()
+	}
+
+	/// Subscribe a watcher, setting the bus on the first subscriber.
+	pub(crate) fn subscribe(&self, sender: async_channel::Sender<BlobEvent>) {
+		if self.inner.subscribers.fetch_add(1, Ordering::SeqCst) == 0 {
+			*self.inner.bus.lock().unwrap() = Some(sender);
+		}
+	}
+
+	/// Unsubscribe a watcher, clearing the bus on the last subscriber.
+	pub(crate) fn unsubscribe(&self) {
+		if self.inner.subscribers.fetch_sub(1, Ordering::SeqCst) == 1 {
+			*self.inner.bus.lock().unwrap() = None;
+		}
+	}
+
+	/// Emit a [`BlobEvent`] built from `self` if a bus is subscribed.
+	fn emit(&self, path: &SmolPath, kind: BlobEventKind) {
+		if let Some(sender) = self.inner.bus.lock().unwrap().as_ref() {
+			let event = BlobEvent::new(
+				BlobStore::new(self.clone()),
+				path.clone(),
+				kind,
+			);
+			sender.try_send(event).ok();
+		}
+	}
+
+	/// Emit the write event for `path`, `Changed` if the key `existed`.
+	fn emit_write(&self, path: &SmolPath, existed: bool) {
+		self.emit(path, match existed {
+			true => BlobEventKind::Changed,
+			false => BlobEventKind::Created,
+		});
+	}
+
+	/// Whether the store's table exists.
+	fn table_exists(&self, conn: &Connection) -> Result<bool> {
+		conn.query_row(
+			"SELECT count(*) FROM sqlite_master \
+			WHERE type = 'table' AND name = ?1",
+			[self.table_name.as_str()],
+			|row| row.get::<_, i64>(0),
+		)?
+		.xmap(|count| count > 0)
+		.xok()
+	}
+
+	/// Whether an object or row exists at the resolved `key`.
+	fn key_exists(&self, conn: &Connection, key: &SmolPath) -> Result<bool> {
+		conn.query_row(
+			&format!(
+				"SELECT count(*) FROM {} WHERE {KEY_COLUMN} = ?1",
+				self.table()
+			),
+			[key.as_str()],
+			|row| row.get::<_, i64>(0),
+		)?
+		.xmap(|count| count > 0)
+		.xok()
+	}
+
+	/// The declared type of every column, keyed by name.
+	fn columns(&self, conn: &Connection) -> Result<HashMap<String, String>> {
+		let mut stmt =
+			conn.prepare(&format!("PRAGMA table_info({})", self.table()))?;
+		stmt.query_map([], |row| {
+			Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+		})?
+		.collect::<rusqlite::Result<HashMap<_, _>>>()?
+		.xok()
+	}
+
+	/// Read every entry whose key starts with this store's prefix, or only
+	/// the entry at the resolved `key`.
+	fn read_rows(
+		&self,
+		conn: &Connection,
+		key: Option<&SmolPath>,
+	) -> Result<Vec<(SmolPath, Result<Value>)>> {
+		match key {
+			Some(key) => self.select_rows(
+				conn,
+				&format!(
+					"SELECT * FROM {} WHERE {KEY_COLUMN} = ?1",
+					self.table()
+				),
+				[key.as_str()],
+			),
+			None => self.select_rows(
+				conn,
+				&format!(
+					"SELECT * FROM {} WHERE {IN_PREFIX} ORDER BY {KEY_COLUMN}",
+					self.table()
+				),
+				[self.key_prefix()],
+			),
+		}
+	}
+
+	/// Run the `SELECT *` statement `sql`, decoding each entry into its row
+	/// document with the key's prefix stripped.
+	fn select_rows(
+		&self,
+		conn: &Connection,
+		sql: &str,
+		params: impl rusqlite::Params,
+	) -> Result<Vec<(SmolPath, Result<Value>)>> {
+		let types = self.columns(conn)?;
+		let prefix = self.key_prefix();
+		let mut stmt = conn.prepare(sql)?;
+		let names = stmt
+			.column_names()
+			.into_iter()
+			.map(String::from)
+			.collect::<Vec<_>>();
+		let mut rows = stmt.query(params)?;
+		let mut out = Vec::new();
+		while let Some(row) = rows.next()? {
+			let key: String = row.get(KEY_COLUMN)?;
+			let path = SmolPath::new(key.strip_prefix(&prefix).unwrap_or(&key));
+			let mut map = Map::default();
+			let mut blob = None;
+			let mut decoded: Result = Ok(());
+			for (idx, name) in names.iter().enumerate() {
+				match (name.as_str(), row.get_ref(idx)?) {
+					(KEY_COLUMN, _) | (_, ValueRef::Null) => {}
+					(BLOB_COLUMN, ValueRef::Blob(bytes)) => {
+						blob = Some(bytes.to_vec());
+					}
+					(_, cell) => {
+						let decl = types.get(name).map(String::as_str);
+						match decode_cell(decl.unwrap_or_default(), cell) {
+							Ok(value) => {
+								map.insert(name.as_str(), value);
+							}
+							Err(err) => decoded = Err(err),
+						}
+					}
+				}
+			}
+			let doc = decoded.and_then(|()| match blob {
+				// an object written through the blob surface, ie by the json
+				// table adapter, reads back as the document it encodes
+				Some(bytes) if map.is_empty() => serde_json::from_slice(&bytes)
+					.map_err(|err| bevyhow!("Failed to deserialize: {err}")),
+				_ => Value::Map(map).xok(),
+			});
+			out.push((path, doc));
+		}
+		out.xok()
+	}
+
+	/// Serve `query` with one `SELECT` on the row columns: its filters as the
+	/// `WHERE`, its order as the `ORDER BY` with the key breaking ties, its
+	/// cursor as a keyset condition on the order column and key, and its limit
+	/// as the `LIMIT`.
+	///
+	/// `None` when SQL would not answer it as [`TableQuery::scan`] does, left
+	/// to the scan: a field that is not a column typed for one kind of value,
+	/// a value of another kind than its column, or a row stored as a blob.
+	fn select_page(
+		&self,
+		conn: &Connection,
+		query: &TableQuery,
+	) -> Result<Option<TablePage<Value>>> {
+		let table = self.table();
+		let prefix = self.key_prefix();
+		// a blob row decodes to a document only in rust
+		let has_blobs = conn.query_row(
+			&format!(
+				"SELECT EXISTS (SELECT 1 FROM {table} \
+				WHERE {IN_PREFIX} AND {BLOB_COLUMN} IS NOT NULL)"
+			),
+			[prefix.as_str()],
+			|row| row.get::<_, bool>(0),
+		)?;
+		if has_blobs {
+			return None.xok();
+		}
+		let types = self.columns(conn)?;
+		let mut conditions = vec![IN_PREFIX.to_string()];
+		let mut params = vec![SqlValue::Text(prefix.clone())];
+		for filter in &query.filters {
+			let Some(column) = SqlColumn::new(&types, &prefix, filter.field())
+			else {
+				return None.xok();
+			};
+			let name = &column.name;
+			let condition = match filter {
+				TableFilter::Eq { value, .. } => {
+					let Some(cell) = column.cell(value) else {
+						return None.xok();
+					};
+					format!("{name} = {}", bind(&mut params, cell))
+				}
+				TableFilter::Range { start, end, .. } => {
+					let mut condition = format!("{name} IS NOT NULL");
+					for (bound, inclusive, exclusive) in
+						[(start, ">=", ">"), (end, "<=", "<")]
+					{
+						let (op, value) = match bound {
+							Bound::Included(value) => (inclusive, value),
+							Bound::Excluded(value) => (exclusive, value),
+							Bound::Unbounded => continue,
+						};
+						let Some(cell) = column.cell(value) else {
+							return None.xok();
+						};
+						let param = bind(&mut params, cell);
+						condition =
+							format!("{condition} AND {name} {op} {param}");
+					}
+					condition
+				}
+			};
+			conditions.push(condition);
+		}
+		let order_column = match &query.order_by {
+			Some(field) => match SqlColumn::new(&types, &prefix, field) {
+				Some(column) => Some(column),
+				None => return None.xok(),
+			},
+			None => None,
+		};
+		if let Some(cursor) = &query.cursor {
+			let Some(Value::Str(key)) = cursor.0.get(TableQuery::KEY) else {
+				return None.xok();
+			};
+			let key =
+				bind(&mut params, SqlValue::Text(format!("{prefix}{key}")));
+			let after = match query.order {
+				SortOrder::Ascending => ">",
+				SortOrder::Descending => "<",
+			};
+			let condition = match &order_column {
+				None => format!("{KEY_COLUMN} {after} {key}"),
+				Some(column) => {
+					let name = &column.name;
+					let value = match query
+						.order_by
+						.as_ref()
+						.and_then(|field| cursor.0.get(field))
+					{
+						Some(value) => match column.cell(value) {
+							Some(cell) => Some(bind(&mut params, cell)),
+							None => return None.xok(),
+						},
+						None => None,
+					};
+					// an absent field sorts first, as `NULL` does ascending
+					match (query.order, value) {
+						(SortOrder::Ascending, Some(value)) => format!(
+							"({name} > {value} \
+							OR ({name} = {value} AND {KEY_COLUMN} > {key}))"
+						),
+						(SortOrder::Descending, Some(value)) => format!(
+							"({name} < {value} \
+							OR ({name} = {value} AND {KEY_COLUMN} < {key}) \
+							OR {name} IS NULL)"
+						),
+						(SortOrder::Ascending, None) => {
+							format!(
+								"({name} IS NOT NULL OR {KEY_COLUMN} > {key})"
+							)
+						}
+						(SortOrder::Descending, None) => {
+							format!("({name} IS NULL AND {KEY_COLUMN} < {key})")
+						}
+					}
+				}
+			};
+			conditions.push(condition);
+		}
+		let dir = match query.order {
+			SortOrder::Ascending => "ASC",
+			SortOrder::Descending => "DESC",
+		};
+		let order = match &order_column {
+			Some(column) => {
+				format!("{} {dir}, {KEY_COLUMN} {dir}", column.name)
+			}
+			None => format!("{KEY_COLUMN} {dir}"),
+		};
+		// one row past the limit tells whether another page follows
+		let limit = match query.limit {
+			Some(limit) => format!(
+				" LIMIT {}",
+				bind(
+					&mut params,
+					SqlValue::Integer(limit.saturating_add(1) as i64)
+				)
+			),
+			None => String::new(),
+		};
+		let mut rows = self.select_rows(
+			conn,
+			&format!(
+				"SELECT * FROM {table} WHERE {} ORDER BY {order}{limit}",
+				conditions.join(" AND ")
+			),
+			rusqlite::params_from_iter(params),
+		)?;
+		let more = query.limit.is_some_and(|limit| rows.len() > limit);
+		if let Some(limit) = query.limit {
+			rows.truncate(limit);
+		}
+		let mut page = Vec::new();
+		for (path, row) in rows {
+			match row {
+				Ok(row) => page.push((path, row)),
+				Err(err) if query.lossy => {
+					warn!("skipping unreadable row {path}: {err}");
+				}
+				Err(err) => return Err(err),
+			}
+		}
+		let cursor = page
+			.last()
+			.filter(|_| more)
+			.map(|(path, row)| query.cursor_after(path.as_str(), row));
+		Some(TablePage {
+			rows: page.into_iter().map(|(_, row)| row).collect(),
+			cursor,
+		})
+		.xok()
+	}
+
+	/// The bytes of the object at the resolved `key`, `None` when absent. A
+	/// table row reads as its json document, as it would from the json table
+	/// adapter.
+	fn read_bytes(
+		&self,
+		conn: &Connection,
+		key: &SmolPath,
+	) -> Result<Option<Bytes>> {
+		let blob = conn
+			.query_row(
+				&format!(
+					"SELECT {BLOB_COLUMN} FROM {} WHERE {KEY_COLUMN} = ?1",
+					self.table()
+				),
+				[key.as_str()],
+				|row| row.get::<_, Option<Vec<u8>>>(0),
+			)
+			.optional()?;
+		match blob {
+			Some(Some(bytes)) => Some(Bytes::from(bytes)).xok(),
+			Some(None) => serde_json::to_vec(&self.read_row(conn, key)?)?
+				.xmap(|bytes| Some(Bytes::from(bytes)))
+				.xok(),
+			None => None.xok(),
+		}
+	}
+
+	/// Fail with [`HttpError::precondition_failed`] unless the content hash of
+	/// the object at `key` is `etag`, or for `None` unless no object exists
+	/// there. Run in one [`immediate`] transaction with the write it guards,
+	/// so no other connection, in this process or another, writes in between.
+	fn check_etag(
+		&self,
+		conn: &Connection,
+		key: &SmolPath,
+		etag: Option<&str>,
+	) -> Result {
+		let current = self
+			.read_bytes(conn, key)?
+			.map(|bytes| content_etag(&bytes));
+		if current.as_deref() == etag {
+			Ok(())
+		} else {
+			Err(HttpError::precondition_failed(match etag {
+				Some(_) => format!("object changed since read: {key}"),
+				None => format!("object already exists: {key}"),
+			})
+			.into())
+		}
+	}
+
+	/// Read the row document at the resolved `key`, a 404 when absent.
+	fn read_row(&self, conn: &Connection, key: &SmolPath) -> Result<Value> {
+		match self.read_rows(conn, Some(key))?.pop() {
+			Some((_, row)) => row,
+			None => Err(not_found(key)),
+		}
+	}
+
+	/// Write the `row` document at the resolved `key`, adding a column for each
+	/// field not seen before. Run in an [`immediate`] transaction, so a
+	/// failed write leaves no column behind.
+	fn write_row(
+		&self,
+		conn: &Connection,
+		key: &SmolPath,
+		row: Value,
+	) -> Result {
+		let Value::Map(row) = row else {
+			bevybail!("sqlite rows must be maps, got {row}");
+		};
+		let mut types = self.columns(conn)?;
+		let mut names = vec![quote(KEY_COLUMN)];
+		let mut values = vec![SqlValue::Text(key.to_string())];
+		for (name, value) in row {
+			if value.is_null() {
+				continue;
+			}
+			if name == KEY_COLUMN || name == BLOB_COLUMN {
+				bevybail!("`{name}` is a reserved sqlite store column");
+			}
+			let (decl, cell) = encode_cell(value.clone())?;
+			let cell = match types.get(name.as_str()) {
+				None => {
+					conn.execute(
+						&format!(
+							"ALTER TABLE {} ADD COLUMN {} {decl}",
+							self.table(),
+							quote(&name)
+						),
+						[],
+					)?;
+					types.insert(name.to_string(), decl.to_string());
+					cell
+				}
+				Some(existing) if existing == decl => cell,
+				// a json column holds any kind, ie the unit and data variants
+				// of one enum field
+				Some(existing) if existing == JSON_TYPE => {
+					SqlValue::Text(serde_json::to_string(&value)?)
+				}
+				Some(existing) => {
+					self.widen_to_json(conn, &name, existing)?;
+					types.insert(name.to_string(), JSON_TYPE.to_string());
+					SqlValue::Text(serde_json::to_string(&value)?)
+				}
+			};
+			names.push(quote(&name));
+			values.push(cell);
+		}
+		conn.execute(
+			&format!(
+				"INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+				self.table(),
+				names.join(", "),
+				(1..=values.len())
+					.map(|idx| format!("?{idx}"))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+			rusqlite::params_from_iter(values),
+		)?;
+		Ok(())
+	}
+
+	/// Retype the column `name` from `decl` to [`JSON_TYPE`], rewriting each
+	/// cell as the json text of the value it decodes to, so rows written
+	/// before a field changed kind stay readable.
+	fn widen_to_json(
+		&self,
+		conn: &Connection,
+		name: &str,
+		decl: &str,
+	) -> Result {
+		let column = quote(name);
+		let cell = match decl {
+			"BOOLEAN" => {
+				format!("CASE WHEN {column} THEN 'true' ELSE 'false' END")
+			}
+			"INTEGER" | "REAL" => format!("CAST({column} AS TEXT)"),
+			"TEXT" => format!("json_quote({column})"),
+			_ => bevybail!("column `{name}` is {decl}, cannot widen to json"),
+		};
+		let table = self.table();
+		let widened = quote(&format!("{name}_json"));
+		conn.execute_batch(&format!(
+			"ALTER TABLE {table} ADD COLUMN {widened} {JSON_TYPE}; \
+			UPDATE {table} SET {widened} = {cell} WHERE {column} IS NOT NULL; \
+			ALTER TABLE {table} DROP COLUMN {column}; \
+			ALTER TABLE {table} RENAME COLUMN {widened} TO {column};"
+		))?;
+		Ok(())
+	}
+}
+
+/// Insert both erased store currencies: the [`BlobStore`] every provider gets,
+/// then the native [`TableStore`], overriding the json-over-blobs table the
+/// blob hook materializes.
+fn on_add_sqlite(mut world: DeferredWorld, cx: HookContext) {
+	BlobStore::on_add::<SqliteStore>(world.reborrow(), cx);
+	TableStore::on_add::<SqliteStore>(world, cx);
+}
+
+/// Run `func` in one `BEGIN IMMEDIATE` transaction, committed only when it
+/// succeeds. Immediate takes the write lock up front, so the reads `func`
+/// makes cannot go stale before its write, even against another process
+/// sharing the database file.
+fn immediate<O>(
+	conn: &Connection,
+	func: impl FnOnce(&Connection) -> Result<O>,
+) -> Result<O> {
+	let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+	let out = func(&tx)?;
+	tx.commit()?;
+	out.xok()
+}
+
+/// Push `cell` onto `params`, returning the placeholder binding it.
+fn bind(params: &mut Vec<SqlValue>, cell: SqlValue) -> String {
+	params.push(cell);
+	format!("?{}", params.len())
+}
+
+/// A row field [`SqliteStore::select_page`] compares in SQL as
+/// [`TableQuery::scan`] would: the key, or a column typed for one kind of
+/// value.
+struct SqlColumn {
+	/// The column, safe to splice into a statement.
+	name: String,
+	/// The declared type of the column.
+	decl: String,
+	/// The store's key prefix when this is the key column.
+	key_prefix: Option<String>,
+}
+
+impl SqlColumn {
+	/// The column holding `field`, `None` for a field with no typed column.
+	fn new(
+		types: &HashMap<String, String>,
+		key_prefix: &str,
+		field: &str,
+	) -> Option<Self> {
+		if field == TableQuery::KEY {
+			return Some(Self {
+				name: KEY_COLUMN.to_string(),
+				decl: "TEXT".to_string(),
+				key_prefix: Some(key_prefix.to_string()),
+			});
+		}
+		if field == KEY_COLUMN || field == BLOB_COLUMN {
+			return None;
+		}
+		let decl = types.get(field)?;
+		matches!(decl.as_str(), "BOOLEAN" | "INTEGER" | "REAL" | "TEXT").then(
+			|| Self {
+				name: quote(field),
+				decl: decl.clone(),
+				key_prefix: None,
+			},
+		)
+	}
+
+	/// The cell `value` compares against this column as, `None` for a value
+	/// of another kind, which a scan never matches but SQLite might.
+	fn cell(&self, value: &Value) -> Option<SqlValue> {
+		match (self.decl.as_str(), value) {
+			("BOOLEAN", Value::Bool(val)) => SqlValue::Integer(*val as i64),
+			("INTEGER" | "REAL", Value::Int(val)) => SqlValue::Integer(*val),
+			("INTEGER" | "REAL", Value::Uint(val)) => {
+				SqlValue::Integer(i64::try_from(*val).ok()?)
+			}
+			("INTEGER" | "REAL", Value::Float(val)) => SqlValue::Real(*val),
+			("TEXT", Value::Str(val)) => match &self.key_prefix {
+				Some(prefix) => SqlValue::Text(format!("{prefix}{val}")),
+				None => SqlValue::Text(val.to_string()),
+			},
+			_ => return None,
+		}
+		.xsome()
+	}
+}
+
+/// Quote an identifier, doubling any embedded quote.
+fn quote(ident: &str) -> String {
+	format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// The 404 for a missing key, matching every other backend.
+fn not_found(key: &SmolPath) -> BevyError {
+	HttpError::new(StatusCode::NOT_FOUND, format!("object not found: {key}"))
+		.into()
+}
+
+/// The declared column type and cell for a non-null field value.
+fn encode_cell(value: Value) -> Result<(&'static str, SqlValue)> {
+	match value {
+		Value::Bool(val) => ("BOOLEAN", SqlValue::Integer(val as i64)),
+		Value::Int(val) => ("INTEGER", SqlValue::Integer(val)),
+		Value::Uint(val) => match i64::try_from(val) {
+			Ok(val) => ("INTEGER", SqlValue::Integer(val)),
+			// past an i64 the json text keeps the full value
+			Err(_) => (JSON_TYPE, SqlValue::Text(val.to_string())),
+		},
+		Value::Float(val) => ("REAL", SqlValue::Real(val)),
+		Value::Bytes(val) => ("BLOB", SqlValue::Blob(val)),
+		Value::Str(val) => ("TEXT", SqlValue::Text(val.to_string())),
+		value @ (Value::Null | Value::Map(_) | Value::List(_)) => {
+			(JSON_TYPE, SqlValue::Text(serde_json::to_string(&value)?))
+		}
+	}
+	.xok()
+}
+
+/// Decode a non-null cell by its column's declared type.
+fn decode_cell(decl: &str, cell: ValueRef) -> Result<Value> {
+	match cell {
+		ValueRef::Null => Value::Null,
+		ValueRef::Integer(val) if decl == "BOOLEAN" => Value::Bool(val != 0),
+		ValueRef::Integer(val) => Value::Int(val),
+		ValueRef::Real(val) => Value::Float(val),
+		ValueRef::Text(text) if decl == JSON_TYPE => {
+			serde_json::from_slice(text)
+				.map_err(|err| bevyhow!("Failed to deserialize: {err}"))?
+		}
+		ValueRef::Text(text) => Value::Str(core::str::from_utf8(text)?.into()),
+		ValueRef::Blob(bytes) => Value::Bytes(bytes.to_vec()),
+	}
+	.xok()
+}
+
+impl BlobStoreProvider for SqliteStore {
+	fn box_clone(&self) -> Box<dyn BlobStoreProvider> { Box::new(self.clone()) }
+
+	fn with_subdir(&self, path: SmolPath) -> Box<dyn BlobStoreProvider> {
+		Box::new(SqliteStore {
+			path: self.path.clone(),
+			table_name: self.table_name.clone(),
+			subdir: Some(match &self.subdir {
+				Some(existing) => existing.join(&path),
+				None => path,
+			}),
+			inner: self.inner.clone(),
+		})
+	}
+
+	fn id(&self) -> &'static str { "sqlite" }
+
+	fn root_key(&self) -> SmolStr {
+		match self.path == Self::MEMORY_PATH {
+			true => format!(
+				"sqlite:memory:{}/{}",
+				self.inner.instance_id, self.table_name
+			),
+			false => format!("sqlite:{}/{}", self.path, self.table_name),
+		}
+		.into()
+	}
+
+	fn subdir(&self) -> SmolPath { self.subdir.clone().unwrap_or_default() }
+
+	fn region(&self) -> Option<String> { None }
+
+	fn store_exists(&self) -> SendBoxedFuture<Result<bool>> {
+		self.with_conn(|this, conn| this.table_exists(conn))
+	}
+
+	fn store_create(&self) -> SendBoxedFuture<Result> {
+		self.with_conn(|this, conn| {
+			if this.table_exists(conn)? {
+				bevybail!("store already exists")
+			}
+			conn.execute(
+				&format!(
+					"CREATE TABLE {} ({KEY_COLUMN} TEXT PRIMARY KEY NOT NULL, \
+					{BLOB_COLUMN} BLOB)",
+					this.table()
+				),
+				[],
+			)?;
+			Ok(())
+		})
+	}
+
+	fn store_remove(&self) -> SendBoxedFuture<Result> {
+		self.with_conn(|this, conn| {
+			if !this.table_exists(conn)? {
+				bevybail!("store does not exist")
+			}
+			conn.execute(&format!("DROP TABLE {}", this.table()), [])?;
+			Ok(())
+		})
+	}
+
+	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		let key = self.resolve_key(path);
+		let path = path.clone();
+		self.with_conn(move |this, conn| {
+			let existed = this.key_exists(conn, &key)?;
+			conn.execute(
+				&format!(
+					"INSERT OR REPLACE INTO {} ({KEY_COLUMN}, {BLOB_COLUMN}) \
+					VALUES (?1, ?2)",
+					this.table()
+				),
+				rusqlite::params![key.as_str(), body.as_ref()],
+			)?;
+			this.emit_write(&path, existed);
+			Ok(())
+		})
+	}
+
+	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
+		let key = self.resolve_key(path);
+		self.with_conn(move |this, conn| this.key_exists(conn, &key))
+	}
+
+	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
+		self.with_conn(|this, conn| {
+			let prefix = this.key_prefix();
+			let mut stmt = conn.prepare(&format!(
+				"SELECT {KEY_COLUMN} FROM {} \
+				WHERE substr({KEY_COLUMN}, 1, length(?1)) = ?1 \
+				ORDER BY {KEY_COLUMN}",
+				this.table()
+			))?;
+			stmt.query_map([prefix.as_str()], |row| row.get::<_, String>(0))?
+				.map(|key| {
+					let key = key?;
+					SmolPath::new(key.strip_prefix(&prefix).unwrap_or(&key))
+						.xok()
+				})
+				.collect()
+		})
+	}
+
+	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
+		let key = self.resolve_key(path);
+		self.with_conn(move |this, conn| {
+			this.read_bytes(conn, &key)?.ok_or_else(|| not_found(&key))
+		})
+	}
+
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let key = self.resolve_key(path);
+		let path = path.clone();
+		self.with_conn(move |this, conn| {
+			immediate(conn, |conn| {
+				this.check_etag(conn, &key, None)?;
+				conn.execute(
+					&format!(
+						"INSERT INTO {} ({KEY_COLUMN}, {BLOB_COLUMN}) \
+						VALUES (?1, ?2)",
+						this.table()
+					),
+					rusqlite::params![key.as_str(), body.as_ref()],
+				)?;
+				Ok(())
+			})?;
+			this.emit_write(&path, false);
+			Ok(())
+		})
+	}
+
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let key = self.resolve_key(path);
+		let path = path.clone();
+		let etag = SmolStr::from(etag);
+		self.with_conn(move |this, conn| {
+			immediate(conn, |conn| {
+				this.check_etag(conn, &key, Some(&etag))?;
+				conn.execute(
+					&format!(
+						"INSERT OR REPLACE INTO {} \
+						({KEY_COLUMN}, {BLOB_COLUMN}) VALUES (?1, ?2)",
+						this.table()
+					),
+					rusqlite::params![key.as_str(), body.as_ref()],
+				)?;
+				Ok(())
+			})?;
+			this.emit_write(&path, true);
+			Ok(())
+		})
+	}
+
+	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
+		let key = self.resolve_key(path);
+		let path = path.clone();
+		self.with_conn(move |this, conn| {
+			let removed = conn.execute(
+				&format!(
+					"DELETE FROM {} WHERE {KEY_COLUMN} = ?1",
+					this.table()
+				),
+				[key.as_str()],
+			)?;
+			if removed == 0 {
+				return Err(not_found(&key));
+			}
+			this.emit(&path, BlobEventKind::Removed);
+			Ok(())
+		})
+	}
+
+	fn public_url(
+		&self,
+		_path: &SmolPath,
+	) -> SendBoxedFuture<Result<Option<String>>> {
+		Box::pin(async move { None.xok() })
+	}
+}
+
+impl TableProvider for SqliteStore {
+	fn box_clone_table(&self) -> Box<dyn TableProvider> {
+		Box::new(self.clone())
+	}
+
+	fn insert_row(&self, id: Uuid, row: Value) -> SendBoxedFuture<Result> {
+		let path = SmolPath::new(id.to_string());
+		let key = self.resolve_key(&path);
+		self.with_conn(move |this, conn| {
+			let existed = immediate(conn, |conn| {
+				let existed = this.key_exists(conn, &key)?;
+				this.write_row(conn, &key, row)?;
+				existed.xok()
+			})?;
+			this.emit_write(&path, existed);
+			Ok(())
+		})
+	}
+
+	fn get_row(&self, id: Uuid) -> SendBoxedFuture<Result<Value>> {
+		let key = self.resolve_key(&SmolPath::new(id.to_string()));
+		self.with_conn(move |this, conn| this.read_row(conn, &key))
+	}
+
+	/// The etag is the content hash of the row's json document, read with the
+	/// row under one connection lock.
+	fn get_row_versioned(
+		&self,
+		id: Uuid,
+	) -> SendBoxedFuture<Result<VersionedRow>> {
+		let key = self.resolve_key(&SmolPath::new(id.to_string()));
+		self.with_conn(move |this, conn| {
+			let bytes = this
+				.read_bytes(conn, &key)?
+				.ok_or_else(|| not_found(&key))?;
+			VersionedRow {
+				row: this.read_row(conn, &key)?,
+				etag: content_etag(&bytes),
+			}
+			.xok()
+		})
+	}
+
+	fn insert_row_if_absent(
+		&self,
+		id: Uuid,
+		row: Value,
+	) -> SendBoxedFuture<Result> {
+		let path = SmolPath::new(id.to_string());
+		let key = self.resolve_key(&path);
+		self.with_conn(move |this, conn| {
+			immediate(conn, |conn| {
+				this.check_etag(conn, &key, None)?;
+				this.write_row(conn, &key, row)
+			})?;
+			this.emit_write(&path, false);
+			Ok(())
+		})
+	}
+
+	fn insert_row_if_match(
+		&self,
+		id: Uuid,
+		row: Value,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let path = SmolPath::new(id.to_string());
+		let key = self.resolve_key(&path);
+		let etag = SmolStr::from(etag);
+		self.with_conn(move |this, conn| {
+			immediate(conn, |conn| {
+				this.check_etag(conn, &key, Some(&etag))?;
+				this.write_row(conn, &key, row)
+			})?;
+			this.emit_write(&path, true);
+			Ok(())
+		})
+	}
+
+	/// Read every row in one `SELECT` rather than the default list and fetch.
+	fn get_all_rows(
+		&self,
+	) -> SendBoxedFuture<Result<Vec<(SmolPath, Result<Value>)>>> {
+		self.with_conn(|this, conn| this.read_rows(conn, None))
+	}
+
+	/// Answer the query in SQL on the row columns (see
+	/// [`SqliteStore::select_page`]), otherwise with the default scan.
+	fn query_rows(
+		&self,
+		query: TableQuery,
+	) -> SendBoxedFuture<Result<TablePage<Value>>> {
+		self.with_conn(move |this, conn| {
+			match this.select_page(conn, &query)? {
+				Some(page) => page.xok(),
+				None => query.scan(this.read_rows(conn, None)?),
+			}
+		})
+	}
+}
+
+/// Subscribe the added [`SqliteStore`] to the [`BlobEventBus`], so its writes
+/// emit [`BlobEvent`]s. Refcounted per backing `Arc`, so a `with_subdir` clone
+/// adds no duplicate sends.
+pub(crate) fn add_sqlite_store_watcher(
+	ev: On<Add, SqliteStore>,
+	bus: Res<BlobEventBus>,
+	stores: Query<&SqliteStore>,
+) {
+	if let Ok(store) = stores.get(ev.entity) {
+		store.subscribe(bus.sender.clone());
+	}
+}
+
+/// Unsubscribe the removed [`SqliteStore`], clearing the bus on the last
+/// subscriber.
+pub(crate) fn remove_sqlite_store_watcher(
+	ev: On<Remove, SqliteStore>,
+	stores: Query<&SqliteStore>,
+) {
+	if let Ok(store) = stores.get(ev.entity) {
+		store.unsubscribe();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	struct Account {
+		id: Uuid,
+		name: String,
+		visits: u32,
+		admin: bool,
+		nickname: Option<String>,
+		tags: Vec<String>,
+	}
+
+	impl TableStoreRow for Account {
+		fn id(&self) -> Uuid { self.id }
+	}
+
+	#[beet_core::test]
+	async fn store() { store_test::run(SqliteStore::new_in_memory()).await; }
+
+	#[beet_core::test]
+	async fn table() { table_test::run(SqliteStore::new_in_memory()).await; }
+
+	#[beet_core::test]
+	async fn rows_are_columns() {
+		let store = SqliteStore::new_in_memory();
+		let table = Table::<Account>::new(store.clone());
+		table.store_try_create().await.unwrap();
+		let account = Account {
+			id: uuid_ext::now_v7(),
+			name: "ada".into(),
+			visits: 3,
+			admin: true,
+			nickname: None,
+			tags: vec!["math".into()],
+		};
+		table.push(account.clone()).await.unwrap();
+		table
+			.get(account.id)
+			.await
+			.unwrap()
+			.xpect_eq(account.clone());
+
+		let mut columns = store
+			.with_conn(|this, conn| this.columns(conn))
+			.await
+			.unwrap()
+			.into_iter()
+			.collect::<Vec<_>>();
+		columns.sort();
+		columns.xpect_eq(
+			[
+				("_blob", "BLOB"),
+				("_key", "TEXT"),
+				("admin", "BOOLEAN"),
+				("id", "TEXT"),
+				("name", "TEXT"),
+				("tags", "JSON"),
+				("visits", "INTEGER"),
+			]
+			.map(|(name, decl)| (name.to_string(), decl.to_string()))
+			.to_vec(),
+		);
+		// plain sql reads the real columns
+		store
+			.with_conn(|this, conn| {
+				conn.query_row(
+					&format!(
+						"SELECT name FROM {} WHERE visits = 3",
+						this.table()
+					),
+					[],
+					|row| row.get::<_, String>(0),
+				)?
+				.xok()
+			})
+			.await
+			.unwrap()
+			.xpect_eq("ada");
+		// the blob surface reads a row as its json document
+		let bytes = BlobStore::new(store)
+			.get(&SmolPath::new(account.id.to_string()))
+			.await
+			.unwrap();
+		serde_json::from_slice::<Account>(&bytes)
+			.unwrap()
+			.xpect_eq(account);
+	}
+
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	enum Shape {
+		Point,
+		Circle { radius: f64 },
+	}
+
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	struct Drawing {
+		id: Uuid,
+		shape: Shape,
+	}
+
+	impl TableStoreRow for Drawing {
+		fn id(&self) -> Uuid { self.id }
+	}
+
+	#[beet_core::test]
+	async fn widens_columns_to_json() {
+		let store = SqliteStore::new_in_memory();
+		let table = Table::<Drawing>::new(store.clone());
+		table.store_try_create().await.unwrap();
+		let drawings =
+			[Shape::Point, Shape::Circle { radius: 2. }].map(|shape| Drawing {
+				id: uuid_ext::now_v7(),
+				shape,
+			});
+		for drawing in &drawings {
+			table.push(drawing.clone()).await.unwrap();
+		}
+		for drawing in drawings {
+			table.get(drawing.id).await.unwrap().xpect_eq(drawing);
+		}
+		store
+			.with_conn(|this, conn| this.columns(conn))
+			.await
+			.unwrap()
+			.get("shape")
+			.cloned()
+			.xpect_eq(Some(JSON_TYPE.to_string()));
+	}
+
+	#[beet_core::test]
+	async fn queries_in_sql() {
+		let store = SqliteStore::new_in_memory();
+		let table = Table::<Account>::new(store.clone());
+		table.store_try_create().await.unwrap();
+		for visits in 0..6 {
+			table
+				.push(Account {
+					id: uuid_ext::now_v7(),
+					name: if visits % 2 == 0 { "ada" } else { "bob" }.into(),
+					visits,
+					admin: visits < 2,
+					nickname: (visits % 3 == 0).then(|| format!("n{visits}")),
+					tags: vec![],
+				})
+				.await
+				.unwrap();
+		}
+		let queries = [
+			TableQuery::new().with_eq("name", "bob").with_limit(2),
+			TableQuery::new()
+				.with_range("visits", 1u32..5)
+				.with_order_by("visits", SortOrder::Descending)
+				.with_limit(2),
+			TableQuery::new()
+				.with_eq("admin", false)
+				.with_order_by("nickname", SortOrder::Ascending)
+				.with_limit(1),
+			TableQuery::new()
+				.with_order_by("nickname", SortOrder::Descending)
+				.with_limit(4),
+		];
+		for query in queries {
+			// every page served in sql, and matching the scan
+			let mut query = query;
+			loop {
+				let sql = query.clone();
+				let page = store
+					.with_conn(move |this, conn| this.select_page(conn, &sql))
+					.await
+					.unwrap()
+					.unwrap();
+				let scan =
+					query.scan(store.get_all_rows().await.unwrap()).unwrap();
+				page.xpect_eq(scan);
+				let Some(cursor) = page.cursor else {
+					break;
+				};
+				query = query.with_cursor(cursor);
+			}
+		}
+		// a json column is left to the scan
+		store
+			.with_conn(|this, conn| {
+				this.select_page(conn, &TableQuery::new().with_eq("tags", 1))
+			})
+			.await
+			.unwrap()
+			.xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn remove_missing_is_not_found() {
+		let store = SqliteStore::new_in_memory();
+		store.store_create().await.unwrap();
+		let err = store.remove(&SmolPath::new("missing")).await.unwrap_err();
+		HttpError::status_of(&err).xpect_eq(Some(StatusCode::NOT_FOUND));
+	}
+
+	#[beet_core::test]
+	async fn persists_to_file() {
+		let path = std::env::temp_dir()
+			.join(format!("beet_sqlite_{}", uuid_ext::now_v7()))
+			.join("store.db");
+		let store = SqliteStore::new(&path).with_subdir("docs");
+		store.store_try_create().await.unwrap();
+		store
+			.insert(&SmolPath::new("index.md"), "# hello".into())
+			.await
+			.unwrap();
+		// a second store over the same file is the same backing store
+		let reopened = SqliteStore::new(&path);
+		reopened.root_key().xpect_eq(store.root_key());
+		reopened
+			.get(&SmolPath::new("docs/index.md"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("# hello"));
+		reopened
+			.list()
+			.await
+			.unwrap()
+			.xpect_eq(vec![SmolPath::new("docs/index.md")]);
+		std::fs::remove_dir_all(path.parent().unwrap()).ok();
+	}
+
+	#[beet_core::test]
+	async fn emits_events() {
+		let store = SqliteStore::new_in_memory();
+		let (sender, receiver) = async_channel::unbounded();
+		store.subscribe(sender);
+		let path = SmolPath::new("a.txt");
+		store.store_create().await.unwrap();
+		store.insert(&path, "1".into()).await.unwrap();
+		store.insert(&path, "2".into()).await.unwrap();
+		store.remove(&path).await.unwrap();
+		core::iter::from_fn(|| receiver.try_recv().ok())
+			.map(|event| event.kind)
+			.collect::<Vec<_>>()
+			.xpect_eq(vec![
+				BlobEventKind::Created,
+				BlobEventKind::Changed,
+				BlobEventKind::Removed,
+			]);
+	}
+}
@@ -1,3 +1,5 @@
+use super::table_migration::decode_row;
+use super::table_migration::encode_row;
 use crate::prelude::*;
 use beet_core::prelude::bevy_ecs::error::ErrorContext;
 use beet_core::prelude::*;
@@ -53,6 +55,23 @@ impl TableStore {
 	#[cfg(feature = "json")]
 	pub fn temp() -> Self { Self::new(BlobStore::temp()) }
 
+	/// The DynamoDB table `table_name`, in `AWS_REGION` else the default
+	/// region. Errors without the `aws_sdk` backend.
+	pub fn remote(table_name: &str) -> Result<Self> {
+		cfg_if! {
+			if #[cfg(all(feature = "aws_sdk", not(target_arch = "wasm32")))] {
+				Self::new(DynamoStore::new(
+					table_name,
+					DynamoStore::env_region(),
+				))
+				.xok()
+			} else {
+				let _ = table_name;
+				bevybail!("a remote table store requires the `aws_sdk` feature")
+			}
+		}
+	}
+
 	/// A typed view over this store, rows serialized at the edge via [`Value`].
 	pub fn table<T: TableStoreRow>(&self) -> Table<T> {
 		Table {
@@ -163,19 +182,176 @@ impl<T: TableStoreRow> Table<T> {
 	/// Insert typed object into table.
 	pub async fn push(&self, body: T) -> Result {
 		let id = body.id();
-		self.provider.insert_row(id, Value::from_serde(body)?).await
+		self.provider.insert_row(id, encode_row(body)?).await
 	}
 
-	/// Insert typed object, failing if it already exists.
+	/// Insert typed object, failing if it already exists. Atomic where the
+	/// provider supports it, see [`TableProvider::insert_row_if_absent`].
 	///
 	/// # Errors
 	/// Returns error if object already exists at path.
 	pub async fn try_push(&self, body: T) -> Result {
 		let id = body.id();
-		if self.exists(id).await? {
-			bevybail!("Row already exists: {}", id)
-		} else {
-			self.push(body).await
+		self.provider
+			.insert_row_if_absent(id, encode_row(body)?)
+			.await
+	}
+
+	/// How many times [`Self::update`] tries a write that keeps losing a race
+	/// with another writer before giving up.
+	pub const UPDATE_ATTEMPTS: usize = 8;
+
+	/// Read the row at `id`, change it with `func` and write it back only if
+	/// nobody wrote it in between, retrying from the read when somebody did, so
+	/// concurrent updates never overwrite each other. Returns the written row.
+	///
+	/// Retries back off exponentially with the jitter of a [`Backoff`], so
+	/// writers that collided spread out rather than colliding again.
+	///
+	/// `func` runs once per try, so it should derive the change from the row
+	/// it is given rather than from captured state it mutates.
+	///
+	/// # Errors
+	/// Returns error if the row doesn't exist or fails to deserialize, or
+	/// still conflicts after [`Self::UPDATE_ATTEMPTS`] tries.
+	///
+	/// # Example
+	/// ```
+	/// # use beet_core::prelude::*;
+	/// # use beet_net::prelude::*;
+	/// # async fn run() -> Result<()> {
+	/// let table = Table::<TableItem<u32>>::temp();
+	/// let item = TableItem::new(0);
+	/// table.push(item.clone()).await?;
+	/// let item = table.update(item.id, |item| item.data += 1).await?;
+	/// assert_eq!(item.data, 1);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn update(
+		&self,
+		id: Uuid,
+		mut func: impl FnMut(&mut T),
+	) -> Result<T> {
+		self.try_update(id, |row| {
+			func(row);
+			Ok(())
+		})
+		.await
+	}
+
+	/// Like [`Self::update`], but `func` may refuse the change by returning
+	/// an error, which is returned with nothing written. The check runs on
+	/// the same read as the write, so no other writer can change the row
+	/// between them.
+	///
+	/// # Errors
+	/// Returns the error of `func`, or as [`Self::update`].
+	pub async fn try_update(
+		&self,
+		id: Uuid,
+		mut func: impl FnMut(&mut T) -> Result,
+	) -> Result<T> {
+		let mut backoff = Backoff::new(
+			Self::UPDATE_ATTEMPTS as u32,
+			Duration::from_millis(5),
+			Duration::from_millis(200),
+		)
+		.iter();
+		loop {
+			let VersionedRow { row, etag } =
+				self.provider.get_row_versioned(id).await?;
+			let mut row = decode_row::<T>(row)?;
+			func(&mut row)?;
+			match self
+				.provider
+				.insert_row_if_match(id, encode_row(row.clone())?, &etag)
+				.await
+			{
+				Ok(()) => return row.xok(),
+				Err(err)
+					if HttpError::status_of(&err)
+						== Some(StatusCode::PRECONDITION_FAILED) =>
+				{
+					// the last try yields no further wait
+					match backoff.next().and_then(|frame| frame.next_attempt) {
+						Some(wait) => time_ext::sleep(wait).await,
+						None => return Err(err),
+					}
+				}
+				Err(err) => return Err(err),
+			}
+		}
+	}
+
+	/// Rewrite every row written at an older schema version at the current
+	/// [`TableStoreRow::VERSION`], the eager counterpart of the upgrade every
+	/// read applies lazily, see [`TableMigrations`].
+	///
+	/// Each row is rewritten with the same conditional write as
+	/// [`Self::update`], so a row written concurrently is never overwritten. A
+	/// row that fails to read, upgrade or write is left as it was and counted
+	/// in [`MigrationReport::failed`] rather than failing the whole pass.
+	///
+	/// # Caution
+	/// Reads every row in the table, bounded by
+	/// [`BlobStore::GET_ALL_CONCURRENCY`].
+	pub async fn migrate(&self) -> Result<MigrationReport> {
+		let mut report = MigrationReport {
+			version: T::VERSION,
+			..default()
+		};
+		let rows = self
+			.list()
+			.await?
+			.into_iter()
+			.map(async |path| {
+				let migrated = match path.to_string().parse::<Uuid>() {
+					Ok(id) => self.migrate_row(id).await,
+					Err(err) => Err(bevyhow!("invalid uuid: {err}")),
+				};
+				(path, migrated)
+			})
+			.xmap(|rows| {
+				async_ext::join_all_bounded(
+					BlobStore::GET_ALL_CONCURRENCY,
+					rows,
+				)
+			})
+			.await;
+		for (path, migrated) in rows {
+			match migrated {
+				Ok(true) => report.migrated += 1,
+				Ok(false) => report.current += 1,
+				Err(err) => {
+					warn!("failed to migrate row {path}: {err}");
+					report.failed += 1;
+				}
+			}
+		}
+		report.xok()
+	}
+
+	/// Rewrite the row at `id` at the current schema version if it is older,
+	/// returning whether it was.
+	async fn migrate_row(&self, id: Uuid) -> Result<bool> {
+		let mut attempts = 0;
+		loop {
+			let VersionedRow { row, etag } =
+				self.provider.get_row_versioned(id).await?;
+			if row_version(&row)? == T::VERSION {
+				return false.xok();
+			}
+			let row = encode_row(decode_row::<T>(row)?)?;
+			attempts += 1;
+			match self.provider.insert_row_if_match(id, row, &etag).await {
+				Ok(()) => return true.xok(),
+				Err(err)
+					if attempts < Self::UPDATE_ATTEMPTS
+						&& HttpError::status_of(&err)
+							== Some(StatusCode::PRECONDITION_FAILED) => {}
+				Err(err) => return Err(err),
+			}
 		}
 	}
 
@@ -195,7 +371,7 @@ impl<T: TableStoreRow> Table<T> {
 	/// # Errors
 	/// Returns error if object doesn't exist or fails to deserialize.
 	pub async fn get(&self, id: Uuid) -> Result<T> {
-		self.provider.get_row(id).await?.into_serde()
+		decode_row(self.provider.get_row(id).await?)
 	}
 
 	/// Get all objects and their typed data.
@@ -207,12 +383,14 @@ impl<T: TableStoreRow> Table<T> {
 			.get_all_rows()
 			.await?
 			.into_iter()
-			.map(|(path, row)| Ok((path, row?.into_serde()?)))
+			.map(|(path, row)| Ok((path, decode_row(row?)?)))
 			.collect()
 	}
 
 	/// Like [`Self::get_all`], but a row that fails to read, parse or
 	/// deserialize is skipped with a warning instead of failing the whole read.
+	/// A row of an older schema version is upgraded rather than skipped, see
+	/// [`TableMigrations`].
 	///
 	/// Prefer for telemetry-style tables (eg analytics) where a legacy-schema or
 	/// corrupt row must not brick every aggregate query over the table.
@@ -226,19 +404,61 @@ impl<T: TableStoreRow> Table<T> {
 			.get_all_rows()
 			.await?
 			.into_iter()
-			.filter_map(|(path, row)| {
-				match row.and_then(|row| row.into_serde::<T>()) {
-					Ok(row) => Some((path, row)),
-					Err(err) => {
-						warn!("skipping unreadable row {path}: {err}");
-						None
-					}
+			.filter_map(|(path, row)| match row.and_then(decode_row::<T>) {
+				Ok(row) => Some((path, row)),
+				Err(err) => {
+					warn!("skipping unreadable row {path}: {err}");
+					None
 				}
 			})
 			.collect::<Vec<_>>()
 			.xok()
 	}
 
+	/// One page of the rows matching `query`, deserialized in query order.
+	///
+	/// Served natively where the provider can (ie a DynamoDB index), otherwise
+	/// by a filtered scan of the whole table, see [`TableQuery`].
+	///
+	/// # Errors
+	/// Returns error if the query fails, or a row fails to read or deserialize
+	/// and the query is not [`lossy`](TableQuery::with_lossy).
+	pub async fn query(&self, query: TableQuery) -> Result<TablePage<T>> {
+		let lossy = query.lossy;
+		let page = self.provider.query_rows(query).await?;
+		let mut rows = Vec::with_capacity(page.rows.len());
+		for row in page.rows {
+			match decode_row::<T>(row) {
+				Ok(row) => rows.push(row),
+				Err(err) if lossy => warn!("skipping unreadable row: {err}"),
+				Err(err) => return Err(err),
+			}
+		}
+		TablePage {
+			rows,
+			cursor: page.cursor,
+		}
+		.xok()
+	}
+
+	/// Every row matching `query`, following its cursor page by page.
+	///
+	/// # Caution
+	/// Collects every match into memory, prefer [`Self::query`] with a limit
+	/// for an unbounded result.
+	pub async fn query_all(&self, mut query: TableQuery) -> Result<Vec<T>> {
+		let mut rows = Vec::new();
+		loop {
+			let page = self.query(query.clone()).await?;
+			rows.extend(page.rows);
+			match page.cursor {
+				Some(cursor) => query.cursor = Some(cursor),
+				None => break,
+			}
+		}
+		rows.xok()
+	}
+
 	/// Remove object from table by id.
 	///
 	/// # Errors
@@ -283,9 +503,33 @@ impl<T: TableStoreRow> Table<T> {
 ///
 /// The serialized row must carry its [`id`](Self::id) as an `id` field, the
 /// primary key a table-native backend (eg DynamoDB) retrieves by.
+///
+/// Usually derived, with an optional schema version and its upgrades:
+///
+/// ```ignore
+/// #[derive(Clone, Serialize, Deserialize, TableStoreRow)]
+/// #[table(version = 1)]
+/// #[table(migrations = TableMigrations::new().with_upgrade(1, add_likes))]
+/// struct Post {
+/// 	id: Uuid,
+/// 	likes: u32,
+/// }
+///
+/// fn add_likes(mut row: Value) -> Result<Value> {
+/// 	row.insert("likes", 0u32)?;
+/// 	row.xok()
+/// }
+/// ```
 pub trait TableStoreRow: TableContent {
+	/// The schema version rows of this type are written at. A row written at
+	/// an older version is upgraded by [`Self::migrations`] as it is read, and
+	/// rewritten by [`Table::migrate`]. Version 0 rows are stored unstamped.
+	const VERSION: u32 = 0;
 	/// Unique identifier for the object, used as the primary key in the table.
 	fn id(&self) -> Uuid;
+	/// The upgrade from each prior [`Self::VERSION`] to the next, none for an
+	/// unversioned row.
+	fn migrations() -> TableMigrations { TableMigrations::default() }
 	/// Decodes the uuid's embedded wall-clock time.
 	/// ## Panics
 	/// Panics if uuid is not v1, v6 or v7.
@@ -335,6 +579,18 @@ impl<T: TableContent> TableStoreRow for TableItem<T> {
 	fn id(&self) -> Uuid { self.id }
 }
 
+/// A row document and the etag of the version read, the token a
+/// compare-and-swap write checks, see
+/// [`TableProvider::insert_row_if_match`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedRow {
+	/// The row document.
+	pub row: Value,
+	/// Opaque version of the row, only comparable between reads of the same
+	/// provider.
+	pub etag: SmolStr,
+}
+
 /// Storage provider for table operations over untyped [`Value`] rows.
 ///
 /// Extends [`BlobStoreProvider`] with document operations, and is deliberately
@@ -349,6 +605,90 @@ pub trait TableProvider: BlobStoreProvider + 'static + Send + Sync {
 	/// Get the row document at `id`.
 	fn get_row(&self, id: Uuid) -> SendBoxedFuture<Result<Value>>;
 
+	/// Get the row document at `id` with the etag of its current version.
+	///
+	/// The default reads the object's [`head`](BlobStoreProvider::head) before
+	/// the row, so a write landing in between pairs the new row with the old
+	/// etag and fails the following
+	/// [`insert_row_if_match`](Self::insert_row_if_match) rather than
+	/// letting it overwrite the write.
+	fn get_row_versioned(
+		&self,
+		id: Uuid,
+	) -> SendBoxedFuture<Result<VersionedRow>> {
+		let head_fut = self.head(&SmolPath::new(id.to_string()));
+		let row_fut = self.get_row(id);
+		Box::pin(async move {
+			let etag = head_fut
+				.await?
+				.etag
+				.ok_or_else(|| bevyhow!("row has no etag: {id}"))?;
+			VersionedRow {
+				row: row_fut.await?,
+				etag,
+			}
+			.xok()
+		})
+	}
+
+	/// Insert the row document at `id` only if no row exists there.
+	///
+	/// The default checks [`exists`](BlobStoreProvider::exists) then inserts,
+	/// which two writers can race; a provider with conditional writes should
+	/// override it.
+	///
+	/// # Errors
+	/// Returns a [`StatusCode::PRECONDITION_FAILED`] [`HttpError`] if the row
+	/// already exists.
+	fn insert_row_if_absent(
+		&self,
+		id: Uuid,
+		row: Value,
+	) -> SendBoxedFuture<Result> {
+		let exists_fut = self.exists(&SmolPath::new(id.to_string()));
+		let insert_fut = self.insert_row(id, row);
+		Box::pin(async move {
+			if exists_fut.await? {
+				Err(HttpError::precondition_failed(format!(
+					"row already exists: {id}"
+				))
+				.into())
+			} else {
+				insert_fut.await
+			}
+		})
+	}
+
+	/// Replace the row document at `id` only if its current version is
+	/// `etag`, as read by [`get_row_versioned`](Self::get_row_versioned).
+	///
+	/// The default compares [`get_row_versioned`](Self::get_row_versioned)
+	/// then inserts, which two writers can race; a provider with conditional
+	/// writes should override it.
+	///
+	/// # Errors
+	/// Returns a [`StatusCode::PRECONDITION_FAILED`] [`HttpError`] if the row
+	/// changed or was removed since `etag` was read.
+	fn insert_row_if_match(
+		&self,
+		id: Uuid,
+		row: Value,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let current_fut = self.get_row_versioned(id);
+		let insert_fut = self.insert_row(id, row);
+		let etag = SmolStr::from(etag);
+		Box::pin(async move {
+			match current_fut.await {
+				Ok(current) if current.etag == etag => insert_fut.await,
+				_ => Err(HttpError::precondition_failed(format!(
+					"row changed since read: {id}"
+				))
+				.into()),
+			}
+		})
+	}
+
 	/// Every row in the table, each paired with the document it read or the
 	/// error that row failed with.
 	///
@@ -387,6 +727,21 @@ pub trait TableProvider: BlobStoreProvider + 'static + Send + Sync {
 				.xok()
 		})
 	}
+
+	/// One page of the rows matching `query`, as row documents in query order.
+	///
+	/// The default answers it with [`TableQuery::scan`] over
+	/// [`Self::get_all_rows`], correct for any provider but a read of the whole
+	/// table. A provider with native filtering, ie a DynamoDB `Query` against a
+	/// declared [`TableIndex`], should override this for the queries it can
+	/// serve and fall back to the default for the rest.
+	fn query_rows(
+		&self,
+		query: TableQuery,
+	) -> SendBoxedFuture<Result<TablePage<Value>>> {
+		let rows = self.get_all_rows();
+		Box::pin(async move { query.scan(rows.await?) })
+	}
 }
 
 /// The [`BlobStore`] wrapper is a [`TableProvider`] for free, encoding rows as
@@ -410,6 +765,38 @@ impl TableProvider for BlobStore {
 		}
 	}
 
+	fn insert_row_if_absent(
+		&self,
+		id: Uuid,
+		row: Value,
+	) -> SendBoxedFuture<Result> {
+		let path = SmolPath::new(id.to_string());
+		match serde_json::to_vec(&row) {
+			Ok(bytes) => self.insert_if_absent(&path, bytes.into()),
+			Err(e) => {
+				Box::pin(async move { bevybail!("Failed to serialize: {}", e) })
+			}
+		}
+	}
+
+	/// Backed by the blob store's own
+	/// [`insert_if_match`](BlobStoreProvider::insert_if_match), the etag
+	/// being the object's.
+	fn insert_row_if_match(
+		&self,
+		id: Uuid,
+		row: Value,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let path = SmolPath::new(id.to_string());
+		match serde_json::to_vec(&row) {
+			Ok(bytes) => self.insert_if_match(&path, bytes.into(), etag),
+			Err(e) => {
+				Box::pin(async move { bevybail!("Failed to serialize: {}", e) })
+			}
+		}
+	}
+
 	fn get_row(&self, id: Uuid) -> SendBoxedFuture<Result<Value>> {
 		let path = SmolPath::new(id.to_string());
 		let fut = BlobStoreProvider::get(self, &path);
@@ -460,8 +847,24 @@ pub mod table_test {
 		table.get(id).await.unwrap().xpect_eq(body.clone());
 		table.get(id).await.unwrap().xpect_eq(body);
 
+		// a second push of the same row is refused, an update applies to the
+		// current row
+		table.try_push(body.clone()).await.xpect_err();
+		table
+			.update(id, |row| row.data.some_key = "updated".into())
+			.await
+			.unwrap();
+		table
+			.get(id)
+			.await
+			.unwrap()
+			.data
+			.some_key
+			.xpect_eq("updated".to_string());
+
 		table.remove(id).await.unwrap();
 		table.get(id).await.xpect_err();
+		table.update(id, |_| {}).await.xpect_err();
 
 		table.store_remove().await.unwrap();
 		table.store_exists().await.unwrap().xpect_false();
@@ -515,6 +918,37 @@ mod test {
 		rows[0].1.id.xpect_eq(valid_id);
 	}
 
+	/// A write against a stale etag is refused rather than overwriting the
+	/// row, as is a second [`Table::try_push`] of one row.
+	#[beet_core::test]
+	async fn stale_etag_is_refused() {
+		let store = BlobStore::temp();
+		let table = Table::<TableItem<u32>>::new(store.clone());
+		let item = TableItem::new(0u32);
+		table.push(item.clone()).await.unwrap();
+		let read = store.get_row_versioned(item.id).await.unwrap();
+		table.update(item.id, |item| item.data += 1).await.unwrap();
+		let err = store
+			.insert_row_if_match(item.id, read.row, &read.etag)
+			.await
+			.unwrap_err();
+		HttpError::status_of(&err)
+			.xpect_eq(Some(StatusCode::PRECONDITION_FAILED));
+		table.get(item.id).await.unwrap().data.xpect_eq(1);
+		// a refused try_update writes nothing
+		table
+			.try_update(item.id, |item| {
+				item.data += 1;
+				bevybail!("refused")
+			})
+			.await
+			.xpect_err();
+		table.get(item.id).await.unwrap().data.xpect_eq(1);
+		let err = table.try_push(item).await.unwrap_err();
+		HttpError::status_of(&err)
+			.xpect_eq(Some(StatusCode::PRECONDITION_FAILED));
+	}
+
 	/// A whole-table read spanning more rows than [`BlobStore::GET_ALL_CONCURRENCY`]
 	/// returns every one of them: the fan-out is bounded, never truncated.
 	#[beet_core::test]
@@ -0,0 +1,347 @@
+//! Versioned row schemas for [`TableStoreRow`] types.
+//!
+//! A row type declares its schema [`VERSION`](TableStoreRow::VERSION) and the
+//! [`TableMigrations`] upgrading a row document from each prior version to the
+//! next. A [`Table`] stamps every row it writes with that version under
+//! [`VERSION_FIELD`], upgrades an older row lazily as it reads it, and
+//! [`Table::migrate`] rewrites every stale row in place, so a schema change no
+//! longer leaves the old rows undeserializable.
+use crate::prelude::*;
+use beet_core::prelude::*;
+use std::collections::BTreeMap;
+
+/// The reserved row field carrying the schema version a row was written at.
+/// A row without it was written before its type was versioned, ie version 0.
+pub const VERSION_FIELD: &str = "_version";
+
+/// Upgrades a row document from the version before the one it is registered
+/// at to that version, see [`TableMigrations::with_upgrade`].
+pub type RowUpgrade = fn(Value) -> Result<Value>;
+
+/// The upgrade steps for a [`TableStoreRow`] schema, one per version bump.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// // v1 renamed `name` to `title`
+/// let migrations = TableMigrations::new().with_upgrade(1, |mut row| {
+/// 	let name = row.as_map_mut()?.0.remove("name").unwrap_or_default();
+/// 	row.insert("title", name)?;
+/// 	row.xok()
+/// });
+/// let row = migrations.upgrade(val!({ "name": "foo" }), 0, 1).unwrap();
+/// row.get("title").xpect_eq(Some(&Value::str("foo")));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TableMigrations {
+	/// Each upgrade keyed by the version it upgrades a row to.
+	upgrades: Vec<(u32, RowUpgrade)>,
+}
+
+impl TableMigrations {
+	/// Creates an empty set of migrations.
+	pub fn new() -> Self { Self::default() }
+
+	/// Register the upgrade of a row document from `version - 1` to
+	/// `version`, replacing any previously registered for that version.
+	pub fn with_upgrade(mut self, version: u32, upgrade: RowUpgrade) -> Self {
+		self.upgrades.retain(|(existing, _)| *existing != version);
+		self.upgrades.push((version, upgrade));
+		self
+	}
+
+	/// Upgrade `row` from version `from` to version `to`, one registered step
+	/// at a time.
+	///
+	/// # Errors
+	/// Returns error if a step between the two has no upgrade registered, or
+	/// an upgrade fails.
+	pub fn upgrade(&self, mut row: Value, from: u32, to: u32) -> Result<Value> {
+		for version in from + 1..=to {
+			let Some((_, upgrade)) =
+				self.upgrades.iter().find(|(step, _)| *step == version)
+			else {
+				bevybail!(
+					"no upgrade registered from v{} to v{version}",
+					version - 1
+				);
+			};
+			row = upgrade(row)?;
+		}
+		row.xok()
+	}
+}
+
+/// The schema version the row document was written at, 0 if unstamped.
+///
+/// # Errors
+/// Returns error if the [`VERSION_FIELD`] is not an unsigned integer.
+pub fn row_version(row: &Value) -> Result<u32> {
+	match row.get(VERSION_FIELD) {
+		Some(version) => u32::try_from(version.as_u64()?)
+			.map_err(|_| bevyhow!("row version out of range: {version}")),
+		None => 0.xok(),
+	}
+}
+
+/// Serialize `row` into a document stamped with its type's
+/// [`VERSION`](TableStoreRow::VERSION). A version 0 type writes the plain
+/// document, so an unversioned table reads exactly as before.
+pub(crate) fn encode_row<T: TableStoreRow>(row: T) -> Result<Value> {
+	let mut doc = Value::from_serde(row)?;
+	if T::VERSION > 0 {
+		doc.insert(VERSION_FIELD, T::VERSION)?;
+	}
+	doc.xok()
+}
+
+/// Deserialize a row document written at any version up to the type's
+/// current one, upgrading it first if it is older.
+///
+/// # Errors
+/// Returns error if the row was written by a newer schema than this one, an
+/// upgrade fails, or the upgraded document fails to deserialize.
+pub(crate) fn decode_row<T: TableStoreRow>(mut doc: Value) -> Result<T> {
+	let version = row_version(&doc)?;
+	if version > T::VERSION {
+		bevybail!(
+			"row schema v{version} is newer than `{}` v{}",
+			core::any::type_name::<T>(),
+			T::VERSION
+		);
+	}
+	if let Value::Map(map) = &mut doc {
+		map.0.remove(VERSION_FIELD);
+	}
+	if version < T::VERSION {
+		doc = T::migrations().upgrade(doc, version, T::VERSION)?;
+	}
+	doc.into_serde()
+}
+
+/// Migrates the table of one row type in a store, type-erased so
+/// [`MigratableTables`] holds one per type.
+type MigrateTable = fn(TableStore) -> SendBoxedFuture<Result<MigrationReport>>;
+
+/// The tables `beet migrate` can name, each by the row type its rows read
+/// as. The [`StorePlugin`] registers `analytics`, an app registers its own
+/// versioned rows so the command rewrites them too.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn run() -> Result<()> {
+/// let mut tables = MigratableTables::default();
+/// tables.register::<TableItem<String>>("items");
+/// tables.migrate("items", TableStore::temp()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, Resource)]
+pub struct MigratableTables {
+	tables: BTreeMap<SmolStr, MigrateTable>,
+}
+
+impl MigratableTables {
+	/// Name the table of `T` rows `name`, replacing any table of that name.
+	pub fn register<T: TableStoreRow>(
+		&mut self,
+		name: impl Into<SmolStr>,
+	) -> &mut Self {
+		self.tables.insert(name.into(), |store| {
+			Box::pin(async move {
+				let table = store.table::<T>();
+				// a table that was never written to has nothing to migrate
+				if !table.store_exists().await.unwrap_or(false) {
+					return MigrationReport {
+						version: T::VERSION,
+						..default()
+					}
+					.xok();
+				}
+				table.migrate().await
+			})
+		});
+		self
+	}
+
+	/// The registered table names, in order.
+	pub fn names(&self) -> Vec<SmolStr> {
+		self.tables.keys().cloned().collect()
+	}
+
+	/// Migrate the table registered as `name`, held in `store`.
+	///
+	/// # Errors
+	/// Returns error if no table is registered as `name`, or the migration
+	/// fails, see [`Table::migrate`].
+	pub async fn migrate(
+		&self,
+		name: &str,
+		store: TableStore,
+	) -> Result<MigrationReport> {
+		let Some(migrate) = self.tables.get(name) else {
+			bevybail!(
+				"unknown table `{name}`, expected one of `{}`",
+				self.names().join("`, `")
+			);
+		};
+		migrate(store).await
+	}
+}
+
+/// The outcome of a [`Table::migrate`] pass.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+	/// The schema version every migrated row now carries.
+	pub version: u32,
+	/// Rows rewritten at [`Self::version`].
+	pub migrated: usize,
+	/// Rows already at [`Self::version`], left untouched.
+	pub current: usize,
+	/// Rows that failed to read, upgrade or write, each logged as a warning
+	/// and left as they were.
+	pub failed: usize,
+}
+
+impl core::fmt::Display for MigrationReport {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(
+			f,
+			"migrated {} row(s) to v{}, {} already current, {} failed",
+			self.migrated, self.version, self.current, self.failed
+		)
+	}
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use serde::Deserialize;
+	use serde::Serialize;
+
+	/// The original schema, a single `name`.
+	#[derive(Debug, Clone, Serialize, Deserialize, TableStoreRow)]
+	struct PostV0 {
+		id: Uuid,
+		name: String,
+	}
+
+	/// v1 renamed `name` to `title`, v2 added a `likes` count.
+	#[derive(
+		Debug, Clone, PartialEq, Serialize, Deserialize, TableStoreRow,
+	)]
+	#[table(version = 2)]
+	#[table(migrations = Self::upgrades())]
+	struct Post {
+		id: Uuid,
+		title: String,
+		likes: u32,
+	}
+
+	impl Post {
+		fn upgrades() -> TableMigrations {
+			TableMigrations::new()
+				.with_upgrade(1, |mut row| {
+					let name =
+						row.as_map_mut()?.0.remove("name").unwrap_or_default();
+					row.insert("title", name)?;
+					row.xok()
+				})
+				.with_upgrade(2, |mut row| {
+					row.insert("likes", 0u32)?;
+					row.xok()
+				})
+		}
+	}
+
+	fn legacy(store: &BlobStore) -> (Uuid, Table<PostV0>) {
+		let id = uuid_ext::now_v7();
+		(id, TableStore::new(store.clone()).table::<PostV0>())
+	}
+
+	#[beet_core::test]
+	async fn upgrades_on_read() {
+		let store = BlobStore::temp();
+		let (id, old) = legacy(&store);
+		old.push(PostV0 {
+			id,
+			name: "hello".into(),
+		})
+		.await
+		.unwrap();
+
+		let table = TableStore::new(store).table::<Post>();
+		table.get(id).await.unwrap().xpect_eq(Post {
+			id,
+			title: "hello".into(),
+			likes: 0,
+		});
+		table.get_all().await.unwrap().len().xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn stamps_written_rows() {
+		let store = BlobStore::temp();
+		let table = TableStore::new(store.clone()).table::<Post>();
+		let id = uuid_ext::now_v7();
+		table
+			.push(Post {
+				id,
+				title: "hello".into(),
+				likes: 3,
+			})
+			.await
+			.unwrap();
+		let doc = store.get_row(id).await.unwrap();
+		row_version(&doc).unwrap().xpect_eq(2);
+		// an older build refuses the newer row rather than misreading it
+		legacy(&store).1.get(id).await.xpect_err();
+	}
+
+	#[beet_core::test]
+	async fn migrate_rewrites_stale_rows() {
+		let store = BlobStore::temp();
+		let (id, old) = legacy(&store);
+		old.push(PostV0 {
+			id,
+			name: "hello".into(),
+		})
+		.await
+		.unwrap();
+		let table = TableStore::new(store.clone()).table::<Post>();
+		table
+			.push(Post {
+				id: uuid_ext::now_v7(),
+				title: "current".into(),
+				likes: 1,
+			})
+			.await
+			.unwrap();
+
+		table.migrate().await.unwrap().xpect_eq(MigrationReport {
+			version: 2,
+			migrated: 1,
+			current: 1,
+			failed: 0,
+		});
+		let doc = store.get_row(id).await.unwrap();
+		row_version(&doc).unwrap().xpect_eq(2);
+		doc.get("title").xpect_eq(Some(&Value::str("hello")));
+		// a second pass finds nothing left to do
+		table.migrate().await.unwrap().migrated.xpect_eq(0);
+	}
+
+	#[beet_core::test]
+	fn missing_upgrade_errors() {
+		TableMigrations::new()
+			.with_upgrade(2, |row| row.xok())
+			.upgrade(Value::map(), 0, 2)
+			.xpect_err();
+	}
+}
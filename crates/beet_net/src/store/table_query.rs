@@ -0,0 +1,736 @@
+//! Queries over a [`Table`]: field filters, creation-time ranges, order, limit
+//! and cursor pagination, plus the secondary indexes a backend serves them from.
+use crate::prelude::*;
+use beet_core::prelude::*;
+use core::cmp::Ordering;
+use core::ops::Bound;
+use core::ops::RangeBounds;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A query over the rows of a table, built up then passed to
+/// [`Table::query`].
+///
+/// Filters compare top-level fields of the row document, ie the columns of a
+/// table-native backend, or with [`Self::KEY`] the key the row is stored at.
+/// A backend serves what it can natively, like a DynamoDB `Query` against a
+/// declared [`TableIndex`]; anything else, and every blob-backed table, falls
+/// back to [`Self::scan`], a filtered read of the whole table.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn run(table: Table<TableItem<String>>) -> Result<()> {
+/// let hour_ago = Timestamp::from_unix_epoch_elapsed(
+/// 	Timestamp::now().unix_epoch_elapsed() - Duration::from_secs(3600),
+/// );
+/// let query = TableQuery::new()
+/// 	.with_created(hour_ago..)
+/// 	.with_order(SortOrder::Descending)
+/// 	.with_limit(20);
+/// let page = table.query(query.clone()).await?;
+/// // the next twenty, if any: a cursor resumes the same query
+/// if let Some(cursor) = page.cursor {
+/// 	table.query(query.with_cursor(cursor)).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TableQuery {
+	/// Every filter a row must pass.
+	pub filters: Vec<TableFilter>,
+	/// The field rows are ordered by, ties broken by row key. `None` orders by
+	/// key alone, which for uuid v7 ids is creation order.
+	pub order_by: Option<SmolStr>,
+	/// The direction of [`Self::order_by`].
+	pub order: SortOrder,
+	/// The most rows a page holds.
+	pub limit: Option<usize>,
+	/// Resume after the last row of a previous page.
+	pub cursor: Option<TableCursor>,
+	/// The secondary index to serve this query from, otherwise a backend picks
+	/// a declared index the filters fit.
+	pub index: Option<SmolStr>,
+	/// Skip rows that fail to read or deserialize with a warning instead of
+	/// failing the query, see [`Table::get_all_lossy`].
+	pub lossy: bool,
+}
+
+impl TableQuery {
+	/// The pseudo-field naming the key a row is stored at, its
+	/// [`TableStoreRow::id`], however the row document names its own id field.
+	pub const KEY: &str = "$key";
+
+	/// A query matching every row, ordered by key.
+	pub fn new() -> Self { Self::default() }
+
+	/// Only rows whose `field` equals `value`.
+	pub fn with_eq(
+		mut self,
+		field: impl Into<SmolStr>,
+		value: impl Into<Value>,
+	) -> Self {
+		self.filters.push(TableFilter::Eq {
+			field: field.into(),
+			value: value.into(),
+		});
+		self
+	}
+
+	/// Only rows whose `field` lies within `range`, ie `10..20` or `"a"..`.
+	pub fn with_range<V: Clone + Into<Value>>(
+		mut self,
+		field: impl Into<SmolStr>,
+		range: impl RangeBounds<V>,
+	) -> Self {
+		self.filters.push(TableFilter::Range {
+			field: field.into(),
+			start: range.start_bound().map(|value| value.clone().into()),
+			end: range.end_bound().map(|value| value.clone().into()),
+		});
+		self
+	}
+
+	/// Only rows created within `range`, read from the timestamp of their uuid
+	/// v7 id (see [`TableStoreRow::timestamp`]) at millisecond precision. A
+	/// range over the row [key](Self::KEY), so every backend serves it without
+	/// a dedicated time field.
+	pub fn with_created(self, range: impl RangeBounds<Timestamp>) -> Self {
+		// an id sorts at or after the floor of its own millisecond, so an
+		// inclusive start and an exclusive end are both that floor, and the
+		// others the floor of the next millisecond.
+		let start = match range.start_bound() {
+			Bound::Included(time) => Bound::Included(v7_floor(time, 0)),
+			Bound::Excluded(time) => Bound::Included(v7_floor(time, 1)),
+			Bound::Unbounded => Bound::Unbounded,
+		};
+		let end = match range.end_bound() {
+			Bound::Included(time) => Bound::Excluded(v7_floor(time, 1)),
+			Bound::Excluded(time) => Bound::Excluded(v7_floor(time, 0)),
+			Bound::Unbounded => Bound::Unbounded,
+		};
+		self.with_range(Self::KEY, (start, end))
+	}
+
+	/// Order rows by key, which for uuid v7 ids is creation order.
+	pub fn with_order(mut self, order: SortOrder) -> Self {
+		self.order = order;
+		self
+	}
+
+	/// Order rows by `field`, ties broken by key.
+	pub fn with_order_by(
+		mut self,
+		field: impl Into<SmolStr>,
+		order: SortOrder,
+	) -> Self {
+		self.order_by = Some(field.into());
+		self.order = order;
+		self
+	}
+
+	/// Return at most `limit` rows per page.
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+
+	/// Resume after the last row of the page `cursor` came from.
+	pub fn with_cursor(mut self, cursor: TableCursor) -> Self {
+		self.cursor = Some(cursor);
+		self
+	}
+
+	/// Serve this query from the secondary index `name`.
+	pub fn with_index(mut self, name: impl Into<SmolStr>) -> Self {
+		self.index = Some(name.into());
+		self
+	}
+
+	/// Skip unreadable rows with a warning rather than failing the query.
+	pub fn with_lossy(mut self) -> Self {
+		self.lossy = true;
+		self
+	}
+
+	/// Whether the row stored at `key` passes every filter.
+	pub fn matches(&self, key: &str, row: &Value) -> bool {
+		let key = Value::Str(key.into());
+		self.filters.iter().all(|filter| filter.matches(&key, row))
+	}
+
+	/// Answer this query from every row of a table: the filtered scan a
+	/// backend without a native query falls back to.
+	pub fn scan(
+		&self,
+		rows: Vec<(SmolPath, Result<Value>)>,
+	) -> Result<TablePage<Value>> {
+		let mut matched = Vec::new();
+		for (path, row) in rows {
+			match row {
+				Ok(row) if self.matches(path.as_str(), &row) => {
+					matched.push((Value::Str(path.as_str().into()), row));
+				}
+				Ok(_) => {}
+				Err(err) if self.lossy => {
+					warn!("skipping unreadable row {path}: {err}");
+				}
+				Err(err) => return Err(err),
+			}
+		}
+		matched.sort_by(|(a_key, a), (b_key, b)| {
+			self.compare((a_key, a), (b_key, b))
+		});
+		if let Some(cursor) = &self.cursor {
+			// the cursor carries its own key, so reads as a row stored there
+			let cursor_key =
+				cursor.0.get(Self::KEY).cloned().unwrap_or_default();
+			matched.retain(|(key, row)| {
+				self.compare((key, row), (&cursor_key, &cursor.0)).is_gt()
+			});
+		}
+		let cursor = match self.limit {
+			Some(limit) if matched.len() > limit => {
+				matched.truncate(limit);
+				matched.last().map(|(key, row)| self.cursor_of(key, row))
+			}
+			_ => None,
+		};
+		TablePage {
+			rows: matched.into_iter().map(|(_, row)| row).collect(),
+			cursor,
+		}
+		.xok()
+	}
+
+	/// The cursor resuming after the row stored at `key`, for a backend serving
+	/// its own pages.
+	pub fn cursor_after(&self, key: &str, row: &Value) -> TableCursor {
+		self.cursor_of(&Value::Str(key.into()), row)
+	}
+
+	/// The fields rows are ordered by, ending with the key that breaks ties.
+	fn order_fields(&self) -> impl Iterator<Item = &str> {
+		self.order_by
+			.iter()
+			.map(SmolStr::as_str)
+			.chain(Some(Self::KEY))
+	}
+
+	/// Order two rows (or a row and a cursor), each with the key it is stored
+	/// at, by this query's order.
+	fn compare(&self, a: (&Value, &Value), b: (&Value, &Value)) -> Ordering {
+		let ordering = self
+			.order_fields()
+			.map(|field| {
+				sort_values(
+					field_of(a.0, a.1, field),
+					field_of(b.0, b.1, field),
+				)
+			})
+			.find(|ordering| ordering.is_ne())
+			.unwrap_or(Ordering::Equal);
+		match self.order {
+			SortOrder::Ascending => ordering,
+			SortOrder::Descending => ordering.reverse(),
+		}
+	}
+
+	/// The cursor resuming after the row stored at `key`: its order field and
+	/// key.
+	fn cursor_of(&self, key: &Value, row: &Value) -> TableCursor {
+		let mut map = Map::default();
+		for field in self.order_fields() {
+			if let Some(value) = field_of(key, row, field) {
+				map.insert(field, value.clone());
+			}
+		}
+		TableCursor(Value::Map(map))
+	}
+}
+
+/// A top-level field of the row stored at `key`, or for [`TableQuery::KEY`]
+/// the key itself.
+fn field_of<'a>(
+	key: &'a Value,
+	row: &'a Value,
+	field: &str,
+) -> Option<&'a Value> {
+	if field == TableQuery::KEY {
+		Some(key)
+	} else {
+		row.get(field)
+	}
+}
+
+/// The smallest uuid v7 of the millisecond `time` falls in, offset by
+/// `add_ms`: version 7, the RFC variant and zeroed random bits.
+fn v7_floor(time: &Timestamp, add_ms: u128) -> Value {
+	let ms = time.unix_epoch_elapsed().as_millis() + add_ms;
+	let id = Uuid::from_u128(ms << 80 | 0x7000 << 64 | 1 << 63);
+	Value::Str(id.to_string().into())
+}
+
+/// A comparison on one top-level field of a row document, or on the row key as
+/// [`TableQuery::KEY`]. A row without the field never matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableFilter {
+	/// The field equals the value.
+	Eq {
+		/// The compared field.
+		field: SmolStr,
+		/// The value it must equal.
+		value: Value,
+	},
+	/// The field lies within the bounds.
+	Range {
+		/// The compared field.
+		field: SmolStr,
+		/// The lower bound.
+		start: Bound<Value>,
+		/// The upper bound.
+		end: Bound<Value>,
+	},
+}
+
+impl TableFilter {
+	/// The field this filter compares.
+	pub fn field(&self) -> &SmolStr {
+		match self {
+			Self::Eq { field, .. } | Self::Range { field, .. } => field,
+		}
+	}
+
+	/// Whether the row stored at `key` passes this filter.
+	pub fn matches(&self, key: &Value, row: &Value) -> bool {
+		let Some(actual) = field_of(key, row, self.field()) else {
+			return false;
+		};
+		match self {
+			Self::Eq { value, .. } => {
+				compare_values(actual, value).is_some_and(Ordering::is_eq)
+			}
+			Self::Range { start, end, .. } => {
+				let above = match start {
+					Bound::Included(start) => compare_values(actual, start)
+						.is_some_and(Ordering::is_ge),
+					Bound::Excluded(start) => compare_values(actual, start)
+						.is_some_and(Ordering::is_gt),
+					Bound::Unbounded => true,
+				};
+				let below =
+					match end {
+						Bound::Included(end) => compare_values(actual, end)
+							.is_some_and(Ordering::is_le),
+						Bound::Excluded(end) => compare_values(actual, end)
+							.is_some_and(Ordering::is_lt),
+						Bound::Unbounded => true,
+					};
+				above && below
+			}
+		}
+	}
+}
+
+/// Compare two field values: numbers by magnitude whether stored as int, uint
+/// or float (a json round trip may change which), any other kind only against
+/// its own.
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+	if let (Some(a), Some(b)) = (integer(a), integer(b)) {
+		return Some(a.cmp(&b));
+	}
+	if let (Some(a), Some(b)) = (float(a), float(b)) {
+		return a.partial_cmp(&b);
+	}
+	(core::mem::discriminant(a) == core::mem::discriminant(b)).then(|| a.cmp(b))
+}
+
+/// The value as a lossless integer, if it is one.
+fn integer(value: &Value) -> Option<i128> {
+	match value {
+		Value::Int(val) => Some(*val as i128),
+		Value::Uint(val) => Some(*val as i128),
+		_ => None,
+	}
+}
+
+/// The value as a float, if it is any number.
+fn float(value: &Value) -> Option<f64> {
+	match value {
+		Value::Int(val) => Some(*val as f64),
+		Value::Uint(val) => Some(*val as f64),
+		Value::Float(val) => Some(*val),
+		_ => None,
+	}
+}
+
+/// A total order for sorting: comparable values by [`compare_values`], others
+/// by kind, and an absent field first.
+fn sort_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+	match (a, b) {
+		(Some(a), Some(b)) => compare_values(a, b).unwrap_or_else(|| a.cmp(b)),
+		_ => a.is_some().cmp(&b.is_some()),
+	}
+}
+
+/// The direction rows are ordered in.
+#[derive(
+	Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum SortOrder {
+	/// Smallest first, ie oldest first when ordered by id.
+	#[default]
+	Ascending,
+	/// Largest first, ie newest first when ordered by id.
+	Descending,
+}
+
+/// Where the next page of a query resumes: the key fields of the last row
+/// returned, opaque to the caller. Serializable, so it can round trip through
+/// a client, ie as a json query param.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TableCursor(pub Value);
+
+/// One page of a [`TableQuery`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablePage<T> {
+	/// The rows of this page, in query order.
+	pub rows: Vec<T>,
+	/// Pass to [`TableQuery::with_cursor`] for the next page, `None` when this
+	/// is the last.
+	pub cursor: Option<TableCursor>,
+}
+
+/// A secondary index declared on a table-native backend, ie a DynamoDB global
+/// secondary index. A query with an equality filter on the partition key is
+/// served from it rather than scanned, ordered by its sort key.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct TableIndex {
+	/// The index name.
+	pub name: SmolStr,
+	/// The field rows are grouped by, queried by equality.
+	pub partition_key: IndexKey,
+	/// The field rows are ordered by within a partition, queried by range.
+	pub sort_key: Option<IndexKey>,
+}
+
+impl TableIndex {
+	/// An index `name` partitioned by `partition_key`.
+	pub fn new(
+		name: impl Into<SmolStr>,
+		partition_key: impl Into<IndexKey>,
+	) -> Self {
+		Self {
+			name: name.into(),
+			partition_key: partition_key.into(),
+			sort_key: None,
+		}
+	}
+
+	/// Order each partition by `sort_key`.
+	pub fn with_sort_key(mut self, sort_key: impl Into<IndexKey>) -> Self {
+		self.sort_key = Some(sort_key.into());
+		self
+	}
+
+	/// Whether this index can serve `query`: an equality filter on its
+	/// partition key, and no order other than its sort key.
+	pub fn serves(&self, query: &TableQuery) -> bool {
+		let keyed = query.filters.iter().any(|filter| {
+			matches!(filter, TableFilter::Eq { field, .. }
+				if *field == self.partition_key.field)
+		});
+		let ordered = match &query.order_by {
+			None => true,
+			Some(field) => self
+				.sort_key
+				.as_ref()
+				.is_some_and(|key| key.field == *field),
+		};
+		keyed && ordered
+	}
+}
+
+/// A key field of a [`TableIndex`] and the type its values are stored as.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct IndexKey {
+	/// The top-level row field.
+	pub field: SmolStr,
+	/// The stored type of the field.
+	pub kind: IndexKeyKind,
+}
+
+impl IndexKey {
+	/// A string key field.
+	pub fn string(field: impl Into<SmolStr>) -> Self {
+		Self {
+			field: field.into(),
+			kind: IndexKeyKind::String,
+		}
+	}
+
+	/// A numeric key field.
+	pub fn number(field: impl Into<SmolStr>) -> Self {
+		Self {
+			field: field.into(),
+			kind: IndexKeyKind::Number,
+		}
+	}
+}
+
+impl From<&str> for IndexKey {
+	fn from(field: &str) -> Self { Self::string(field) }
+}
+
+/// The stored type of an [`IndexKey`], which DynamoDB declares up front.
+#[derive(
+	Debug,
+	Default,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	Hash,
+	Reflect,
+	Serialize,
+	Deserialize,
+)]
+pub enum IndexKeyKind {
+	/// A string field, ie an id, email or enum variant.
+	#[default]
+	String,
+	/// A numeric field, ie a count or epoch time.
+	Number,
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use serde::Deserialize;
+	use serde::Serialize;
+	use uuid::Uuid;
+
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	struct Visit {
+		id: Uuid,
+		path: String,
+		status: u16,
+	}
+
+	impl TableStoreRow for Visit {
+		fn id(&self) -> Uuid { self.id }
+	}
+
+	/// A table of `count` visits alternating between two paths, with the
+	/// status climbing by one per row.
+	async fn visits(count: u16) -> (Table<Visit>, Vec<Visit>) {
+		let table = Table::<Visit>::temp();
+		let mut rows = Vec::new();
+		for idx in 0..count {
+			let row = Visit {
+				id: uuid_ext::now_v7(),
+				path: if idx % 2 == 0 { "/" } else { "/about" }.into(),
+				status: 200 + idx,
+			};
+			table.push(row.clone()).await.unwrap();
+			rows.push(row);
+		}
+		(table, rows)
+	}
+
+	#[beet_core::test]
+	async fn filters_fields() {
+		let (table, rows) = visits(6).await;
+		table
+			.query_all(TableQuery::new().with_eq("path", "/about"))
+			.await
+			.unwrap()
+			.xpect_eq(vec![rows[1].clone(), rows[3].clone(), rows[5].clone()]);
+		// the uint filter matches the json round tripped int
+		table
+			.query_all(TableQuery::new().with_range("status", 202u16..=203))
+			.await
+			.unwrap()
+			.xpect_eq(vec![rows[2].clone(), rows[3].clone()]);
+		table
+			.query_all(TableQuery::new().with_eq("missing", 1))
+			.await
+			.unwrap()
+			.xpect_empty();
+	}
+
+	#[beet_core::test]
+	async fn orders_and_paginates() {
+		let (table, rows) = visits(5).await;
+		let query = TableQuery::new()
+			.with_order_by("status", SortOrder::Descending)
+			.with_limit(2);
+		let first = table.query(query.clone()).await.unwrap();
+		first.rows.xpect_eq(vec![rows[4].clone(), rows[3].clone()]);
+		let second = table
+			.query(query.clone().with_cursor(first.cursor.unwrap()))
+			.await
+			.unwrap();
+		second.rows.xpect_eq(vec![rows[2].clone(), rows[1].clone()]);
+		let last = table
+			.query(query.clone().with_cursor(second.cursor.unwrap()))
+			.await
+			.unwrap();
+		last.rows.xpect_eq(vec![rows[0].clone()]);
+		last.cursor.xpect_none();
+		// following every cursor yields the whole table in order
+		let mut all = rows.clone();
+		all.reverse();
+		table.query_all(query).await.unwrap().xpect_eq(all);
+	}
+
+	#[beet_core::test]
+	async fn filters_creation_time() {
+		let table = Table::<Visit>::temp();
+		let at = |secs: u64| {
+			Timestamp::from_unix_epoch_elapsed(Duration::from_secs(secs))
+		};
+		let visit = |secs: u64| Visit {
+			id: Uuid::new_v7(uuid::Timestamp::from_unix(
+				uuid::NoContext,
+				secs,
+				0,
+			)),
+			path: "/".into(),
+			status: 200,
+		};
+		for secs in [100, 200, 300] {
+			table.push(visit(secs)).await.unwrap();
+		}
+		let created = async |range: TableQuery| {
+			table
+				.query_all(range)
+				.await
+				.unwrap()
+				.into_iter()
+				.map(|visit| visit.timestamp().unix_epoch_elapsed().as_secs())
+				.collect::<Vec<_>>()
+		};
+		created(TableQuery::new().with_created(at(200)..))
+			.await
+			.xpect_eq(vec![200, 300]);
+		created(TableQuery::new().with_created(at(100)..at(300)))
+			.await
+			.xpect_eq(vec![100, 200]);
+		created(TableQuery::new().with_created(..=at(100)))
+			.await
+			.xpect_eq(vec![100]);
+	}
+
+	/// A row naming its id something other than `id`.
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	struct Event {
+		event_id: Uuid,
+		kind: String,
+	}
+
+	impl TableStoreRow for Event {
+		fn id(&self) -> Uuid { self.event_id }
+	}
+
+	#[beet_core::test]
+	async fn pages_by_row_key() {
+		let table = Table::<Event>::temp();
+		let at = |secs: u64| {
+			Timestamp::from_unix_epoch_elapsed(Duration::from_secs(secs))
+		};
+		let mut rows = Vec::new();
+		for secs in [100, 200, 300, 400, 500] {
+			let row = Event {
+				event_id: Uuid::new_v7(uuid::Timestamp::from_unix(
+					uuid::NoContext,
+					secs,
+					0,
+				)),
+				kind: "click".into(),
+			};
+			table.push(row.clone()).await.unwrap();
+			rows.push(row);
+		}
+		// every row ties on the order field, so the key alone orders them
+		let query = TableQuery::new()
+			.with_order_by("kind", SortOrder::Ascending)
+			.with_limit(2);
+		let first = table.query(query.clone()).await.unwrap();
+		first.rows.xpect_eq(rows[..2].to_vec());
+		table
+			.query(query.clone().with_cursor(first.cursor.unwrap()))
+			.await
+			.unwrap()
+			.rows
+			.xpect_eq(rows[2..4].to_vec());
+		table.query_all(query).await.unwrap().xpect_eq(rows.clone());
+		table
+			.query_all(
+				TableQuery::new()
+					.with_created(at(200)..at(400))
+					.with_order(SortOrder::Descending),
+			)
+			.await
+			.unwrap()
+			.xpect_eq(vec![rows[2].clone(), rows[1].clone()]);
+	}
+
+	#[beet_core::test]
+	async fn lossy_skips_unreadable_rows() {
+		let store = BlobStore::temp();
+		let table = Table::<Visit>::new(store.clone());
+		table
+			.push(Visit {
+				id: uuid_ext::now_v7(),
+				path: "/".into(),
+				status: 200,
+			})
+			.await
+			.unwrap();
+		store
+			.insert(&SmolPath::new(uuid_ext::now_v7().to_string()), "{")
+			.await
+			.unwrap();
+		table.query(TableQuery::new()).await.xpect_err();
+		table
+			.query(TableQuery::new().with_lossy())
+			.await
+			.unwrap()
+			.rows
+			.len()
+			.xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	fn index_serves_keyed_queries() {
+		let index = TableIndex::new("by_path", "path")
+			.with_sort_key(IndexKey::number("status"));
+		index
+			.serves(&TableQuery::new().with_eq("path", "/"))
+			.xpect_true();
+		index
+			.serves(
+				&TableQuery::new()
+					.with_eq("path", "/")
+					.with_order_by("status", SortOrder::Descending),
+			)
+			.xpect_true();
+		index
+			.serves(&TableQuery::new().with_range("path", "a"..))
+			.xpect_false();
+		index
+			.serves(
+				&TableQuery::new()
+					.with_eq("path", "/")
+					.with_order_by("id", SortOrder::Ascending),
+			)
+			.xpect_false();
+	}
+}
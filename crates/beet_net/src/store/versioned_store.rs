@@ -0,0 +1,539 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+use bytes::Bytes;
+use uuid::Uuid;
+
+/// The hidden directory a [`VersionedStore`] keeps prior versions in, on a
+/// backend without native versioning.
+pub const VERSIONS_DIR: &str = ".versions";
+
+/// A store keeping the prior versions of each object, so an overwrite or a
+/// removal can be undone with [`restore`](BlobStoreProvider::restore).
+///
+/// - **Native:** over a store that [keeps versions](BlobStoreProvider::keeps_versions)
+///   itself, ie an [`S3Store`] with versioning, writes go straight through and
+///   this store only prunes the versions past its limit.
+/// - **Emulated:** elsewhere each write first copies the current content to
+///   `.versions/<path>/<id>`, the id a time-ordered uuid. The subtree is
+///   hidden from listings and rejects writes.
+///
+/// Emulated versioning reads the current content before each write, which
+/// two writers can race, and every [`with_subdir`](BlobStoreProvider::with_subdir)
+/// view shares the one `.versions/` subtree of the wrapped store.
+///
+/// ## Example
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn run() -> Result<()> {
+/// let store = VersionedStore::new(InMemoryStore::new()).with_max_versions(5);
+/// let path = SmolPath::from("notes.md");
+/// store.insert(&path, "draft".into()).await?;
+/// store.insert(&path, "oops".into()).await?;
+/// let versions = store.list_versions(&path).await?;
+/// store.restore(&path, &versions[0].id).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Component)]
+#[component(on_add = BlobStore::on_add::<Self>)]
+pub struct VersionedStore {
+	/// The wrapped store, scoped by each subdir view.
+	inner: BlobStore,
+	/// The wrapped store as given, holding the `.versions/` subtree.
+	root: BlobStore,
+	/// The subdir of this view relative to `root`.
+	view: SmolPath,
+	/// How many prior versions of each object are kept.
+	max_versions: usize,
+}
+
+impl VersionedStore {
+	/// The versions kept per object unless set by
+	/// [`with_max_versions`](Self::with_max_versions).
+	pub const DEFAULT_MAX_VERSIONS: usize = 10;
+
+	/// Keep the [`DEFAULT_MAX_VERSIONS`](Self::DEFAULT_MAX_VERSIONS) prior
+	/// versions of each object in `inner`.
+	pub fn new(inner: impl BlobStoreProvider) -> Self {
+		let inner = BlobStore::new(inner);
+		Self {
+			root: inner.clone(),
+			inner,
+			view: default(),
+			max_versions: Self::DEFAULT_MAX_VERSIONS,
+		}
+	}
+
+	/// Keep `max_versions` prior versions of each object, pruning the oldest.
+	pub fn with_max_versions(mut self, max_versions: usize) -> Self {
+		self.max_versions = max_versions;
+		self
+	}
+
+	/// The wrapped store.
+	pub fn inner(&self) -> &BlobStore { &self.inner }
+
+	/// True if the wrapped store keeps the versions itself.
+	fn native(&self) -> bool { self.inner.keeps_versions() }
+
+	/// True if `path` is inside the `.versions/` subtree.
+	fn is_hidden(&self, path: &SmolPath) -> bool {
+		key_covers(&SmolPath::new(VERSIONS_DIR), &self.view.join(path))
+	}
+
+	/// The directory of the emulated versions of `path`, relative to `root`.
+	fn versions_dir(&self, path: &SmolPath) -> SmolPath {
+		SmolPath::new(VERSIONS_DIR).join(self.view.join(path))
+	}
+
+	/// The emulated version `version` of `path`, relative to `root`.
+	///
+	/// # Errors
+	/// Errors if `version` is not a uuid as [`snapshot`](Self::snapshot)
+	/// names them, so an id can never reach outside the versions directory.
+	fn version_path(&self, path: &SmolPath, version: &str) -> Result<SmolPath> {
+		if !Uuid::parse_str(version).is_ok_and(|id| id.to_string() == version) {
+			return Err(HttpError::bad_request(format!(
+				"invalid version id `{version}`"
+			))
+			.into());
+		}
+		self.versions_dir(path).join(version).xok()
+	}
+
+	fn check_writable(&self, path: &SmolPath) -> Result {
+		if self.is_hidden(path) {
+			return Err(HttpError::bad_request(format!(
+				"`{path}` is reserved for prior versions"
+			))
+			.into());
+		}
+		Ok(())
+	}
+
+	/// Ready `path` for a write: copy its current content to a new version
+	/// unless the backend keeps versions itself.
+	async fn snapshot(&self, path: &SmolPath) -> Result {
+		self.check_writable(path)?;
+		if self.native() || !self.inner.exists(path).await? {
+			return Ok(());
+		}
+		let body = self.inner.get(path).await?;
+		let id = uuid_ext::now_v7().to_string();
+		BlobStoreProvider::insert(
+			&self.root,
+			&self.versions_dir(path).join(id),
+			body,
+		)
+		.await
+	}
+
+	/// Discard the versions of `path` past the limit, oldest first.
+	async fn prune(&self, path: &SmolPath) -> Result {
+		for version in self
+			.list_versions(path)
+			.await?
+			.into_iter()
+			.skip(self.max_versions)
+		{
+			self.remove_version(path, &version.id).await?;
+		}
+		Ok(())
+	}
+}
+
+impl BlobStoreProvider for VersionedStore {
+	fn box_clone(&self) -> Box<dyn BlobStoreProvider> { Box::new(self.clone()) }
+	fn with_subdir(&self, path: SmolPath) -> Box<dyn BlobStoreProvider> {
+		Box::new(Self {
+			inner: self.inner.with_subdir(path.clone()),
+			view: self.view.join(path),
+			..self.clone()
+		})
+	}
+	fn id(&self) -> &'static str { "versioned" }
+	fn root_key(&self) -> SmolStr { self.inner.root_key() }
+	fn subdir(&self) -> SmolPath { self.inner.subdir() }
+	fn watch_dir(&self) -> Option<AbsPathBuf> { self.inner.watch_dir() }
+	fn base_dir(&self) -> Option<AbsPathBuf> { self.inner.base_dir() }
+	fn region(&self) -> Option<String> { self.inner.region() }
+	fn store_exists(&self) -> SendBoxedFuture<Result<bool>> {
+		self.inner.store_exists()
+	}
+	fn store_create(&self) -> SendBoxedFuture<Result> {
+		self.inner.store_create()
+	}
+	fn store_remove(&self) -> SendBoxedFuture<Result> {
+		self.inner.store_remove()
+	}
+	fn insert(&self, path: &SmolPath, body: Bytes) -> SendBoxedFuture<Result> {
+		self.insert_with_meta(path, body, default())
+	}
+	fn insert_with_meta(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.snapshot(&path).await?;
+			this.inner.insert_with_meta(&path, body, meta).await?;
+			this.prune(&path).await
+		})
+	}
+	/// Nothing exists to version, so only the reserved paths are checked.
+	fn insert_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.check_writable(&path)?;
+			this.inner.insert_if_absent(&path, body).await
+		})
+	}
+	/// Nothing exists to version, so only the reserved paths are checked.
+	fn insert_with_meta_if_absent(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		meta: InsertMeta,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.check_writable(&path)?;
+			this.inner
+				.insert_with_meta_if_absent(&path, body, meta)
+				.await
+		})
+	}
+	/// Checks the condition before versioning, so a rejected write leaves no
+	/// version behind. The write itself stays conditional.
+	fn insert_if_match(
+		&self,
+		path: &SmolPath,
+		body: Bytes,
+		etag: &str,
+	) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		let etag = SmolStr::from(etag);
+		Box::pin(async move {
+			match this.inner.head(&path).await {
+				Ok(meta) if meta.etag.as_ref() == Some(&etag) => {}
+				_ => {
+					return Err(HttpError::precondition_failed(format!(
+						"object changed since read: {path}"
+					))
+					.into());
+				}
+			}
+			this.snapshot(&path).await?;
+			this.inner.insert_if_match(&path, body, &etag).await?;
+			this.prune(&path).await
+		})
+	}
+	fn list(&self) -> SendBoxedFuture<Result<Vec<SmolPath>>> {
+		let this = self.clone();
+		Box::pin(async move {
+			this.inner
+				.list()
+				.await?
+				.into_iter()
+				.filter(|path| !this.is_hidden(path))
+				.collect::<Vec<_>>()
+				.xok()
+		})
+	}
+	fn list_prefix(
+		&self,
+		prefix: &str,
+		delimiter: Option<&str>,
+		cursor: Option<&str>,
+		limit: Option<usize>,
+	) -> SendBoxedFuture<Result<BlobListing>> {
+		let this = self.clone();
+		let list_fut = self.inner.list_prefix(prefix, delimiter, cursor, limit);
+		Box::pin(async move {
+			let mut listing = list_fut.await?;
+			listing.paths.retain(|path| !this.is_hidden(path));
+			listing.common_prefixes.retain(|prefix| {
+				!this.is_hidden(&SmolPath::new(prefix.trim_end_matches('/')))
+			});
+			listing.xok()
+		})
+	}
+	fn get(&self, path: &SmolPath) -> SendBoxedFuture<Result<Bytes>> {
+		self.inner.get(path)
+	}
+	fn get_range(
+		&self,
+		path: &SmolPath,
+		range: header::ByteRange,
+	) -> SendBoxedFuture<Result<BlobRange>> {
+		self.inner.get_range(path, range)
+	}
+	fn head(&self, path: &SmolPath) -> SendBoxedFuture<Result<BlobMeta>> {
+		self.inner.head(path)
+	}
+	fn exists(&self, path: &SmolPath) -> SendBoxedFuture<Result<bool>> {
+		self.inner.exists(path)
+	}
+	/// Versions the content first, so a removal can be restored.
+	fn remove(&self, path: &SmolPath) -> SendBoxedFuture<Result> {
+		let this = self.clone();
+		let path = path.clone();
+		Box::pin(async move {
+			this.snapshot(&path).await?;
+			this.inner.remove(&path).await?;
+			this.prune(&path).await
+		})
+	}
+	fn keeps_versions(&self) -> bool { true }
+	fn list_versions(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Vec<BlobVersion>>> {
+		if self.native() {
+			return self.inner.list_versions(path);
+		}
+		let this = self.clone();
+		let dir = self.versions_dir(path);
+		Box::pin(async move {
+			let prefix = format!("{dir}/");
+			let mut versions = Vec::new();
+			let mut cursor = None;
+			loop {
+				let listing = this
+					.root
+					.list_prefix(&prefix, Some("/"), cursor.as_deref(), None)
+					.await?;
+				for key in listing.paths {
+					let Some(id) = key.as_str().strip_prefix(&prefix) else {
+						continue;
+					};
+					let modified = Uuid::parse_str(id)
+						.ok()
+						.and_then(|uuid| uuid.get_timestamp())
+						.map(|time| {
+							let (secs, nanos) = time.to_unix();
+							Timestamp::from_unix_epoch_elapsed(Duration::new(
+								secs, nanos,
+							))
+						});
+					versions.push(BlobVersion {
+						id: id.into(),
+						size: this.root.head(&key).await?.size,
+						modified,
+					});
+				}
+				cursor = listing.cursor;
+				if cursor.is_none() {
+					break;
+				}
+			}
+			// the ids are time-ordered, so newest first is descending
+			versions.sort_by(|a, b| b.id.cmp(&a.id));
+			versions.xok()
+		})
+	}
+	fn get_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result<Bytes>> {
+		if self.native() {
+			return self.inner.get_version(path, version);
+		}
+		match self.version_path(path, version) {
+			Ok(key) => self.root.get(&key),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn remove_version(
+		&self,
+		path: &SmolPath,
+		version: &str,
+	) -> SendBoxedFuture<Result> {
+		if self.native() {
+			return self.inner.remove_version(path, version);
+		}
+		match self.version_path(path, version) {
+			Ok(key) => self.root.remove(&key),
+			Err(err) => Box::pin(async move { Err(err) }),
+		}
+	}
+	fn public_url(
+		&self,
+		path: &SmolPath,
+	) -> SendBoxedFuture<Result<Option<String>>> {
+		self.inner.public_url(path)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use bytes::Bytes;
+
+	#[beet_core::test]
+	async fn works() {
+		store_test::run(VersionedStore::new(InMemoryStore::new())).await;
+	}
+
+	#[beet_core::test]
+	async fn restores_prior_versions() {
+		let store = VersionedStore::new(InMemoryStore::new());
+		let path = SmolPath::from("notes.md");
+		store.insert(&path, "one".into()).await.unwrap();
+		store.insert(&path, "two".into()).await.unwrap();
+		store.insert(&path, "three".into()).await.unwrap();
+
+		let versions = store.list_versions(&path).await.unwrap();
+		versions.len().xpect_eq(2);
+		store
+			.get_version(&path, &versions[0].id)
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("two"));
+		store
+			.get_version(&path, &versions[1].id)
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("one"));
+
+		store.restore(&path, &versions[1].id).await.unwrap();
+		store.get(&path).await.unwrap().xpect_eq(Bytes::from("one"));
+		// the restore is itself versioned, so it can be undone
+		let versions = store.list_versions(&path).await.unwrap();
+		store
+			.get_version(&path, &versions[0].id)
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("three"));
+	}
+
+	#[beet_core::test]
+	async fn prunes_past_the_limit() {
+		let store =
+			VersionedStore::new(InMemoryStore::new()).with_max_versions(2);
+		let path = SmolPath::from("counter");
+		for count in 0..5 {
+			store.insert(&path, count.to_string().into()).await.unwrap();
+		}
+		let versions = store.list_versions(&path).await.unwrap();
+		versions.len().xpect_eq(2);
+		store
+			.get_version(&path, &versions[1].id)
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("2"));
+	}
+
+	#[beet_core::test]
+	async fn removal_can_be_restored() {
+		let store = VersionedStore::new(InMemoryStore::new());
+		let path = SmolPath::from("doc.txt");
+		store.insert(&path, "keep me".into()).await.unwrap();
+		store.remove(&path).await.unwrap();
+		store.exists(&path).await.unwrap().xpect_false();
+
+		let versions = store.list_versions(&path).await.unwrap();
+		store.restore(&path, &versions[0].id).await.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("keep me"));
+	}
+
+	#[beet_core::test]
+	async fn hides_the_versions_subtree() {
+		let inner = BlobStore::new(InMemoryStore::new());
+		let store = VersionedStore::new(inner.clone());
+		let path = SmolPath::from("a.txt");
+		store.insert(&path, "1".into()).await.unwrap();
+		store.insert(&path, "2".into()).await.unwrap();
+
+		store.list().await.unwrap().xpect_eq(vec![path]);
+		// the backing store holds the version
+		inner.list().await.unwrap().len().xpect_eq(2);
+		store
+			.insert(&SmolPath::from(".versions/a.txt/x"), "forged".into())
+			.await
+			.unwrap_err()
+			.xmap(|err| HttpError::status_of(&err))
+			.xpect_eq(Some(StatusCode::BAD_REQUEST));
+	}
+
+	#[beet_core::test]
+	async fn subdir_views_share_versions() {
+		let store = VersionedStore::new(InMemoryStore::new());
+		let sub = store.with_subdir(SmolPath::from("docs"));
+		let path = SmolPath::from("docs/readme.md");
+		store.insert(&path, "root".into()).await.unwrap();
+		sub.insert(&SmolPath::from("readme.md"), "sub".into())
+			.await
+			.unwrap();
+		store.list_versions(&path).await.unwrap().len().xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn nested_keys_are_not_versions() {
+		let store =
+			VersionedStore::new(InMemoryStore::new()).with_max_versions(1);
+		let (dir, nested) = (SmolPath::from("a"), SmolPath::from("a/b"));
+		store.insert(&dir, "1".into()).await.unwrap();
+		store.insert(&dir, "2".into()).await.unwrap();
+		store.insert(&nested, "1".into()).await.unwrap();
+		store.insert(&nested, "2".into()).await.unwrap();
+		// pruning `a` leaves the versions of `a/b` alone
+		store.insert(&dir, "3".into()).await.unwrap();
+		store.list_versions(&dir).await.unwrap().len().xpect_eq(1);
+		store
+			.list_versions(&nested)
+			.await
+			.unwrap()
+			.len()
+			.xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn rejects_foreign_version_ids() {
+		let store = VersionedStore::new(InMemoryStore::new());
+		let secret = SmolPath::from("secret.txt");
+		store.insert(&secret, "secret".into()).await.unwrap();
+		let path = SmolPath::from("a.txt");
+		for version in ["../../../secret.txt", "x/y", ""] {
+			store
+				.get_version(&path, version)
+				.await
+				.unwrap_err()
+				.xmap(|err| HttpError::status_of(&err))
+				.xpect_eq(Some(StatusCode::BAD_REQUEST));
+			store
+				.remove_version(&path, version)
+				.await
+				.unwrap_err()
+				.xmap(|err| HttpError::status_of(&err))
+				.xpect_eq(Some(StatusCode::BAD_REQUEST));
+		}
+		store.exists(&secret).await.unwrap().xpect_true();
+	}
+
+	#[beet_core::test]
+	async fn unversioned_store_errors() {
+		BlobStore::temp()
+			.list_versions(&SmolPath::from("a.txt"))
+			.await
+			.xpect_err();
+	}
+}
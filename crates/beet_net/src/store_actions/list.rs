@@ -1,32 +1,60 @@
 use crate::prelude::*;
 use beet_core::prelude::*;
 
+const DEFAULT_LIMIT: usize = 1000;
+
 /// Parameters for listing blobs in a store subdirectory.
 #[derive(Debug, Clone, Reflect, serde::Serialize, serde::Deserialize)]
 pub struct ListBlobsParams {
 	/// Subdirectory path to list relative to the store root.
 	pub path: SmolPath,
+	/// List only the direct children, grouping deeper blobs into their
+	/// directories, rather than every blob beneath the path.
+	#[serde(default)]
+	pub shallow: bool,
+	/// The cursor of the previous page, to continue the listing.
+	#[serde(default)]
+	pub cursor: Option<SmolStr>,
+	/// Maximum number of blobs and directories to return (default: 1000).
+	#[serde(default)]
+	pub limit: Option<usize>,
 }
 
-/// List all blobs in the given subdirectory of the nearest ancestor [`BlobStore`].
+/// List the blobs in the given subdirectory of the nearest ancestor
+/// [`BlobStore`], one page at a time.
 ///
-/// Outputs a [`Vec<SmolPath>`] of blob paths relative to the given subdirectory.
+/// Outputs a [`BlobListing`] of blob paths (and directories when shallow)
+/// relative to the given subdirectory, with a cursor while more remain.
 #[action]
 #[derive(Component, Reflect)]
 pub async fn ListBlobs(
 	cx: ActionContext<ListBlobsParams>,
-) -> Result<Vec<SmolPath>> {
+) -> Result<BlobListing> {
 	let store = cx
 		.caller
 		.with_state::<AncestorQuery<&BlobStore>, _>(|entity, query| {
 			query.get(entity).cloned()
 		})
 		.await??;
-	let sub = store.with_subdir(cx.input.path);
+	let ListBlobsParams {
+		path,
+		shallow,
+		cursor,
+		limit,
+	} = cx.input;
+	let sub = store.with_subdir(path);
 	// gracefully return empty list if store directory doesn't exist yet
 	match sub.store_exists().await {
-		Ok(true) => sub.list().await,
-		_ => Ok(vec![]),
+		Ok(true) => {
+			sub.list_prefix(
+				"",
+				shallow.then_some("/"),
+				cursor.as_deref(),
+				Some(limit.unwrap_or(DEFAULT_LIMIT)),
+			)
+			.await
+		}
+		_ => Ok(BlobListing::default()),
 	}
 }
 
@@ -70,4 +98,25 @@ mod test {
 		result.sort();
 		result.xpect_eq(vec![SmolPath::from("a.txt"), SmolPath::from("b.txt")]);
 	}
+
+	#[beet_core::test]
+	async fn lists_shallow_pages() {
+		let store = BlobStore::temp();
+		for key in ["subdir/a.txt", "subdir/b.txt", "subdir/nested/c.txt"] {
+			store.insert(&SmolPath::from(key), "abc").await.unwrap();
+		}
+		let sub = store.with_subdir(SmolPath::from("subdir"));
+		let first =
+			sub.list_prefix("", Some("/"), None, Some(2)).await.unwrap();
+		first
+			.paths
+			.xpect_eq(vec![SmolPath::from("a.txt"), SmolPath::from("b.txt")]);
+		let last = sub
+			.list_prefix("", Some("/"), first.cursor.as_deref(), Some(2))
+			.await
+			.unwrap();
+		last.common_prefixes
+			.xpect_eq(vec![SmolStr::from("nested/")]);
+		last.cursor.xpect_none();
+	}
 }
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Parameters for listing the prior versions of a blob.
+#[derive(Debug, Clone, Reflect, serde::Serialize, serde::Deserialize)]
+pub struct ListBlobVersionsParams {
+	/// Path to the blob whose versions to list.
+	pub path: SmolPath,
+}
+
+/// List the prior versions of a blob in the nearest ancestor [`BlobStore`],
+/// newest first, for [`ReadBlobVersion`] and [`RestoreBlob`].
+///
+/// Errors unless the store [keeps versions](BlobStoreProvider::keeps_versions),
+/// ie a [`VersionedStore`].
+#[action]
+#[derive(Component, Reflect)]
+pub async fn ListBlobVersions(
+	cx: ActionContext<ListBlobVersionsParams>,
+) -> Result<Vec<BlobVersion>> {
+	let store = cx
+		.caller
+		.with_state::<AncestorQuery<&BlobStore>, _>(|entity, query| {
+			query.get(entity).cloned()
+		})
+		.await??;
+	store.list_versions(&cx.input.path).await
+}
@@ -1,11 +1,17 @@
 //! Actions for operating on [`BlobStore`] storage.
 mod edit;
 mod list;
+mod list_versions;
 mod read;
+mod read_version;
 mod remove;
+mod restore;
 mod write;
 pub use edit::*;
 pub use list::*;
+pub use list_versions::*;
 pub use read::*;
+pub use read_version::*;
 pub use remove::*;
+pub use restore::*;
 pub use write::*;
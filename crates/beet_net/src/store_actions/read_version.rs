@@ -0,0 +1,29 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Parameters for reading a prior version of a blob.
+#[derive(Debug, Clone, Reflect, serde::Serialize, serde::Deserialize)]
+pub struct ReadBlobVersionParams {
+	/// Path to the blob.
+	pub path: SmolPath,
+	/// The version id, as listed by [`ListBlobVersions`].
+	pub version: SmolStr,
+}
+
+/// Read a prior version of a blob from the nearest ancestor [`BlobStore`],
+/// inferring the media type from its path like [`ReadBlob`].
+#[action]
+#[derive(Component, Reflect)]
+pub async fn ReadBlobVersion(
+	cx: ActionContext<ReadBlobVersionParams>,
+) -> Result<MediaBytes> {
+	let store = cx
+		.caller
+		.with_state::<AncestorQuery<&BlobStore>, _>(|entity, query| {
+			query.get(entity).cloned()
+		})
+		.await??;
+	let media_type = cx.input.path.media_type().unwrap_or(MediaType::Bytes);
+	let bytes = store.get_version(&cx.input.path, &cx.input.version).await?;
+	MediaBytes::new(media_type, bytes.to_vec()).xok()
+}
@@ -0,0 +1,76 @@
+use crate::prelude::*;
+use beet_core::prelude::*;
+
+/// Parameters for restoring a prior version of a blob.
+#[derive(Debug, Clone, Reflect, serde::Serialize, serde::Deserialize)]
+pub struct RestoreBlobParams {
+	/// Path to the blob to restore.
+	pub path: SmolPath,
+	/// The version id to restore, as listed by [`ListBlobVersions`].
+	pub version: SmolStr,
+}
+
+/// Make a prior version of a blob current again in the nearest ancestor
+/// [`BlobStore`], undoing a write or a removal. The replaced content becomes
+/// a version in turn, so a restore can itself be undone.
+///
+/// Emits a [`BlobEvent`] on success, like [`WriteBlob`].
+#[action]
+#[derive(Component, Reflect)]
+pub async fn RestoreBlob(cx: ActionContext<RestoreBlobParams>) -> Result<()> {
+	let store = cx
+		.caller
+		.with_state::<AncestorQuery<&BlobStore>, _>(|entity, query| {
+			query.get(entity).cloned()
+		})
+		.await??;
+	// existence picks Created vs Changed so a restored removal refreshes the
+	// listing
+	let existed = store.exists(&cx.input.path).await.unwrap_or(false);
+	store.restore(&cx.input.path, &cx.input.version).await?;
+	let kind = match existed {
+		true => BlobEventKind::Changed,
+		false => BlobEventKind::Created,
+	};
+	cx.caller
+		.world()
+		.trigger(BlobEvent::new(store, cx.input.path, kind))
+		.await;
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_action::prelude::*;
+	use beet_core::prelude::*;
+
+	#[beet_core::test]
+	async fn undoes_a_write() {
+		let store = BlobStore::new(VersionedStore::new(InMemoryStore::new()));
+		let path = SmolPath::from("notes.md");
+		store.insert(&path, "original").await.unwrap();
+		store.insert(&path, "clobbered").await.unwrap();
+
+		let mut world = AsyncPlugin::world();
+		let mut entity = world.spawn((store.clone(), RestoreBlob));
+		let version = store.list_versions(&path).await.unwrap()[0].id.clone();
+		entity
+			.run_async_then(async move |entity| {
+				entity
+					.call::<RestoreBlobParams, ()>(RestoreBlobParams {
+						path: SmolPath::from("notes.md"),
+						version,
+					})
+					.await
+			})
+			.await
+			.unwrap();
+		store
+			.get(&path)
+			.await
+			.unwrap()
+			.to_vec()
+			.xpect_eq(b"original".to_vec());
+	}
+}
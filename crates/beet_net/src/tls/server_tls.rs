@@ -10,6 +10,7 @@ use std::sync::Arc;
 /// when both paths are set.
 #[derive(Clone)]
 pub struct ServerTls {
+	config: Arc<rustls::ServerConfig>,
 	acceptor: TlsAcceptor,
 	provided: bool,
 }
@@ -36,13 +37,30 @@ impl ServerTls {
 				.with_safe_default_protocol_versions()?
 				.with_no_client_auth()
 				.with_single_cert(certs, key)?;
+		let config = Arc::new(config);
 		Self {
-			acceptor: TlsAcceptor::from(Arc::new(config)),
+			acceptor: TlsAcceptor::from(config.clone()),
+			config,
 			provided: tls.provided(),
 		}
 		.xok()
 	}
 
+	/// This acceptor advertising `protocols` via ALPN, in preference order
+	/// (eg `[b"h2", b"http/1.1"]`). Only a listener able to serve every listed
+	/// protocol should advertise it, so the base acceptor advertises none.
+	pub fn with_alpn(&self, protocols: &[&[u8]]) -> Self {
+		let mut config = (*self.config).clone();
+		config.alpn_protocols =
+			protocols.iter().map(|protocol| protocol.to_vec()).collect();
+		let config = Arc::new(config);
+		Self {
+			acceptor: TlsAcceptor::from(config.clone()),
+			config,
+			provided: self.provided,
+		}
+	}
+
 	/// Whether real (provided) certificates back this acceptor, in which case
 	/// a socket listener rejects remote plaintext rather than serving it.
 	pub fn provided(&self) -> bool { self.provided }
@@ -13,7 +13,17 @@ use std::sync::Arc;
 pub(crate) async fn connect(
 	addr: SocketAddr,
 ) -> Result<futures_rustls::client::TlsStream<Async<TcpStream>>> {
-	let config = DevCert::client_config()?;
+	connect_with_alpn(addr, &[]).await
+}
+
+/// Like [`connect`], offering the `alpn` protocols in order of preference.
+pub(crate) async fn connect_with_alpn(
+	addr: SocketAddr,
+	alpn: &[&[u8]],
+) -> Result<futures_rustls::client::TlsStream<Async<TcpStream>>> {
+	let mut config = DevCert::client_config()?;
+	config.alpn_protocols =
+		alpn.iter().map(|protocol| protocol.to_vec()).collect();
 	let connector = TlsConnector::from(Arc::new(config));
 	let tcp = Async::<TcpStream>::connect(addr).await?;
 	let server_name =
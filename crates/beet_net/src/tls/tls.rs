@@ -139,6 +139,23 @@ impl MaybeTls {
 	#[cfg(all(feature = "secure", not(target_arch = "wasm32")))]
 	pub fn get(&self) -> Option<&ServerTls> { self.inner.as_ref() }
 
+	/// The acceptor advertising `protocols` via ALPN, see
+	/// `ServerTls::with_alpn`. Inert stays inert.
+	pub fn with_alpn(&self, protocols: &[&[u8]]) -> Self {
+		#[cfg(all(feature = "secure", not(target_arch = "wasm32")))]
+		return Self {
+			inner: self
+				.inner
+				.as_ref()
+				.map(|server_tls| server_tls.with_alpn(protocols)),
+		};
+		#[cfg(not(all(feature = "secure", not(target_arch = "wasm32"))))]
+		{
+			let _ = protocols;
+			self.clone()
+		}
+	}
+
 	/// `https`/`http` for logs and bind messages.
 	pub fn http_scheme(&self) -> &'static str {
 		match self.is_active() {
@@ -157,6 +157,24 @@ impl Body {
 		}
 	}
 
+	/// Like [`Body::into_bytes`], but fails with `413 Content Too Large` once
+	/// more than `limit` bytes have been read, so a client cannot make the
+	/// server buffer an unbounded body.
+	pub async fn into_bytes_limited(mut self, limit: usize) -> Result<Bytes> {
+		let mut buffer = bytes::BytesMut::new();
+		while let Some(chunk) = self.next().await? {
+			if buffer.len() + chunk.len() > limit {
+				return Err(super::HttpError::new(
+					super::StatusCode::CONTENT_TOO_LARGE,
+					format!("body larger than {limit} bytes"),
+				)
+				.into());
+			}
+			buffer.extend_from_slice(&chunk);
+		}
+		Ok(buffer.freeze())
+	}
+
 	/// Consumes the body and returns the content as a UTF-8 string.
 	pub async fn into_string(self) -> Result<String> {
 		let bytes = self.into_bytes().await?;
@@ -8,6 +8,8 @@
 //! - [`QueryParams`]: Parse URL query parameters into a struct
 //! - [`JsonQueryParams`]: Parse complex types encoded as JSON in query params
 //! - [`Json`]: Parse JSON request body
+//! - [`Form`]: Parse an `application/x-www-form-urlencoded` request body
+//! - [`Multipart`]: Stream the fields of a `multipart/form-data` request body
 //! - [`Html`], [`Css`], [`Javascript`], [`Png`]: Response type wrappers
 #[allow(unused)]
 use super::*;
@@ -150,6 +152,63 @@ impl<T: serde::Serialize> TryInto<Response> for Json<T> {
 	}
 }
 
+/// Extractor for an `application/x-www-form-urlencoded` request body, the
+/// default encoding of a browser `<form method="post">`.
+///
+/// The body is buffered up to [`Form::MAX_SIZE`]; a form with file inputs
+/// submits `multipart/form-data` instead, read with [`Multipart`].
+/// # Example
+/// ```
+/// # use beet_net::prelude::*;
+/// # use serde::Deserialize;
+/// #[derive(Deserialize)]
+/// struct NewTodo {
+/// 	title: String,
+/// }
+///
+/// fn create_todo(form: Form<NewTodo>) -> String {
+///   form.title.clone()
+/// }
+/// ```
+#[derive(Debug, Clone, Deref, DerefMut)]
+pub struct Form<T>(pub T);
+
+impl Form<()> {
+	/// The largest form body accepted, in bytes.
+	pub const MAX_SIZE: usize = Body::MAX_BUFFER_SIZE;
+}
+
+#[cfg(all(feature = "serde", feature = "std"))]
+impl<T: serde::de::DeserializeOwned> FromRequest<Self> for Form<T> {
+	fn from_request(
+		req: Request,
+	) -> MaybeSendBoxedFuture<'static, Result<Self, Response>> {
+		Box::pin(async move {
+			if req
+				.headers()
+				.get::<header::ContentType>()
+				.and_then(|res| res.ok())
+				!= Some(MediaType::FormUrlEncoded)
+			{
+				return Err(HttpError::new(
+					StatusCode::UNSUPPORTED_MEDIA_TYPE,
+					"expected an application/x-www-form-urlencoded body",
+				)
+				.into());
+			}
+			let body = req
+				.body
+				.into_bytes_limited(Form::<()>::MAX_SIZE)
+				.await
+				.map_err(HttpError::from_opaque)?;
+			let form = serde_urlencoded::from_bytes(&body).map_err(|err| {
+				HttpError::bad_request(format!("Failed to parse form: {}", err))
+			})?;
+			Ok(Self(form))
+		})
+	}
+}
+
 /// Query params wrapper that supports complex types via JSON encoding.
 ///
 /// [`QueryParams`] is limited (no enums or tuples). This type accepts any
@@ -321,6 +380,37 @@ mod test {
 		parts_without.has_body().xpect_false();
 	}
 
+	#[beet_core::test]
+	#[cfg(all(feature = "serde", feature = "std"))]
+	async fn form_extractor() {
+		#[derive(serde::Deserialize, Debug, PartialEq)]
+		struct Login {
+			user: String,
+			remember: bool,
+		}
+		let request = Request::post("/login")
+			.with_header::<header::ContentType>(MediaType::FormUrlEncoded)
+			.with_body("user=ada+l&remember=true");
+		Form::<Login>::from_request(request)
+			.await
+			.ok()
+			.unwrap()
+			.0
+			.xpect_eq(Login {
+				user: "ada l".into(),
+				remember: true,
+			});
+		let json = Request::post("/login")
+			.with_header::<header::ContentType>(MediaType::Json)
+			.with_body("{}");
+		Form::<Login>::from_request(json)
+			.await
+			.err()
+			.unwrap()
+			.status()
+			.xpect_eq(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+	}
+
 	#[beet_core::test]
 	#[cfg(feature = "json")]
 	fn json_query_params() {
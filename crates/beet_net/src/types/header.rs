@@ -502,6 +502,258 @@ impl Header for TransferEncoding {
 	}
 }
 
+// ============================================================================
+// ContentEncoding / AcceptEncoding
+// ============================================================================
+
+/// A content coding, the value of `Content-Encoding` and the entries of
+/// `Accept-Encoding`.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set::<headers::ContentEncoding>(headers::ContentCoding::Brotli);
+/// assert_eq!(
+///     map.get::<headers::ContentEncoding>().unwrap().unwrap(),
+///     headers::ContentCoding::Brotli,
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ContentCoding {
+	/// `gzip` — GNU zip compression.
+	Gzip,
+	/// `deflate` — zlib deflate compression.
+	Deflate,
+	/// `br` — Brotli compression.
+	Brotli,
+	/// `zstd` — Zstandard compression.
+	Zstd,
+	/// `identity` — no encoding.
+	Identity,
+	/// `*` — any coding not otherwise listed, only meaningful in `Accept-Encoding`.
+	Any,
+	/// Any other content-coding value.
+	Other(String),
+}
+
+impl ContentCoding {
+	/// Returns the canonical string representation.
+	pub fn as_str(&self) -> &str {
+		match self {
+			Self::Gzip => "gzip",
+			Self::Deflate => "deflate",
+			Self::Brotli => "br",
+			Self::Zstd => "zstd",
+			Self::Identity => "identity",
+			Self::Any => "*",
+			Self::Other(val) => val.as_str(),
+		}
+	}
+
+	/// The file extension a precompressed sibling carries, eg `index.html.br`.
+	pub fn extension(&self) -> Option<&'static str> {
+		match self {
+			Self::Gzip => Some("gz"),
+			Self::Deflate => Some("zz"),
+			Self::Brotli => Some("br"),
+			Self::Zstd => Some("zst"),
+			_ => None,
+		}
+	}
+}
+
+impl core::fmt::Display for ContentCoding {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl From<&str> for ContentCoding {
+	fn from(val: &str) -> Self {
+		match val.trim().to_ascii_lowercase().as_str() {
+			"gzip" | "x-gzip" => Self::Gzip,
+			"deflate" => Self::Deflate,
+			"br" => Self::Brotli,
+			"zstd" => Self::Zstd,
+			"identity" => Self::Identity,
+			"*" => Self::Any,
+			other => Self::Other(other.to_string()),
+		}
+	}
+}
+
+/// Typed `Content-Encoding` header.
+pub struct ContentEncoding;
+
+impl Header for ContentEncoding {
+	type Value = ContentCoding;
+	const KEY: &'static str = "content-encoding";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		values
+			.first()
+			.map(|val| ContentCoding::from(val.as_str()))
+			.ok_or_else(|| bevyhow!("content-encoding header has no value"))
+	}
+
+	fn serialize(value: ContentCoding) -> Vec<String> {
+		vec![value.as_str().to_string()]
+	}
+}
+
+/// One `Accept-Encoding` entry: a coding and its quality, where a quality of
+/// `0` refuses the coding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityCoding {
+	/// The coding, or [`ContentCoding::Any`] for a `*` entry.
+	pub coding: ContentCoding,
+	/// The quality between `0` and `1`, defaulting to `1`.
+	pub quality: f32,
+}
+
+impl QualityCoding {
+	/// Whether this entry refuses its coding, ie `q=0`.
+	pub fn is_refused(&self) -> bool { self.quality <= 0.0 }
+}
+
+/// The parsed `Accept-Encoding` entries, ordered by quality.
+///
+/// Refusals (`q=0`) are kept, ordered last, so they can override a `*`
+/// wildcard: `br;q=0, *` accepts anything but brotli, and `identity;q=0`
+/// marks an unencoded body as unacceptable.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AcceptedCodings(pub Vec<QualityCoding>);
+
+impl AcceptedCodings {
+	/// The entry naming `coding` exactly, if any.
+	pub fn entry(&self, coding: &ContentCoding) -> Option<&QualityCoding> {
+		self.0.iter().find(|entry| &entry.coding == coding)
+	}
+
+	/// Whether `coding` is acceptable: an explicit entry decides, otherwise a
+	/// `*` entry, otherwise only `identity` is acceptable by default.
+	pub fn accepts(&self, coding: &ContentCoding) -> bool {
+		match self
+			.entry(coding)
+			.or_else(|| self.entry(&ContentCoding::Any))
+		{
+			Some(entry) => !entry.is_refused(),
+			None => coding == &ContentCoding::Identity,
+		}
+	}
+
+	/// The acceptable codings, most preferred first, excluding `*`.
+	pub fn codings(&self) -> Vec<ContentCoding> {
+		self.0
+			.iter()
+			.filter(|entry| {
+				!entry.is_refused() && entry.coding != ContentCoding::Any
+			})
+			.map(|entry| entry.coding.clone())
+			.collect()
+	}
+}
+
+/// Typed `Accept-Encoding` header, parsed as [`AcceptedCodings`] ordered by
+/// quality.
+///
+/// Unlike [`Accept`], the quality scores are kept: a `q=0` refusal overrides
+/// a `*` wildcard, so cannot simply be dropped.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set_raw("accept-encoding", "gzip;q=0.5, br, zstd;q=0, *");
+/// let accepted = map.get::<headers::AcceptEncoding>().unwrap().unwrap();
+/// assert_eq!(accepted.codings(), vec![headers::ContentCoding::Brotli, headers::ContentCoding::Gzip]);
+/// assert!(accepted.accepts(&headers::ContentCoding::Deflate));
+/// assert!(!accepted.accepts(&headers::ContentCoding::Zstd));
+/// ```
+pub struct AcceptEncoding;
+
+impl Header for AcceptEncoding {
+	type Value = AcceptedCodings;
+	const KEY: &'static str = "accept-encoding";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		let mut entries: Vec<QualityCoding> = Vec::new();
+		for value in values {
+			for part in value.split(',') {
+				let part = part.trim();
+				if part.is_empty() {
+					continue;
+				}
+				let (coding, quality) = parse_quality(part);
+				entries.push(QualityCoding {
+					coding: ContentCoding::from(coding),
+					quality,
+				});
+			}
+		}
+		// Stable sort preserves insertion order for equal quality values
+		entries.sort_by(|left, right| {
+			right
+				.quality
+				.partial_cmp(&left.quality)
+				.unwrap_or(core::cmp::Ordering::Equal)
+		});
+		AcceptedCodings(entries).xok()
+	}
+
+	fn serialize(value: AcceptedCodings) -> Vec<String> {
+		vec![
+			value
+				.0
+				.iter()
+				.map(|entry| {
+					if entry.quality >= 1.0 {
+						entry.coding.as_str().to_string()
+					} else {
+						format!("{};q={}", entry.coding, entry.quality)
+					}
+				})
+				.collect::<Vec<_>>()
+				.join(", "),
+		]
+	}
+}
+
+// ============================================================================
+// Vary
+// ============================================================================
+
+/// Typed `Vary` header: the request headers a response was negotiated on,
+/// lowercased and flattened across repeated header lines.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set_raw("vary", "Accept, Accept-Encoding");
+/// let vary = map.get::<headers::Vary>().unwrap().unwrap();
+/// assert_eq!(vary, vec!["accept".to_string(), "accept-encoding".to_string()]);
+/// ```
+pub struct Vary;
+
+impl Header for Vary {
+	type Value = Vec<String>;
+	const KEY: &'static str = "vary";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		values
+			.iter()
+			.flat_map(|value| value.split(','))
+			.map(|key| key.trim().to_ascii_lowercase())
+			.filter(|key| !key.is_empty())
+			.collect::<Vec<_>>()
+			.xok()
+	}
+
+	fn serialize(value: Vec<String>) -> Vec<String> { vec![value.join(", ")] }
+}
+
 // ============================================================================
 // SetCookie / Cookie
 // ============================================================================
@@ -588,6 +840,475 @@ impl Header for Expires {
 	fn serialize(value: String) -> Vec<String> { vec![value] }
 }
 
+// ============================================================================
+// ETag / IfNoneMatch
+// ============================================================================
+
+/// An entity tag, the opaque validator of `ETag` and `If-None-Match`, written
+/// as `"tag"` or, when weak, `W/"tag"`.
+///
+/// A strong tag promises byte-identical representations; a weak one only
+/// semantic equivalence, eg the same page compressed two ways.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let tag = headers::EntityTag::parse("W/\"abc\"").unwrap();
+/// assert_eq!(tag, headers::EntityTag::weak("abc"));
+/// assert_eq!(tag.to_string(), "W/\"abc\"");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+	/// The opaque tag, without quotes.
+	pub tag: String,
+	/// Whether the tag is weak (`W/` prefixed).
+	pub weak: bool,
+}
+
+impl EntityTag {
+	/// A strong entity tag.
+	pub fn strong(tag: impl Into<String>) -> Self {
+		Self {
+			tag: tag.into(),
+			weak: false,
+		}
+	}
+
+	/// A weak entity tag.
+	pub fn weak(tag: impl Into<String>) -> Self {
+		Self {
+			tag: tag.into(),
+			weak: true,
+		}
+	}
+
+	/// Parses a single `"tag"` or `W/"tag"`.
+	pub fn parse(value: &str) -> Result<Self> {
+		let value = value.trim();
+		let (weak, quoted) = match value.strip_prefix("W/") {
+			Some(quoted) => (true, quoted),
+			None => (false, value),
+		};
+		let tag = quoted
+			.strip_prefix('"')
+			.and_then(|rest| rest.strip_suffix('"'))
+			.filter(|tag| !tag.contains('"'))
+			.ok_or_else(|| bevyhow!("invalid entity tag: {value}"))?;
+		Self {
+			tag: tag.to_string(),
+			weak,
+		}
+		.xok()
+	}
+
+	/// This tag with the weak flag set, for a representation that is no longer
+	/// byte-identical to the tagged one (eg after content encoding).
+	pub fn into_weak(self) -> Self { Self { weak: true, ..self } }
+
+	/// The weak comparison `If-None-Match` uses: the opaque tags are equal,
+	/// whatever either side's weak flag.
+	pub fn weak_eq(&self, other: &Self) -> bool { self.tag == other.tag }
+}
+
+impl core::fmt::Display for EntityTag {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		if self.weak {
+			write!(f, "W/\"{}\"", self.tag)
+		} else {
+			write!(f, "\"{}\"", self.tag)
+		}
+	}
+}
+
+/// Typed `ETag` header.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set::<headers::ETag>(headers::EntityTag::strong("v1"));
+/// assert_eq!(map.get_raw("etag").unwrap()[0], "\"v1\"");
+/// ```
+pub struct ETag;
+
+impl Header for ETag {
+	type Value = EntityTag;
+	const KEY: &'static str = "etag";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		values
+			.first()
+			.ok_or_else(|| bevyhow!("etag header has no value"))
+			.and_then(|value| EntityTag::parse(value))
+	}
+
+	fn serialize(value: EntityTag) -> Vec<String> { vec![value.to_string()] }
+}
+
+/// The value of `If-None-Match`: either `*` or a list of entity tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityTagMatch {
+	/// `*` — matches any current representation.
+	Any,
+	/// The listed entity tags.
+	Tags(Vec<EntityTag>),
+}
+
+impl EntityTagMatch {
+	/// Whether `etag` matches under the weak comparison `If-None-Match`
+	/// specifies.
+	pub fn matches(&self, etag: &EntityTag) -> bool {
+		match self {
+			Self::Any => true,
+			Self::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+		}
+	}
+}
+
+/// Typed `If-None-Match` header, flattened across repeated header lines.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set_raw("if-none-match", "\"a\", W/\"b\"");
+/// let condition = map.get::<headers::IfNoneMatch>().unwrap().unwrap();
+/// assert!(condition.matches(&headers::EntityTag::strong("b")));
+/// assert!(!condition.matches(&headers::EntityTag::strong("c")));
+/// ```
+pub struct IfNoneMatch;
+
+impl Header for IfNoneMatch {
+	type Value = EntityTagMatch;
+	const KEY: &'static str = "if-none-match";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		if values.iter().any(|value| value.trim() == "*") {
+			return EntityTagMatch::Any.xok();
+		}
+		values
+			.iter()
+			.flat_map(|value| value.split(','))
+			.map(str::trim)
+			.filter(|tag| !tag.is_empty())
+			.map(EntityTag::parse)
+			.collect::<Result<Vec<_>>>()?
+			.xmap(EntityTagMatch::Tags)
+			.xok()
+	}
+
+	fn serialize(value: EntityTagMatch) -> Vec<String> {
+		match value {
+			EntityTagMatch::Any => vec!["*".to_string()],
+			EntityTagMatch::Tags(tags) => vec![
+				tags.iter()
+					.map(|tag| tag.to_string())
+					.collect::<Vec<_>>()
+					.join(", "),
+			],
+		}
+	}
+}
+
+// ============================================================================
+// LastModified / IfModifiedSince
+// ============================================================================
+
+/// Typed `Last-Modified` header, an HTTP date parsed as a [`Timestamp`].
+///
+/// ```
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set::<headers::LastModified>(Timestamp::from_unix_epoch_elapsed(
+/// 	Duration::from_secs(784_111_777),
+/// ));
+/// assert_eq!(map.get_raw("last-modified").unwrap()[0], "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub struct LastModified;
+
+impl Header for LastModified {
+	type Value = Timestamp;
+	const KEY: &'static str = "last-modified";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		parse_http_timestamp(values, Self::KEY)
+	}
+
+	fn serialize(value: Timestamp) -> Vec<String> {
+		vec![time_ext::format_http_date(value.unix_epoch_elapsed())]
+	}
+}
+
+/// Typed `If-Modified-Since` header, an HTTP date parsed as a [`Timestamp`].
+pub struct IfModifiedSince;
+
+impl Header for IfModifiedSince {
+	type Value = Timestamp;
+	const KEY: &'static str = "if-modified-since";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		parse_http_timestamp(values, Self::KEY)
+	}
+
+	fn serialize(value: Timestamp) -> Vec<String> {
+		vec![time_ext::format_http_date(value.unix_epoch_elapsed())]
+	}
+}
+
+/// Parses the first value of the `key` header as an HTTP date.
+fn parse_http_timestamp(values: &Vec<String>, key: &str) -> Result<Timestamp> {
+	values
+		.first()
+		.ok_or_else(|| bevyhow!("{key} header has no value"))
+		.and_then(|value| time_ext::parse_http_date(value))
+		.map(Timestamp::from_unix_epoch_elapsed)
+}
+
+// ============================================================================
+// Range / ContentRange / IfRange / AcceptRanges
+// ============================================================================
+
+/// One `bytes` range of a `Range` request header, resolved against an
+/// object's length with [`resolve`](Self::resolve).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+	/// `first-last`, both inclusive.
+	FromTo(u64, u64),
+	/// `first-`, to the end of the object.
+	From(u64),
+	/// `-length`, the final `length` bytes.
+	Suffix(u64),
+}
+
+impl ByteRange {
+	/// The half-open byte range this selects from an object of `total` bytes,
+	/// clamped to its end, or `None` if it selects nothing (unsatisfiable).
+	pub fn resolve(&self, total: u64) -> Option<core::ops::Range<u64>> {
+		let (start, end) = match *self {
+			Self::FromTo(first, last) => {
+				(first, last.saturating_add(1).min(total))
+			}
+			Self::From(first) => (first, total),
+			Self::Suffix(length) => (total.saturating_sub(length), total),
+		};
+		(start < end).then_some(start..end)
+	}
+
+	/// Parses a single range spec, eg `0-99`, `100-` or `-50`.
+	pub fn parse(value: &str) -> Result<Self> {
+		let invalid = || bevyhow!("invalid byte range: {value}");
+		let (first, last) = value.trim().split_once('-').ok_or_else(invalid)?;
+		let parse = |num: &str| num.parse::<u64>().map_err(|_| invalid());
+		match (first.is_empty(), last.is_empty()) {
+			(true, false) => Self::Suffix(parse(last)?),
+			(false, true) => Self::From(parse(first)?),
+			(false, false) => {
+				let (first, last) = (parse(first)?, parse(last)?);
+				if first > last {
+					return Err(invalid());
+				}
+				Self::FromTo(first, last)
+			}
+			(true, true) => return Err(invalid()),
+		}
+		.xok()
+	}
+}
+
+impl core::fmt::Display for ByteRange {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::FromTo(first, last) => write!(f, "{first}-{last}"),
+			Self::From(first) => write!(f, "{first}-"),
+			Self::Suffix(length) => write!(f, "-{length}"),
+		}
+	}
+}
+
+/// Typed `Range` header, parsed as its list of [`ByteRange`]. Only the
+/// `bytes` unit is understood; any other fails to parse, so a server ignores
+/// the header and sends the whole object.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set_raw("range", "bytes=0-99, -50");
+/// let ranges = map.get::<headers::Range>().unwrap().unwrap();
+/// assert_eq!(ranges, vec![headers::ByteRange::FromTo(0, 99), headers::ByteRange::Suffix(50)]);
+/// ```
+pub struct Range;
+
+impl Header for Range {
+	type Value = Vec<ByteRange>;
+	const KEY: &'static str = "range";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		let value = values
+			.first()
+			.ok_or_else(|| bevyhow!("range header has no value"))?;
+		let specs = value
+			.trim()
+			.strip_prefix("bytes=")
+			.ok_or_else(|| bevyhow!("unsupported range unit: {value}"))?;
+		let ranges = specs
+			.split(',')
+			.filter(|spec| !spec.trim().is_empty())
+			.map(ByteRange::parse)
+			.collect::<Result<Vec<_>>>()?;
+		if ranges.is_empty() {
+			bevybail!("range header has no ranges: {value}");
+		}
+		ranges.xok()
+	}
+
+	fn serialize(value: Vec<ByteRange>) -> Vec<String> {
+		vec![format!(
+			"bytes={}",
+			value
+				.iter()
+				.map(|range| range.to_string())
+				.collect::<Vec<_>>()
+				.join(", ")
+		)]
+	}
+}
+
+/// The value of a `Content-Range` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentRangeValue {
+	/// `bytes first-last/total`: the half-open `range` of a `total`-byte
+	/// object this part carries.
+	Satisfied {
+		/// The half-open byte range sent.
+		range: core::ops::Range<u64>,
+		/// The full object length.
+		total: u64,
+	},
+	/// `bytes */total`, sent with a `416 Range Not Satisfiable`.
+	Unsatisfied {
+		/// The full object length.
+		total: u64,
+	},
+}
+
+impl core::fmt::Display for ContentRangeValue {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Satisfied { range, total } => {
+				write!(f, "bytes {}-{}/{total}", range.start, range.end - 1)
+			}
+			Self::Unsatisfied { total } => write!(f, "bytes */{total}"),
+		}
+	}
+}
+
+/// Typed `Content-Range` header.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut map = HeaderMap::new();
+/// map.set::<headers::ContentRange>(headers::ContentRangeValue::Satisfied {
+/// 	range: 0..100,
+/// 	total: 1000,
+/// });
+/// assert_eq!(map.get_raw("content-range").unwrap()[0], "bytes 0-99/1000");
+/// ```
+pub struct ContentRange;
+
+impl Header for ContentRange {
+	type Value = ContentRangeValue;
+	const KEY: &'static str = "content-range";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		let value = values
+			.first()
+			.ok_or_else(|| bevyhow!("content-range header has no value"))?;
+		let invalid = || bevyhow!("invalid content-range: {value}");
+		let (range, total) = value
+			.trim()
+			.strip_prefix("bytes ")
+			.and_then(|rest| rest.split_once('/'))
+			.ok_or_else(invalid)?;
+		let total = total.parse::<u64>().map_err(|_| invalid())?;
+		if range == "*" {
+			return ContentRangeValue::Unsatisfied { total }.xok();
+		}
+		match ByteRange::parse(range)? {
+			ByteRange::FromTo(first, last) => ContentRangeValue::Satisfied {
+				range: first..last + 1,
+				total,
+			}
+			.xok(),
+			_ => Err(invalid()),
+		}
+	}
+
+	fn serialize(value: ContentRangeValue) -> Vec<String> {
+		vec![value.to_string()]
+	}
+}
+
+/// The value of an `If-Range` header: the validator a ranged request is
+/// conditional on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfRangeValue {
+	/// An entity tag, which must match strongly.
+	ETag(EntityTag),
+	/// A `Last-Modified` date, which must match exactly.
+	Date(Timestamp),
+}
+
+/// Typed `If-Range` header.
+pub struct IfRange;
+
+impl Header for IfRange {
+	type Value = IfRangeValue;
+	const KEY: &'static str = "if-range";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		let value = values
+			.first()
+			.ok_or_else(|| bevyhow!("if-range header has no value"))?;
+		if value.trim_start().starts_with(['"', 'W']) {
+			EntityTag::parse(value).map(IfRangeValue::ETag)
+		} else {
+			time_ext::parse_http_date(value)
+				.map(Timestamp::from_unix_epoch_elapsed)
+				.map(IfRangeValue::Date)
+		}
+	}
+
+	fn serialize(value: IfRangeValue) -> Vec<String> {
+		match value {
+			IfRangeValue::ETag(etag) => vec![etag.to_string()],
+			IfRangeValue::Date(date) => {
+				vec![time_ext::format_http_date(date.unix_epoch_elapsed())]
+			}
+		}
+	}
+}
+
+/// Typed `Accept-Ranges` header, eg `bytes` or `none`.
+pub struct AcceptRanges;
+
+impl Header for AcceptRanges {
+	type Value = String;
+	const KEY: &'static str = "accept-ranges";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		values
+			.first()
+			.cloned()
+			.ok_or_else(|| bevyhow!("accept-ranges header has no value"))
+	}
+
+	fn serialize(value: String) -> Vec<String> { vec![value] }
+}
+
 // ============================================================================
 // UserAgent
 // ============================================================================
@@ -651,6 +1372,37 @@ impl Header for Referer {
 	fn serialize(value: String) -> Vec<String> { vec![value] }
 }
 
+// ============================================================================
+// LastEventId
+// ============================================================================
+
+/// Typed `Last-Event-ID` header: the id of the last Server-Sent Event a
+/// reconnecting `EventSource` received.
+///
+/// ```
+/// # use beet_net::prelude::*;
+/// # use beet_net::headers;
+/// let mut headers = HeaderMap::new();
+/// headers.set_raw("Last-Event-ID", "42");
+/// let id: String = headers.get::<headers::LastEventId>().unwrap().unwrap();
+/// assert_eq!(id, "42");
+/// ```
+pub struct LastEventId;
+
+impl Header for LastEventId {
+	type Value = String;
+	const KEY: &'static str = "last-event-id";
+
+	fn parse(values: &Vec<String>) -> Result<Self::Value> {
+		values
+			.first()
+			.cloned()
+			.ok_or_else(|| bevyhow!("last-event-id header has no value"))
+	}
+
+	fn serialize(value: String) -> Vec<String> { vec![value] }
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -817,6 +1569,117 @@ mod test {
 		}
 	}
 
+	#[beet_core::test]
+	fn accept_encoding_quality_ordering() {
+		let mut map = HeaderMap::new();
+		map.set_raw("accept-encoding", "gzip;q=0.8, br, identity;q=0");
+		let accepted = map.get::<AcceptEncoding>().unwrap().unwrap();
+		accepted
+			.codings()
+			.xpect_eq(vec![ContentCoding::Brotli, ContentCoding::Gzip]);
+		accepted.accepts(&ContentCoding::Identity).xpect_false();
+	}
+
+	#[beet_core::test]
+	fn accept_encoding_refusal_overrides_wildcard() {
+		let mut map = HeaderMap::new();
+		map.set_raw("accept-encoding", "br;q=0, *");
+		let accepted = map.get::<AcceptEncoding>().unwrap().unwrap();
+		accepted.accepts(&ContentCoding::Brotli).xpect_false();
+		accepted.accepts(&ContentCoding::Gzip).xpect_true();
+		accepted.accepts(&ContentCoding::Identity).xpect_true();
+	}
+
+	#[beet_core::test]
+	fn content_encoding_roundtrip() {
+		let mut map = HeaderMap::new();
+		map.set::<ContentEncoding>(ContentCoding::Zstd);
+		map.get::<ContentEncoding>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(ContentCoding::Zstd);
+	}
+
+	#[beet_core::test]
+	fn vary_flattens_lines() {
+		let mut map = HeaderMap::new();
+		map.set_raw("vary", "Accept");
+		map.set_raw("vary", "Accept-Encoding, Origin");
+		map.get::<Vary>().unwrap().unwrap().xpect_eq(vec![
+			"accept".to_string(),
+			"accept-encoding".to_string(),
+			"origin".to_string(),
+		]);
+	}
+
+	#[beet_core::test]
+	fn entity_tag_parsing() {
+		EntityTag::parse("\"abc\"")
+			.unwrap()
+			.xpect_eq(EntityTag::strong("abc"));
+		EntityTag::parse("W/\"abc\"")
+			.unwrap()
+			.xpect_eq(EntityTag::weak("abc"));
+		EntityTag::parse("abc").xpect_err();
+		EntityTag::weak("abc")
+			.weak_eq(&EntityTag::strong("abc"))
+			.xpect_true();
+	}
+
+	#[beet_core::test]
+	fn if_none_match_any() {
+		let mut map = HeaderMap::new();
+		map.set_raw("if-none-match", "*");
+		map.get::<IfNoneMatch>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(EntityTagMatch::Any);
+	}
+
+	#[beet_core::test]
+	fn last_modified_roundtrip() {
+		let date = Timestamp::from_unix_epoch_elapsed(Duration::from_secs(
+			1_709_164_800,
+		));
+		let mut map = HeaderMap::new();
+		map.set::<LastModified>(date);
+		map.get::<LastModified>().unwrap().unwrap().xpect_eq(date);
+		map.set_raw("if-modified-since", "not a date");
+		map.get::<IfModifiedSince>().unwrap().xpect_err();
+	}
+
+	#[beet_core::test]
+	fn byte_range_resolution() {
+		ByteRange::FromTo(0, 99).resolve(50).xpect_eq(Some(0..50));
+		ByteRange::From(10).resolve(50).xpect_eq(Some(10..50));
+		ByteRange::Suffix(100).resolve(50).xpect_eq(Some(0..50));
+		ByteRange::From(50).resolve(50).xpect_none();
+		ByteRange::Suffix(0).resolve(50).xpect_none();
+		ByteRange::parse("9-3").xpect_err();
+	}
+
+	#[beet_core::test]
+	fn range_rejects_other_units() {
+		let mut map = HeaderMap::new();
+		map.set_raw("range", "items=0-4");
+		map.get::<Range>().unwrap().xpect_err();
+	}
+
+	#[beet_core::test]
+	fn content_range_roundtrip() {
+		for value in [
+			ContentRangeValue::Satisfied {
+				range: 10..20,
+				total: 100,
+			},
+			ContentRangeValue::Unsatisfied { total: 100 },
+		] {
+			let mut map = HeaderMap::new();
+			map.set::<ContentRange>(value.clone());
+			map.get::<ContentRange>().unwrap().unwrap().xpect_eq(value);
+		}
+	}
+
 	#[beet_core::test]
 	fn user_agent_roundtrip() {
 		let mut map = HeaderMap::new();
@@ -55,6 +55,20 @@ impl HttpError {
 		Self::new(StatusCode::BAD_REQUEST, message)
 	}
 
+	/// Creates a 412 Precondition Failed error, ie a conditional write whose
+	/// condition no longer holds.
+	pub fn precondition_failed(message: impl Into<String>) -> Self {
+		Self::new(StatusCode::PRECONDITION_FAILED, message)
+	}
+
+	/// The status code of `error` if it is an [`HttpError`], ie to tell a
+	/// conflicting write apart from a broken backend.
+	pub fn status_of(error: &BevyError) -> Option<StatusCode> {
+		error
+			.downcast_ref::<HttpError>()
+			.map(|inner| inner.status_code)
+	}
+
 	/// Creates a 500 Internal Server Error.
 	///
 	/// In debug builds, the full error message is included.
@@ -78,5 +78,15 @@ mod extractors;
 pub use exchange::*;
 pub use exchange_stats::*;
 pub use extractors::*;
+// the streaming `multipart/form-data` reader, an extractor alongside `extractors`
+// (no_std: it only needs the body stream).
+mod multipart;
+pub use multipart::*;
+// the `text/event-stream` producer, the server half of the client's
+// `event_source`: std for its channels and the `TableStore` replay.
+#[cfg(feature = "std")]
+mod sse_response;
+#[cfg(feature = "std")]
+pub use sse_response::*;
 mod settle_time;
 pub use settle_time::*;
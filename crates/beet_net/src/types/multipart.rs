@@ -0,0 +1,554 @@
+//! Streaming `multipart/form-data` parsing (RFC 7578), the body a browser
+//! `<form enctype="multipart/form-data">` submits.
+//!
+//! [`Multipart`] reads fields off a [`Body`] one at a time, so a file upload
+//! streams through in chunks rather than being buffered whole. A field is read
+//! to its end (or dropped) before the next is requested; requesting the next
+//! field skips whatever of the current one was left unread.
+//!
+//! ```
+//! # use beet_core::prelude::*;
+//! # use beet_net::prelude::*;
+//! # async fn run(body: Body) -> Result {
+//! let mut multipart = Multipart::new(body, "boundary");
+//! while let Some(mut field) = multipart.next_field().await? {
+//! 	let name = field.name().to_string();
+//! 	while let Some(chunk) = field.chunk().await? {
+//! 		println!("{name}: {} bytes", chunk.len());
+//! 	}
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use super::*;
+use beet_core::prelude::*;
+use bytes::Buf;
+use bytes::Bytes;
+use bytes::BytesMut;
+
+/// Size limits applied while parsing a [`Multipart`] body. Exceeding any of
+/// them fails the read with `413 Content Too Large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultipartLimits {
+	/// The most fields (files included) one body may contain.
+	pub max_fields: usize,
+	/// The largest a single field's content may be, in bytes.
+	pub max_field_size: u64,
+	/// The largest the whole body may be, in bytes.
+	pub max_total_size: u64,
+}
+
+impl MultipartLimits {
+	/// The largest the header block of one field may be, in bytes.
+	pub const MAX_HEADER_SIZE: usize = 8 * 1024;
+}
+
+impl Default for MultipartLimits {
+	fn default() -> Self {
+		Self {
+			max_fields: 64,
+			max_field_size: 16 * 1024 * 1024,
+			max_total_size: 64 * 1024 * 1024,
+		}
+	}
+}
+
+/// A streaming `multipart/form-data` reader over a request [`Body`].
+///
+/// As an extractor it requires a `multipart/form-data` content type carrying a
+/// `boundary`, answering `415`/`400` otherwise, and applies the default
+/// [`MultipartLimits`]; use [`Multipart::with_limits`] to change them.
+pub struct Multipart {
+	body: Body,
+	/// `\r\n--{boundary}`, the delimiter ending each part's content.
+	delimiter: Vec<u8>,
+	/// Bytes read off `body` but not yet consumed.
+	buffer: BytesMut,
+	limits: MultipartLimits,
+	/// Bytes read off `body` so far.
+	total_read: u64,
+	/// Fields started so far.
+	fields_read: usize,
+	state: MultipartState,
+}
+
+/// Where a [`Multipart`] is in its body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MultipartState {
+	/// Before the first boundary, skipping any preamble.
+	Preamble,
+	/// At a `--{boundary}` line, before the next field's headers.
+	Boundary,
+	/// Streaming a field's content, `read` bytes in.
+	Field { read: u64 },
+	/// Past the closing `--{boundary}--`.
+	Done,
+}
+
+impl Multipart {
+	/// Read the fields of `body`, delimited by `boundary`.
+	pub fn new(body: Body, boundary: impl AsRef<str>) -> Self {
+		let mut delimiter = b"\r\n--".to_vec();
+		delimiter.extend_from_slice(boundary.as_ref().as_bytes());
+		Self {
+			body,
+			delimiter,
+			buffer: BytesMut::new(),
+			limits: default(),
+			total_read: 0,
+			fields_read: 0,
+			state: MultipartState::Preamble,
+		}
+	}
+
+	/// Apply `limits` instead of the defaults.
+	pub fn with_limits(mut self, limits: MultipartLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	/// The `boundary` parameter of a raw `multipart/form-data` content type,
+	/// ie `multipart/form-data; boundary=abc` gives `abc`.
+	pub fn boundary_from_content_type(content_type: &str) -> Option<String> {
+		let mut params = content_type.split(';');
+		let media_type = params.next()?.trim();
+		if !media_type.eq_ignore_ascii_case("multipart/form-data") {
+			return None;
+		}
+		params
+			.filter_map(|param| param.split_once('='))
+			.find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+			.map(|(_, value)| value.trim().trim_matches('"').to_string())
+			.filter(|boundary| !boundary.is_empty())
+	}
+
+	/// The next field, or `None` past the closing boundary. Any unread
+	/// content of the previous field is skipped.
+	pub async fn next_field(&mut self) -> Result<Option<MultipartField<'_>>> {
+		let head = self.next_head().await?;
+		Ok(head.map(|head| MultipartField {
+			multipart: self,
+			head,
+		}))
+	}
+
+	/// Advance to the next field's headers and parse them.
+	async fn next_head(&mut self) -> Result<Option<FieldHead>> {
+		loop {
+			match self.state {
+				MultipartState::Done => return Ok(None),
+				MultipartState::Field { .. } => {
+					while self.field_chunk().await?.is_some() {}
+				}
+				MultipartState::Preamble => {
+					// the first boundary has no leading CRLF when the body
+					// opens with it, so match it without one
+					let boundary = &self.delimiter[2..];
+					if let Some(pos) = find(&self.buffer, boundary) {
+						self.buffer.advance(pos);
+						self.state = MultipartState::Boundary;
+					} else {
+						// keep only a tail that may begin a split boundary
+						let keep = boundary.len() - 1;
+						let drop = self.buffer.len().saturating_sub(keep);
+						self.buffer.advance(drop);
+						if !self.fill().await? {
+							return Err(malformed("no boundary in body"));
+						}
+					}
+				}
+				MultipartState::Boundary => {
+					let boundary_len = self.delimiter.len() - 2;
+					if self.buffer.len() < boundary_len + 2 {
+						if !self.fill().await? {
+							return Err(malformed("unexpected end of body"));
+						}
+						continue;
+					}
+					match &self.buffer[boundary_len..boundary_len + 2] {
+						// the closing boundary, any epilogue is ignored
+						b"--" => {
+							self.state = MultipartState::Done;
+							return Ok(None);
+						}
+						b"\r\n" => {}
+						_ => return Err(malformed("invalid boundary line")),
+					}
+					let Some(end) =
+						find(&self.buffer[boundary_len..], b"\r\n\r\n")
+							.map(|pos| pos + boundary_len)
+					else {
+						if self.buffer.len()
+							> boundary_len + MultipartLimits::MAX_HEADER_SIZE
+						{
+							return Err(too_large("field headers too large"));
+						}
+						if !self.fill().await? {
+							return Err(malformed("unexpected end of body"));
+						}
+						continue;
+					};
+					self.fields_read += 1;
+					if self.fields_read > self.limits.max_fields {
+						return Err(too_large(format!(
+							"more than {} fields",
+							self.limits.max_fields
+						)));
+					}
+					// an empty header block ends at the boundary's own CRLF
+					let headers = self
+						.buffer
+						.get(boundary_len + 2..end)
+						.unwrap_or_default();
+					let head = FieldHead::parse(headers)?;
+					self.buffer.advance(end + 4);
+					self.state = MultipartState::Field { read: 0 };
+					return Ok(Some(head));
+				}
+			}
+		}
+	}
+
+	/// The next chunk of the current field's content, or `None` at its end.
+	async fn field_chunk(&mut self) -> Result<Option<Bytes>> {
+		let MultipartState::Field { read } = self.state else {
+			return Ok(None);
+		};
+		loop {
+			let (chunk, end) = match find(&self.buffer, &self.delimiter) {
+				Some(pos) => (self.buffer.split_to(pos).freeze(), true),
+				None => {
+					// everything but a tail that may begin a split delimiter
+					let keep = self.delimiter.len() - 1;
+					let len = self.buffer.len().saturating_sub(keep);
+					(self.buffer.split_to(len).freeze(), false)
+				}
+			};
+			let read = read + chunk.len() as u64;
+			if read > self.limits.max_field_size {
+				return Err(too_large(format!(
+					"field larger than {} bytes",
+					self.limits.max_field_size
+				)));
+			}
+			if end {
+				// drop the CRLF, leaving the buffer at the next boundary
+				self.buffer.advance(2);
+				self.state = MultipartState::Boundary;
+				return Ok((!chunk.is_empty()).then_some(chunk));
+			}
+			self.state = MultipartState::Field { read };
+			if !chunk.is_empty() {
+				return Ok(Some(chunk));
+			}
+			if !self.fill().await? {
+				return Err(malformed("unexpected end of body"));
+			}
+		}
+	}
+
+	/// Read the next chunk off the body into the buffer, `false` at its end.
+	async fn fill(&mut self) -> Result<bool> {
+		let Some(chunk) = self.body.next().await? else {
+			return Ok(false);
+		};
+		self.total_read += chunk.len() as u64;
+		if self.total_read > self.limits.max_total_size {
+			return Err(too_large(format!(
+				"body larger than {} bytes",
+				self.limits.max_total_size
+			)));
+		}
+		self.buffer.extend_from_slice(&chunk);
+		Ok(true)
+	}
+}
+
+impl FromRequest<Self> for Multipart {
+	fn from_request(
+		request: Request,
+	) -> MaybeSendBoxedFuture<'static, Result<Self, Response>> {
+		Box::pin(async move {
+			let content_type = request
+				.headers()
+				.first_raw("content-type")
+				.ok_or_else(|| unsupported_media_type())?;
+			let boundary = match MediaType::from_content_type(content_type) {
+				MediaType::FormData => {
+					Multipart::boundary_from_content_type(content_type)
+						.ok_or_else(|| {
+							HttpError::bad_request(
+								"multipart/form-data content type has no boundary",
+							)
+						})?
+				}
+				_ => return Err(unsupported_media_type().into()),
+			};
+			Ok(Self::new(request.body, boundary))
+		})
+	}
+}
+
+fn unsupported_media_type() -> HttpError {
+	HttpError::new(
+		StatusCode::UNSUPPORTED_MEDIA_TYPE,
+		"expected a multipart/form-data body",
+	)
+}
+
+/// One field of a [`Multipart`] body: a form value or an uploaded file.
+pub struct MultipartField<'a> {
+	multipart: &'a mut Multipart,
+	head: FieldHead,
+}
+
+impl MultipartField<'_> {
+	/// The form control name, from the `content-disposition` header.
+	pub fn name(&self) -> &str { &self.head.name }
+
+	/// The uploaded file's name as the client gave it, set only for a file
+	/// field. Untrusted: it may contain path separators or be empty.
+	pub fn file_name(&self) -> Option<&str> { self.head.file_name.as_deref() }
+
+	/// The field's declared media type, if any.
+	pub fn content_type(&self) -> Option<MediaType> {
+		self.head
+			.headers
+			.get::<header::ContentType>()
+			.and_then(|result| result.ok())
+	}
+
+	/// All of the field's headers.
+	pub fn headers(&self) -> &HeaderMap { &self.head.headers }
+
+	/// The next chunk of the field's content, or `None` at its end.
+	pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+		self.multipart.field_chunk().await
+	}
+
+	/// Collect the rest of the field's content.
+	pub async fn bytes(mut self) -> Result<Bytes> {
+		let mut buffer = BytesMut::new();
+		while let Some(chunk) = self.chunk().await? {
+			buffer.extend_from_slice(&chunk);
+		}
+		Ok(buffer.freeze())
+	}
+
+	/// Collect the rest of the field's content as UTF-8 text.
+	pub async fn text(self) -> Result<String> {
+		let bytes = self.bytes().await?;
+		String::from_utf8(bytes.to_vec())
+			.map_err(|_| malformed("field is not valid UTF-8"))
+	}
+}
+
+/// The parsed header block of one field.
+struct FieldHead {
+	name: String,
+	file_name: Option<String>,
+	headers: HeaderMap,
+}
+
+impl FieldHead {
+	fn parse(block: &[u8]) -> Result<Self> {
+		let block = core::str::from_utf8(block)
+			.map_err(|_| malformed("field headers are not valid UTF-8"))?;
+		let mut headers = HeaderMap::new();
+		for line in block.split("\r\n").filter(|line| !line.is_empty()) {
+			let (key, value) = line
+				.split_once(':')
+				.ok_or_else(|| malformed("invalid field header"))?;
+			headers.set_raw(key.trim(), value.trim());
+		}
+		let disposition = headers
+			.first_raw("content-disposition")
+			.ok_or_else(|| malformed("field has no content-disposition"))?;
+		let params = disposition_params(disposition);
+		let param = |key: &str| {
+			params
+				.iter()
+				.find(|(param, _)| param == key)
+				.map(|(_, value)| value.clone())
+		};
+		let name =
+			param("name").ok_or_else(|| malformed("field has no name"))?;
+		let file_name = param("filename");
+		Ok(Self {
+			name,
+			file_name,
+			headers,
+		})
+	}
+}
+
+/// The `key=value` parameters of a `content-disposition` value, keys
+/// lowercased and quoted values unescaped, ie `form-data; name="a"` gives
+/// `[("name", "a")]`.
+fn disposition_params(value: &str) -> Vec<(String, String)> {
+	let mut params = Vec::new();
+	// skip the disposition type
+	let Some((_, mut rest)) = value.split_once(';') else {
+		return params;
+	};
+	loop {
+		rest = rest.trim_start();
+		let Some((key, after)) = rest.split_once('=') else {
+			break;
+		};
+		let key = key.trim().to_ascii_lowercase();
+		let after = after.trim_start();
+		let (value, remaining) = match after.strip_prefix('"') {
+			Some(quoted) => {
+				let mut value = String::new();
+				let mut end = quoted.len();
+				let mut chars = quoted.char_indices().peekable();
+				while let Some((index, ch)) = chars.next() {
+					match ch {
+						// only `\"` and `\\` are escapes: browsers send a
+						// windows path's backslashes unescaped
+						'\\' if matches!(
+							chars.peek(),
+							Some((_, '"')) | Some((_, '\\'))
+						) =>
+						{
+							value.push(chars.next().unwrap().1);
+						}
+						'"' => {
+							end = index + 1;
+							break;
+						}
+						ch => value.push(ch),
+					}
+				}
+				let remaining = &quoted[end..];
+				let remaining = remaining
+					.split_once(';')
+					.map(|(_, remaining)| remaining)
+					.unwrap_or("");
+				(value, remaining)
+			}
+			None => {
+				let (value, remaining) =
+					after.split_once(';').unwrap_or((after, ""));
+				(value.trim().to_string(), remaining)
+			}
+		};
+		params.push((key, value));
+		rest = remaining;
+	}
+	params
+}
+
+/// The position of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack
+		.windows(needle.len())
+		.position(|window| window == needle)
+}
+
+fn malformed(message: impl Into<String>) -> BevyError {
+	HttpError::bad_request(format!(
+		"malformed multipart body: {}",
+		message.into()
+	))
+	.into()
+}
+
+fn too_large(message: impl Into<String>) -> BevyError {
+	HttpError::new(StatusCode::CONTENT_TOO_LARGE, message).into()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const BOUNDARY: &str = "XyZ";
+
+	fn form_body() -> String {
+		[
+			"preamble\r\n",
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"title\"\r\n",
+			"\r\n",
+			"hello\r\n",
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"file\"; filename=\"a;b.txt\"\r\n",
+			"Content-Type: text/plain\r\n",
+			"\r\n",
+			"line one\r\nline two\r\n",
+			"--XyZ--\r\n",
+		]
+		.concat()
+	}
+
+	/// Stream `body` a few bytes at a time, so boundaries split across chunks.
+	fn chunked(body: String, size: usize) -> Body {
+		let chunks = body
+			.into_bytes()
+			.chunks(size)
+			.map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+			.collect::<Vec<_>>();
+		Body::stream(futures::stream::iter(chunks))
+	}
+
+	async fn collect(
+		mut multipart: Multipart,
+	) -> Vec<(String, Option<String>, String)> {
+		let mut fields = Vec::new();
+		while let Some(field) = multipart.next_field().await.unwrap() {
+			let name = field.name().to_string();
+			let file_name = field.file_name().map(str::to_string);
+			fields.push((name, file_name, field.text().await.unwrap()));
+		}
+		fields
+	}
+
+	#[beet_core::test]
+	async fn parses_fields() {
+		for size in [1, 3, 7, 1024] {
+			collect(Multipart::new(chunked(form_body(), size), BOUNDARY))
+				.await
+				.xpect_eq(vec![
+					("title".to_string(), None, "hello".to_string()),
+					(
+						"file".to_string(),
+						Some("a;b.txt".to_string()),
+						"line one\r\nline two".to_string(),
+					),
+				]);
+		}
+	}
+
+	#[beet_core::test]
+	async fn skips_unread_fields() {
+		let mut multipart = Multipart::new(form_body().into(), BOUNDARY);
+		multipart.next_field().await.unwrap().unwrap();
+		let field = multipart.next_field().await.unwrap().unwrap();
+		field.content_type().xpect_eq(Some(MediaType::Text));
+		multipart.next_field().await.unwrap().xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn enforces_limits() {
+		let mut multipart = Multipart::new(form_body().into(), BOUNDARY)
+			.with_limits(MultipartLimits {
+				max_field_size: 8,
+				..default()
+			});
+		multipart.next_field().await.unwrap().unwrap();
+		let field = multipart.next_field().await.unwrap().unwrap();
+		HttpError::from_opaque(field.bytes().await.unwrap_err())
+			.status_code
+			.xpect_eq(StatusCode::CONTENT_TOO_LARGE);
+	}
+
+	#[beet_core::test]
+	async fn extracts_boundary() {
+		Multipart::boundary_from_content_type(
+			"multipart/form-data; boundary=\"abc def\"",
+		)
+		.xpect_eq(Some("abc def".to_string()));
+		Multipart::boundary_from_content_type("application/json").xpect_none();
+	}
+}
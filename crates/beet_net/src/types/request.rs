@@ -174,6 +174,15 @@ impl Request {
 		self
 	}
 
+	/// Tags the request with the direct client address, as a server does on
+	/// accept, for a transport reaching a router without a socket (an ssh
+	/// session's in-world navigator).
+	pub fn with_peer_addr(mut self, addr: core::net::SocketAddr) -> Self {
+		self.headers.remove_raw(PEER_ADDR_HEADER);
+		self.headers.set_raw(PEER_ADDR_HEADER, addr.to_string());
+		self
+	}
+
 	/// Sets the request body from bytes
 	pub fn with_body(mut self, body: impl AsRef<[u8]>) -> Self {
 		self.body = Bytes::copy_from_slice(body.as_ref()).into();
@@ -125,6 +125,17 @@ impl Response {
 		}
 	}
 
+	/// Creates a See Other (303) response with the given location, the
+	/// redirect answering a form `POST` with a `GET` of the location
+	pub fn see_other(location: impl Into<String>) -> Self {
+		let mut parts = ResponseParts::new(StatusCode::SEE_OTHER);
+		parts.headers.set::<header::Location>(location.into());
+		Self {
+			parts,
+			body: Default::default(),
+		}
+	}
+
 	/// Returns the status code
 	pub fn status(&self) -> StatusCode { self.parts.status() }
 
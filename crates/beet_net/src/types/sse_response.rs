@@ -0,0 +1,629 @@
+//! Server-Sent Events responses: the server half of the client's
+//! `event_source` readers.
+//!
+//! An [`SseResponse`] streams [`SseEvent`]s as a `text/event-stream` body,
+//! writing a keep-alive comment whenever the stream idles so proxies hold the
+//! connection open. A browser `EventSource` reconnects by itself after a drop,
+//! echoing the id of the last event it saw as `Last-Event-ID`; an
+//! [`SseReplay`] answers with the events it missed, from a bounded in-memory
+//! [`SseReplayBuffer`] or a capped set of persisted [`TableStore`] rows.
+//!
+//! For one-to-many live events an [`SseBroadcast`] fans each event out to every
+//! subscribed response, replaying from its own buffer.
+//!
+//! ```no_run
+//! # use beet_core::prelude::*;
+//! # use beet_net::prelude::*;
+//! # fn handler(broadcast: &SseBroadcast, request: &Request) -> Result<Response> {
+//! broadcast.send(SseEvent::new("reload"));
+//! let response = broadcast.subscribe(request)?.into_response();
+//! # Ok(response)
+//! # }
+//! ```
+
+use crate::prelude::*;
+use beet_core::prelude::*;
+use bevy::tasks::futures_lite::FutureExt;
+use bevy::tasks::futures_lite::StreamExt;
+use bevy::tasks::futures_lite::stream;
+use bytes::Bytes;
+use core::ops::Bound;
+use futures::Stream;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// How often an [`SseResponse`] writes a keep-alive comment by default, well
+/// under the idle timeout of common proxies and load balancers.
+pub const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// How many events an [`SseReplayBuffer`] or [`SseReplay::Table`] retains by
+/// default.
+pub const SSE_REPLAY_CAPACITY: usize = 128;
+
+/// The comment written while the event stream idles. Clients ignore comments,
+/// but the bytes reset every intermediary's idle timer.
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// A single Server-Sent Event as written to a `text/event-stream`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SseEvent {
+	/// The id a reconnecting client echoes back as `Last-Event-ID`.
+	pub id: Option<String>,
+	/// The event type, `message` when unset.
+	pub event: Option<String>,
+	/// The payload, written as one `data:` line per line.
+	pub data: String,
+}
+
+impl SseEvent {
+	/// Creates an unnamed `message` event with the given payload.
+	pub fn new(data: impl Into<String>) -> Self {
+		Self {
+			data: data.into(),
+			..default()
+		}
+	}
+
+	/// Creates a `message` event with the JSON-serialized payload, the form
+	/// the client's `event_source_typed` reads back.
+	#[cfg(feature = "json")]
+	pub fn json<T: serde::Serialize>(data: &T) -> Result<Self> {
+		Self::new(serde_json::to_string(data)?).xok()
+	}
+
+	/// Sets the event id.
+	pub fn with_id(mut self, id: impl Into<String>) -> Self {
+		self.id = Some(id.into());
+		self
+	}
+
+	/// Sets the event type.
+	pub fn with_event(mut self, event: impl Into<String>) -> Self {
+		self.event = Some(event.into());
+		self
+	}
+
+	/// Serializes this event into its wire chunk, terminated by a blank line.
+	/// Line breaks in the id or event type are dropped as they would end the
+	/// field early.
+	pub fn to_chunk(&self) -> Bytes {
+		let mut chunk = String::new();
+		if let Some(id) = &self.id {
+			chunk.push_str(&format!("id: {}\n", single_line(id)));
+		}
+		if let Some(event) = &self.event {
+			chunk.push_str(&format!("event: {}\n", single_line(event)));
+		}
+		for line in self.data.split('\n') {
+			let line = line.strip_suffix('\r').unwrap_or(line);
+			chunk.push_str(&format!("data: {line}\n"));
+		}
+		chunk.push('\n');
+		Bytes::from(chunk)
+	}
+}
+
+fn single_line(value: &str) -> String { value.replace(['\r', '\n'], "") }
+
+/// A bounded, shareable ring of the most recent events, numbering each with a
+/// sequential id so a reconnecting client resumes after its `Last-Event-ID`.
+///
+/// Clones share the same ring. The ids restart with the process, so an id the
+/// buffer never issued replays everything it retains.
+#[derive(Debug, Clone)]
+pub struct SseReplayBuffer {
+	inner: Arc<Mutex<ReplayBufferInner>>,
+}
+
+#[derive(Debug)]
+struct ReplayBufferInner {
+	events: VecDeque<(u64, SseEvent)>,
+	capacity: usize,
+	next_id: u64,
+}
+
+impl Default for SseReplayBuffer {
+	fn default() -> Self { Self::new(SSE_REPLAY_CAPACITY) }
+}
+
+impl SseReplayBuffer {
+	/// Creates a buffer retaining the last `capacity` events.
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			inner: Arc::new(Mutex::new(ReplayBufferInner {
+				events: VecDeque::with_capacity(capacity),
+				capacity,
+				next_id: 1,
+			})),
+		}
+	}
+
+	/// The number of events this buffer retains.
+	pub fn capacity(&self) -> usize { self.inner.lock().unwrap().capacity }
+
+	/// Records an event, replacing its id with the next sequence number and
+	/// evicting the oldest event past capacity. Returns the numbered event.
+	pub fn push(&self, event: SseEvent) -> SseEvent {
+		let mut inner = self.inner.lock().unwrap();
+		let id = inner.next_id;
+		inner.next_id += 1;
+		let event = event.with_id(id.to_string());
+		if inner.capacity > 0 {
+			if inner.events.len() == inner.capacity {
+				inner.events.pop_front();
+			}
+			inner.events.push_back((id, event.clone()));
+		}
+		event
+	}
+
+	/// The retained events after `last_event_id`, oldest first.
+	pub fn after(&self, last_event_id: &str) -> Vec<SseEvent> {
+		let inner = self.inner.lock().unwrap();
+		let last = last_event_id
+			.parse::<u64>()
+			.ok()
+			.filter(|id| *id < inner.next_id);
+		inner
+			.events
+			.iter()
+			.filter(|(id, _)| last.is_none_or(|last| *id > last))
+			.map(|(_, event)| event.clone())
+			.collect()
+	}
+}
+
+/// Where a reconnecting client's missed events are recorded and replayed from.
+#[derive(Debug, Clone)]
+pub enum SseReplay {
+	/// A bounded in-memory ring, lost on restart.
+	Buffer(SseReplayBuffer),
+	/// [`TableItem<SseEvent>`] rows keyed by their time-ordered uuid v7 ids,
+	/// surviving restarts and shared by every server on the store.
+	Table {
+		/// The store holding the rows.
+		store: TableStore,
+		/// How many of the latest events are retained, older rows being
+		/// removed as each event is recorded.
+		capacity: usize,
+	},
+}
+
+impl SseReplay {
+	/// Records to and replays from `store`, retaining the last
+	/// [`SSE_REPLAY_CAPACITY`] events.
+	pub fn table(store: TableStore) -> Self {
+		Self::Table {
+			store,
+			capacity: SSE_REPLAY_CAPACITY,
+		}
+	}
+
+	/// Records an event, assigning its id. Returns the event to send, carrying
+	/// the assigned id.
+	pub async fn record(&self, event: SseEvent) -> Result<SseEvent> {
+		match self {
+			Self::Buffer(buffer) => buffer.push(event).xok(),
+			Self::Table { store, capacity } => {
+				let table = store.table::<TableItem<SseEvent>>();
+				let mut row = TableItem::new(event);
+				row.data.id = Some(row.id.to_string());
+				let event = row.data.clone();
+				table.push(row).await?;
+				// every row older than the latest `capacity`
+				let latest = table
+					.query(
+						TableQuery::new()
+							.with_order(SortOrder::Descending)
+							.with_limit(*capacity)
+							.with_lossy(),
+					)
+					.await?;
+				if let Some(cursor) = latest.cursor {
+					let expired = table
+						.query_all(
+							TableQuery::new()
+								.with_order(SortOrder::Descending)
+								.with_cursor(cursor)
+								.with_lossy(),
+						)
+						.await?;
+					for row in expired {
+						table.remove(row.id).await?;
+					}
+				}
+				event.xok()
+			}
+		}
+	}
+
+	/// The recorded events after `last_event_id`, oldest first. An id this
+	/// replay did not assign replays everything retained.
+	pub async fn after(&self, last_event_id: &str) -> Result<Vec<SseEvent>> {
+		match self {
+			Self::Buffer(buffer) => buffer.after(last_event_id).xok(),
+			Self::Table { store, capacity } => {
+				let table = store.table::<TableItem<SseEvent>>();
+				let query = match Uuid::parse_str(last_event_id) {
+					// ids are time-ordered, so the missed events are a range
+					Ok(last) => TableQuery::new().with_range(
+						"id",
+						(Bound::Excluded(last.to_string()), Bound::Unbounded),
+					),
+					Err(_) => TableQuery::new(),
+				};
+				// the latest `capacity`, at most what a table retains
+				let mut rows = table
+					.query(
+						query
+							.with_order(SortOrder::Descending)
+							.with_limit(*capacity)
+							.with_lossy(),
+					)
+					.await?
+					.rows;
+				rows.reverse();
+				rows.into_iter()
+					.map(|row| row.data)
+					.collect::<Vec<_>>()
+					.xok()
+			}
+		}
+	}
+}
+
+type SseEventStream =
+	Pin<Box<dyn 'static + Send + Sync + Stream<Item = Result<SseEvent>>>>;
+
+/// Builds a `text/event-stream` [`Response`] from a stream of events.
+///
+/// Writes a keep-alive comment every [`SSE_KEEP_ALIVE`] the stream idles, and
+/// with [`Self::with_replay`] first sends the events a reconnecting client
+/// missed.
+///
+/// ```no_run
+/// # use beet_core::prelude::*;
+/// # use beet_net::prelude::*;
+/// # async fn handler(request: Request, replay: SseReplay) -> Result<Response> {
+/// let events = ["a", "b"].map(|data| Ok(SseEvent::new(data)));
+/// let events = futures::stream::iter(events);
+/// SseResponse::new(events)
+///     .with_retry(Duration::from_secs(2))
+///     .with_replay(&replay, &request)
+///     .await?
+///     .into_response()
+///     .xok()
+/// # }
+/// ```
+pub struct SseResponse {
+	replayed: Vec<SseEvent>,
+	events: SseEventStream,
+	keep_alive: Option<Duration>,
+	retry: Option<Duration>,
+}
+
+impl SseResponse {
+	/// Creates a response streaming the given events.
+	pub fn new(
+		events: impl 'static + Send + Sync + Stream<Item = Result<SseEvent>>,
+	) -> Self {
+		Self {
+			replayed: Vec::new(),
+			events: Box::pin(events),
+			keep_alive: Some(SSE_KEEP_ALIVE),
+			retry: None,
+		}
+	}
+
+	/// Creates a response streaming typed payloads as JSON `message` events.
+	#[cfg(feature = "json")]
+	pub fn json<T: 'static + serde::Serialize>(
+		events: impl 'static + Send + Sync + Stream<Item = Result<T>>,
+	) -> Self {
+		Self::new(
+			events.map(|data| data.and_then(|data| SseEvent::json(&data))),
+		)
+	}
+
+	/// Sets how long the stream may idle before a keep-alive comment.
+	pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+		self.keep_alive = Some(interval);
+		self
+	}
+
+	/// Disables keep-alive comments.
+	pub fn without_keep_alive(mut self) -> Self {
+		self.keep_alive = None;
+		self
+	}
+
+	/// Sets how long the client waits before reconnecting after a drop.
+	pub fn with_retry(mut self, retry: Duration) -> Self {
+		self.retry = Some(retry);
+		self
+	}
+
+	/// Sends the events `replay` recorded after the request's `Last-Event-ID`
+	/// before the stream. A first connection, with no such header, replays
+	/// nothing.
+	///
+	/// Events recorded between this read and the stream starting are the
+	/// caller's to order; [`SseBroadcast::subscribe`] does so for a broadcast.
+	pub async fn with_replay(
+		mut self,
+		replay: &SseReplay,
+		request: &RequestParts,
+	) -> Result<Self> {
+		if let Some(last_event_id) =
+			request.headers.get::<header::LastEventId>().transpose()?
+		{
+			self.replayed = replay.after(&last_event_id).await?;
+		}
+		self.xok()
+	}
+
+	/// Builds the streaming response.
+	pub fn into_response(self) -> Response {
+		let retry = self.retry.map(|retry| {
+			Ok(Bytes::from(format!("retry: {}\n\n", retry.as_millis())))
+		});
+		let replayed =
+			self.replayed.into_iter().map(|event| Ok(event.to_chunk()));
+		let body = stream::iter(retry.into_iter().chain(replayed))
+			.chain(keep_alive(self.events, self.keep_alive));
+		Response::ok()
+			.with_content_type(MediaType::EventStream)
+			// an event stream is live, never a cached or buffered response
+			.with_header("cache-control", "no-cache")
+			.with_header("x-accel-buffering", "no")
+			.with_body(Body::stream(body))
+	}
+}
+
+impl From<SseResponse> for Response {
+	fn from(response: SseResponse) -> Self { response.into_response() }
+}
+
+/// Writes each event's chunk, interleaving a keep-alive comment whenever
+/// `interval` passes without one, and ends with the events.
+fn keep_alive(
+	events: SseEventStream,
+	interval: Option<Duration>,
+) -> impl 'static + Send + Sync + Stream<Item = Result<Bytes>> {
+	stream::unfold(events, move |mut events| async move {
+		let next = match interval {
+			Some(interval) => {
+				async { Some(events.next().await) }
+					.or(async {
+						time_ext::sleep(interval).await;
+						None
+					})
+					.await
+			}
+			None => Some(events.next().await),
+		};
+		match next {
+			// the interval won the race: a stream keeps an item it has not yet
+			// yielded, so dropping the pending `next` loses nothing
+			None => Some((Ok(Bytes::from_static(KEEP_ALIVE_COMMENT)), events)),
+			Some(Some(event)) => {
+				Some((event.map(|event| event.to_chunk()), events))
+			}
+			Some(None) => None,
+		}
+	})
+}
+
+/// Fans live events out to every subscribed [`SseResponse`], numbering each
+/// through an [`SseReplayBuffer`] so a reconnecting subscriber catches up.
+///
+/// Clones share the same subscribers and buffer. Each subscriber queues at
+/// most the buffer's capacity of unsent events: one that falls further behind
+/// is disconnected rather than buffered without bound, and on reconnecting
+/// replays what it missed.
+#[derive(Debug, Clone, Default)]
+pub struct SseBroadcast {
+	buffer: SseReplayBuffer,
+	subscribers: Arc<Mutex<Vec<async_channel::Sender<SseEvent>>>>,
+}
+
+impl SseBroadcast {
+	/// Creates a broadcast replaying from the given buffer.
+	pub fn new(buffer: SseReplayBuffer) -> Self {
+		Self {
+			buffer,
+			subscribers: default(),
+		}
+	}
+
+	/// The number of connected subscribers.
+	pub fn subscriber_count(&self) -> usize {
+		self.subscribers.lock().unwrap().len()
+	}
+
+	/// Records the event and sends it to every subscriber, dropping those
+	/// disconnected or too far behind.
+	pub fn send(&self, event: SseEvent) {
+		// record under the subscribers lock so a concurrent `subscribe` sees the
+		// event either replayed or live, never both or neither
+		let mut subscribers = self.subscribers.lock().unwrap();
+		let event = self.buffer.push(event);
+		subscribers
+			.retain(|subscriber| subscriber.try_send(event.clone()).is_ok());
+	}
+
+	/// Subscribes a client, replaying the buffered events after its
+	/// `Last-Event-ID` then streaming every event sent from now on.
+	pub fn subscribe(&self, request: &RequestParts) -> Result<SseResponse> {
+		let last_event_id =
+			request.headers.get::<header::LastEventId>().transpose()?;
+		let (send, recv) =
+			async_channel::bounded(self.buffer.capacity().max(1));
+		let mut subscribers = self.subscribers.lock().unwrap();
+		let replayed = last_event_id
+			.map(|last_event_id| self.buffer.after(&last_event_id))
+			.unwrap_or_default();
+		subscribers.push(send);
+		drop(subscribers);
+		let mut response = SseResponse::new(recv.map(Ok));
+		response.replayed = replayed;
+		response.xok()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn reconnect(last_event_id: &str) -> Request {
+		let mut request = Request::get("/events");
+		request
+			.headers
+			.set::<header::LastEventId>(last_event_id.to_string());
+		request
+	}
+
+	async fn next_chunk(body: &mut Body) -> String {
+		let bytes = body.next().await.unwrap().unwrap();
+		String::from_utf8(bytes.to_vec()).unwrap()
+	}
+
+	#[beet_core::test]
+	fn formats_chunks() {
+		SseEvent::new("one\ntwo")
+			.with_id("7")
+			.with_event("tick")
+			.to_chunk()
+			.xpect_eq(Bytes::from(
+				"id: 7\nevent: tick\ndata: one\ndata: two\n\n",
+			));
+		SseEvent::new("")
+			.with_id("a\nb")
+			.to_chunk()
+			.xpect_eq(Bytes::from("id: ab\ndata: \n\n"));
+	}
+
+	#[beet_core::test]
+	fn buffer_replays_after_id() {
+		let buffer = SseReplayBuffer::new(2);
+		for data in ["a", "b", "c"] {
+			buffer.push(SseEvent::new(data));
+		}
+		// `a` was evicted
+		buffer
+			.after("2")
+			.xpect_eq(vec![SseEvent::new("c").with_id("3")]);
+		// an id from before a restart replays everything retained
+		buffer.after("99").len().xpect_eq(2);
+	}
+
+	#[beet_core::test]
+	async fn streams_events_with_replay() {
+		let replay = SseReplay::Buffer(SseReplayBuffer::default());
+		for data in ["a", "b"] {
+			replay.record(SseEvent::new(data)).await.unwrap();
+		}
+		let events = stream::iter([Ok(SseEvent::new("live"))]);
+		let response = SseResponse::new(events)
+			.with_retry(Duration::from_millis(500))
+			.with_replay(&replay, &reconnect("1"))
+			.await
+			.unwrap()
+			.into_response();
+		response
+			.headers
+			.get::<header::ContentType>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(MediaType::EventStream);
+		response
+			.text()
+			.await
+			.unwrap()
+			.xpect_eq("retry: 500\n\nid: 2\ndata: b\n\ndata: live\n\n");
+	}
+
+	#[beet_core::test]
+	async fn writes_keep_alive() {
+		let mut body = SseResponse::new(stream::pending::<Result<SseEvent>>())
+			.with_keep_alive(Duration::from_millis(10))
+			.into_response()
+			.body;
+		next_chunk(&mut body).await.xpect_eq(": keep-alive\n\n");
+	}
+
+	#[beet_core::test]
+	async fn broadcast_replays_then_streams() {
+		let broadcast = SseBroadcast::default();
+		broadcast.send(SseEvent::new("missed"));
+		let mut body = broadcast
+			.subscribe(&reconnect("0"))
+			.unwrap()
+			.without_keep_alive()
+			.into_response()
+			.body;
+		broadcast.subscriber_count().xpect_eq(1);
+		broadcast.send(SseEvent::new("live"));
+		next_chunk(&mut body)
+			.await
+			.xpect_eq("id: 1\ndata: missed\n\n");
+		next_chunk(&mut body)
+			.await
+			.xpect_eq("id: 2\ndata: live\n\n");
+		drop(body);
+		// the closed subscriber is dropped on the next send
+		broadcast.send(SseEvent::new("after"));
+		broadcast.subscriber_count().xpect_eq(0);
+	}
+
+	#[cfg(feature = "json")]
+	#[beet_core::test]
+	async fn table_replays_after_id() {
+		let replay = SseReplay::table(TableStore::temp());
+		let first = replay.record(SseEvent::new("a")).await.unwrap();
+		replay.record(SseEvent::new("b")).await.unwrap();
+		replay
+			.after(first.id.as_deref().unwrap())
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|event| event.data)
+			.collect::<Vec<_>>()
+			.xpect_eq(vec!["b".to_string()]);
+	}
+
+	#[cfg(feature = "json")]
+	#[beet_core::test]
+	async fn table_retains_capacity() {
+		let store = TableStore::temp();
+		let replay = SseReplay::Table {
+			store: store.clone(),
+			capacity: 2,
+		};
+		for data in ["a", "b", "c"] {
+			replay.record(SseEvent::new(data)).await.unwrap();
+			// uuid v7 ids order by millisecond
+			time_ext::sleep(Duration::from_millis(2)).await;
+		}
+		store
+			.table::<TableItem<SseEvent>>()
+			.list()
+			.await
+			.unwrap()
+			.len()
+			.xpect_eq(2);
+		replay
+			.after("unknown")
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|event| event.data)
+			.collect::<Vec<_>>()
+			.xpect_eq(vec!["b".to_string(), "c".to_string()]);
+	}
+}
@@ -0,0 +1,92 @@
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+
+/// Middleware gating a route subtree on a logged in [`Session`], answering
+/// `401 Unauthorized` without one.
+///
+/// A browser page request (one whose `Accept` lists `text/html`) is instead
+/// sent `303` to the [`SessionConfig::login_path`] when one is set, carrying
+/// the requested path and query, url-encoded, as `next` for the login route to
+/// return to.
+/// Must sit below a [`SessionMiddleware`].
+#[action]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn Authenticated(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let caller = cx.caller.clone();
+	let (request, next) = cx.take();
+	if Session::from_headers(request.headers()).is_some() {
+		return next.call(request).await;
+	}
+	let accepts_html = request
+		.headers()
+		.get::<header::Accept>()
+		.and_then(|accept| accept.ok())
+		.is_some_and(|accept| accept.contains(&MediaType::Html));
+	let login_path = caller
+		.get_in_ancestors_cloned::<SessionConfig>()
+		.await
+		.ok()
+		.and_then(|config| config.login_path);
+	match login_path {
+		Some(login_path) if accepts_html => {
+			let mut target = request.path_string();
+			let query = request.query_string();
+			if !query.is_empty() {
+				target = format!("{target}?{query}");
+			}
+			let next = QueryParams([("next", target)]).encode()?;
+			Response::see_other(format!("{login_path}?{next}")).xok()
+		}
+		_ => HttpError::new(StatusCode::UNAUTHORIZED, "login required")
+			.into_response()
+			.xok(),
+	}
+}
+
+/// Gates a route subtree on the logged in [`Session`] holding this role,
+/// answering `401 Unauthorized` without a session and `403 Forbidden` without
+/// the role. Must sit below a [`SessionMiddleware`].
+///
+/// ```ignore
+/// (RequireRole::new("admin"), children![route::new("users", users)])
+/// ```
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[require(RequireRoleMiddleware)]
+pub struct RequireRole(pub String);
+
+impl RequireRole {
+	/// Require `role`.
+	pub fn new(role: impl Into<String>) -> Self { Self(role.into()) }
+}
+
+/// The middleware a [`RequireRole`] brings, checking the session against the
+/// co-located role.
+#[action]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn RequireRoleMiddleware(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let role = cx.caller.get_in_ancestors_cloned::<RequireRole>().await?.0;
+	let (request, next) = cx.take();
+	match Session::from_headers(request.headers()) {
+		Some(session) if session.has_role(&role) => next.call(request).await,
+		Some(_) => HttpError::new(
+			StatusCode::FORBIDDEN,
+			format!("the `{role}` role is required"),
+		)
+		.into_response()
+		.xok(),
+		None => HttpError::new(StatusCode::UNAUTHORIZED, "login required")
+			.into_response()
+			.xok(),
+	}
+}
@@ -0,0 +1,479 @@
+//! The handlers behind [`route::login`](crate::prelude::route::login),
+//! [`route::magic_link`](crate::prelude::route::magic_link) and
+//! [`route::logout`](crate::prelude::route::logout).
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use serde::Deserialize;
+
+/// How long an emailed magic link stays redeemable.
+pub const MAGIC_LINK_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Triggered by [`route::magic_link`](crate::prelude::route::magic_link) for a
+/// known email, for the app to deliver the link, ie in an email. Not
+/// triggered for an unknown email, the route answering alike either way so
+/// it cannot be used to probe for accounts.
+#[derive(Debug, Clone, Event)]
+pub struct MagicLinkRequested {
+	/// The user the link logs in.
+	pub user_id: Uuid,
+	/// Where to send the link.
+	pub email: String,
+	/// The link path and query, to be joined to the site origin.
+	pub link: String,
+}
+
+/// The `POST` body of a password login form.
+#[derive(Debug, Deserialize)]
+pub(crate) struct LoginForm {
+	email: String,
+	password: String,
+	/// The local path to return to, as set by [`Authenticated`].
+	#[serde(default)]
+	next: Option<String>,
+}
+
+/// The `POST` body of a magic-link form: an `email` requesting a link, or the
+/// `token` a link carries, posted from its confirm page to redeem it.
+#[derive(Debug, Deserialize)]
+pub(crate) struct MagicLinkForm {
+	#[serde(default)]
+	email: Option<String>,
+	#[serde(default)]
+	token: Option<String>,
+}
+
+/// The stores and config every login handler resolves from its ancestors.
+async fn resolve(
+	caller: &AsyncEntity,
+) -> Result<(SessionConfig, SessionStore, UserStore)> {
+	(
+		caller.get_in_ancestors_cloned::<SessionConfig>().await?,
+		caller.get_in_ancestors_cloned::<SessionStore>().await?,
+		caller.get_in_ancestors_cloned::<UserStore>().await?,
+	)
+		.xok()
+}
+
+/// A `303` to `next`, or `/` when it is not a local path: an open redirect
+/// would let a crafted login link forward a user to any site.
+fn redirect_local(next: Option<&str>) -> Response {
+	let next = next
+		.filter(|next| {
+			next.starts_with('/')
+				&& !next.starts_with("//")
+				&& !next.contains('\\')
+		})
+		.unwrap_or("/");
+	Response::see_other(next)
+}
+
+/// Verifies an email and password against the [`UserStore`], answering a
+/// `303` to the form's `next` with a fresh session cookie, or `401`.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn LoginHandler(
+	cx: ActionContext<Form<LoginForm>>,
+) -> Result<Response> {
+	let (config, sessions, users) = resolve(&cx.caller).await?;
+	let Form(form) = cx.take();
+	let Some(user) = users.verify_login(&form.email, &form.password).await?
+	else {
+		return Err(HttpError::new(
+			StatusCode::UNAUTHORIZED,
+			"incorrect email or password",
+		)
+		.into());
+	};
+	let session = sessions.create(&user, config.max_age).await?;
+	redirect_local(form.next.as_deref())
+		.with_header(header::SetCookie::KEY, &config.set_cookie(&session))
+		.xok()
+}
+
+/// `POST` an `email` to trigger a [`MagicLinkRequested`] carrying a
+/// single-use link back to this route, answering `202 Accepted`. A `GET` of
+/// that link answers a confirm page whose form `POST`s the `token` back,
+/// redeeming it for a session cookie and a `303` to `/`.
+///
+/// The `GET` leaves the token unspent: email scanners and link prefetchers
+/// fetch links before the user clicks them, and would otherwise burn it.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn MagicLinkHandler(
+	cx: ActionContext<Request>,
+) -> Result<Response> {
+	let (config, sessions, users) = resolve(&cx.caller).await?;
+	let world = cx.world();
+	let request = cx.take();
+	match request.method() {
+		HttpMethod::Post => {
+			let path = request.path_string();
+			let form = match Form::<MagicLinkForm>::from_request(request).await
+			{
+				Ok(Form(form)) => form,
+				Err(response) => return Ok(response),
+			};
+			if let Some(token) = form.token {
+				return redeem_magic_link(&config, &sessions, &users, &token)
+					.await;
+			}
+			let Some(email) = form.email else {
+				return Err(
+					HttpError::bad_request("missing email or token").into()
+				);
+			};
+			if let Some(user) = users.find_by_email(&email).await? {
+				let mut pending = Session::new(&user, MAGIC_LINK_TTL);
+				pending.pending = true;
+				sessions.push(pending.clone()).await?;
+				let event = MagicLinkRequested {
+					user_id: user.id,
+					email: user.email,
+					link: format!(
+						"{path}?token={}",
+						config.key.sign(&pending.id.to_string())
+					),
+				};
+				world
+					.with(move |world: &mut World| world.trigger(event))
+					.await;
+			}
+			Response::from_status(StatusCode::ACCEPTED).xok()
+		}
+		HttpMethod::Get | HttpMethod::Head => {
+			let token = request.get_param("token").unwrap_or_default();
+			// checked but not spent, so a dead link fails before the confirm
+			pending_magic_link(&config, &sessions, token).await?;
+			confirm_page(token).xok()
+		}
+		_ => Err(HttpError::from_status(StatusCode::METHOD_NOT_ALLOWED).into()),
+	}
+}
+
+/// The live pending session a magic link `token` names, without spending it.
+///
+/// ## Errors
+/// Answers `400` for a missing or forged token, `401` for a spent or expired
+/// one.
+async fn pending_magic_link(
+	config: &SessionConfig,
+	sessions: &SessionStore,
+	token: &str,
+) -> Result<Session> {
+	let Some(id) = config
+		.key
+		.verify(token)
+		.and_then(|id| id.parse::<Uuid>().ok())
+	else {
+		return Err(HttpError::bad_request("missing or invalid token").into());
+	};
+	let expired =
+		|| HttpError::new(StatusCode::UNAUTHORIZED, "this link has expired");
+	if !sessions.exists(id).await? {
+		return Err(expired().into());
+	}
+	let pending = sessions.get(id).await?;
+	if !pending.pending || pending.is_expired() {
+		return Err(expired().into());
+	}
+	pending.xok()
+}
+
+/// Spends a magic link `token`, answering a `303` to `/` with a fresh session
+/// cookie.
+///
+/// The pending row is spent by expiring it with a conditional write, and a
+/// session is issued only if that write won, so of two redeems racing for one
+/// link exactly one logs in.
+async fn redeem_magic_link(
+	config: &SessionConfig,
+	sessions: &SessionStore,
+	users: &UserStore,
+	token: &str,
+) -> Result<Response> {
+	let pending = pending_magic_link(config, sessions, token).await?;
+	let pending = sessions
+		.try_update(pending.id, |pending| {
+			if !pending.pending || pending.is_expired() {
+				return Err(HttpError::new(
+					StatusCode::UNAUTHORIZED,
+					"this link has expired",
+				)
+				.into());
+			}
+			pending.expires = Timestamp::now();
+			Ok(())
+		})
+		.await?;
+	let user = users.get(pending.user_id).await?;
+	let session = sessions.create(&user, config.max_age).await?;
+	redirect_local(None)
+		.with_header(header::SetCookie::KEY, &config.set_cookie(&session))
+		.xok()
+}
+
+/// The page a magic link opens, its form posting the `token` back to the
+/// same url. The token has been verified, so is only url-safe base64 and
+/// uuid characters, never markup.
+fn confirm_page(token: &str) -> Response {
+	Response::ok_body(
+		format!(
+			"<!DOCTYPE html><html><body><form method=\"post\">\
+			<input type=\"hidden\" name=\"token\" value=\"{token}\">\
+			<button type=\"submit\">Log in</button>\
+			</form></body></html>"
+		),
+		MediaType::Html,
+	)
+}
+
+/// Ends the request's session, answering a `303` to `/` that clears the
+/// cookie.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn LogoutHandler(
+	cx: ActionContext<Request>,
+) -> Result<Response> {
+	let (config, sessions, _) = resolve(&cx.caller).await?;
+	let request = cx.take();
+	if let Some(session) = Session::from_headers(request.headers())
+		&& sessions.exists(session.id).await?
+	{
+		sessions.remove(session.id).await?;
+	}
+	redirect_local(None)
+		.with_header(header::SetCookie::KEY, &config.clear_cookie())
+		.xok()
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_action::prelude::*;
+	use beet_core::prelude::*;
+	use beet_net::prelude::*;
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn Whoami(cx: ActionContext<Session>) -> Response {
+		Response::ok_text(cx.take().roles.join(","))
+	}
+
+	fn ada() -> AuthUser {
+		AuthUser {
+			password_hash: Some(hash_password_rounds("hunter2", 10)),
+			..AuthUser::new("Ada@example.com")
+		}
+		.with_role("editor")
+	}
+
+	async fn spawn_site(world: &mut World, user: AuthUser) -> Entity {
+		let users = UserStore::temp();
+		users.push(user).await.unwrap();
+		let mut config =
+			SessionConfig::new(SessionKey::random()).with_login_path("/login");
+		config.secure = false;
+		world
+			.spawn((
+				Router::with_defaults(),
+				SessionMiddleware,
+				config,
+				SessionStore::temp(),
+				users,
+				children![
+					route::login("login"),
+					route::magic_link("magic"),
+					route::logout("logout"),
+					(route::exchange("me", Whoami), Authenticated),
+					(
+						route::exchange("admin", Whoami),
+						RequireRole::new("admin")
+					),
+				],
+			))
+			.flush()
+	}
+
+	fn login_request(password: &str) -> Request {
+		Request::post("login")
+			.with_header::<header::ContentType>(MediaType::FormUrlEncoded)
+			.with_body(format!(
+				"email=ada%40example.com&password={password}&next=%2Fme"
+			))
+	}
+
+	/// The `name=value` pair of a `Set-Cookie` response.
+	fn cookie(response: &Response) -> String {
+		response
+			.parts
+			.headers
+			.first_raw("set-cookie")
+			.unwrap()
+			.split(';')
+			.next()
+			.unwrap()
+			.to_string()
+	}
+
+	#[beet_core::test]
+	async fn password_login() {
+		let mut world = (AsyncPlugin, RouterPlugin).into_world();
+		let site = spawn_site(&mut world, ada()).await;
+		let mut site = world.entity_mut(site);
+
+		site.exchange(login_request("wrong"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::UNAUTHORIZED);
+		site.exchange(Request::get("me"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::UNAUTHORIZED);
+		// a page request is sent to the login path instead
+		site.exchange(
+			Request::get("me")
+				.with_header::<header::Accept>(vec![MediaType::Html]),
+		)
+		.await
+		.parts
+		.headers
+		.first_raw("location")
+		.xpect_eq(Some("/login?next=%2Fme"));
+		// with its query, encoded so it stays one param
+		site.exchange(
+			Request::get("me?tab=a&b=c")
+				.with_header::<header::Accept>(vec![MediaType::Html]),
+		)
+		.await
+		.parts
+		.headers
+		.first_raw("location")
+		.xpect_eq(Some("/login?next=%2Fme%3Ftab%3Da%26b%3Dc"));
+
+		let response = site.exchange(login_request("hunter2")).await;
+		response.status().xpect_eq(StatusCode::SEE_OTHER);
+		response
+			.parts
+			.headers
+			.first_raw("location")
+			.xpect_eq(Some("/me"));
+		let session_cookie = cookie(&response);
+		site.exchange(
+			Request::get("me").with_header_raw("cookie", &session_cookie),
+		)
+		.await
+		.unwrap_str()
+		.await
+		.xpect_eq("editor");
+		site.exchange(
+			Request::get("admin").with_header_raw("cookie", &session_cookie),
+		)
+		.await
+		.status()
+		.xpect_eq(StatusCode::FORBIDDEN);
+		// a forged session header is stripped
+		site.exchange(
+			Request::get("me")
+				.with_header_raw("x-beet-auth-session", "e30.AAAA"),
+		)
+		.await
+		.status()
+		.xpect_eq(StatusCode::UNAUTHORIZED);
+
+		site.exchange(
+			Request::post("logout").with_header_raw("cookie", &session_cookie),
+		)
+		.await
+		.status()
+		.xpect_eq(StatusCode::SEE_OTHER);
+		let response = site
+			.exchange(
+				Request::get("me").with_header_raw("cookie", &session_cookie),
+			)
+			.await;
+		response.status().xpect_eq(StatusCode::UNAUTHORIZED);
+		// the revoked cookie is cleared
+		cookie(&response).xpect_eq("beet_auth=");
+	}
+
+	#[beet_core::test]
+	async fn magic_link_login() {
+		#[derive(Resource, Default)]
+		struct Links(Vec<String>);
+
+		let mut world = (AsyncPlugin, RouterPlugin).into_world();
+		world.init_resource::<Links>();
+		world.add_observer(
+			|ev: On<MagicLinkRequested>, mut links: ResMut<Links>| {
+				links.0.push(ev.event().link.clone());
+			},
+		);
+		let site =
+			spawn_site(&mut world, AuthUser::new("ada@example.com")).await;
+
+		for email in ["ada%40example.com", "nobody%40example.com"] {
+			world
+				.entity_mut(site)
+				.exchange(
+					Request::post("magic")
+						.with_header::<header::ContentType>(
+							MediaType::FormUrlEncoded,
+						)
+						.with_body(format!("email={email}")),
+				)
+				.await
+				.status()
+				.xpect_eq(StatusCode::ACCEPTED);
+		}
+		let links = world.resource::<Links>().0.clone();
+		links.len().xpect_eq(1);
+		links[0].starts_with("/magic?token=").xpect_true();
+
+		let token = links[0].trim_start_matches("/magic?token=").to_string();
+		// opening the link, as a prefetcher would, does not spend it
+		for _ in 0..2 {
+			world
+				.entity_mut(site)
+				.exchange(Request::get(links[0].as_str()))
+				.await
+				.unwrap_str()
+				.await
+				.xpect_contains(&token);
+		}
+		let redeem = || {
+			Request::post("magic")
+				.with_header::<header::ContentType>(MediaType::FormUrlEncoded)
+				.with_body(format!("token={token}"))
+		};
+		let response = world.entity_mut(site).exchange(redeem()).await;
+		response.status().xpect_eq(StatusCode::SEE_OTHER);
+		let session_cookie = cookie(&response);
+		world
+			.entity_mut(site)
+			.exchange(
+				Request::get("me").with_header_raw("cookie", &session_cookie),
+			)
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+		// single use
+		world
+			.entity_mut(site)
+			.exchange(redeem())
+			.await
+			.status()
+			.xpect_eq(StatusCode::UNAUTHORIZED);
+		world
+			.entity_mut(site)
+			.exchange(Request::get(links[0].as_str()))
+			.await
+			.status()
+			.xpect_eq(StatusCode::UNAUTHORIZED);
+	}
+}
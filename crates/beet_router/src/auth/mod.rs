@@ -0,0 +1,26 @@
+//! Sessions and authentication for a router.
+//!
+//! - [`SessionMiddleware`] verifies the signed session cookie against the
+//!   [`SessionStore`] and threads the live [`Session`] to handlers, which read it
+//!   with the [`Session`] extractor.
+//! - [`Authenticated`] and [`RequireRole`] gate a route subtree on that session.
+//! - [`route::login`](crate::prelude::route::login),
+//!   [`route::magic_link`](crate::prelude::route::magic_link) and
+//!   [`route::logout`](crate::prelude::route::logout) issue and revoke sessions
+//!   for the users of a [`UserStore`].
+//!
+//! The [`SessionConfig`], [`SessionStore`] and [`UserStore`] are resolved from the
+//! nearest self-or-ancestor entity, so one set on the router serves every route.
+
+// the signing key, session rows and the cookie-verifying middleware
+mod session;
+pub use session::*;
+// the `Authenticated` / `RequireRole` subtree gates
+mod guard;
+pub use guard::*;
+// user rows and PBKDF2 password hashing
+mod user;
+pub use user::*;
+// the password, magic-link and logout route handlers
+mod login;
+pub use login::*;
@@ -0,0 +1,423 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use bevy::platform::sync::LazyLock;
+use hmac::Hmac;
+use hmac::KeyInit;
+use hmac::Mac;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// The internal request header [`SessionMiddleware`] threads the verified
+/// [`Session`] to handlers in. Signed with a per-process key, so a client
+/// cannot forge it by sending the header itself.
+const SESSION_HEADER: &str = "x-beet-auth-session";
+
+/// Signs the [`SESSION_HEADER`], regenerated each process: the header never
+/// leaves it.
+static REQUEST_KEY: LazyLock<SessionKey> = LazyLock::new(SessionKey::random);
+
+/// An HMAC-SHA256 key signing session cookies.
+///
+/// Every server sharing sessions (ie every replica behind a load balancer, and
+/// every restart of one) must share the key, so deployed servers set it with
+/// [`SessionKey::ENV_VAR`]; a random key logs every user out on restart.
+#[derive(Clone)]
+pub struct SessionKey(Arc<[u8]>);
+
+impl core::fmt::Debug for SessionKey {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str("SessionKey(..)")
+	}
+}
+
+impl SessionKey {
+	/// The environment variable [`SessionKey::from_env`] reads.
+	pub const ENV_VAR: &str = "BEET_SESSION_SECRET";
+	/// The shortest secret accepted, in bytes: the HMAC-SHA256 output size.
+	pub const MIN_LEN: usize = 32;
+
+	/// A key from `secret`, which must be at least [`Self::MIN_LEN`] bytes.
+	pub fn new(secret: impl AsRef<[u8]>) -> Result<Self> {
+		let secret = secret.as_ref();
+		if secret.len() < Self::MIN_LEN {
+			bevybail!(
+				"a session secret must be at least {} bytes, got {}",
+				Self::MIN_LEN,
+				secret.len()
+			);
+		}
+		Self(secret.into()).xok()
+	}
+
+	/// A key of fresh random bytes, valid only for this process.
+	pub fn random() -> Self { Self(random_bytes::<32>().to_vec().into()) }
+
+	/// The key set by [`Self::ENV_VAR`].
+	pub fn from_env() -> Result<Self> {
+		let secret = env_ext::var(Self::ENV_VAR)?;
+		Self::new(secret.as_bytes())
+	}
+
+	/// `payload` with its signature appended, ie `payload.signature`.
+	pub fn sign(&self, payload: &str) -> String {
+		let signature =
+			URL_SAFE_NO_PAD.encode(self.mac(payload).finalize().into_bytes());
+		format!("{payload}.{signature}")
+	}
+
+	/// The payload of a [`Self::sign`]ed value, `None` when the signature does
+	/// not match. Compared in constant time.
+	pub fn verify<'a>(&self, signed: &'a str) -> Option<&'a str> {
+		let (payload, signature) = signed.rsplit_once('.')?;
+		let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+		self.mac(payload)
+			.verify_slice(&signature)
+			.ok()
+			.map(|_| payload)
+	}
+
+	fn mac(&self, payload: &str) -> Hmac<Sha256> {
+		let mut mac = Hmac::<Sha256>::new_from_slice(&self.0)
+			.expect("hmac accepts keys of any length");
+		mac.update(payload.as_bytes());
+		mac
+	}
+}
+
+/// `N` bytes from the platform entropy source.
+pub(super) fn random_bytes<const N: usize>() -> [u8; N] {
+	let mut bytes = [0; N];
+	getrandom::fill(&mut bytes).expect("the platform entropy source failed");
+	bytes
+}
+
+/// How a router issues session cookies, resolved by [`SessionMiddleware`] and
+/// the login routes from the nearest self-or-ancestor entity.
+#[derive(Debug, Clone, Component)]
+pub struct SessionConfig {
+	/// Signs and verifies the session cookie.
+	pub key: SessionKey,
+	/// The session cookie name.
+	pub cookie_name: String,
+	/// How long a session lives after login.
+	pub max_age: Duration,
+	/// Whether the cookie is marked `Secure` (https only). Browsers exempt
+	/// `localhost`, so this can stay on for a plaintext dev server.
+	pub secure: bool,
+	/// Where [`Authenticated`] sends a browser page request without a session,
+	/// instead of answering `401`.
+	pub login_path: Option<String>,
+	/// How often [`SessionMiddleware`] sweeps expired sessions out of the
+	/// [`SessionStore`], see [`SessionStore::sweep`].
+	pub sweep_interval: Duration,
+}
+
+impl Default for SessionConfig {
+	/// Reads the key from [`SessionKey::ENV_VAR`], falling back to a random
+	/// per-process key with a warning.
+	fn default() -> Self {
+		let key = SessionKey::from_env().unwrap_or_else(|_| {
+			warn!(
+				"{} is not set, using a random session key: every session ends with this process",
+				SessionKey::ENV_VAR
+			);
+			SessionKey::random()
+		});
+		Self::new(key)
+	}
+}
+
+impl SessionConfig {
+	/// A config signing with `key`: a `beet_auth` cookie living seven days.
+	pub fn new(key: SessionKey) -> Self {
+		Self {
+			key,
+			cookie_name: "beet_auth".into(),
+			max_age: Duration::from_secs(7 * 24 * 60 * 60),
+			secure: true,
+			login_path: None,
+			sweep_interval: Duration::from_secs(60 * 60),
+		}
+	}
+
+	/// Redirect unauthenticated page requests to `path`.
+	pub fn with_login_path(mut self, path: impl Into<String>) -> Self {
+		self.login_path = Some(path.into());
+		self
+	}
+
+	/// The `Set-Cookie` value issuing `session`.
+	pub fn set_cookie(&self, session: &Session) -> String {
+		self.cookie(
+			&self.key.sign(&session.id.to_string()),
+			self.max_age.as_secs(),
+		)
+	}
+
+	/// The `Set-Cookie` value deleting the session cookie.
+	pub fn clear_cookie(&self) -> String { self.cookie("", 0) }
+
+	fn cookie(&self, value: &str, max_age: u64) -> String {
+		let secure = if self.secure { "; Secure" } else { "" };
+		format!(
+			"{}={value}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}",
+			self.cookie_name
+		)
+	}
+
+	/// The session id a request's cookie carries, `None` when it is absent or
+	/// its signature does not match. Not checked against the [`SessionStore`].
+	pub fn session_id(&self, headers: &HeaderMap) -> Option<Uuid> {
+		self.cookie_value(headers)
+			.and_then(|cookie| self.key.verify(cookie))
+			.and_then(|id| id.parse().ok())
+	}
+
+	/// The raw session cookie value sent with a request, if any.
+	fn cookie_value<'a>(&self, headers: &'a HeaderMap) -> Option<&'a str> {
+		headers
+			.get_raw(header::Cookie::KEY)?
+			.iter()
+			.flat_map(|line| line.split(';'))
+			.filter_map(|pair| pair.trim().split_once('='))
+			.find(|(key, _)| *key == self.cookie_name)
+			.map(|(_, value)| value)
+	}
+}
+
+/// A session row: who is logged in, until when.
+///
+/// Read by a handler under [`SessionMiddleware`] as an extractor, answering
+/// `401` when the request has no live session; use [`Session::from_headers`]
+/// where a session is optional.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+	/// The session id, the signed cookie payload.
+	pub id: Uuid,
+	/// The logged in user.
+	pub user_id: Uuid,
+	/// The user's roles at login, checked by [`RequireRole`].
+	pub roles: Vec<String>,
+	/// When the session ends.
+	pub expires: Timestamp,
+	/// Issued by a magic link but not yet redeemed, so not yet a login.
+	pub pending: bool,
+}
+
+impl TableStoreRow for Session {
+	fn id(&self) -> Uuid { self.id }
+}
+
+impl Session {
+	/// A session for `user` ending `max_age` from now.
+	pub fn new(user: &AuthUser, max_age: Duration) -> Self {
+		Self {
+			id: uuid_ext::now_v7(),
+			user_id: user.id,
+			roles: user.roles.clone(),
+			expires: Timestamp::from_unix_epoch_elapsed(
+				time_ext::now() + max_age,
+			),
+			pending: false,
+		}
+	}
+
+	/// Whether the session has ended.
+	pub fn is_expired(&self) -> bool {
+		self.expires.unix_epoch_elapsed() <= time_ext::now()
+	}
+
+	/// Whether the session's user holds `role`.
+	pub fn has_role(&self, role: &str) -> bool {
+		self.roles.iter().any(|held| held == role)
+	}
+
+	/// The session [`SessionMiddleware`] verified for this request, if any.
+	pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+		let signed = headers.first_raw(SESSION_HEADER)?;
+		let json = URL_SAFE_NO_PAD.decode(REQUEST_KEY.verify(signed)?).ok()?;
+		serde_json::from_slice(&json).ok()
+	}
+
+	/// The signed [`SESSION_HEADER`] value carrying this session.
+	fn to_header(&self) -> Result<String> {
+		let json = serde_json::to_vec(self)?;
+		REQUEST_KEY.sign(&URL_SAFE_NO_PAD.encode(json)).xok()
+	}
+}
+
+impl FromRequestMeta<Self> for Session {
+	fn from_request_meta(request: &RequestMeta) -> Result<Self, Response> {
+		Self::from_headers(request.headers()).ok_or_else(|| {
+			HttpError::new(StatusCode::UNAUTHORIZED, "login required").into()
+		})
+	}
+}
+
+/// The table of [`Session`] rows, resolved from the nearest self-or-ancestor
+/// entity.
+#[derive(Clone, Deref, Component)]
+pub struct SessionStore {
+	#[deref]
+	table: Table<Session>,
+	/// When the next [`Self::sweep`] is due, shared by clones so one store
+	/// sweeps once per interval however many routes resolve it.
+	schedule: SweepSchedule,
+}
+
+impl SessionStore {
+	/// Store sessions as rows of `store`.
+	pub fn new(store: TableStore) -> Self { Self::from_table(store.table()) }
+
+	/// An in-memory session store for testing.
+	pub fn temp() -> Self { Self::from_table(Table::temp()) }
+
+	fn from_table(table: Table<Session>) -> Self {
+		Self {
+			table,
+			schedule: default(),
+		}
+	}
+
+	/// Logs in `user`, storing a session ending `max_age` from now. Issue it
+	/// with [`SessionConfig::set_cookie`].
+	pub async fn create(
+		&self,
+		user: &AuthUser,
+		max_age: Duration,
+	) -> Result<Session> {
+		let session = Session::new(user, max_age);
+		self.push(session.clone()).await?;
+		session.xok()
+	}
+
+	/// The live session a request's cookie names: `None` when the cookie is
+	/// absent, forged, or names a pending, expired or removed session.
+	pub async fn load(
+		&self,
+		config: &SessionConfig,
+		headers: &HeaderMap,
+	) -> Result<Option<Session>> {
+		let Some(id) = config.session_id(headers) else {
+			return Ok(None);
+		};
+		if !self.exists(id).await? {
+			return Ok(None);
+		}
+		let session = self.get(id).await?;
+		if session.is_expired() {
+			self.remove(id).await?;
+			return Ok(None);
+		}
+		Ok(Some(session).filter(|session| !session.pending))
+	}
+
+	/// Removes every expired session, including unredeemed magic links,
+	/// returning how many were removed. [`Self::load`] only removes an expired
+	/// session its cookie names, so a session whose user never returns would
+	/// otherwise be kept forever. See [`SweepSchedule::sweep_table`].
+	pub async fn sweep(&self) -> Result<usize> {
+		SweepSchedule::sweep_table(&self.table, Session::is_expired).await
+	}
+}
+
+/// Middleware resolving each request's session cookie to a live [`Session`],
+/// which handlers read with the [`Session`] extractor and [`Authenticated`] /
+/// [`RequireRole`] gate on.
+///
+/// Requires a [`SessionConfig`] and [`SessionStore`] on a self-or-ancestor
+/// entity. A cookie that no longer names a live session is cleared on the
+/// response, and once per [`SessionConfig::sweep_interval`] a request starts a
+/// detached [`SessionStore::sweep`] of the expired sessions.
+#[action]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn SessionMiddleware(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let caller = cx.caller.clone();
+	let (mut request, next) = cx.take();
+	let config = caller.get_in_ancestors_cloned::<SessionConfig>().await?;
+	let store = caller.get_in_ancestors_cloned::<SessionStore>().await?;
+	let sweeper = store.clone();
+	store
+		.schedule
+		.run(caller.world(), config.sweep_interval, move || async move {
+			sweeper.sweep().await
+		})
+		.await;
+	// only this middleware may set the session header
+	request.headers_mut().remove_raw(SESSION_HEADER);
+	let session = store.load(&config, request.headers()).await?;
+	let stale_cookie =
+		session.is_none() && config.cookie_value(request.headers()).is_some();
+	if let Some(session) = &session {
+		request
+			.headers_mut()
+			.set_raw(SESSION_HEADER, session.to_header()?);
+	}
+	let mut response = next.call(request).await?;
+	// a handler issuing its own cookie (ie a login) wins
+	let sets_cookie = response
+		.parts
+		.headers
+		.get_raw(header::SetCookie::KEY)
+		.into_iter()
+		.flatten()
+		.any(|value| value.starts_with(&format!("{}=", config.cookie_name)));
+	if stale_cookie && !sets_cookie {
+		response
+			.parts
+			.headers
+			.set_raw(header::SetCookie::KEY, config.clear_cookie());
+	}
+	Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[beet_core::test]
+	fn signs_and_verifies() {
+		let key = SessionKey::new([7u8; 32]).unwrap();
+		let signed = key.sign("payload");
+		key.verify(&signed).xpect_eq(Some("payload"));
+		key.verify(&signed.replace("payload", "pAyload"))
+			.xpect_none();
+		SessionKey::random().verify(&signed).xpect_none();
+		SessionKey::new([7u8; 8]).xpect_err();
+	}
+
+	#[beet_core::test]
+	async fn sweeps_expired_sessions() {
+		let store = SessionStore::temp();
+		let user = AuthUser::new("ada@example.com");
+		let live = store.create(&user, Duration::from_secs(60)).await.unwrap();
+		store.create(&user, Duration::ZERO).await.unwrap();
+		store.sweep().await.unwrap().xpect_eq(1);
+		store.list().await.unwrap().len().xpect_eq(1);
+		store.exists(live.id).await.unwrap().xpect_true();
+	}
+
+	#[beet_core::test]
+	fn session_header_roundtrip() {
+		let session = Session::new(
+			&AuthUser::new("ada@example.com"),
+			Duration::from_secs(60),
+		);
+		let mut headers = HeaderMap::new();
+		headers.set_raw(SESSION_HEADER, session.to_header().unwrap());
+		Session::from_headers(&headers).xpect_eq(Some(session));
+		// a client-sent header is not signed with the process key
+		headers.remove_raw(SESSION_HEADER);
+		headers.set_raw(SESSION_HEADER, "e30.AAAA");
+		Session::from_headers(&headers).xpect_none();
+	}
+}
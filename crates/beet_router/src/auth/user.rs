@@ -0,0 +1,194 @@
+use super::session::random_bytes;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use bevy::platform::sync::LazyLock;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Sha256;
+
+/// The PBKDF2-HMAC-SHA256 iteration count for new password hashes, the OWASP
+/// recommendation. Stored in each hash, so raising it leaves existing hashes
+/// verifiable.
+pub const PASSWORD_ROUNDS: u32 = 600_000;
+
+/// A well-formed [`PASSWORD_ROUNDS`] hash of a zero salt and digest, verified
+/// in place of a hash a login has not got, so a login for an unknown email
+/// costs as long as one with a wrong password.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+	format!(
+		"pbkdf2-sha256${PASSWORD_ROUNDS}${}${}",
+		STANDARD_NO_PAD.encode([0u8; 16]),
+		STANDARD_NO_PAD.encode([0u8; 32])
+	)
+});
+
+/// Hashes `password` with a fresh random salt at [`PASSWORD_ROUNDS`], as
+/// `pbkdf2-sha256$<rounds>$<salt>$<hash>`.
+pub fn hash_password(password: &str) -> String {
+	hash_password_rounds(password, PASSWORD_ROUNDS)
+}
+
+/// Like [`hash_password`] with a custom iteration count, ie a low one in
+/// tests.
+pub fn hash_password_rounds(password: &str, rounds: u32) -> String {
+	let salt = random_bytes::<16>();
+	let hash = pbkdf2_sha256(password, &salt, rounds);
+	format!(
+		"pbkdf2-sha256${rounds}${}${}",
+		STANDARD_NO_PAD.encode(salt),
+		STANDARD_NO_PAD.encode(hash)
+	)
+}
+
+/// Whether `password` matches a [`hash_password`] hash, compared in constant
+/// time. A malformed hash matches nothing.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+	let mut parts = hash.split('$');
+	let (Some("pbkdf2-sha256"), Some(rounds), Some(salt), Some(expected), None) = (
+		parts.next(),
+		parts.next(),
+		parts.next(),
+		parts.next(),
+		parts.next(),
+	) else {
+		return false;
+	};
+	let (Ok(rounds), Ok(salt), Ok(expected)) = (
+		rounds.parse::<u32>(),
+		STANDARD_NO_PAD.decode(salt),
+		STANDARD_NO_PAD.decode(expected),
+	) else {
+		return false;
+	};
+	let actual = pbkdf2_sha256(password, &salt, rounds);
+	expected.len() == actual.len()
+		&& expected
+			.iter()
+			.zip(actual)
+			.fold(0, |diff, (expected, actual)| diff | (expected ^ actual))
+			== 0
+}
+
+fn pbkdf2_sha256(password: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+	let mut hash = [0; 32];
+	pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, rounds, &mut hash);
+	hash
+}
+
+/// A user who can log in, by password or magic link.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthUser {
+	/// The user id, referenced by their [`Session`]s.
+	pub id: Uuid,
+	/// The login email, stored lowercase.
+	pub email: String,
+	/// The [`hash_password`] hash, `None` for a magic-link only user.
+	pub password_hash: Option<String>,
+	/// The roles checked by [`RequireRole`].
+	pub roles: Vec<String>,
+}
+
+impl TableStoreRow for AuthUser {
+	fn id(&self) -> Uuid { self.id }
+}
+
+impl AuthUser {
+	/// A magic-link only user without roles.
+	pub fn new(email: impl AsRef<str>) -> Self {
+		Self {
+			id: uuid_ext::now_v7(),
+			email: normalize_email(email.as_ref()),
+			password_hash: None,
+			roles: Vec::new(),
+		}
+	}
+
+	/// Allow a password login, hashing `password` with [`hash_password`].
+	pub fn with_password(mut self, password: &str) -> Self {
+		self.password_hash = Some(hash_password(password));
+		self
+	}
+
+	/// Grant `role`.
+	pub fn with_role(mut self, role: impl Into<String>) -> Self {
+		self.roles.push(role.into());
+		self
+	}
+
+	/// Whether `password` is this user's password.
+	pub fn verify_password(&self, password: &str) -> bool {
+		self.password_hash
+			.as_deref()
+			.is_some_and(|hash| verify_password(password, hash))
+	}
+}
+
+/// Emails compare case-insensitively and without surrounding whitespace.
+fn normalize_email(email: &str) -> String { email.trim().to_lowercase() }
+
+/// The table of [`AuthUser`] rows the login routes check, resolved from the
+/// nearest self-or-ancestor entity.
+#[derive(Clone, Deref, Component)]
+pub struct UserStore(pub Table<AuthUser>);
+
+impl UserStore {
+	/// Store users as rows of `store`.
+	pub fn new(store: TableStore) -> Self { Self(store.table()) }
+
+	/// An in-memory user store for testing.
+	pub fn temp() -> Self { Self(Table::temp()) }
+
+	/// The user with `email`, if any.
+	///
+	/// Served by an `email` [`TableIndex`] where the backend declares one,
+	/// otherwise a filtered scan of the whole table.
+	pub async fn find_by_email(&self, email: &str) -> Result<Option<AuthUser>> {
+		self.query(
+			TableQuery::new()
+				.with_eq("email", normalize_email(email))
+				.with_limit(1),
+		)
+		.await?
+		.rows
+		.into_iter()
+		.next()
+		.xok()
+	}
+
+	/// The user with `email`, if `password` is theirs.
+	///
+	/// Without such a user, or one without a password, `password` is still
+	/// verified against a dummy hash, so the time a login takes does not
+	/// reveal whether an account exists.
+	pub async fn verify_login(
+		&self,
+		email: &str,
+		password: &str,
+	) -> Result<Option<AuthUser>> {
+		let user = self.find_by_email(email).await?;
+		let hash = user
+			.as_ref()
+			.and_then(|user| user.password_hash.as_deref())
+			.unwrap_or(DUMMY_HASH.as_str());
+		let verified = verify_password(password, hash);
+		user.filter(|_| verified).xok()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[beet_core::test]
+	fn verifies_passwords() {
+		let hash = hash_password_rounds("hunter2", 10);
+		hash.starts_with("pbkdf2-sha256$10$").xpect_true();
+		verify_password("hunter2", &hash).xpect_true();
+		verify_password("hunter3", &hash).xpect_false();
+		verify_password("hunter2", "plaintext").xpect_false();
+		// salted, so equal passwords hash apart
+		hash_password_rounds("hunter2", 10).xpect_not_eq(hash);
+	}
+}
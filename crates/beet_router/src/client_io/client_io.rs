@@ -22,10 +22,24 @@ pub(crate) const CLIENT_IO_PATH: &str = "__client_io";
 /// The channel rides the main HTTP port: browsers upgrade at [`CLIENT_IO_PATH`]
 /// (the [`client_io_route`] `Router::with_defaults` wires in), the backend lands the
 /// upgraded connection as a [`Socket`] and fires [`OnWebSocketUpgrade`], and
-/// [`adopt_client_io_socket`] re-parents it under this channel.
+/// [`adopt_client_io_socket`] re-parents it under this channel. Where websockets
+/// are blocked, eg by a corporate proxy, clients subscribe to the same text
+/// messages over Server-Sent Events instead, see [`ClientIoSse`].
 #[derive(Debug, Default, Clone, Component)]
+#[require(ClientIoSse)]
 pub struct ClientIo;
 
+/// The Server-Sent Events fallback of a [`ClientIo`] channel: every text
+/// message broadcast to the channel is also sent to the event streams served at
+/// [`CLIENT_IO_SSE_PATH`], which a reconnecting `EventSource` resumes from its
+/// `Last-Event-ID`.
+#[derive(Debug, Default, Clone, Deref, Component)]
+pub struct ClientIoSse(pub SseBroadcast);
+
+/// The main-port path a client subscribes to the [`ClientIo`] channel's
+/// Server-Sent Events at, where it cannot upgrade to a websocket.
+pub(crate) const CLIENT_IO_SSE_PATH: &str = "__client_io/sse";
+
 /// The `/__client_io` route: a [`WebSocketUpgrade`] handler `Router::with_defaults`
 /// wires in under the `client_io` feature, so every HTTP router exposes the
 /// upgrade endpoint on its own port.
@@ -42,6 +56,37 @@ fn client_io_upgrade(cx: ActionContext<Request>) -> Response {
 	WebSocketUpgrade::from_request(&cx).into()
 }
 
+/// The `/__client_io/sse` route: the channel's [`ClientIoSse`] event stream,
+/// wired in beside [`client_io_route`].
+pub(crate) fn client_io_sse_route() -> impl Bundle {
+	(
+		route::exchange(CLIENT_IO_SSE_PATH, ClientIoSseHandler),
+		HttpMethod::Get,
+		CacheHeaders::no_store(),
+	)
+}
+
+/// Handler: subscribe to the [`ClientIo`] channel's event stream, replaying
+/// the messages missed since the request's `Last-Event-ID`.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn ClientIoSseHandler(
+	cx: ActionContext<Request>,
+) -> Result<Response> {
+	let broadcast = cx
+		.caller
+		.with_state::<Query<&ClientIoSse, With<ClientIo>>, _>(|_, channels| {
+			channels.iter().next().map(|sse| sse.0.clone())
+		})
+		.await?
+		.ok_or_else(|| bevyhow!("no ClientIo channel to subscribe to"))?;
+	broadcast
+		.subscribe(cx.input.request_parts())?
+		.into_response()
+		.xok()
+}
+
 /// Broadcasts a [`Message`] to every connected client of the target
 /// [`ClientIo`] channel.
 #[derive(Debug, Clone, EntityTargetEvent)]
@@ -79,12 +124,20 @@ pub(crate) fn adopt_client_io_socket(
 }
 
 /// Observer: fan a [`ClientIoBroadcast`] out to the channel's connected
-/// clients, ie its child [`Socket`](beet_net::prelude::Socket) entities.
+/// clients, ie its child [`Socket`](beet_net::prelude::Socket) entities, and
+/// send a text message to its [`ClientIoSse`] subscribers too.
 pub(crate) fn broadcast_to_clients(
 	ev: On<ClientIoBroadcast>,
 	children: Query<&Children>,
+	sse: Query<&ClientIoSse>,
 	mut commands: Commands,
 ) {
+	// binary and control frames have no event-stream form
+	if let (Message::Text(text), Ok(sse)) =
+		(&ev.event().0, sse.get(ev.target()))
+	{
+		sse.send(SseEvent::new(text.clone()));
+	}
 	for client in children
 		.get(ev.target())
 		.into_iter()
@@ -137,6 +190,38 @@ mod test {
 		stranger.get().xpect_eq(Vec::<Message>::new());
 	}
 
+	/// A text broadcast also reaches the channel's event stream, where a client
+	/// reconnecting from before it replays it.
+	#[beet_core::test]
+	async fn broadcasts_text_over_sse() {
+		let mut world = World::new();
+		world.add_observer(broadcast_to_clients);
+		let channel = world.spawn(ClientIo).id();
+		world
+			.entity_mut(channel)
+			.trigger_target(ClientIoBroadcast(Message::text(RELOAD_MESSAGE)));
+		world.flush();
+
+		let mut request = Request::get(CLIENT_IO_SSE_PATH);
+		request.headers.set::<header::LastEventId>("0".to_string());
+		let chunk = world
+			.entity(channel)
+			.get::<ClientIoSse>()
+			.unwrap()
+			.subscribe(&request)
+			.unwrap()
+			.without_keep_alive()
+			.into_response()
+			.body
+			.next()
+			.await
+			.unwrap()
+			.unwrap();
+		String::from_utf8(chunk.to_vec())
+			.unwrap()
+			.xpect_eq(format!("id: 1\ndata: {RELOAD_MESSAGE}\n\n"));
+	}
+
 	#[beet_core::test]
 	fn adopts_an_upgraded_socket_into_the_channel() {
 		let mut world = World::new();
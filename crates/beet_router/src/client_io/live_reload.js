@@ -3,11 +3,27 @@
 // reconnect after a disconnect (the server restarted under us).
 // `CLIENT_IO_PATH` is injected by the `LiveReloadScript` widget; the channel
 // rides the same host and port as the page (a same-port websocket upgrade).
+// Where the websocket never opens, eg a proxy blocks the upgrade, it falls back
+// to the channel's server-sent events, which the browser reconnects itself.
 (function () {
 	const INITIAL_RETRY_MILLIS = 500;
 	const MAX_RETRY_MILLIS = 10000;
 	let retryMillis = INITIAL_RETRY_MILLIS;
 	let wasDisconnected = false;
+	let socketOpened = false;
+
+	function connectEventSource() {
+		const source = new EventSource(`/${CLIENT_IO_PATH}/sse`);
+		source.addEventListener("open", () => {
+			if (wasDisconnected) location.reload();
+		});
+		source.addEventListener("message", (ev) => {
+			if (ev.data === "reload") location.reload();
+		});
+		source.addEventListener("error", () => {
+			wasDisconnected = true;
+		});
+	}
 
 	function connect() {
 		const scheme = location.protocol === "https:" ? "wss" : "ws";
@@ -15,6 +31,7 @@
 			`${scheme}://${location.host}/${CLIENT_IO_PATH}`,
 		);
 		socket.addEventListener("open", () => {
+			socketOpened = true;
 			retryMillis = INITIAL_RETRY_MILLIS;
 			if (wasDisconnected) location.reload();
 		});
@@ -22,6 +39,7 @@
 			if (ev.data === "reload") location.reload();
 		});
 		socket.addEventListener("close", () => {
+			if (!socketOpened) return connectEventSource();
 			wasDisconnected = true;
 			setTimeout(connect, retryMillis);
 			retryMillis = Math.min(retryMillis * 2, MAX_RETRY_MILLIS);
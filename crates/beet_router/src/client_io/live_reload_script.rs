@@ -7,7 +7,8 @@ use beet_core::prelude::*;
 /// world's [`ClientIo`] channel (a same-port websocket upgrade at
 /// [`CLIENT_IO_PATH`]), calls `location.reload()` on a
 /// [`RELOAD_MESSAGE`](super::RELOAD_MESSAGE), and reconnects with exponential
-/// backoff, reloading after the server restarts.
+/// backoff, reloading after the server restarts. A websocket that never opens
+/// falls back to the channel's [`ClientIoSse`] event stream.
 ///
 /// Registered by name (see [`RouterPlugin`](crate::prelude::RouterPlugin)), so
 /// a BSX layout drops `<LiveReloadScript/>` in its head. Renders nothing when
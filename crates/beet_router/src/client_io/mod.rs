@@ -8,6 +8,10 @@
 //! [`Socket`](beet_net::prelude::Socket) entity, and [`adopt_client_io_socket`]
 //! re-parents it under the channel. Connected browsers are thus child `Socket`
 //! entities; [`ClientIoBroadcast`] fans a message out to all of them.
+//!
+//! Where websockets are blocked, eg by a corporate proxy, the channel's text
+//! messages also stream as Server-Sent Events from [`client_io_sse_route`],
+//! which the live-reload client falls back to.
 
 mod client_io;
 pub use client_io::*;
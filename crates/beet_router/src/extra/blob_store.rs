@@ -3,33 +3,205 @@
 //! [`HtmlStore`](crate::prelude::HtmlStore) gate.
 
 use beet_core::prelude::*;
+use beet_net::exports::bytes::Bytes;
 use beet_net::prelude::*;
+use core::hash::BuildHasher;
+use core::hash::Hasher;
+
+/// The most ranges one request may ask for before its `Range` is ignored and
+/// the whole object sent: many tiny or overlapping ranges cost far more to
+/// serve than the object itself (RFC 9110 §14.2).
+const MAX_RANGES: usize = 16;
 
 /// Serves a single path from a [`BlobStore`] using static-host conventions:
 /// - extensioned path + a store public URL → permanent redirect
 /// - extensioned path, no public URL → stream the bytes (mime from extension)
 /// - extensionless path → serve `<path>/index.html` as HTML
+///
+/// A served file honors the request's `Range` (see [`serve_file`]).
 pub(crate) async fn serve_blob(
 	store: &BlobStore,
 	path: &SmolPath,
+	headers: &HeaderMap,
 ) -> Result<Response> {
 	if path.extension().is_some() {
 		if let Some(url) = store.public_url(path).await? {
 			Response::permanent_redirect(url).xok()
 		} else {
-			store
-				.get_media(path)
-				.await
-				.map(|media| Response::ok().with_media(media))
+			serve_file(store, path, headers).await
 		}
 	} else {
-		store
-			.get_media(&path.join("index.html"))
-			.await
-			.map(|media| Response::ok().with_media(media))
+		serve_file(store, &path.join("index.html"), headers).await
+	}
+}
+
+/// Serves one object, answering a `Range` request with `206 Partial Content`
+/// read through [`BlobStoreProvider::get_range`]: a single range as the bare
+/// part, several as `multipart/byteranges`, none satisfiable as a `416`.
+///
+/// An `If-Range` serves the range only if its validator still matches the
+/// object, and otherwise sends the whole object, as RFC 9110 asks of a server
+/// whose representation may have changed.
+///
+/// Every `200` carries the object's `ETag` and `Last-Modified` from
+/// [`BlobStoreProvider::head`], so a `ConditionalMiddleware` can answer a
+/// revalidation with a `304`. A `206` reads the object's length from the
+/// ranges themselves, and heads the object for its validators only when the
+/// request is conditional, so seeking through a large object never costs a
+/// `head` per read.
+async fn serve_file(
+	store: &BlobStore,
+	path: &SmolPath,
+	headers: &HeaderMap,
+) -> Result<Response> {
+	let ranges = headers
+		.get::<header::Range>()
+		.and_then(|result| result.ok())
+		.filter(|ranges| ranges.len() <= MAX_RANGES);
+	let conditional = headers.contains::<header::IfRange>()
+		|| headers.contains::<header::IfNoneMatch>()
+		|| headers.contains::<header::IfModifiedSince>();
+	let meta = if ranges.is_some() && conditional {
+		Some(store.head(path).await?)
+	} else {
+		None
+	};
+	let ranges = ranges.filter(|_| {
+		meta.as_ref()
+			.is_none_or(|meta| if_range_matches(headers, meta))
+	});
+	let Some(ranges) = ranges else {
+		let meta = match meta {
+			Some(meta) => meta,
+			None => store.head(path).await?,
+		};
+		let mut response =
+			Response::ok().with_media(store.get_media(path).await?);
+		response
+			.parts
+			.headers
+			.set::<header::AcceptRanges>("bytes".to_string());
+		set_validators(&mut response.parts.headers, &meta);
+		return response.xok();
+	};
+	let media_type = path.media_type().unwrap_or(MediaType::Bytes);
+	let mut parts = Vec::with_capacity(ranges.len());
+	for range in ranges {
+		parts.push(store.get_range(path, range).await?);
+	}
+	// every part reports the same object length
+	let total = parts.first().map(|part| part.total).unwrap_or_default();
+	let mut satisfied = parts
+		.into_iter()
+		.filter_map(|part| part.range.map(|range| (range, part.bytes)))
+		.collect::<Vec<_>>();
+	match satisfied.len() {
+		0 => {
+			let mut response =
+				Response::from_status(StatusCode::RANGE_NOT_SATISFIABLE);
+			response.parts.headers.set::<header::ContentRange>(
+				header::ContentRangeValue::Unsatisfied { total },
+			);
+			response.xok()
+		}
+		1 => {
+			let (range, bytes) = satisfied.remove(0);
+			let mut response =
+				Response::from_status(StatusCode::PARTIAL_CONTENT)
+					.with_content_type(media_type)
+					.with_body(bytes);
+			response.parts.headers.set::<header::ContentRange>(
+				header::ContentRangeValue::Satisfied { range, total },
+			);
+			if let Some(meta) = &meta {
+				set_validators(&mut response.parts.headers, meta);
+			}
+			response.xok()
+		}
+		_ => {
+			let mut response = byteranges(media_type, total, satisfied);
+			if let Some(meta) = &meta {
+				set_validators(&mut response.parts.headers, meta);
+			}
+			response.xok()
+		}
 	}
 }
 
+/// The strong entity tag of a stored object, from the backend's opaque etag,
+/// which some backends (ie S3) already quote.
+fn blob_etag(meta: &BlobMeta) -> Option<header::EntityTag> {
+	meta.etag
+		.as_ref()
+		.map(|etag| header::EntityTag::strong(etag.trim_matches('"')))
+}
+
+/// Whether the request's `If-Range`, if any, still matches the object
+/// described by `meta`: an entity tag by strong comparison, a date exactly to
+/// the second. An unreadable `If-Range` never matches.
+fn if_range_matches(headers: &HeaderMap, meta: &BlobMeta) -> bool {
+	match headers.get::<header::IfRange>() {
+		None => true,
+		Some(Ok(header::IfRangeValue::ETag(tag))) => {
+			!tag.weak && blob_etag(meta).is_some_and(|etag| etag == tag)
+		}
+		Some(Ok(header::IfRangeValue::Date(date))) => {
+			meta.modified.is_some_and(|modified| {
+				modified.unix_epoch_elapsed().as_secs()
+					== date.unix_epoch_elapsed().as_secs()
+			})
+		}
+		Some(Err(_)) => false,
+	}
+}
+
+/// Sets the `ETag` and `Last-Modified` of a response serving the object
+/// described by `meta`, each only if the backend records it.
+fn set_validators(headers: &mut HeaderMap, meta: &BlobMeta) {
+	if let Some(etag) = blob_etag(meta) {
+		headers.set::<header::ETag>(etag);
+	}
+	if let Some(modified) = meta.modified {
+		headers.set::<header::LastModified>(modified);
+	}
+}
+
+/// A `206` carrying several ranges of a `total`-byte object as a
+/// `multipart/byteranges` body, each part with its own `Content-Range`.
+fn byteranges(
+	media_type: MediaType,
+	total: u64,
+	parts: Vec<(core::ops::Range<u64>, Bytes)>,
+) -> Response {
+	// a boundary derived from the parts themselves, so it cannot be a fixed
+	// string some served file happens to contain
+	let mut hasher = FixedHasher::default().build_hasher();
+	for (_, bytes) in &parts {
+		hasher.write(bytes);
+	}
+	let boundary = format!("beet-{:016x}", hasher.finish());
+	let mut body = Vec::new();
+	for (range, bytes) in parts {
+		let content_range =
+			header::ContentRangeValue::Satisfied { range, total };
+		body.extend_from_slice(
+			format!(
+				"--{boundary}\r\ncontent-type: {}\r\ncontent-range: {content_range}\r\n\r\n",
+				media_type.as_str()
+			)
+			.as_bytes(),
+		);
+		body.extend_from_slice(&bytes);
+		body.extend_from_slice(b"\r\n");
+	}
+	body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+	Response::from_status(StatusCode::PARTIAL_CONTENT)
+		.with_content_type(MediaType::Other(format!(
+			"multipart/byteranges; boundary={boundary}"
+		)))
+		.with_body(body)
+}
+
 // the tests serve from a temp fs-backed store (std-only).
 #[cfg(all(test, feature = "std"))]
 mod test {
@@ -43,7 +215,7 @@ mod test {
 			.insert(&SmolPath::from("style.css"), "body { color: red; }")
 			.await
 			.unwrap();
-		super::serve_blob(&store, &SmolPath::from("style.css"))
+		super::serve_blob(&store, &SmolPath::from("style.css"), &default())
 			.await
 			.unwrap()
 			.text()
@@ -59,7 +231,7 @@ mod test {
 			.insert(&SmolPath::from("docs/index.html"), "<h1>Hello</h1>")
 			.await
 			.unwrap();
-		super::serve_blob(&store, &SmolPath::from("docs"))
+		super::serve_blob(&store, &SmolPath::from("docs"), &default())
 			.await
 			.unwrap()
 			.text()
@@ -67,4 +239,123 @@ mod test {
 			.unwrap()
 			.xpect_eq("<h1>Hello</h1>");
 	}
+
+	#[beet_core::test]
+	async fn serve_blob_sets_validators() {
+		let store = BlobStore::temp();
+		let path = SmolPath::from("style.css");
+		store.insert(&path, "body { color: red; }").await.unwrap();
+		let meta = store.head(&path).await.unwrap();
+		let response =
+			super::serve_blob(&store, &path, &default()).await.unwrap();
+		response
+			.headers
+			.get::<header::ETag>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(header::EntityTag::strong(meta.etag.unwrap().as_str()));
+		response
+			.headers
+			.contains::<header::LastModified>()
+			.xpect_true();
+	}
+
+	/// A store with a ten-byte `clip.mp4`.
+	async fn clip_store() -> BlobStore {
+		let store = BlobStore::temp();
+		store
+			.insert(&SmolPath::from("clip.mp4"), "0123456789")
+			.await
+			.unwrap();
+		store
+	}
+
+	fn range(value: &str) -> HeaderMap {
+		let mut headers = HeaderMap::new();
+		headers.set_raw("range", value);
+		headers
+	}
+
+	#[beet_core::test]
+	async fn serve_blob_single_range() {
+		let response = super::serve_blob(
+			&clip_store().await,
+			&SmolPath::from("clip.mp4"),
+			&range("bytes=2-5"),
+		)
+		.await
+		.unwrap();
+		response.status().xpect_eq(StatusCode::PARTIAL_CONTENT);
+		response
+			.headers
+			.get::<header::ContentRange>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(header::ContentRangeValue::Satisfied {
+				range: 2..6,
+				total: 10,
+			});
+		// an unconditional range is served without heading the object
+		response.headers.contains::<header::ETag>().xpect_false();
+		response.text().await.unwrap().xpect_eq("2345");
+	}
+
+	#[beet_core::test]
+	async fn serve_blob_multiple_ranges() {
+		let response = super::serve_blob(
+			&clip_store().await,
+			&SmolPath::from("clip.mp4"),
+			&range("bytes=0-1, -2"),
+		)
+		.await
+		.unwrap();
+		response.status().xpect_eq(StatusCode::PARTIAL_CONTENT);
+		let body = response.text().await.unwrap();
+		body.clone()
+			.xpect_contains("content-range: bytes 0-1/10\r\n\r\n01");
+		body.xpect_contains("content-range: bytes 8-9/10\r\n\r\n89");
+	}
+
+	#[beet_core::test]
+	async fn serve_blob_unsatisfiable_range() {
+		let response = super::serve_blob(
+			&clip_store().await,
+			&SmolPath::from("clip.mp4"),
+			&range("bytes=20-"),
+		)
+		.await
+		.unwrap();
+		response
+			.status()
+			.xpect_eq(StatusCode::RANGE_NOT_SATISFIABLE);
+		response
+			.headers
+			.get::<header::ContentRange>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(header::ContentRangeValue::Unsatisfied { total: 10 });
+	}
+
+	#[beet_core::test]
+	async fn serve_blob_if_range() {
+		let store = clip_store().await;
+		let path = SmolPath::from("clip.mp4");
+		let etag = store.head(&path).await.unwrap().etag.unwrap();
+		let mut matching = range("bytes=2-5");
+		matching.set::<header::IfRange>(header::IfRangeValue::ETag(
+			header::EntityTag::strong(etag.as_str()),
+		));
+		let response =
+			super::serve_blob(&store, &path, &matching).await.unwrap();
+		response.status().xpect_eq(StatusCode::PARTIAL_CONTENT);
+		response.headers.contains::<header::ETag>().xpect_true();
+
+		let mut stale = range("bytes=2-5");
+		stale.set::<header::IfRange>(header::IfRangeValue::ETag(
+			header::EntityTag::strong("stale"),
+		));
+		let response = super::serve_blob(&store, &path, &stale).await.unwrap();
+		response.status().xpect_eq(StatusCode::OK);
+		response.text().await.unwrap().xpect_eq("0123456789");
+	}
 }
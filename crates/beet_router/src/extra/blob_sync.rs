@@ -0,0 +1,115 @@
+//! The server half of [`BlobSync`], the handler behind
+//! [`route::blob_sync`](crate::prelude::route::blob_sync).
+
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+
+/// Applies a [`SyncRequest`] to the nearest self-or-ancestor [`BlobStore`],
+/// answering its [`SyncResponse`] as json.
+///
+/// A change based on a stale server version is answered as a conflict rather
+/// than an error, so the client resolves it by its own policy.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn BlobSyncHandler(
+	cx: ActionContext<Json<SyncRequest>>,
+) -> Result<Response> {
+	let store = cx
+		.caller
+		.with_state::<AncestorQuery<&BlobStore>, Result<BlobStore>>(
+			|entity, stores| stores.get(entity).cloned(),
+		)
+		.await??;
+	let response: Response = Json(cx.input.apply(&store).await?).try_into()?;
+	response.xok()
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use beet_net::exports::bytes::Bytes;
+	use beet_net::prelude::*;
+
+	fn change(path: &str, body: &str, base: Option<SmolStr>) -> SyncChange {
+		SyncChange {
+			path: SmolPath::from(path),
+			body: Some(Bytes::from(body.to_string())),
+			base,
+			modified: Timestamp::now(),
+		}
+	}
+
+	async fn exchange(
+		store: &BlobStore,
+		request: &SyncRequest,
+	) -> SyncResponse {
+		(AsyncPlugin, RouterPlugin)
+			.into_world()
+			.spawn((Router::with_defaults(), store.clone(), children![
+				route::blob_sync("sync")
+			]))
+			.exchange(Request::with_json("sync", request).unwrap())
+			.await
+			.into_result()
+			.await
+			.unwrap()
+			.json::<SyncResponse>()
+			.await
+			.unwrap()
+	}
+
+	#[beet_core::test]
+	async fn applies_and_pulls() {
+		let store = BlobStore::temp();
+		store
+			.insert(&SmolPath::from("remote.txt"), "server")
+			.await
+			.unwrap();
+		let response = exchange(&store, &SyncRequest {
+			changes: vec![change("local.txt", "client", None)],
+			known: default(),
+			cursor: None,
+		})
+		.await;
+		response.results.len().xpect_eq(1);
+		matches!(response.results[0], SyncResult::Applied { .. }).xpect_true();
+		response.pulled.len().xpect_eq(1);
+		response.pulled[0]
+			.path
+			.xpect_eq(SmolPath::from("remote.txt"));
+		store
+			.get(&SmolPath::from("local.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("client"));
+	}
+
+	#[beet_core::test]
+	async fn stale_base_conflicts() {
+		let store = BlobStore::temp();
+		store
+			.insert(&SmolPath::from("doc.txt"), "v2")
+			.await
+			.unwrap();
+		let response = exchange(&store, &SyncRequest {
+			changes: vec![change("doc.txt", "client", Some("stale".into()))],
+			known: default(),
+			cursor: None,
+		})
+		.await;
+		let SyncResult::Conflict { server, .. } = &response.results[0] else {
+			panic!("expected a conflict");
+		};
+		server.as_ref().unwrap().body.xpect_eq(Bytes::from("v2"));
+		// the conflict carries the server version, so it is not pulled again
+		response.pulled.xpect_empty();
+		store
+			.get(&SmolPath::from("doc.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("v2"));
+	}
+}
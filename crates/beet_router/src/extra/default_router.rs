@@ -38,9 +38,10 @@ impl Router {
 }
 /// The default app routes as a bundle of [`OnSpawn::insert_child`] effects: the
 /// reactivity-runtime asset (`/js/reactivity.js`), `/app-info`, `POST /analytics`,
-/// and the `/__client_io` websocket channel, each attached as its own child so it
-/// keeps its own path. Shared by [`Router::with_defaults`] and the [`DefaultAppRoutes`]
-/// template. `app_info`/`analytics` need a [`PackageConfig`] resource.
+/// and the `/__client_io` websocket channel with its `/__client_io/sse`
+/// fallback, each attached as its own child so it keeps its own path. Shared by
+/// [`Router::with_defaults`] and the [`DefaultAppRoutes`] template.
+/// `app_info`/`analytics` need a [`PackageConfig`] resource.
 #[cfg(feature = "std")]
 fn default_app_routes() -> impl Bundle {
 	(
@@ -52,6 +53,8 @@ fn default_app_routes() -> impl Bundle {
 		OnSpawn::insert_child(analytics_handler()),
 		#[cfg(all(feature = "client_io", not(target_arch = "wasm32")))]
 		OnSpawn::insert_child(client_io_route()),
+		#[cfg(all(feature = "client_io", not(target_arch = "wasm32")))]
+		OnSpawn::insert_child(client_io_sse_route()),
 	)
 }
 
@@ -65,7 +65,9 @@ pub async fn HtmlStoreAction(
 	};
 
 	// serve the prebuilt file, falling through to live rendering on a miss
-	match serve_blob(&store, &SmolPath::from(request.path())).await {
+	match serve_blob(&store, &SmolPath::from(request.path()), &request.headers)
+		.await
+	{
 		Ok(response) => Ok(response),
 		Err(_) => next.call(request).await,
 	}
@@ -11,12 +11,22 @@ pub use html_store::*;
 // `ServeBlobs` and the HTML-store gate (no_std core).
 mod blob_store;
 pub(crate) use blob_store::*;
+// the `route::upload` handler, writing multipart file uploads into a `BlobStore`
+// (no_std core, like the serve side above).
+mod upload;
+pub(crate) use upload::*;
 // the standard blob-store agent toolset + a markup store mount, composing
 // `exchange_route` with beet_net's blob-store actions.
 #[cfg(feature = "std")]
 mod store_toolset;
 #[cfg(feature = "std")]
 pub use store_toolset::*;
+// the `route::blob_sync` handler, the server half of beet_net's offline-first
+// `BlobSync` replication.
+#[cfg(all(feature = "std", feature = "json"))]
+mod blob_sync;
+#[cfg(all(feature = "std", feature = "json"))]
+pub(crate) use blob_sync::*;
 
 // std: the analytics emitters build beet_net's `AnalyticsEvent` (serde, via std);
 // only the beacon route's json-body parsing needs `json`, gated inside.
@@ -27,6 +27,19 @@ pub fn StoreToolset() -> impl Bundle {
 	]
 }
 
+/// Equip an agent with undo for the nearest ancestor [`BlobStore`]: list,
+/// read and restore the prior versions of a blob. Nest it beside a
+/// [`StoreToolset`] over a store that keeps versions, ie a [`VersionedStore`],
+/// so a clobbered file can be recovered.
+#[template]
+pub fn StoreHistoryToolset() -> impl Bundle {
+	children![
+		route::exchange("list-blob-versions", ListBlobVersions),
+		route::exchange("read-blob-version", ReadBlobVersion),
+		route::exchange("restore-blob", RestoreBlob),
+	]
+}
+
 // `<StoreToolset/>` equipping the five routed blob tools (and the `ToolDefinition`
 // an agent derives from each) is covered downstream where the tool-definition
 // observer lives: `tests/thread_scenes.rs` (`coding_agent`/`self_evolving` reduce
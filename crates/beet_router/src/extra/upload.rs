@@ -0,0 +1,182 @@
+//! Writing `multipart/form-data` file uploads into a [`BlobStore`], the handler
+//! behind [`route::upload`](crate::prelude::route::upload).
+
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+
+/// Writes each file field of a `multipart/form-data` body into the nearest
+/// self-or-ancestor [`BlobStore`], answering `201 Created` with the stored
+/// paths, one per line. Plain form values are skipped.
+///
+/// A file is stored under the last segment of its client-supplied name, so a
+/// name cannot escape the store root; a co-located [`DirPath`] scopes the store
+/// to an upload directory, as it does for [`ServeBlobs`](crate::prelude::ServeBlobs).
+/// Each file is buffered up to the [`MultipartLimits`] field size before the
+/// insert, the store api taking whole objects, and stored with the field's
+/// declared `Content-Type`.
+///
+/// An upload never replaces an existing object: a file whose name is taken
+/// answers `409 Conflict`, leaving any earlier files of the same body stored.
+/// The write is [`insert_with_meta_if_absent`](BlobStoreProvider::insert_with_meta_if_absent),
+/// so where the backend makes that atomic, of two uploads racing for one new
+/// name exactly one succeeds.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn UploadHandler(
+	cx: ActionContext<Multipart>,
+) -> Result<Response> {
+	let store = cx
+		.caller
+		.with_state::<AncestorQuery<&BlobStore>, Result<BlobStore>>(
+			|entity, stores| stores.get(entity).cloned(),
+		)
+		.await??;
+	let mut multipart = cx.take();
+	let mut stored = Vec::new();
+	while let Some(field) = multipart.next_field().await? {
+		let Some(file_name) = field.file_name().and_then(upload_file_name)
+		else {
+			continue;
+		};
+		let path = SmolPath::from(file_name);
+		let meta = match field.content_type() {
+			Some(content_type) => {
+				InsertMeta::default().with_content_type(content_type)
+			}
+			None => InsertMeta::default(),
+		};
+		match store
+			.insert_with_meta_if_absent(&path, field.bytes().await?, meta)
+			.await
+		{
+			Err(err)
+				if HttpError::status_of(&err)
+					== Some(StatusCode::PRECONDITION_FAILED) =>
+			{
+				return Err(HttpError::new(
+					StatusCode::CONFLICT,
+					format!("an object named {path} already exists"),
+				)
+				.into());
+			}
+			result => result?,
+		}
+		stored.push(path.to_string());
+	}
+	Response::from_status(StatusCode::CREATED)
+		.with_body(stored.join("\n"))
+		.xok()
+}
+
+/// The store-safe name for a client-supplied upload file name: its last path
+/// segment (browsers on windows may send a full path), `None` when that is
+/// empty or a dot segment, as for a file input left empty.
+fn upload_file_name(file_name: &str) -> Option<String> {
+	file_name
+		.rsplit(['/', '\\'])
+		.next()
+		.map(str::trim)
+		.filter(|name| !name.is_empty() && *name != "." && *name != "..")
+		.map(str::to_string)
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use beet_net::exports::bytes::Bytes;
+	use beet_net::prelude::*;
+
+	fn upload_request(body: &str) -> Request {
+		Request::post("upload")
+			.with_header_raw(
+				"content-type",
+				"multipart/form-data; boundary=XyZ",
+			)
+			.with_body(body)
+	}
+
+	#[beet_core::test]
+	async fn stores_files() {
+		let store = BlobStore::temp();
+		let body = [
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+			"ignored\r\n",
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"file\"; filename=\"C:\\docs\\notes.txt\"\r\n",
+			"Content-Type: text/markdown\r\n\r\n",
+			"hello upload\r\n",
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"empty\"; filename=\"\"\r\n\r\n",
+			"\r\n",
+			"--XyZ--\r\n",
+		]
+		.concat();
+		let response = (AsyncPlugin, RouterPlugin)
+			.into_world()
+			.spawn((Router::with_defaults(), store.clone(), children![
+				route::upload("upload")
+			]))
+			.exchange(upload_request(&body))
+			.await;
+		response.status().xpect_eq(StatusCode::CREATED);
+		response.unwrap_str().await.xpect_eq("notes.txt");
+		store
+			.get(&SmolPath::from("notes.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("hello upload"));
+		store
+			.head(&SmolPath::from("notes.txt"))
+			.await
+			.unwrap()
+			.content_type
+			.xpect_eq(MediaType::Markdown);
+	}
+
+	#[beet_core::test]
+	async fn refuses_to_overwrite() {
+		let store = BlobStore::temp();
+		store
+			.insert(&SmolPath::from("notes.txt"), "original")
+			.await
+			.unwrap();
+		let body = [
+			"--XyZ\r\n",
+			"Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\r\n",
+			"replacement\r\n",
+			"--XyZ--\r\n",
+		]
+		.concat();
+		(AsyncPlugin, RouterPlugin)
+			.into_world()
+			.spawn((Router::with_defaults(), store.clone(), children![
+				route::upload("upload")
+			]))
+			.exchange(upload_request(&body))
+			.await
+			.status()
+			.xpect_eq(StatusCode::CONFLICT);
+		store
+			.get(&SmolPath::from("notes.txt"))
+			.await
+			.unwrap()
+			.xpect_eq(Bytes::from("original"));
+	}
+
+	#[beet_core::test]
+	async fn rejects_other_media_types() {
+		(AsyncPlugin, RouterPlugin)
+			.into_world()
+			.spawn((Router::with_defaults(), BlobStore::temp(), children![
+				route::upload("upload")
+			]))
+			.exchange(Request::post("upload").with_body("{}"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+	}
+}
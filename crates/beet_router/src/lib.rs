@@ -4,6 +4,9 @@ extern crate alloc;
 
 beet_core::test_main!();
 
+// sessions, login routes and the route gates built on them.
+#[cfg(feature = "auth")]
+mod auth;
 // the reusable stack-of-cards machinery (the HyperCard model): a `CardDeck`
 // router plus its `CardStackPlugin`. Self-contained; the navigate/router core
 // stays card-agnostic.
@@ -31,6 +34,8 @@ mod static_export;
 
 /// Exports the most commonly used items.
 pub mod prelude {
+	#[cfg(feature = "auth")]
+	pub use crate::auth::*;
 	pub use crate::card_stack::*;
 	#[cfg(all(feature = "client_io", not(target_arch = "wasm32")))]
 	pub use crate::client_io::*;
@@ -342,6 +342,18 @@ impl Navigator {
 				let request = Request::get(&url)
 					.with_header::<header::UserAgent>(user_agent)
 					.with_header::<header::Accept>(accepts);
+				// an ssh session's navigator sits on its connection: tag the
+				// remote address so per-client middleware (rate limits) tells
+				// sessions apart
+				#[cfg(feature = "ssh")]
+				let request =
+					match entity.get(|peer: &SshPeerInfo| peer.peer_addr).await
+					{
+						Ok(Some(peer_addr)) => {
+							request.with_peer_addr(peer_addr)
+						}
+						_ => request,
+					};
 				build_live_page(&entity.world().entity(router), request).await?
 			}
 		};
@@ -19,7 +19,7 @@ use syn::Item;
 use syn::ItemFn;
 
 /// How a client caller transports its single input argument.
-enum ClientInput {
+pub(crate) enum ClientInput {
 	/// No input argument.
 	None,
 	/// `Json<T>` extractor: send `T` as a JSON body.
@@ -28,15 +28,30 @@ enum ClientInput {
 	Query(syn::Type),
 }
 
-/// A node in the client-action module tree, keyed by path segment.
+/// A node in the client-action module tree, keyed by path segment. Shared
+/// with the TypeScript client, whose nested objects mirror the same tree.
 #[derive(Default)]
-struct ActionNode {
-	ident: String,
-	methods: Vec<(SmolPath, RouteMethod)>,
-	children: Vec<ActionNode>,
+pub(crate) struct ClientActionNode {
+	/// The snake_case path segment naming this node.
+	pub ident: String,
+	/// The handlers routed at this node, with their full route paths.
+	pub methods: Vec<(SmolPath, RouteMethod)>,
+	/// The nodes one path segment deeper.
+	pub children: Vec<ClientActionNode>,
 }
 
-impl ActionNode {
+impl ClientActionNode {
+	/// Builds the root of the tree from a list of route files.
+	pub fn from_files(files: &[RouteFile]) -> Self {
+		let mut root = Self::default();
+		for file in files {
+			for method in file.methods() {
+				root.insert(&file.route_path, method.clone());
+			}
+		}
+		root
+	}
+
 	fn insert(&mut self, path: &SmolPath, method: RouteMethod) {
 		let mut node = self;
 		for seg in path.segments() {
@@ -46,7 +61,7 @@ impl ActionNode {
 				.iter()
 				.position(|child| child.ident == ident)
 				.unwrap_or_else(|| {
-					node.children.push(ActionNode { ident, ..default() });
+					node.children.push(ClientActionNode { ident, ..default() });
 					node.children.len() - 1
 				});
 			node = &mut node.children[idx];
@@ -57,17 +72,15 @@ impl ActionNode {
 
 /// Builds the top-level client-action module items from a list of route files.
 pub(crate) fn emit_client_actions(files: &[RouteFile]) -> Result<Vec<Item>> {
-	let mut root = ActionNode::default();
-	for file in files {
-		for method in file.methods() {
-			root.insert(&file.route_path, method.clone());
-		}
-	}
-	root.children.iter().map(client_mod).collect()
+	ClientActionNode::from_files(files)
+		.children
+		.iter()
+		.map(client_mod)
+		.collect()
 }
 
 /// Builds the module item for a single client-action tree node.
-fn client_mod(node: &ActionNode) -> Result<Item> {
+fn client_mod(node: &ClientActionNode) -> Result<Item> {
 	let ident = Ident::new(&node.ident, Span::call_site());
 	let funcs = node
 		.methods
@@ -129,7 +142,7 @@ fn client_fn(path: &SmolPath, method: &RouteMethod) -> Result<ItemFn> {
 }
 
 /// Classifies a handler's input extractor for the client caller.
-fn client_input(item: &ItemFn) -> ClientInput {
+pub(crate) fn client_input(item: &ItemFn) -> ClientInput {
 	let Some(syn::FnArg::Typed(pat_type)) = item.sig.inputs.first() else {
 		return ClientInput::None;
 	};
@@ -79,6 +79,7 @@ pub(crate) fn emit_collection(
 				mod_ident,
 				mod_path,
 				methods,
+				..
 			} => {
 				codegen.add_item(item_mod(mod_ident, mod_path, collection));
 				for method in methods {
@@ -0,0 +1,565 @@
+//! Emits a typed TypeScript client for server actions.
+//!
+//! The TypeScript twin of [`emit_client_actions`]: the same handlers become
+//! `async` fetch wrappers, nested in `const` objects mirroring the route
+//! paths, so the Rust `add::post(args)` is the TypeScript `add.post(args)`.
+//! Each wrapper sends to the configurable `serverUrl`, a `Json<T>` input as
+//! the body and a `QueryParams<T>` input as the query, and decodes the JSON
+//! response.
+//!
+//! The input and output types become interfaces, declared from the structs
+//! and enums of the route files and any [`TsClientFile`] type sources. Only
+//! the types the handlers reach are declared, following serde's default
+//! externally tagged enum layout and its `rename`, `rename_all` and `skip`
+//! attributes. A type with no definition in scope is `unknown`.
+
+use crate::prelude::*;
+use crate::route_codegen::syn_utils::action_output_ty;
+use crate::route_codegen::syn_utils::type_last_ident;
+use beet_core::prelude::*;
+use heck::ToKebabCase;
+use heck::ToLowerCamelCase;
+use heck::ToShoutyKebabCase;
+use heck::ToShoutySnakeCase;
+use heck::ToSnakeCase;
+use heck::ToUpperCamelCase;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use syn::Attribute;
+use syn::Fields;
+use syn::Item;
+use syn::Type;
+
+/// The fetch runtime every generated client starts with.
+const TS_RUNTIME: &str = r#"// Generated by beet route codegen, do not edit.
+
+/** The base url prepended to every server-action path: the page origin in a
+ * browser, else the local dev server. Override with {@link setServerUrl}. */
+let serverUrl: string =
+	(globalThis as { location?: { origin: string } }).location?.origin ??
+	"http://127.0.0.1:8337";
+
+/** Sets the server url used by all subsequent server-action calls. */
+export function setServerUrl(url: string): void {
+	serverUrl = url;
+}
+
+/** A server action answered with a non-2xx status. */
+export class ServerActionError extends Error {
+	constructor(
+		readonly status: number,
+		readonly body: string,
+	) {
+		super(`server action failed with ${status}: ${body}`);
+	}
+}
+
+async function send(
+	method: string,
+	path: string,
+	init: { body?: unknown; query?: object } = {},
+): Promise<Response> {
+	let url = serverUrl.replace(/\/+$/, "") + path;
+	if (init.query !== undefined) {
+		const query = new URLSearchParams();
+		for (const [key, value] of Object.entries(init.query)) {
+			if (value !== undefined && value !== null) {
+				query.append(key, String(value));
+			}
+		}
+		url += `?${query}`;
+	}
+	const headers: Record<string, string> = { accept: "application/json" };
+	if (init.body !== undefined) {
+		headers["content-type"] = "application/json";
+	}
+	const response = await fetch(url, {
+		method,
+		headers,
+		body: init.body === undefined ? undefined : JSON.stringify(init.body),
+	});
+	if (!response.ok) {
+		throw new ServerActionError(response.status, await response.text());
+	}
+	return response;
+}
+"#;
+
+/// Builds the TypeScript client module for the actions in `files`, declaring
+/// its interfaces from their types and the extra `type_sources`.
+pub(crate) fn emit_ts_client(
+	files: &[RouteFile],
+	type_sources: &[Item],
+) -> String {
+	let mut types = TsTypes::new(
+		files
+			.iter()
+			.flat_map(|file| file.types())
+			.chain(type_sources),
+	);
+	let actions = ClientActionNode::from_files(files)
+		.children
+		.iter()
+		.map(|node| {
+			format!(
+				"export const {} = {};\n",
+				ts_binding(&node.ident),
+				ts_object(node, &mut types, 0)
+			)
+		})
+		.collect::<Vec<_>>();
+
+	let mut out = TS_RUNTIME.to_string();
+	for declaration in types.declare_used() {
+		out.push('\n');
+		out.push_str(&declaration);
+	}
+	for action in actions {
+		out.push('\n');
+		out.push_str(&action);
+	}
+	out
+}
+
+/// The object literal for one node of the action tree: its handlers, then
+/// its children.
+fn ts_object(
+	node: &ClientActionNode,
+	types: &mut TsTypes,
+	depth: usize,
+) -> String {
+	let indent = "\t".repeat(depth + 1);
+	let mut out = String::from("{\n");
+	for (path, method) in &node.methods {
+		out.push_str(&js_doc(&method.item.attrs, &indent));
+		out.push_str(&format!(
+			"{indent}{}: {},\n",
+			method.method.to_string_lowercase(),
+			ts_fn(path, method, types, &indent)
+		));
+	}
+	for child in &node.children {
+		out.push_str(&format!(
+			"{indent}{}: {},\n",
+			ts_key(&child.ident),
+			ts_object(child, types, depth + 1)
+		));
+	}
+	out.push_str(&"\t".repeat(depth));
+	out.push('}');
+	out
+}
+
+/// The `async` arrow function calling a single server-action handler.
+fn ts_fn(
+	path: &SmolPath,
+	method: &RouteMethod,
+	types: &mut TsTypes,
+	indent: &str,
+) -> String {
+	let (param, init) = match client_input(&method.item) {
+		ClientInput::None => (String::new(), String::new()),
+		ClientInput::Json(ty) => (
+			format!("input: {}", types.ts_type(&ty)),
+			", { body: input }".to_string(),
+		),
+		ClientInput::Query(ty) => (
+			format!("input: {}", types.ts_type(&ty)),
+			", { query: input }".to_string(),
+		),
+	};
+	let http = method.method.to_string().to_uppercase();
+	let route = path.with_leading_slash();
+	let call = format!("send(\"{http}\", \"{route}\"{init})");
+	let output = action_output_ty(&method.item);
+	// `()`, or a bare `Result` defaulting to `Result<()>`
+	let is_unit = match &output {
+		Type::Tuple(tuple) => tuple.elems.is_empty(),
+		ty => type_last_ident(ty).as_deref() == Some("Result"),
+	};
+	if is_unit {
+		format!(
+			"async ({param}): Promise<void> => {{\n{indent}\tawait {call};\n{indent}}}"
+		)
+	} else {
+		format!(
+			"async ({param}): Promise<{}> =>\n{indent}\t(await {call}).json()",
+			types.ts_type(&output)
+		)
+	}
+}
+
+/// The struct and enum definitions in scope, tracking which of them the
+/// handlers reach so only those are declared.
+struct TsTypes {
+	definitions: BTreeMap<String, Item>,
+	used: BTreeSet<String>,
+}
+
+impl TsTypes {
+	fn new<'a>(items: impl IntoIterator<Item = &'a Item>) -> Self {
+		let definitions = items
+			.into_iter()
+			.filter_map(|item| {
+				let ident = match item {
+					Item::Struct(item) => &item.ident,
+					Item::Enum(item) => &item.ident,
+					_ => return None,
+				};
+				Some((ident.to_string(), item.clone()))
+			})
+			.collect();
+		Self {
+			definitions,
+			used: default(),
+		}
+	}
+
+	/// Declares every used type, including those only reached through the
+	/// fields of another, in name order.
+	fn declare_used(&mut self) -> Vec<String> {
+		let mut declared = BTreeMap::<String, String>::new();
+		while let Some(name) = self
+			.used
+			.iter()
+			.find(|name| !declared.contains_key(*name))
+			.cloned()
+		{
+			let item = self.definitions[&name].clone();
+			declared.insert(name, self.declaration(&item));
+		}
+		declared.into_values().collect()
+	}
+
+	/// The TypeScript type of a Rust type, marking any in-scope definition as
+	/// used.
+	fn ts_type(&mut self, ty: &Type) -> String {
+		match ty {
+			Type::Reference(reference) => self.ts_type(&reference.elem),
+			Type::Paren(paren) => self.ts_type(&paren.elem),
+			Type::Group(group) => self.ts_type(&group.elem),
+			Type::Slice(slice) => ts_array(self.ts_type(&slice.elem)),
+			Type::Array(array) => ts_array(self.ts_type(&array.elem)),
+			Type::Tuple(tuple) if tuple.elems.is_empty() => "null".into(),
+			Type::Tuple(tuple) => format!(
+				"[{}]",
+				tuple
+					.elems
+					.iter()
+					.map(|elem| self.ts_type(elem))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+			Type::Path(path) => {
+				let Some(segment) = path.path.segments.last() else {
+					return "unknown".into();
+				};
+				let args = match &segment.arguments {
+					syn::PathArguments::AngleBracketed(args) => args
+						.args
+						.iter()
+						.filter_map(|arg| match arg {
+							syn::GenericArgument::Type(ty) => Some(ty),
+							_ => None,
+						})
+						.collect::<Vec<_>>(),
+					_ => Vec::new(),
+				};
+				let ident = segment.ident.to_string();
+				match (ident.as_str(), args.as_slice()) {
+					("bool", _) => "boolean".into(),
+					(
+						"u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8"
+						| "i16" | "i32" | "i64" | "i128" | "isize" | "f32"
+						| "f64",
+						_,
+					) => "number".into(),
+					(
+						"String" | "str" | "char" | "SmolStr" | "Cow" | "Uuid"
+						| "SmolPath" | "PathBuf" | "Url",
+						_,
+					) => "string".into(),
+					("Option", [inner]) => {
+						format!("{} | null", self.ts_type(inner))
+					}
+					(
+						"Vec" | "VecDeque" | "HashSet" | "BTreeSet"
+						| "IndexSet",
+						[inner],
+					) => ts_array(self.ts_type(inner)),
+					("HashMap" | "BTreeMap" | "IndexMap", [_, value]) => {
+						format!("Record<string, {}>", self.ts_type(value))
+					}
+					(
+						"Box" | "Arc" | "Rc" | "Json" | "QueryParams",
+						[inner],
+					) => self.ts_type(inner),
+					(name, _) if self.definitions.contains_key(name) => {
+						self.used.insert(name.to_string());
+						name.to_string()
+					}
+					_ => "unknown".into(),
+				}
+			}
+			_ => "unknown".into(),
+		}
+	}
+
+	/// The exported declaration of a struct or enum, as serde encodes it.
+	fn declaration(&mut self, item: &Item) -> String {
+		match item {
+			Item::Struct(item) => {
+				let rename_all = SerdeAttrs::parse(&item.attrs).rename_all;
+				let ident = &item.ident;
+				let doc = js_doc(&item.attrs, "");
+				match &item.fields {
+					Fields::Named(_) => format!(
+						"{doc}export interface {ident} {}\n",
+						self.ts_fields(&item.fields, rename_all.as_deref(), 0)
+					),
+					fields => format!(
+						"{doc}export type {ident} = {};\n",
+						self.ts_fields(fields, None, 0)
+					),
+				}
+			}
+			Item::Enum(item) => {
+				let rename_all = SerdeAttrs::parse(&item.attrs).rename_all;
+				let variants = item
+					.variants
+					.iter()
+					.filter_map(|variant| {
+						let serde = SerdeAttrs::parse(&variant.attrs);
+						if serde.skip {
+							return None;
+						}
+						let name = serde.rename.unwrap_or_else(|| {
+							rename(
+								&variant.ident.to_string(),
+								rename_all.as_deref(),
+							)
+						});
+						let name = ts_string(&name);
+						Some(match &variant.fields {
+							Fields::Unit => name,
+							fields => format!(
+								"{{ {name}: {} }}",
+								self.ts_fields(fields, None, 1)
+							),
+						})
+					})
+					.collect::<Vec<_>>();
+				let variants = if variants.is_empty() {
+					"never".to_string()
+				} else {
+					variants.join(" | ")
+				};
+				format!(
+					"{}export type {} = {variants};\n",
+					js_doc(&item.attrs, ""),
+					item.ident
+				)
+			}
+			_ => unreachable!("only structs and enums are definitions"),
+		}
+	}
+
+	/// The TypeScript type of a struct or enum variant's fields: an object
+	/// type for named fields, the inner type of a newtype, a tuple otherwise.
+	fn ts_fields(
+		&mut self,
+		fields: &Fields,
+		rename_all: Option<&str>,
+		depth: usize,
+	) -> String {
+		match fields {
+			Fields::Named(named) => {
+				let indent = "\t".repeat(depth + 1);
+				let mut out = String::from("{\n");
+				for field in &named.named {
+					let serde = SerdeAttrs::parse(&field.attrs);
+					if serde.skip {
+						continue;
+					}
+					let Some(ident) = &field.ident else { continue };
+					let name = serde.rename.unwrap_or_else(|| {
+						rename(&ident.to_string(), rename_all)
+					});
+					let optional =
+						is_generic_of(&field.ty, "Option") || serde.default;
+					out.push_str(&js_doc(&field.attrs, &indent));
+					out.push_str(&format!(
+						"{indent}{}{}: {};\n",
+						ts_key(&name),
+						if optional { "?" } else { "" },
+						self.ts_type(&field.ty)
+					));
+				}
+				out.push_str(&"\t".repeat(depth));
+				out.push('}');
+				out
+			}
+			Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+				self.ts_type(&unnamed.unnamed[0].ty)
+			}
+			Fields::Unnamed(unnamed) => format!(
+				"[{}]",
+				unnamed
+					.unnamed
+					.iter()
+					.map(|field| self.ts_type(&field.ty))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+			Fields::Unit => "null".into(),
+		}
+	}
+}
+
+/// The serde attributes the interfaces follow.
+#[derive(Default)]
+struct SerdeAttrs {
+	rename: Option<String>,
+	rename_all: Option<String>,
+	skip: bool,
+	default: bool,
+}
+
+impl SerdeAttrs {
+	/// Reads the `#[serde(..)]` attributes, passing over any other key.
+	fn parse(attrs: &[Attribute]) -> Self {
+		let mut out = Self::default();
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+			// a malformed attribute is serde's error to report, not codegen's
+			attr.parse_nested_meta(|meta| {
+				let key = meta.path.get_ident().map(|ident| ident.to_string());
+				match key.as_deref() {
+					Some("rename") if meta.input.peek(syn::Token![=]) => {
+						out.rename =
+							Some(meta.value()?.parse::<syn::LitStr>()?.value());
+					}
+					Some("rename_all") if meta.input.peek(syn::Token![=]) => {
+						out.rename_all =
+							Some(meta.value()?.parse::<syn::LitStr>()?.value());
+					}
+					Some("skip" | "skip_serializing") => out.skip = true,
+					Some("default") => out.default = true,
+					_ => {}
+				}
+				// consume the value of a key not handled above
+				if meta.input.peek(syn::Token![=]) {
+					meta.value()?.parse::<syn::Expr>()?;
+				} else if meta.input.peek(syn::token::Paren) {
+					meta.parse_nested_meta(|nested| {
+						if nested.input.peek(syn::Token![=]) {
+							nested.value()?.parse::<syn::Expr>()?;
+						}
+						Ok(())
+					})?;
+				}
+				Ok(())
+			})
+			.ok();
+		}
+		out
+	}
+}
+
+/// Applies a serde `rename_all` rule to a field or variant name.
+fn rename(name: &str, rename_all: Option<&str>) -> String {
+	match rename_all {
+		Some("lowercase") => name.to_lowercase(),
+		Some("UPPERCASE") => name.to_uppercase(),
+		Some("PascalCase") => name.to_upper_camel_case(),
+		Some("camelCase") => name.to_lower_camel_case(),
+		Some("snake_case") => name.to_snake_case(),
+		Some("SCREAMING_SNAKE_CASE") => name.to_shouty_snake_case(),
+		Some("kebab-case") => name.to_kebab_case(),
+		Some("SCREAMING-KEBAB-CASE") => name.to_shouty_kebab_case(),
+		_ => name.to_string(),
+	}
+}
+
+/// Whether a type is `outer<..>`, ie an `Option` field serde may omit.
+fn is_generic_of(ty: &Type, outer: &str) -> bool {
+	matches!(ty, Type::Path(path) if path
+		.path
+		.segments
+		.last()
+		.is_some_and(|segment| segment.ident == outer))
+}
+
+fn ts_array(item: String) -> String {
+	if item.contains(' ') {
+		format!("({item})[]")
+	} else {
+		format!("{item}[]")
+	}
+}
+
+/// A double-quoted TypeScript string literal.
+fn ts_string(value: &str) -> String {
+	format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// An object key, quoted unless it is a plain identifier.
+fn ts_key(name: &str) -> String {
+	let is_ident_char =
+		|char: char| char.is_ascii_alphanumeric() || char == '_' || char == '$';
+	let plain = name
+		.chars()
+		.next()
+		.is_some_and(|char| !char.is_ascii_digit())
+		&& name.chars().all(is_ident_char);
+	if plain {
+		name.to_string()
+	} else {
+		ts_string(name)
+	}
+}
+
+/// A top-level binding name, suffixed with `_` when it is a reserved word.
+fn ts_binding(ident: &str) -> String {
+	const RESERVED: &str = "break case catch class const continue debugger \
+		default delete do else enum export extends false finally for function \
+		if import in instanceof new null return super switch this throw true \
+		try typeof var void while with let static yield await implements \
+		interface package private protected public";
+	if RESERVED.split_whitespace().any(|word| word == ident) {
+		format!("{ident}_")
+	} else {
+		ident.to_string()
+	}
+}
+
+/// The `#[doc]` attributes of an item as a JSDoc comment.
+fn js_doc(attrs: &[Attribute], indent: &str) -> String {
+	let lines = attrs
+		.iter()
+		.filter_map(|attr| match &attr.meta {
+			syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => {
+				match &meta.value {
+					syn::Expr::Lit(syn::ExprLit {
+						lit: syn::Lit::Str(lit),
+						..
+					}) => Some(lit.value().trim().replace("*/", "*\\/")),
+					_ => None,
+				}
+			}
+			_ => None,
+		})
+		.collect::<Vec<_>>();
+	match lines.as_slice() {
+		[] => String::new(),
+		[line] => format!("{indent}/** {line} */\n"),
+		lines => {
+			let mut out = format!("{indent}/**\n");
+			for line in lines {
+				out.push_str(format!("{indent} * {line}").trim_end());
+				out.push('\n');
+			}
+			out.push_str(&format!("{indent} */\n"));
+			out
+		}
+	}
+}
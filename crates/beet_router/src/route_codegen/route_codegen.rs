@@ -1,9 +1,9 @@
 //! Top-level codegen orchestration.
 //!
 //! A [`RouteCodegen`] groups one or more [`RouteCollection`]s with optional
-//! typed-route-tree and client-action outputs. Scanning each collection once,
-//! it emits the per-collection bundle files, the shared `routes::` module, and
-//! the client-action callers.
+//! typed-route-tree, client-action and TypeScript client outputs. Scanning
+//! each collection once, it emits the per-collection bundle files, the shared
+//! `routes::` module, the client-action callers and the TypeScript client.
 
 use crate::prelude::*;
 use beet_core::prelude::*;
@@ -17,6 +17,8 @@ pub struct RouteCodegen {
 	pub route_tree: Option<CodegenFile>,
 	/// Output for client-action callers, built from all actions collections.
 	pub client_actions: Option<CodegenFile>,
+	/// Output for the TypeScript client, built from all actions collections.
+	pub ts_client: Option<TsClientFile>,
 }
 
 impl RouteCodegen {
@@ -41,18 +43,67 @@ impl RouteCodegen {
 		self
 	}
 
+	/// Sets the TypeScript client output.
+	pub fn with_ts_client(mut self, ts_client: TsClientFile) -> Self {
+		self.ts_client = Some(ts_client);
+		self
+	}
+
 	/// Scans every collection and builds the populated codegen files without
 	/// writing them to disk.
 	pub async fn build(&self) -> Result<Vec<CodegenFile>> {
+		self.build_scanned(&self.scan().await?)
+	}
+
+	/// Scans the actions collections and builds the TypeScript client source
+	/// without writing it to disk, or `None` if no client output is set.
+	pub async fn build_ts_client(&self) -> Result<Option<String>> {
+		self.build_ts_client_scanned(&self.scan().await?)
+	}
+
+	/// Runs the full codegen pass, writing every output file to disk.
+	pub async fn export(self) -> Result<()> {
+		let scanned = self.scan().await?;
+		for codegen in self.build_scanned(&scanned)? {
+			codegen.build_and_write()?;
+		}
+		if let (Some(ts_client), Some(src)) =
+			(&self.ts_client, self.build_ts_client_scanned(&scanned)?)
+		{
+			ts_client.write(&src)?;
+		}
+		Ok(())
+	}
+
+	/// Scans each collection once, in order.
+	async fn scan(&self) -> Result<Vec<Vec<RouteFile>>> {
 		let mut scanned = Vec::with_capacity(self.collections.len());
 		for collection in &self.collections {
 			scanned.push(collection.scan().await?);
 		}
+		Ok(scanned)
+	}
 
+	/// The route files of every actions collection.
+	fn action_files(&self, scanned: &[Vec<RouteFile>]) -> Vec<RouteFile> {
+		self.collections
+			.iter()
+			.zip(scanned)
+			.filter(|(collection, _)| {
+				collection.category == RouteCollectionCategory::Actions
+			})
+			.flat_map(|(_, files)| files.iter().cloned())
+			.collect()
+	}
+
+	fn build_scanned(
+		&self,
+		scanned: &[Vec<RouteFile>],
+	) -> Result<Vec<CodegenFile>> {
 		let mut outputs = Vec::new();
 
 		// per-collection bundle files
-		for (collection, files) in self.collections.iter().zip(&scanned) {
+		for (collection, files) in self.collections.iter().zip(scanned) {
 			outputs.push(emit_collection(collection, files)?);
 		}
 
@@ -62,7 +113,7 @@ impl RouteCodegen {
 			let route_paths = self
 				.collections
 				.iter()
-				.zip(&scanned)
+				.zip(scanned)
 				.filter(|(collection, _)| {
 					collection.category.include_in_route_tree()
 				})
@@ -77,16 +128,7 @@ impl RouteCodegen {
 		// client-action callers across all actions collections
 		if let Some(codegen) = &self.client_actions {
 			let mut codegen = codegen.clone();
-			let files = self
-				.collections
-				.iter()
-				.zip(&scanned)
-				.filter(|(collection, _)| {
-					collection.category == RouteCollectionCategory::Actions
-				})
-				.flat_map(|(_, files)| files.iter().cloned())
-				.collect::<Vec<_>>();
-			for item in emit_client_actions(&files)? {
+			for item in emit_client_actions(&self.action_files(scanned))? {
 				codegen.add_item(item);
 			}
 			outputs.push(codegen);
@@ -95,12 +137,18 @@ impl RouteCodegen {
 		Ok(outputs)
 	}
 
-	/// Runs the full codegen pass, writing every output file to disk.
-	pub async fn export(self) -> Result<()> {
-		for codegen in self.build().await? {
-			codegen.build_and_write()?;
-		}
-		Ok(())
+	fn build_ts_client_scanned(
+		&self,
+		scanned: &[Vec<RouteFile>],
+	) -> Result<Option<String>> {
+		let Some(ts_client) = &self.ts_client else {
+			return Ok(None);
+		};
+		let src = emit_ts_client(
+			&self.action_files(scanned),
+			&ts_client.type_items()?,
+		);
+		Ok(Some(src))
 	}
 }
 
@@ -157,4 +205,22 @@ mod test {
 			.join("\n\n// ───────────────\n\n")
 			.xpect_snapshot();
 	}
+
+	#[beet_core::test]
+	async fn builds_ts_client() {
+		test_codegen()
+			.with_ts_client(TsClientFile::new(site_dir(
+				"codegen/client_actions.ts",
+			)))
+			.build_ts_client()
+			.await
+			.unwrap()
+			.unwrap()
+			.xpect_contains(
+				"export interface AddArgs {\n\ta: number;\n\tb: number;\n}",
+			)
+			.xpect_contains("export const add = {")
+			.xpect_contains("post: async (input: AddArgs): Promise<number> =>")
+			.xpect_contains("send(\"POST\", \"/add\", { body: input })");
+	}
 }
@@ -11,6 +11,7 @@ use beet_net::prelude::*;
 use proc_macro2::Span;
 use std::str::FromStr;
 use syn::Ident;
+use syn::Item;
 use syn::ItemFn;
 use syn::Visibility;
 
@@ -125,7 +126,7 @@ impl RouteCollection {
 				"rs" => {
 					let bytes = store.get(&store_path).await?;
 					let src = String::from_utf8(bytes.to_vec())?;
-					let methods = parse_route_methods(&src)?;
+					let (methods, types) = parse_route_file(&src)?;
 					if methods.is_empty() {
 						continue;
 					}
@@ -135,6 +136,7 @@ impl RouteCollection {
 							mod_ident: mod_ident_from_file(&store_path),
 							mod_path: self.mod_path(&store_path)?,
 							methods,
+							types,
 						},
 					});
 				}
@@ -194,6 +196,9 @@ pub enum RouteFileKind {
 		mod_path: String,
 		/// The HTTP handlers in this file.
 		methods: Vec<RouteMethod>,
+		/// The structs and enums defined in this file, which type the
+		/// TypeScript client's interfaces.
+		types: Vec<Item>,
 	},
 	/// A content file (markdown/html) served via [`BlobScene`].
 	Blob {
@@ -212,6 +217,15 @@ impl RouteFile {
 			RouteFileKind::Blob { .. } => &[],
 		}
 	}
+
+	/// The struct and enum definitions in this file if it is a Rust handler
+	/// file.
+	pub fn types(&self) -> &[Item] {
+		match &self.kind {
+			RouteFileKind::Rust { types, .. } => types,
+			RouteFileKind::Blob { .. } => &[],
+		}
+	}
 }
 
 /// An HTTP handler function extracted from a Rust route file.
@@ -258,11 +272,20 @@ fn mod_ident_from_file(store_path: &SmolPath) -> Ident {
 	Ident::new(&path_to_ident(&joined), Span::call_site())
 }
 
-/// Parses a Rust source file into its public HTTP-method handlers.
-fn parse_route_methods(src: &str) -> Result<Vec<RouteMethod>> {
+/// Parses a Rust source file into its public HTTP-method handlers and the
+/// struct and enum definitions beside them.
+fn parse_route_file(src: &str) -> Result<(Vec<RouteMethod>, Vec<Item>)> {
 	let mut methods = Vec::new();
+	let mut types = Vec::new();
 	for item in syn::parse_file(src)?.items {
-		let syn::Item::Fn(func) = item else { continue };
+		let func = match item {
+			Item::Fn(func) => func,
+			Item::Struct(_) | Item::Enum(_) => {
+				types.push(item);
+				continue;
+			}
+			_ => continue,
+		};
 		if !matches!(func.vis, Visibility::Public(_)) {
 			continue;
 		}
@@ -272,7 +295,7 @@ fn parse_route_methods(src: &str) -> Result<Vec<RouteMethod>> {
 		};
 		methods.push(RouteMethod { method, item: func });
 	}
-	Ok(methods)
+	Ok((methods, types))
 }
 
 /// Converts a path-like string into a valid, deduplicated Rust identifier.
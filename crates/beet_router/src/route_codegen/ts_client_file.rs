@@ -0,0 +1,58 @@
+//! The TypeScript client output of a codegen pass.
+//!
+//! Unlike a [`CodegenFile`] this is not Rust source, so it holds only the
+//! output path and the extra Rust files whose types the client's interfaces
+//! are declared from.
+
+use beet_core::prelude::*;
+use syn::Item;
+
+/// Output for the typed TypeScript client of the actions collections.
+///
+/// Action input and output types defined outside the route files, ie a
+/// `shared.rs` used by both server and client, are only declared when added
+/// with [`Self::with_type_source`], otherwise they are typed `unknown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsClientFile {
+	/// The output `.ts` file location.
+	output: AbsPathBuf,
+	/// Rust files whose structs and enums may type the actions.
+	type_sources: Vec<AbsPathBuf>,
+}
+
+impl TsClientFile {
+	/// Creates a new [`TsClientFile`] writing to `output`.
+	pub fn new(output: AbsPathBuf) -> Self {
+		Self {
+			output,
+			type_sources: Vec::new(),
+		}
+	}
+
+	/// Returns the output path for this client.
+	pub fn output(&self) -> &AbsPathBuf { &self.output }
+
+	/// Adds a Rust file whose structs and enums may type the actions.
+	pub fn with_type_source(mut self, path: AbsPathBuf) -> Self {
+		self.type_sources.push(path);
+		self
+	}
+
+	/// Parses the structs and enums of every type source.
+	pub(crate) fn type_items(&self) -> Result<Vec<Item>> {
+		let mut items = Vec::new();
+		for path in &self.type_sources {
+			let src = fs_ext::read_to_string(path)?;
+			items.extend(syn::parse_file(&src)?.items.into_iter().filter(
+				|item| matches!(item, Item::Struct(_) | Item::Enum(_)),
+			));
+		}
+		Ok(items)
+	}
+
+	/// Writes the built client to the output path if changed.
+	pub fn write(&self, src: &str) -> Result<()> {
+		fs_ext::write_if_diff(&self.output, src)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,567 @@
+//! Response compression negotiated on `Accept-Encoding`.
+//!
+//! [`CompressionMiddleware`] encodes eligible responses with the best coding the
+//! client accepts, per the nearest self-or-ancestor [`CompressionConfig`], and
+//! [`StaticExport::precompress`] writes the matching `.gz`/`.br` siblings for a
+//! static host that serves precompressed files.
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::exports::futures_lite;
+use beet_core::prelude::*;
+use beet_net::header::AcceptedCodings;
+use beet_net::header::ContentCoding;
+use beet_net::prelude::*;
+use bytes::Bytes;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The response compression policy for a route or subtree, read by
+/// [`CompressionMiddleware`].
+///
+/// Resolved from the handling router by nearest self-or-ancestor, like
+/// [`CorsConfig`] for [`CorsHandler`]; without one the middleware uses
+/// [`CompressionConfig::default`]. Already-compressed media types (images,
+/// video, archives, fonts, see [`MediaType::is_compressed`]) and opaque
+/// `application/octet-stream` bodies are always sent as-is.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct CompressionConfig {
+	/// In-memory bodies shorter than this many bytes are sent as-is: below a
+	/// few hundred bytes the coding overhead outweighs the saving. Streaming
+	/// bodies have no known length, so are always eligible.
+	pub min_bytes: u64,
+	/// Offer `gzip`, understood by every client.
+	pub gzip: bool,
+	/// Offer `br`, smaller than gzip for text at a similar speed.
+	pub brotli: bool,
+	/// Offer `zstd`, only encoded in builds with the `zstd` feature.
+	pub zstd: bool,
+}
+
+impl Default for CompressionConfig {
+	fn default() -> Self {
+		Self {
+			min_bytes: 1024,
+			gzip: true,
+			brotli: true,
+			zstd: true,
+		}
+	}
+}
+
+impl CompressionConfig {
+	/// The enabled codings this build can encode, most preferred first.
+	pub fn codings(&self) -> Vec<ContentCoding> {
+		let mut codings = Vec::new();
+		#[cfg(feature = "zstd")]
+		if self.zstd {
+			codings.push(ContentCoding::Zstd);
+		}
+		if self.brotli {
+			codings.push(ContentCoding::Brotli);
+		}
+		if self.gzip {
+			codings.push(ContentCoding::Gzip);
+		}
+		codings
+	}
+
+	/// The coding to answer a request accepting `accepted` with, or `None` to
+	/// send the body as-is.
+	///
+	/// Picks by server preference among the codings the client accepts:
+	/// browsers list every coding at equal quality, so their order says nothing
+	/// about which is smaller. A `*` entry accepts any enabled coding not
+	/// explicitly refused with `q=0`.
+	pub fn negotiate(
+		&self,
+		accepted: &AcceptedCodings,
+	) -> Option<ContentCoding> {
+		self.codings()
+			.into_iter()
+			.find(|coding| accepted.accepts(coding))
+	}
+
+	/// Whether `response` is worth encoding at all, independent of what the
+	/// client accepts: a successful, not-yet-encoded, whole body of a
+	/// compressible media type.
+	fn eligible(&self, response: &Response) -> bool {
+		let status = response.status();
+		if !status.is_success()
+			|| status == StatusCode::NO_CONTENT
+			|| status == StatusCode::PARTIAL_CONTENT
+		{
+			return false;
+		}
+		let headers = &response.parts.headers;
+		if headers.contains::<header::ContentEncoding>() {
+			return false;
+		}
+		let compressible = match headers
+			.get::<header::ContentType>()
+			.and_then(|result| result.ok())
+		{
+			// an event stream is flushed event by event and read by proxies
+			// that buffer encoded streams, so it is always sent as-is
+			Some(MediaType::EventStream) => false,
+			Some(MediaType::Bytes) | Some(MediaType::Other(_)) | None => false,
+			Some(media_type) => !media_type.is_compressed(),
+		};
+		compressible
+			&& match &response.body {
+				Body::Bytes(bytes) => bytes.len() as u64 >= self.min_bytes,
+				Body::Stream(_) => true,
+			}
+	}
+}
+
+/// Middleware encoding responses with the best coding the request's
+/// `Accept-Encoding` allows, per the nearest self-or-ancestor
+/// [`CompressionConfig`].
+///
+/// In-memory bodies are encoded whole, streaming bodies chunk by chunk with a
+/// flush after each, so a live stream still arrives promptly. An eligible
+/// response always gains `Vary: accept-encoding`, encoded or not, so a shared
+/// cache never serves one client's coding to another; the stale
+/// `Content-Length` of an encoded body is removed.
+///
+/// Attach it at the router, outside any middleware that inspects the body.
+#[action]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn CompressionMiddleware(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let caller = cx.caller.clone();
+	let (request, next) = cx.take();
+	let accepted = request
+		.headers
+		.get::<header::AcceptEncoding>()
+		.and_then(|result| result.ok())
+		.unwrap_or_default();
+	let mut response = next.call(request).await?;
+
+	let config = caller
+		.with_state::<AncestorQuery<&CompressionConfig>, CompressionConfig>(
+			|entity, query| query.get(entity).cloned().unwrap_or_default(),
+		)
+		.await?;
+	if !config.eligible(&response) {
+		return Ok(response);
+	}
+	append_vary(&mut response.parts.headers, header::AcceptEncoding::KEY);
+	let Some(coding) = config.negotiate(&accepted) else {
+		return Ok(response);
+	};
+
+	let (mut parts, body) = response.into_parts();
+	let body = match body {
+		Body::Bytes(bytes) => {
+			Body::Bytes(compress_bytes(&coding, &bytes, Quality::Fast)?.into())
+		}
+		body @ Body::Stream(_) => compress_stream(body, &coding)?,
+	};
+	parts.headers.remove::<header::ContentLength>();
+	// the encoded bytes differ from the tagged ones, so a strong etag (eg from
+	// an inner `ConditionalMiddleware`) only survives as a weak one
+	if let Some(Ok(etag)) = parts.headers.get::<header::ETag>() {
+		parts.headers.set::<header::ETag>(etag.into_weak());
+	}
+	parts.headers.set::<header::ContentEncoding>(coding);
+	Response::new(parts, body).xok()
+}
+
+impl CompressionMiddleware {
+	/// Bundle combining the [`CompressionMiddleware`] with its [`CompressionConfig`].
+	pub fn bundle(config: CompressionConfig) -> impl Bundle {
+		(CompressionMiddleware, config)
+	}
+}
+
+/// Add `key` to the response `Vary` header, unless already listed.
+fn append_vary(headers: &mut HeaderMap, key: &str) {
+	let mut vary = headers
+		.get::<header::Vary>()
+		.and_then(|result| result.ok())
+		.unwrap_or_default();
+	if vary
+		.iter()
+		.any(|existing| existing == key || existing == "*")
+	{
+		return;
+	}
+	vary.push(key.to_string());
+	headers.set::<header::Vary>(vary);
+}
+
+/// The effort an encoder spends: per-request encoding favours speed, an
+/// offline precompression pass the smallest output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quality {
+	Fast,
+	Best,
+}
+
+/// A [`Write`] target the encoders share with their owner, so the encoded
+/// output can be drained after every chunk without tearing the encoder down.
+#[derive(Debug, Default, Clone)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+	fn take(&self) -> Vec<u8> { core::mem::take(&mut *self.0.lock().unwrap()) }
+}
+
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().unwrap().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+/// A streaming encoder for one [`ContentCoding`].
+enum Encoder {
+	Gzip(flate2::write::GzEncoder<SharedBuffer>),
+	Brotli(Box<brotli::CompressorWriter<SharedBuffer>>),
+	#[cfg(feature = "zstd")]
+	Zstd(zstd::stream::write::Encoder<'static, SharedBuffer>),
+}
+
+impl Encoder {
+	fn new(
+		coding: &ContentCoding,
+		quality: Quality,
+		out: SharedBuffer,
+	) -> Result<Self> {
+		match coding {
+			ContentCoding::Gzip => {
+				let level = match quality {
+					Quality::Fast => flate2::Compression::default(),
+					Quality::Best => flate2::Compression::best(),
+				};
+				Self::Gzip(flate2::write::GzEncoder::new(out, level))
+			}
+			ContentCoding::Brotli => {
+				let level = match quality {
+					Quality::Fast => 5,
+					Quality::Best => 11,
+				};
+				Self::Brotli(Box::new(brotli::CompressorWriter::new(
+					out, 4096, level, 22,
+				)))
+			}
+			#[cfg(feature = "zstd")]
+			ContentCoding::Zstd => {
+				let level = match quality {
+					Quality::Fast => 3,
+					Quality::Best => 19,
+				};
+				Self::Zstd(zstd::stream::write::Encoder::new(out, level)?)
+			}
+			other => bevybail!("unsupported content coding: {other}"),
+		}
+		.xok()
+	}
+
+	fn writer(&mut self) -> &mut dyn Write {
+		match self {
+			Self::Gzip(encoder) => encoder,
+			Self::Brotli(encoder) => encoder.as_mut(),
+			#[cfg(feature = "zstd")]
+			Self::Zstd(encoder) => encoder,
+		}
+	}
+
+	/// Encode `chunk` and flush, so everything written so far is decodable.
+	fn write_flush(&mut self, chunk: &[u8]) -> Result<()> {
+		let writer = self.writer();
+		writer.write_all(chunk)?;
+		writer.flush()?;
+		Ok(())
+	}
+
+	/// Write the trailer, consuming the encoder.
+	fn finish(self) -> Result<()> {
+		match self {
+			Self::Gzip(encoder) => {
+				encoder.finish()?;
+			}
+			// the brotli writer emits its final block on drop
+			Self::Brotli(encoder) => drop(encoder),
+			#[cfg(feature = "zstd")]
+			Self::Zstd(encoder) => {
+				encoder.finish()?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Encode a whole in-memory body.
+fn compress_bytes(
+	coding: &ContentCoding,
+	bytes: &[u8],
+	quality: Quality,
+) -> Result<Vec<u8>> {
+	let out = SharedBuffer::default();
+	let mut encoder = Encoder::new(coding, quality, out.clone())?;
+	encoder.writer().write_all(bytes)?;
+	encoder.finish()?;
+	out.take().xok()
+}
+
+/// Encode a streaming body chunk by chunk, flushing after each so the client
+/// can decode every chunk as it lands.
+fn compress_stream(body: Body, coding: &ContentCoding) -> Result<Body> {
+	let out = SharedBuffer::default();
+	let encoder = Encoder::new(coding, Quality::Fast, out.clone())?;
+	Body::stream(futures_lite::stream::unfold(
+		(body, Some(encoder), out),
+		|(mut body, encoder, out)| async move {
+			// `None` once the trailer has been emitted or the stream errored
+			let mut encoder = encoder?;
+			let next = match body.next().await {
+				Some(Ok(chunk)) => encoder
+					.write_flush(&chunk)
+					.map(|_| Bytes::from(out.take()))
+					.map(|bytes| (bytes, Some(encoder))),
+				Some(Err(err)) => Err(err),
+				None => encoder
+					.finish()
+					.map(|_| Bytes::from(out.take()))
+					.map(|bytes| (bytes, None)),
+			};
+			match next {
+				Ok((bytes, encoder)) => Some((Ok(bytes), (body, encoder, out))),
+				Err(err) => Some((Err(err), (body, None, out))),
+			}
+		},
+	))
+	.xok()
+}
+
+impl StaticExport {
+	/// The codings [`precompress`](Self::precompress) writes siblings for, the
+	/// ones static hosts (nginx `gzip_static`/`brotli_static`, most CDNs) look
+	/// for.
+	pub const PRECOMPRESSED_CODINGS: [ContentCoding; 2] =
+		[ContentCoding::Gzip, ContentCoding::Brotli];
+
+	/// Writes a maximally compressed `<path>.gz` and `<path>.br` sibling beside
+	/// every compressible file in `paths`, returning the written siblings.
+	///
+	/// Run over the output of [`export`](Self::export) so a static host can serve
+	/// the precompressed variant directly, the offline counterpart of
+	/// [`CompressionMiddleware`]. Files of an already-compressed media type, or
+	/// ones the coding would not shrink, are skipped.
+	pub async fn precompress(
+		out: &BlobStore,
+		paths: &[SmolPath],
+	) -> Result<Vec<SmolPath>> {
+		let mut written = Vec::new();
+		for path in paths {
+			let media_type = path
+				.extension()
+				.map(MediaType::from_extension)
+				.unwrap_or(MediaType::Html);
+			if media_type.is_compressed() || media_type == MediaType::Bytes {
+				continue;
+			}
+			let bytes = out.get(path).await?;
+			for coding in Self::PRECOMPRESSED_CODINGS {
+				let encoded = compress_bytes(&coding, &bytes, Quality::Best)?;
+				if encoded.len() >= bytes.len() {
+					continue;
+				}
+				let sibling = SmolPath::new(format!(
+					"{path}.{}",
+					coding.extension().unwrap_or_default()
+				));
+				out.insert(&sibling, encoded).await?;
+				written.push(sibling);
+			}
+		}
+		Ok(written)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn router_world() -> World { (AsyncPlugin, RouterPlugin).into_world() }
+
+	/// A page well over the default `min_bytes`.
+	fn long_page() -> String { "<p>Hello</p>".repeat(200) }
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn LongHtml(_cx: ActionContext<RequestParts>) -> Response {
+		Response::ok_body(long_page(), MediaType::Html)
+	}
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn LongPng(_cx: ActionContext<RequestParts>) -> Response {
+		Response::ok_body(vec![0u8; 4096], MediaType::Png)
+	}
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn StreamedHtml(_cx: ActionContext<RequestParts>) -> Response {
+		let chunks = (0..4).map(|_| Ok(Bytes::from(long_page())));
+		Response::ok()
+			.with_body(Body::stream(futures_lite::stream::iter(chunks)))
+			.with_content_type(MediaType::Html)
+	}
+
+	fn spawn_router(world: &mut World) -> Entity {
+		world
+			.spawn((CompressionMiddleware, Router::with_defaults(), children![
+				route::exchange("page", LongHtml),
+				route::exchange("image", LongPng),
+				route::exchange("stream", StreamedHtml),
+			]))
+			.flush()
+	}
+
+	fn gunzip(bytes: &[u8]) -> String {
+		let mut text = String::new();
+		std::io::Read::read_to_string(
+			&mut flate2::read::GzDecoder::new(bytes),
+			&mut text,
+		)
+		.unwrap();
+		text
+	}
+
+	#[beet_core::test]
+	fn negotiates_by_server_preference() {
+		fn accepted(raw: &str) -> AcceptedCodings {
+			let mut map = HeaderMap::new();
+			map.set_raw("accept-encoding", raw);
+			map.get::<header::AcceptEncoding>().unwrap().unwrap()
+		}
+		let config = CompressionConfig::default();
+		config
+			.negotiate(&accepted("gzip, br"))
+			.xpect_eq(Some(ContentCoding::Brotli));
+		config
+			.negotiate(&accepted("gzip"))
+			.xpect_eq(Some(ContentCoding::Gzip));
+		config.negotiate(&accepted("identity")).xpect_none();
+		CompressionConfig {
+			brotli: false,
+			zstd: false,
+			..default()
+		}
+		.negotiate(&accepted("*"))
+		.xpect_eq(Some(ContentCoding::Gzip));
+		// an explicit refusal overrides the wildcard
+		CompressionConfig {
+			zstd: false,
+			..default()
+		}
+		.negotiate(&accepted("br;q=0, *"))
+		.xpect_eq(Some(ContentCoding::Gzip));
+	}
+
+	#[beet_core::test]
+	async fn encodes_html() {
+		let mut world = router_world();
+		let root = spawn_router(&mut world);
+		let response = world
+			.entity_mut(root)
+			.exchange(
+				Request::get("page").with_header_raw("accept-encoding", "gzip"),
+			)
+			.await;
+		response
+			.headers
+			.get::<header::ContentEncoding>()
+			.unwrap()
+			.unwrap()
+			.xpect_eq(ContentCoding::Gzip);
+		response
+			.headers
+			.get::<header::Vary>()
+			.unwrap()
+			.unwrap()
+			.xpect_any(|key| key == "accept-encoding");
+		let bytes = response.body.into_bytes().await.unwrap();
+		gunzip(&bytes).xpect_eq(long_page());
+	}
+
+	#[beet_core::test]
+	async fn encodes_stream() {
+		let mut world = router_world();
+		let root = spawn_router(&mut world);
+		let response = world
+			.entity_mut(root)
+			.exchange(
+				Request::get("stream")
+					.with_header_raw("accept-encoding", "gzip"),
+			)
+			.await;
+		let bytes = response.body.into_bytes().await.unwrap();
+		gunzip(&bytes).xpect_eq(long_page().repeat(4));
+	}
+
+	#[beet_core::test]
+	async fn skips_compressed_media() {
+		let mut world = router_world();
+		let root = spawn_router(&mut world);
+		world
+			.entity_mut(root)
+			.exchange(
+				Request::get("image")
+					.with_header_raw("accept-encoding", "gzip, br"),
+			)
+			.await
+			.headers
+			.get::<header::ContentEncoding>()
+			.xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn identity_without_accept_encoding() {
+		let mut world = router_world();
+		let root = spawn_router(&mut world);
+		let response =
+			world.entity_mut(root).exchange(Request::get("page")).await;
+		response
+			.headers
+			.get::<header::ContentEncoding>()
+			.xpect_none();
+		// still varies: another client may have been sent gzip
+		response
+			.headers
+			.get::<header::Vary>()
+			.unwrap()
+			.unwrap()
+			.xpect_any(|key| key == "accept-encoding");
+	}
+
+	#[beet_core::test]
+	async fn precompress_writes_siblings() {
+		let out = BlobStore::temp();
+		let page = SmolPath::new("index.html");
+		out.insert(&page, long_page()).await.unwrap();
+		let written = StaticExport::precompress(&out, &[page]).await.unwrap();
+		written.len().xpect_eq(2);
+		out.get(&SmolPath::new("index.html.gz"))
+			.await
+			.unwrap()
+			.xmap(|bytes| gunzip(&bytes))
+			.xpect_eq(long_page());
+		out.exists(&SmolPath::new("index.html.br"))
+			.await
+			.unwrap()
+			.xpect_true();
+	}
+}
@@ -0,0 +1,243 @@
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use core::hash::BuildHasher;
+
+/// The response headers a `304 Not Modified` repeats from the `200` it stands
+/// in for (RFC 9110 §15.4.5), so a cache can refresh its stored copy.
+const NOT_MODIFIED_HEADERS: [&str; 6] = [
+	"cache-control",
+	"content-location",
+	"etag",
+	"expires",
+	"last-modified",
+	"vary",
+];
+
+/// Middleware answering conditional `GET`/`HEAD` requests with a bodiless
+/// `304 Not Modified` when the client's copy is still current.
+///
+/// Validators come from the response: a handler that knows its content (eg a
+/// blob store's metadata) sets `ETag`/`Last-Modified` itself, and any other
+/// in-memory `200` body is tagged with a hash of its bytes. Streaming bodies
+/// are left untagged, as hashing one would mean buffering it. `If-None-Match`
+/// is checked against the `ETag` with the weak comparison; only without it is
+/// `If-Modified-Since` checked against `Last-Modified`.
+///
+/// The handler still runs, so this saves bandwidth rather than rendering: the
+/// point for a site behind [`CacheHeaders`] with a zero browser TTL, where
+/// every browser revalidation would otherwise re-download the page.
+#[action]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn ConditionalMiddleware(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let (request, next) = cx.take();
+	let method = request.method();
+	// an unreadable validator is ignored, as if absent
+	let if_none_match = request
+		.headers
+		.get::<header::IfNoneMatch>()
+		.and_then(|result| result.ok());
+	let if_modified_since = request
+		.headers
+		.get::<header::IfModifiedSince>()
+		.and_then(|result| result.ok());
+	let mut response = next.call(request).await?;
+	if !matches!(method, HttpMethod::Get | HttpMethod::Head)
+		|| response.status() != StatusCode::OK
+	{
+		return Ok(response);
+	}
+	if !response.parts.headers.contains::<header::ETag>()
+		&& let Body::Bytes(bytes) = &response.body
+	{
+		let etag = body_etag(bytes);
+		response.parts.headers.set::<header::ETag>(etag);
+	}
+	if is_fresh(&response.parts.headers, if_none_match, if_modified_since) {
+		not_modified(&response.parts.headers).xok()
+	} else {
+		response.xok()
+	}
+}
+
+/// A strong entity tag for an in-memory body: its length and a hash of its
+/// bytes, stable across processes of the same build.
+pub fn body_etag(bytes: &[u8]) -> header::EntityTag {
+	let hash = FixedHasher::default().hash_one(bytes);
+	header::EntityTag::strong(format!("{:x}-{hash:016x}", bytes.len()))
+}
+
+/// Whether the client's validators show its copy of the response with
+/// `headers` is current (RFC 9110 §13.2.2).
+fn is_fresh(
+	headers: &HeaderMap,
+	if_none_match: Option<header::EntityTagMatch>,
+	if_modified_since: Option<Timestamp>,
+) -> bool {
+	if let Some(condition) = if_none_match {
+		// a response without an etag matches only `*`
+		return match headers.get::<header::ETag>().and_then(|res| res.ok()) {
+			Some(etag) => condition.matches(&etag),
+			None => condition == header::EntityTagMatch::Any,
+		};
+	}
+	match (
+		if_modified_since,
+		headers
+			.get::<header::LastModified>()
+			.and_then(|result| result.ok()),
+	) {
+		// http dates have second precision, so compare whole seconds
+		(Some(since), Some(modified)) => {
+			modified.unix_epoch_elapsed().as_secs()
+				<= since.unix_epoch_elapsed().as_secs()
+		}
+		_ => false,
+	}
+}
+
+/// The `304 Not Modified` standing in for a response with `headers`.
+fn not_modified(headers: &HeaderMap) -> Response {
+	let mut response = Response::from_status(StatusCode::NOT_MODIFIED);
+	for key in NOT_MODIFIED_HEADERS {
+		for value in headers.get_raw(key).into_iter().flatten() {
+			response.parts.headers.set_raw(key, value.clone());
+		}
+	}
+	response
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_core::prelude::*;
+	use beet_net::prelude::*;
+
+	fn router_world() -> World { (AsyncPlugin, RouterPlugin).into_world() }
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn HelloHtml(_cx: ActionContext<RequestParts>) -> Response {
+		Response::ok_body("<p>Hello</p>", MediaType::Html)
+	}
+
+	/// A fixed `Last-Modified` and no body hash: a handler supplying its own
+	/// validator, as a blob store does from its metadata.
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn Dated(_cx: ActionContext<RequestParts>) -> Response {
+		let mut response = Response::ok_body("# Hello", MediaType::Markdown);
+		response
+			.parts
+			.headers
+			.set::<header::LastModified>(modified());
+		response
+			.parts
+			.headers
+			.set::<header::ETag>(header::EntityTag::weak("v1"));
+		response
+	}
+
+	fn modified() -> Timestamp {
+		Timestamp::from_unix_epoch_elapsed(Duration::from_secs(1_709_164_800))
+	}
+
+	fn conditional_router() -> impl Bundle {
+		(
+			Router::with_defaults(),
+			CacheHeaders::default(),
+			CacheHeadersMiddleware::default(),
+			ConditionalMiddleware::default(),
+		)
+	}
+
+	/// The first response is tagged; sending the tag back earns a bodiless 304
+	/// that keeps the cache headers.
+	#[beet_core::test]
+	async fn revalidates_with_etag() {
+		let mut world = router_world();
+		let root = world
+			.spawn((conditional_router(), children![route::exchange(
+				"page", HelloHtml
+			)]))
+			.flush();
+		let etag = world
+			.entity_mut(root)
+			.exchange(Request::get("page"))
+			.await
+			.headers
+			.get::<header::ETag>()
+			.unwrap()
+			.unwrap();
+		etag.xpect_eq(body_etag(b"<p>Hello</p>"));
+		let request = Request::get("page").with_header::<header::IfNoneMatch>(
+			header::EntityTagMatch::Tags(vec![etag]),
+		);
+		let response = world.entity_mut(root).exchange(request).await;
+		response.status().xpect_eq(StatusCode::NOT_MODIFIED);
+		response.headers.get::<header::CacheControl>().xpect_some();
+		response.text().await.unwrap().xpect_eq("");
+	}
+
+	/// A stale tag gets the full page.
+	#[beet_core::test]
+	async fn stale_etag_gets_body() {
+		let mut world = router_world();
+		let root = world
+			.spawn((conditional_router(), children![route::exchange(
+				"page", HelloHtml
+			)]))
+			.flush();
+		let request = Request::get("page").with_header::<header::IfNoneMatch>(
+			header::EntityTagMatch::Tags(vec![header::EntityTag::strong(
+				"old",
+			)]),
+		);
+		world
+			.entity_mut(root)
+			.exchange(request)
+			.await
+			.unwrap_str()
+			.await
+			.xpect_eq("<p>Hello</p>");
+	}
+
+	/// A handler-set validator wins over the body hash, and `If-Modified-Since`
+	/// is compared against its `Last-Modified`.
+	#[beet_core::test]
+	async fn revalidates_with_last_modified() {
+		let mut world = router_world();
+		let root = world
+			.spawn((conditional_router(), children![route::exchange(
+				"doc", Dated
+			)]))
+			.flush();
+		world
+			.entity_mut(root)
+			.exchange(
+				Request::get("doc")
+					.with_header::<header::IfModifiedSince>(modified()),
+			)
+			.await
+			.status()
+			.xpect_eq(StatusCode::NOT_MODIFIED);
+		world
+			.entity_mut(root)
+			.exchange(
+				Request::get("doc").with_header::<header::IfModifiedSince>(
+					Timestamp::from_unix_epoch_elapsed(Duration::from_secs(
+						1_000,
+					)),
+				),
+			)
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+	}
+}
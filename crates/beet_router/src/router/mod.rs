@@ -6,6 +6,32 @@ mod no_cache;
 pub use no_cache::*;
 mod cache_headers;
 pub use cache_headers::*;
+mod conditional;
+pub use conditional::*;
+// per-client request quotas, std-only: counters sit behind a std `Mutex` or a
+// `TableStore`.
+#[cfg(feature = "std")]
+mod rate_limit;
+#[cfg(feature = "std")]
+pub use rate_limit::*;
+// the once-per-interval sweep of expired rate limit counters and sessions,
+// std-only like the stores it sweeps.
+#[cfg(feature = "std")]
+mod sweep_schedule;
+#[cfg(feature = "std")]
+pub use sweep_schedule::*;
+// OpenAPI documents of the route tree, titled by the std `PackageConfig` and
+// written through `serde_json`.
+#[cfg(all(feature = "std", feature = "json"))]
+mod openapi;
+#[cfg(all(feature = "std", feature = "json"))]
+pub use openapi::*;
+// response compression negotiated on `Accept-Encoding`, std-only: the codecs
+// are std `io::Write` encoders.
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+pub use compression::*;
 mod exchange_overload;
 pub use exchange_overload::*;
 /// The Rust route constructors: `route::new`, `route::exchange`, `route::fallback`.
@@ -0,0 +1,663 @@
+//! OpenAPI 3.1 documents generated from a [`RouteTree`], served by
+//! [`route::openapi`](crate::prelude::route::openapi) and written by the
+//! `beet openapi` command.
+//!
+//! Each [`ActionNode`] becomes one operation:
+//! - the path is templated from its [`PathPattern`], dynamic segments
+//!   becoming required `in: path` parameters, ie `users/:id` → `/users/{id}`.
+//!   OpenAPI has no optional path parameter, so a route with an optional
+//!   segment is listed under each path it matches, ie `users/:id?` under both
+//!   `/users` and `/users/{id}`.
+//! - its [`ParamsPattern`] becomes the `in: query` parameters.
+//! - the method is the node's [`HttpMethod`] filter, else `post` for a route
+//!   taking a typed body and `get` otherwise.
+//! - a typed input becomes the JSON `requestBody`, a typed output the JSON
+//!   `200` response, and a scene route answers `text/html`. Nested types are
+//!   hoisted from each schema's `$defs` into `components.schemas`, a
+//!   different type sharing a name being numbered, ie `Point_2`.
+//!
+//! Routes sharing a path are listed as that path's methods; where two share
+//! the method too the first is kept, as the router would match it first.
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use serde_json::Map as JsonMap;
+use serde_json::Value as Json;
+use serde_json::json;
+
+/// The OpenAPI specification version of the generated documents.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// An OpenAPI 3.1 document describing the routes of a [`RouteTree`].
+///
+/// ```
+/// # use beet_router::prelude::*;
+/// # use beet_core::prelude::*;
+/// let doc = OpenApiDoc::new(&PackageConfig::default(), []);
+/// assert!(doc.to_yaml().contains("\nopenapi: \"3.1.0\"\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Deref)]
+pub struct OpenApiDoc(Json);
+
+impl OpenApiDoc {
+	/// Build a document titled by the `package`, with one operation per node.
+	pub fn new<'a>(
+		package: &PackageConfig,
+		nodes: impl IntoIterator<Item = &'a ActionNode>,
+	) -> Self {
+		let mut paths = JsonMap::new();
+		let mut schemas = JsonMap::new();
+		for node in nodes {
+			for segments in path_variants(&node.path) {
+				let (method, operation) =
+					operation(node, &segments, &mut schemas);
+				let path = openapi_path(&segments);
+				let item = paths
+					.entry(path.clone())
+					.or_insert_with(|| json!({}))
+					.as_object_mut()
+					.expect("path items are objects");
+				if item.contains_key(&method) {
+					warn!("openapi: skipping duplicate route {method} {path}");
+				} else {
+					item.insert(method, operation);
+				}
+			}
+		}
+		let mut doc = json!({
+			"openapi": OPENAPI_VERSION,
+			"info": {
+				"title": package.title.as_str(),
+				"version": package.version.as_str(),
+			},
+			"paths": paths,
+		});
+		if !package.description.is_empty() {
+			doc["info"]["description"] = package.description.as_str().into();
+		}
+		if !schemas.is_empty() {
+			doc["components"] = json!({ "schemas": schemas });
+		}
+		Self(doc)
+	}
+
+	/// Build the document of the [`RouteTree`] at or under `root`, titled by
+	/// the world's [`PackageConfig`].
+	///
+	/// # Errors
+	/// Errors when nothing at or under `root` carries a tree.
+	pub fn build(world: &World, root: Entity) -> Result<Self> {
+		let package = world
+			.get_resource::<PackageConfig>()
+			.cloned()
+			.unwrap_or_default();
+		let tree = RouteTree::of(world, root)?;
+		Self::new(&package, tree.flatten_nodes()).xok()
+	}
+
+	/// The document as a [`serde_json::Value`].
+	pub fn into_json(self) -> Json { self.0 }
+
+	/// The document as pretty-printed JSON.
+	pub fn to_json(&self) -> Result<String> {
+		serde_json::to_string_pretty(&self.0)?.xok()
+	}
+
+	/// The document as block-style YAML. Strings are always double-quoted,
+	/// the JSON escapes being valid YAML, so no scalar is misread as a number
+	/// or boolean.
+	pub fn to_yaml(&self) -> String {
+		let mut out = String::new();
+		write_yaml(&mut out, &self.0, 0);
+		out
+	}
+}
+
+/// The lowercase method and operation object of one route, listed under the
+/// path of `segments`.
+fn operation(
+	node: &ActionNode,
+	segments: &[&PathPatternSegment],
+	schemas: &mut JsonMap<String, Json>,
+) -> (String, Json) {
+	let request_schema = request_schema(&node.meta)
+		.map(|schema| hoist_defs(schema.into_inner().into_json(), schemas));
+	let method = match node.method {
+		Some(method) => method,
+		None if request_schema.is_some() => HttpMethod::Post,
+		None => HttpMethod::Get,
+	}
+	.to_string()
+	.to_ascii_lowercase();
+
+	let mut operation = json!({
+		"operationId": operation_id(&method, segments),
+		"responses": { "200": response(node, schemas) },
+	});
+	if let Some(description) = node.description() {
+		operation["description"] = description.trim().into();
+	}
+	let parameters = segments
+		.iter()
+		.filter(|segment| !segment.is_static())
+		.copied()
+		.map(path_parameter)
+		.chain(node.params.iter().map(query_parameter))
+		.collect::<Vec<_>>();
+	if !parameters.is_empty() {
+		operation["parameters"] = parameters.into();
+	}
+	if let Some(schema) = request_schema {
+		operation["requestBody"] = json!({
+			"required": true,
+			"content": { "application/json": { "schema": schema } },
+		});
+	}
+	(method, operation)
+}
+
+/// The schema of a typed input, or `None` for a handler reading the raw
+/// request, or taking nothing.
+fn request_schema(meta: &ActionMeta) -> Option<Schema> {
+	let input = *meta.input();
+	if input == TypeMeta::of::<()>()
+		|| input == TypeMeta::of::<Request>()
+		|| input == TypeMeta::of::<RequestParts>()
+	{
+		return None;
+	}
+	meta.input_json_schema()
+}
+
+/// The `200` response object: HTML for a scene route, JSON for a typed
+/// output, else an undescribed body.
+fn response(node: &ActionNode, schemas: &mut JsonMap<String, Json>) -> Json {
+	let output = *node.meta.output();
+	if node.is_scene() {
+		json!({
+			"description": "The rendered page.",
+			"content": { "text/html": { "schema": { "type": "string" } } },
+		})
+	} else if output == TypeMeta::of::<()>()
+		|| output == TypeMeta::of::<Response>()
+	{
+		json!({ "description": "OK" })
+	} else if let Some(schema) = node.meta.output_json_schema() {
+		json!({
+			"description": "OK",
+			"content": { "application/json": {
+				"schema": hoist_defs(schema.into_inner().into_json(), schemas),
+			} },
+		})
+	} else {
+		json!({ "description": "OK" })
+	}
+}
+
+/// Moves a schema's `$defs` into the document's `components.schemas`,
+/// repointing its `$ref`s there: in an OpenAPI document a `#/..` reference
+/// resolves against the document root, not the schema.
+///
+/// Defs are named by the type's short name, so one already taken by a
+/// different schema is numbered rather than overwritten, and one taken by an
+/// identical schema is shared.
+fn hoist_defs(mut schema: Json, schemas: &mut JsonMap<String, Json>) -> Json {
+	let defs = match schema
+		.as_object_mut()
+		.and_then(|schema| schema.remove("$defs"))
+	{
+		Some(Json::Object(defs)) => defs,
+		_ => default(),
+	};
+	// renaming a def changes the defs referencing it, so repeat until no
+	// more collide
+	let mut renames = HashMap::<String, String>::default();
+	loop {
+		let mut renamed = false;
+		for (name, def) in &defs {
+			if renames.contains_key(name) {
+				continue;
+			}
+			let def = with_refs_rewritten(def, &renames);
+			if schemas.get(name).is_some_and(|existing| existing != &def) {
+				let unique = (2..)
+					.map(|index| format!("{name}_{index}"))
+					.find(|candidate| {
+						schemas
+							.get(candidate)
+							.is_none_or(|existing| existing == &def)
+					})
+					.expect("unbounded");
+				renames.insert(name.clone(), unique);
+				renamed = true;
+			}
+		}
+		if !renamed {
+			break;
+		}
+	}
+	for (name, def) in &defs {
+		let name = renames.get(name).unwrap_or(name).clone();
+		schemas.insert(name, with_refs_rewritten(def, &renames));
+	}
+	rewrite_refs(&mut schema, &renames);
+	schema
+}
+
+fn with_refs_rewritten(
+	value: &Json,
+	renames: &HashMap<String, String>,
+) -> Json {
+	let mut value = value.clone();
+	rewrite_refs(&mut value, renames);
+	value
+}
+
+fn rewrite_refs(value: &mut Json, renames: &HashMap<String, String>) {
+	match value {
+		Json::Object(map) => {
+			for (key, value) in map.iter_mut() {
+				if key == "$ref"
+					&& let Json::String(reference) = value
+					&& let Some(name) = reference.strip_prefix("#/$defs/")
+				{
+					let name =
+						renames.get(name).map(String::as_str).unwrap_or(name);
+					*reference = format!("#/components/schemas/{name}");
+				} else {
+					rewrite_refs(value, renames);
+				}
+			}
+		}
+		Json::Array(list) => list
+			.iter_mut()
+			.for_each(|value| rewrite_refs(value, renames)),
+		_ => {}
+	}
+}
+
+/// Every path a pattern matches as OpenAPI sees it: one with and one without
+/// each optional segment, ie `users/:id?` → `users` and `users/:id`.
+fn path_variants(path: &PathPattern) -> Vec<Vec<&PathPatternSegment>> {
+	let mut variants = vec![Vec::new()];
+	for segment in path.iter() {
+		if segment.modifier().is_optional() {
+			let with = variants
+				.iter()
+				.cloned()
+				.map(|mut variant| {
+					variant.push(segment);
+					variant
+				})
+				.collect::<Vec<_>>();
+			variants.extend(with);
+		} else {
+			variants
+				.iter_mut()
+				.for_each(|variant| variant.push(segment));
+		}
+	}
+	variants
+}
+
+/// The templated path of some segments, ie `users/:id` → `/users/{id}`.
+fn openapi_path(segments: &[&PathPatternSegment]) -> String {
+	let segments = segments
+		.iter()
+		.map(|segment| {
+			if segment.is_static() {
+				segment.name().to_string()
+			} else {
+				format!("{{{}}}", segment.name())
+			}
+		})
+		.collect::<Vec<_>>()
+		.join("/");
+	format!("/{segments}")
+}
+
+/// A unique id for code generators, ie `get_users_id`.
+fn operation_id(method: &str, segments: &[&PathPatternSegment]) -> String {
+	let mut id = method.to_string();
+	for segment in segments {
+		id.push('_');
+		id.extend(segment.name().chars().map(|char| {
+			if char.is_ascii_alphanumeric() {
+				char
+			} else {
+				'_'
+			}
+		}));
+	}
+	if segments.is_empty() {
+		id.push_str("_root");
+	}
+	id
+}
+
+/// OpenAPI path parameters are always required, an optional segment being
+/// listed under a path without it, so a greedy segment says what it matches
+/// in its description instead.
+fn path_parameter(segment: &PathPatternSegment) -> Json {
+	let mut parameter = json!({
+		"name": segment.name(),
+		"in": "path",
+		"required": true,
+		"schema": { "type": "string" },
+	});
+	let description = match segment.modifier() {
+		PathPatternModifier::OneOrMore | PathPatternModifier::ZeroOrMore => {
+			Some("One or more `/` separated segments.")
+		}
+		_ => None,
+	};
+	if let Some(description) = description {
+		parameter["description"] = description.into();
+	}
+	parameter
+}
+
+fn query_parameter(param: &ParamMeta) -> Json {
+	let schema = match param.value() {
+		ParamValue::Flag => json!({ "type": "boolean" }),
+		ParamValue::Single => scalar_schema(param.type_path()),
+		ParamValue::Multiple => json!({
+			"type": "array",
+			"items": param
+				.type_path()
+				.split_once('<')
+				.and_then(|(_, item)| item.strip_suffix('>'))
+				.map(scalar_schema)
+				.unwrap_or_else(|| json!({ "type": "string" })),
+		}),
+	};
+	let mut parameter = json!({
+		"name": param.name(),
+		"in": "query",
+		"required": param.is_required(),
+		"schema": schema,
+	});
+	if let Some(description) = param.description() {
+		parameter["description"] = description.trim().into();
+	}
+	parameter
+}
+
+/// The schema of a param's concrete type, which is parsed from text so is a
+/// string unless it names a primitive.
+fn scalar_schema(type_path: &str) -> Json {
+	let kind = match type_path.rsplit("::").next().unwrap_or(type_path) {
+		"bool" => "boolean",
+		"u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16"
+		| "i32" | "i64" | "i128" | "isize" => "integer",
+		"f32" | "f64" => "number",
+		_ => "string",
+	};
+	json!({ "type": kind })
+}
+
+fn write_yaml(out: &mut String, value: &Json, indent: usize) {
+	let pad = " ".repeat(indent);
+	match value {
+		Json::Object(map) if !map.is_empty() => {
+			for (key, value) in map {
+				out.push_str(&pad);
+				out.push_str(&yaml_key(key));
+				out.push(':');
+				write_yaml_child(out, value, indent);
+			}
+		}
+		Json::Array(list) if !list.is_empty() => {
+			for value in list {
+				out.push_str(&pad);
+				out.push('-');
+				write_yaml_child(out, value, indent);
+			}
+		}
+		scalar => {
+			out.push_str(&pad);
+			out.push_str(&yaml_scalar(scalar));
+			out.push('\n');
+		}
+	}
+}
+
+/// Writes a mapping value or sequence item after its `key:` or `-`: inline
+/// when a scalar, else as an indented block on the following lines.
+fn write_yaml_child(out: &mut String, value: &Json, indent: usize) {
+	match value {
+		Json::Object(map) if !map.is_empty() => {
+			out.push('\n');
+			write_yaml(out, value, indent + 2);
+		}
+		Json::Array(list) if !list.is_empty() => {
+			out.push('\n');
+			write_yaml(out, value, indent + 2);
+		}
+		scalar => {
+			out.push(' ');
+			out.push_str(&yaml_scalar(scalar));
+			out.push('\n');
+		}
+	}
+}
+
+fn yaml_scalar(value: &Json) -> String {
+	match value {
+		Json::Object(_) => "{}".to_string(),
+		Json::Array(_) => "[]".to_string(),
+		// a json string or number is a valid yaml flow scalar
+		other => other.to_string(),
+	}
+}
+
+/// A key is written plain only when yaml cannot read it as anything but a
+/// string, so `200` and `null` are quoted.
+fn yaml_key(key: &str) -> String {
+	let plain = key
+		.chars()
+		.next()
+		.is_some_and(|char| char.is_ascii_alphabetic() || char == '$')
+		&& key.chars().all(|char| {
+			char.is_ascii_alphanumeric() || matches!(char, '_' | '-' | '$')
+		}) && !matches!(
+		key.to_ascii_lowercase().as_str(),
+		"true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+	);
+	if plain {
+		key.to_string()
+	} else {
+		Json::String(key.to_string()).to_string()
+	}
+}
+
+/// Serves the [`OpenApiDoc`] of the nearest ancestor [`RouteTree`], leaving
+/// out this route itself. Answers YAML when the route path ends in `.yaml` or
+/// `.yml` or the request asks for `?format=yaml`, else JSON.
+#[action(handler_only)]
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub(crate) async fn OpenApiHandler(
+	cx: ActionContext<Request>,
+) -> Result<Response> {
+	let route = cx.caller.id();
+	let path = cx.input.path_string();
+	let yaml = cx.input.get_param("format") == Some("yaml")
+		|| path.ends_with(".yaml")
+		|| path.ends_with(".yml");
+	let doc = cx
+		.caller
+		.with_state::<(
+			AncestorQuery<&RouteTree>,
+			Option<Res<PackageConfig>>,
+		), Result<OpenApiDoc>>(move |entity, (trees, package)| {
+			let tree = trees.get(entity)?;
+			let package = package
+				.map(|package| package.clone())
+				.unwrap_or_default();
+			OpenApiDoc::new(
+				&package,
+				tree.flatten_nodes()
+					.into_iter()
+					.filter(|node| node.entity != route),
+			)
+			.xok()
+		})
+		.await??;
+	if yaml {
+		Response::ok_body(doc.to_yaml(), MediaType::Yaml).xok()
+	} else {
+		Response::ok_body(doc.to_json()?, MediaType::Json).xok()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use beet_action::prelude::*;
+	use beet_core::prelude::*;
+	use beet_net::prelude::*;
+	use serde_json::Value as Json;
+
+	#[derive(Debug, Reflect, serde::Deserialize)]
+	struct Point {
+		x: i32,
+		y: i32,
+	}
+
+	#[derive(Debug, Reflect, serde::Deserialize)]
+	struct Line {
+		from: Point,
+		to: Point,
+	}
+
+	mod label {
+		use beet_core::prelude::*;
+
+		/// Shares its short name with the other [`Point`](super::Point).
+		#[derive(Debug, Reflect, serde::Deserialize)]
+		pub struct Point {
+			pub name: String,
+		}
+
+		#[derive(Debug, Reflect, serde::Deserialize)]
+		pub struct Label {
+			pub at: Point,
+		}
+	}
+
+	#[derive(Reflect)]
+	struct VerboseParams {
+		/// Print the working.
+		verbose: bool,
+	}
+
+	/// The squared length of a line.
+	#[action]
+	#[derive(Component, Reflect)]
+	async fn LineLength(cx: ActionContext<Line>) -> i32 {
+		let Line { from, to } = cx.take();
+		(to.x - from.x).pow(2) + (to.y - from.y).pow(2)
+	}
+
+	#[action]
+	#[derive(Component, Reflect)]
+	async fn LabelName(cx: ActionContext<label::Label>) -> String {
+		cx.take().at.name
+	}
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn GetUser(cx: ActionContext<Request>) -> Response {
+		Response::ok_text(cx.take().path_string())
+	}
+
+	async fn fetch(path: &str) -> String {
+		let mut world = (AsyncPlugin, RouterPlugin).into_world();
+		let router = world
+			.spawn((Router::with_defaults(), children![
+				route::openapi("openapi.json"),
+				route::exchange("length", LineLength),
+				route::exchange("label", LabelName),
+				(route::exchange("posts/:slug?", GetUser), HttpMethod::Get),
+				(route::exchange("posts", GetUser), HttpMethod::Post),
+				(
+					route::exchange("users/:id", GetUser),
+					HttpMethod::Get,
+					ParamsPartial::new::<VerboseParams>(),
+				),
+			]))
+			.flush();
+		world
+			.entity_mut(router)
+			.exchange(Request::get(path))
+			.await
+			.unwrap_str()
+			.await
+	}
+
+	#[beet_core::test]
+	async fn serves_json() {
+		let doc: Json =
+			serde_json::from_str(&fetch("openapi.json").await).unwrap();
+		doc["openapi"].xpect_eq("3.1.0");
+		// the document route leaves itself out
+		doc["paths"].get("/openapi.json").xpect_none();
+
+		let user = &doc["paths"]["/users/{id}"]["get"];
+		user["operationId"].xpect_eq("get_users_id");
+		user["parameters"][0]["in"].xpect_eq("path");
+		user["parameters"][0]["name"].xpect_eq("id");
+		user["parameters"][1]["name"].xpect_eq("verbose");
+		user["parameters"][1]["schema"]["type"].xpect_eq("boolean");
+		user["parameters"][1]["description"].xpect_eq("Print the working.");
+
+		let length = &doc["paths"]["/length"]["post"];
+		length["description"].xpect_eq("The squared length of a line.");
+		length["requestBody"]["content"]["application/json"]["schema"]
+			["properties"]["from"]["$ref"]
+			.xpect_eq("#/components/schemas/Point");
+		doc["components"]["schemas"]["Point"]["properties"]["x"]["type"]
+			.xpect_eq("integer");
+		length["responses"]["200"]["content"]["application/json"]["schema"]
+			["type"]
+			.xpect_eq("integer");
+	}
+
+	#[beet_core::test]
+	async fn numbers_colliding_schemas() {
+		let doc: Json =
+			serde_json::from_str(&fetch("openapi.json").await).unwrap();
+		let label = &doc["paths"]["/label"]["post"]["requestBody"];
+		let label = &label["content"]["application/json"]["schema"];
+		label["properties"]["at"]["$ref"]
+			.xpect_eq("#/components/schemas/Point_2");
+		let schemas = &doc["components"]["schemas"];
+		schemas["Point"]["properties"]["x"]["type"].xpect_eq("integer");
+		schemas["Point_2"]["properties"]["name"]["type"].xpect_eq("string");
+	}
+
+	#[beet_core::test]
+	async fn lists_optional_segments_and_merges_methods() {
+		let doc: Json =
+			serde_json::from_str(&fetch("openapi.json").await).unwrap();
+		let posts = &doc["paths"]["/posts"];
+		posts["get"]["operationId"].xpect_eq("get_posts");
+		posts["get"].get("parameters").xpect_none();
+		posts["post"]["operationId"].xpect_eq("post_posts");
+		let post = &doc["paths"]["/posts/{slug}"]["get"];
+		post["operationId"].xpect_eq("get_posts_slug");
+		post["parameters"][0]["required"].xpect_eq(true);
+	}
+
+	#[beet_core::test]
+	async fn serves_yaml() {
+		fetch("openapi.json?format=yaml")
+			.await
+			.xpect_contains("\nopenapi: \"3.1.0\"\n")
+			.xpect_contains("\n  \"/users/{id}\":\n    get:\n")
+			.xpect_contains("\n        \"200\":\n");
+	}
+}
@@ -0,0 +1,764 @@
+use crate::prelude::*;
+use beet_action::prelude::*;
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use core::hash::BuildHasher;
+use core::hash::Hash;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The request quota for a route or subtree, enforced by
+/// [`RateLimitMiddleware`], which answers `429 Too Many Requests` with a
+/// `Retry-After` once a client exhausts it.
+///
+/// Pure config, resolved from the handled route by nearest self-or-ancestor
+/// like [`CacheHeaders`]: add one to the router for a site-wide quota and to
+/// any route or subtree for a tighter one. Each limit counts separately per
+/// [`RateLimitKey`] client, in the [`RateLimitStore`] nearest the route.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, Default)]
+pub struct RateLimit {
+	/// Names this limit's counters in the store, so limits sharing a store
+	/// (every limit under one router, every task sharing a table) must have
+	/// distinct names.
+	pub name: String,
+	/// How requests are counted against the quota.
+	pub algorithm: RateLimitAlgorithm,
+	/// Who a quota belongs to.
+	pub key: RateLimitKey,
+	/// The requests allowed per `window`, and for a token bucket the burst.
+	pub limit: u32,
+	/// The period `limit` applies to.
+	pub window: Duration,
+}
+
+impl Default for RateLimit {
+	/// A token bucket of 60 requests a minute per client ip.
+	fn default() -> Self {
+		Self {
+			name: "default".into(),
+			algorithm: RateLimitAlgorithm::TokenBucket,
+			key: RateLimitKey::Ip,
+			limit: 60,
+			window: Duration::from_secs(60),
+		}
+	}
+}
+
+/// How a [`RateLimit`] counts requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum RateLimitAlgorithm {
+	/// A bucket of `limit` tokens refilling evenly over the window: allows a
+	/// burst of `limit`, then a steady `limit / window`.
+	#[default]
+	TokenBucket,
+	/// At most `limit` requests in any trailing window, approximated from the
+	/// current and previous fixed windows' counts.
+	SlidingWindow,
+}
+
+/// Who a [`RateLimit`] quota belongs to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum RateLimitKey {
+	/// The client ip: the connection's peer, or behind one of the
+	/// [`TrustedProxies`] the client it forwarded for, see
+	/// [`analytics_ext::trusted_client_ip`].
+	#[default]
+	Ip,
+	/// The signed session cookie of a [`SessionConfig`] (under the `auth`
+	/// feature), else the client ip.
+	Session,
+	/// The `Authorization` header, ie an api token, else the client ip.
+	Authorization,
+	/// One quota for the matched route pattern, ie `users/:id`, shared by
+	/// every client and every path it matches.
+	Route,
+}
+
+impl RateLimit {
+	/// A token bucket of `limit` requests per `window`.
+	pub fn token_bucket(limit: u32, window: Duration) -> Self {
+		Self {
+			limit,
+			window,
+			..default()
+		}
+	}
+
+	/// A sliding window of `limit` requests per `window`.
+	pub fn sliding_window(limit: u32, window: Duration) -> Self {
+		Self {
+			algorithm: RateLimitAlgorithm::SlidingWindow,
+			limit,
+			window,
+			..default()
+		}
+	}
+
+	/// Count the quota per `key` rather than per ip.
+	pub fn keyed_by(mut self, key: RateLimitKey) -> Self {
+		self.key = key;
+		self
+	}
+
+	/// Name this limit's counters, see [`Self::name`].
+	pub fn with_name(mut self, name: impl Into<String>) -> Self {
+		self.name = name.into();
+		self
+	}
+
+	/// Counts a request against `counter` at `now`, returning the updated
+	/// counter and, when the quota is exhausted, how long until a request
+	/// would be allowed.
+	pub fn acquire(
+		&self,
+		counter: Option<RateLimitCounter>,
+		id: Uuid,
+		now: Duration,
+	) -> (RateLimitCounter, Option<Duration>) {
+		let (mut counter, wait) = self.count(counter, id, now);
+		// a counter idle for two windows is back to a full quota
+		counter.expires = Timestamp::from_unix_epoch_elapsed(
+			now + self.window.max(Duration::from_millis(1)) * 2,
+		);
+		(counter, wait)
+	}
+
+	fn count(
+		&self,
+		counter: Option<RateLimitCounter>,
+		id: Uuid,
+		now: Duration,
+	) -> (RateLimitCounter, Option<Duration>) {
+		let limit = self.limit as f64;
+		let window = self.window.max(Duration::from_millis(1));
+		match self.algorithm {
+			RateLimitAlgorithm::TokenBucket => {
+				let rate = limit / window.as_secs_f64();
+				let mut counter = counter.unwrap_or(RateLimitCounter {
+					id,
+					updated: Timestamp::from_unix_epoch_elapsed(now),
+					current: limit,
+					previous: 0.,
+					expires: default(),
+				});
+				let elapsed =
+					now.saturating_sub(counter.updated.unix_epoch_elapsed());
+				counter.current =
+					(counter.current + elapsed.as_secs_f64() * rate).min(limit);
+				counter.updated = Timestamp::from_unix_epoch_elapsed(now);
+				if counter.current >= 1. {
+					counter.current -= 1.;
+					(counter, None)
+				} else if rate == 0. {
+					(counter, Some(window))
+				} else {
+					let wait = (1. - counter.current) / rate;
+					(counter, Some(Duration::from_secs_f64(wait)))
+				}
+			}
+			RateLimitAlgorithm::SlidingWindow => {
+				let window_ms = window.as_millis();
+				let start = Duration::from_millis(
+					(now.as_millis() / window_ms * window_ms) as u64,
+				);
+				let mut counter = counter.unwrap_or(RateLimitCounter {
+					id,
+					updated: Timestamp::from_unix_epoch_elapsed(start),
+					current: 0.,
+					previous: 0.,
+					expires: default(),
+				});
+				let counter_start = counter.updated.unix_epoch_elapsed();
+				if counter_start != start {
+					// the window rolled: the last one is the previous only when
+					// adjacent, else nothing was counted since
+					counter.previous = if counter_start + window == start {
+						counter.current
+					} else {
+						0.
+					};
+					counter.current = 0.;
+					counter.updated = Timestamp::from_unix_epoch_elapsed(start);
+				}
+				let elapsed =
+					(now - start).as_secs_f64() / window.as_secs_f64();
+				// the previous window's share of the trailing window
+				let weight = 1. - elapsed;
+				if counter.previous * weight + counter.current + 1. <= limit {
+					counter.current += 1.;
+					return (counter, None);
+				}
+				let wait = if counter.current + 1. <= limit {
+					// the previous window's share decays enough within this one
+					let target =
+						(limit - counter.current - 1.) / counter.previous;
+					(weight - target) * window.as_secs_f64()
+				} else if limit >= 1. {
+					// this window is spent: wait for it to become the previous one
+					// and decay enough in turn
+					((1. - elapsed) + (1. - (limit - 1.) / counter.current))
+						* window.as_secs_f64()
+				} else {
+					window.as_secs_f64()
+				};
+				(counter, Some(Duration::from_secs_f64(wait.max(0.))))
+			}
+		}
+	}
+
+	/// The store key counting `request` to the `route` pattern against this
+	/// limit, trusting forwarded headers only from `trusted_proxies`.
+	fn counter_key(
+		&self,
+		caller_session: Option<Uuid>,
+		route: Option<&PathPattern>,
+		trusted_proxies: &[IpAddr],
+		request: &Request,
+	) -> String {
+		let ip = || {
+			analytics_ext::trusted_client_ip(request.headers(), trusted_proxies)
+				.map(|ip| format!("ip:{ip}"))
+				.unwrap_or_else(|| "ip:unknown".into())
+		};
+		let client = match self.key {
+			RateLimitKey::Ip => ip(),
+			RateLimitKey::Session => caller_session
+				.map(|id| format!("session:{id}"))
+				.unwrap_or_else(ip),
+			// hashed, so a shared store never holds the credential itself
+			RateLimitKey::Authorization => request
+				.headers()
+				.first_raw(header::Authorization::KEY)
+				.map(|token| format!("token:{:016x}", stable_hash(token)))
+				.unwrap_or_else(ip),
+			RateLimitKey::Route => match route {
+				Some(route) => format!("route:{route}"),
+				None => format!("route:{}", request.path_string()),
+			},
+		};
+		format!("{}:{client}", self.name)
+	}
+}
+
+/// A fixed-seed hash, so every task sharing a [`RateLimitStore::Table`] keys
+/// a client to the same row.
+fn stable_hash(value: impl Hash) -> u64 {
+	FixedHasher::default().hash_one(value)
+}
+
+/// The proxies whose `cf-connecting-ip` / `x-forwarded-for` a
+/// [`RateLimitKey`] falling back to the client ip believes, resolved from the
+/// handled route by nearest self-or-ancestor. Without one every request is
+/// counted against its connection's peer, so a client can't dodge its quota
+/// by sending a forwarded header itself.
+#[derive(Debug, Default, Clone, Component)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+/// One client's count against one [`RateLimit`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitCounter {
+	/// Derived from the counter key.
+	pub id: Uuid,
+	/// For a token bucket the last refill, for a sliding window the start of
+	/// the current window.
+	pub updated: Timestamp,
+	/// For a token bucket the tokens left, for a sliding window the requests
+	/// in the current window.
+	pub current: f64,
+	/// For a sliding window the requests in the previous window.
+	pub previous: f64,
+	/// When the counter is back to a full quota, after which it may be
+	/// dropped, see [`RateLimitStore::sweep`].
+	#[serde(default)]
+	pub expires: Timestamp,
+}
+
+impl TableStoreRow for RateLimitCounter {
+	fn id(&self) -> Uuid { self.id }
+}
+
+/// Where [`RateLimitMiddleware`] keeps its counters, resolved from the
+/// handled route by nearest self-or-ancestor. The middleware brings an
+/// in-memory store; insert a [`Self::table`] store alongside it to share
+/// limits between processes, ie several tasks behind one load balancer.
+///
+/// Either store is swept of its expired counters once per
+/// [`Self::SWEEP_INTERVAL`], see [`SweepSchedule`].
+#[derive(Clone, Component)]
+pub enum RateLimitStore {
+	/// Counters in this process, at most [`Self::MAX_MEMORY_COUNTERS`]: a new
+	/// client past the cap evicts the counters nearest expiry, handing those
+	/// clients a full quota early rather than growing without bound.
+	Memory {
+		/// The counters by key.
+		counters: Arc<Mutex<HashMap<String, RateLimitCounter>>>,
+		/// When the next sweep is due, shared by clones.
+		schedule: SweepSchedule,
+	},
+	/// Counters as rows of a table, shared by every process using it.
+	///
+	/// Each count is a conditional [`Table::update`], so concurrent requests
+	/// from one client on two tasks never both take the last token.
+	Table {
+		/// The counter rows.
+		table: Table<RateLimitCounter>,
+		/// When the next sweep is due, shared by clones.
+		schedule: SweepSchedule,
+	},
+}
+
+impl Default for RateLimitStore {
+	fn default() -> Self {
+		Self::Memory {
+			counters: default(),
+			schedule: default(),
+		}
+	}
+}
+
+impl RateLimitStore {
+	/// The most counters a memory store holds.
+	pub const MAX_MEMORY_COUNTERS: usize = 10_000;
+
+	/// How often a store sweeps its expired counters.
+	pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+	/// How long a client is told to wait when its counter is so contended
+	/// that [`Table::update`] gave up on it.
+	pub const CONTENDED_WAIT: Duration = Duration::from_secs(1);
+
+	/// Share counters as rows of `store`.
+	pub fn table(store: TableStore) -> Self {
+		Self::Table {
+			table: store.table(),
+			schedule: default(),
+		}
+	}
+
+	/// When the next [`Self::sweep`] is due.
+	fn schedule(&self) -> &SweepSchedule {
+		match self {
+			Self::Memory { schedule, .. } | Self::Table { schedule, .. } => {
+				schedule
+			}
+		}
+	}
+
+	/// Removes every expired counter, returning how many were removed. See
+	/// [`SweepSchedule::sweep_table`] for a [`Self::Table`] store.
+	pub async fn sweep(&self) -> Result<usize> {
+		let now = Timestamp::now();
+		match self {
+			Self::Memory { counters, .. } => {
+				let mut counters = counters.lock().unwrap();
+				let len = counters.len();
+				counters.retain(|_, counter| counter.expires > now);
+				(len - counters.len()).xok()
+			}
+			Self::Table { table, .. } => {
+				SweepSchedule::sweep_table(table, |counter| {
+					counter.expires <= now
+				})
+				.await
+			}
+		}
+	}
+
+	/// Evicts the counters nearest expiry until a tenth of
+	/// [`Self::MAX_MEMORY_COUNTERS`] is free, so a flood of new clients pays
+	/// for one eviction per thousand of them rather than one each.
+	fn evict(counters: &mut HashMap<String, RateLimitCounter>) {
+		let keep = Self::MAX_MEMORY_COUNTERS - Self::MAX_MEMORY_COUNTERS / 10;
+		let evict = counters.len().saturating_sub(keep);
+		if evict == 0 {
+			return;
+		}
+		let mut by_expiry = counters
+			.iter()
+			.map(|(key, counter)| (counter.expires, key.clone()))
+			.collect::<Vec<_>>();
+		by_expiry.select_nth_unstable(evict - 1);
+		for (_, key) in &by_expiry[..evict] {
+			counters.remove(key);
+		}
+	}
+
+	/// Counts a request under `key` against `policy`, returning how long the
+	/// client must wait when the quota is exhausted.
+	pub async fn acquire(
+		&self,
+		policy: &RateLimit,
+		key: &str,
+	) -> Result<Option<Duration>> {
+		let now = time_ext::now();
+		let id = Uuid::from_u64_pair(
+			stable_hash(("rate_limit", key)),
+			stable_hash(key),
+		);
+		match self {
+			Self::Memory { counters, .. } => {
+				let mut counters = counters.lock().unwrap();
+				let counter = counters.get(key).cloned();
+				if counter.is_none()
+					&& counters.len() >= Self::MAX_MEMORY_COUNTERS
+				{
+					Self::evict(&mut counters);
+				}
+				let (counter, wait) = policy.acquire(counter, id, now);
+				counters.insert(key.to_string(), counter);
+				Ok(wait)
+			}
+			Self::Table { table, .. } => {
+				if !table.exists(id).await? {
+					let (counter, wait) = policy.acquire(None, id, now);
+					match table.try_push(counter).await {
+						Ok(()) => return Ok(wait),
+						// another task created it first, count against theirs
+						Err(err)
+							if HttpError::status_of(&err)
+								== Some(StatusCode::PRECONDITION_FAILED) => {}
+						Err(err) => return Err(err),
+					}
+				}
+				let mut wait = None;
+				let updated = table
+					.update(id, |counter| {
+						let (next, next_wait) =
+							policy.acquire(Some(counter.clone()), id, now);
+						*counter = next;
+						wait = next_wait;
+					})
+					.await;
+				match updated {
+					Ok(_) => Ok(wait),
+					// so many requests race for this counter that it kept
+					// losing: count this one as limited rather than failing it
+					Err(err)
+						if HttpError::status_of(&err)
+							== Some(StatusCode::PRECONDITION_FAILED) =>
+					{
+						Ok(Some(Self::CONTENDED_WAIT))
+					}
+					Err(err) => Err(err),
+				}
+			}
+		}
+	}
+}
+
+/// Middleware enforcing the handled route's nearest self-or-ancestor
+/// [`RateLimit`], answering `429 Too Many Requests` with a `Retry-After` in
+/// whole seconds once a client exhausts it. Attach once at the router, the
+/// way [`CacheHeadersMiddleware`] pairs with [`CacheHeaders`]; without a
+/// resolved limit it is a pass-through.
+///
+/// Requests from an ssh session's in-world navigator carry its connection
+/// address, so each session counts as its own client.
+#[action]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
+#[require(RateLimitStore)]
+#[component(on_add = on_add_middleware::<Self, Request, Response>)]
+pub async fn RateLimitMiddleware(
+	cx: ActionContext<(Request, Next<Request, Response>)>,
+) -> Result<Response> {
+	let caller = cx.caller.clone();
+	let (request, next) = cx.take();
+	let Some(policy) = caller
+		.with_state::<AncestorQuery<&RateLimit>, Option<RateLimit>>(
+			|entity, query| query.get(entity).ok().cloned(),
+		)
+		.await?
+	else {
+		return next.call(request).await;
+	};
+	#[cfg(feature = "auth")]
+	let session = caller
+		.get_in_ancestors_cloned::<SessionConfig>()
+		.await
+		.ok()
+		.and_then(|config| config.session_id(request.headers()));
+	#[cfg(not(feature = "auth"))]
+	let session = None;
+	let trusted = caller
+		.get_in_ancestors_cloned::<TrustedProxies>()
+		.await
+		.unwrap_or_default();
+	let route = match policy.key {
+		RateLimitKey::Route => {
+			caller
+				.with_state::<AncestorQuery<&PathPattern>, Option<PathPattern>>(
+					|entity, query| query.get(entity).ok().cloned(),
+				)
+				.await?
+		}
+		_ => None,
+	};
+	let store = caller.get_in_ancestors_cloned::<RateLimitStore>().await?;
+	let sweeper = store.clone();
+	store
+		.schedule()
+		.run(
+			caller.world(),
+			RateLimitStore::SWEEP_INTERVAL,
+			move || async move { sweeper.sweep().await },
+		)
+		.await;
+	let key = policy.counter_key(session, route.as_ref(), &trusted.0, &request);
+	match store.acquire(&policy, &key).await? {
+		None => next.call(request).await,
+		Some(wait) => {
+			// round up, so a client retrying on time is let through
+			let secs = wait.as_secs() + (wait.subsec_nanos() > 0) as u64;
+			HttpError::new(
+				StatusCode::TOO_MANY_REQUESTS,
+				format!("rate limit exceeded, retry in {secs}s"),
+			)
+			.into_response()
+			.with_header("retry-after", &secs.max(1).to_string())
+			.xok()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn router_world() -> World { (AsyncPlugin, RouterPlugin).into_world() }
+
+	#[action(handler_only)]
+	#[derive(Default, Clone, Component, Reflect)]
+	#[reflect(Component)]
+	async fn Hello(_cx: ActionContext<RequestParts>) -> Response {
+		Response::ok_text("hello")
+	}
+
+	fn from_ip(path: &str, ip: &str) -> Request {
+		Request::get(path).with_peer_addr(format!("{ip}:1234").parse().unwrap())
+	}
+
+	#[beet_core::test]
+	fn token_bucket_refills() {
+		let policy = RateLimit::token_bucket(2, Duration::from_secs(4));
+		let id = Uuid::nil();
+		let now = Duration::from_secs(1000);
+		let (counter, wait) = policy.acquire(None, id, now);
+		wait.xpect_none();
+		let (counter, wait) = policy.acquire(Some(counter), id, now);
+		wait.xpect_none();
+		let (counter, wait) = policy.acquire(Some(counter), id, now);
+		// one token refills every 2s
+		wait.xpect_eq(Some(Duration::from_secs(2)));
+		policy
+			.acquire(Some(counter), id, now + Duration::from_secs(2))
+			.1
+			.xpect_none();
+	}
+
+	#[beet_core::test]
+	fn sliding_window_weighs_previous() {
+		let policy = RateLimit::sliding_window(4, Duration::from_secs(10));
+		let id = Uuid::nil();
+		let mut counter = None;
+		for _ in 0..4 {
+			let (next, wait) =
+				policy.acquire(counter, id, Duration::from_secs(1000));
+			wait.xpect_none();
+			counter = Some(next);
+		}
+		let (next, wait) =
+			policy.acquire(counter, id, Duration::from_secs(1001));
+		wait.xpect_some();
+		// halfway through the next window the previous four weigh two
+		let (next, wait) =
+			policy.acquire(Some(next), id, Duration::from_secs(1015));
+		wait.xpect_none();
+		policy
+			.acquire(Some(next), id, Duration::from_secs(1015))
+			.1
+			.xpect_none();
+	}
+
+	#[beet_core::test]
+	async fn answers_too_many_requests() {
+		let mut world = router_world();
+		let root = world
+			.spawn((
+				RateLimitMiddleware,
+				RateLimit::token_bucket(2, Duration::from_secs(64)),
+				Router::with_defaults(),
+				children![route::exchange("hello", Hello)],
+			))
+			.flush();
+		for _ in 0..2 {
+			world
+				.entity_mut(root)
+				.exchange(from_ip("hello", "10.0.0.1"))
+				.await
+				.status()
+				.xpect_eq(StatusCode::OK);
+		}
+		let response = world
+			.entity_mut(root)
+			.exchange(from_ip("hello", "10.0.0.1"))
+			.await;
+		response.status().xpect_eq(StatusCode::TOO_MANY_REQUESTS);
+		response
+			.parts
+			.headers
+			.first_raw("retry-after")
+			.xpect_eq(Some("32"));
+		// another client has its own quota
+		world
+			.entity_mut(root)
+			.exchange(from_ip("hello", "10.0.0.2"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+	}
+
+	#[beet_core::test]
+	async fn trusts_forwarded_only_from_proxies() {
+		let mut world = router_world();
+		let policy = RateLimit::token_bucket(1, Duration::from_secs(64));
+		let forwarded = |peer: &str, client: &str| {
+			from_ip("hello", peer).with_header_raw("x-forwarded-for", client)
+		};
+		let direct = world
+			.spawn((
+				RateLimitMiddleware,
+				policy.clone(),
+				Router::with_defaults(),
+				children![route::exchange("hello", Hello)],
+			))
+			.flush();
+		world
+			.entity_mut(direct)
+			.exchange(forwarded("10.0.0.1", "10.0.0.2"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+		// a spoofed header doesn't buy a fresh quota
+		world
+			.entity_mut(direct)
+			.exchange(forwarded("10.0.0.1", "10.0.0.3"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::TOO_MANY_REQUESTS);
+
+		let proxied = world
+			.spawn((
+				RateLimitMiddleware,
+				TrustedProxies(vec!["10.0.0.1".parse().unwrap()]),
+				policy,
+				Router::with_defaults(),
+				children![route::exchange("hello", Hello)],
+			))
+			.flush();
+		for client in ["10.0.0.2", "10.0.0.3"] {
+			world
+				.entity_mut(proxied)
+				.exchange(forwarded("10.0.0.1", client))
+				.await
+				.status()
+				.xpect_eq(StatusCode::OK);
+		}
+	}
+
+	#[beet_core::test]
+	async fn route_key_counts_the_pattern() {
+		let mut world = router_world();
+		let root = world
+			.spawn((
+				RateLimitMiddleware,
+				RateLimit::token_bucket(1, Duration::from_secs(64))
+					.keyed_by(RateLimitKey::Route),
+				Router::with_defaults(),
+				children![route::exchange("users/:id", Hello)],
+			))
+			.flush();
+		world
+			.entity_mut(root)
+			.exchange(from_ip("users/1", "10.0.0.1"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+		// another path of the same route, from another client
+		world
+			.entity_mut(root)
+			.exchange(from_ip("users/2", "10.0.0.2"))
+			.await
+			.status()
+			.xpect_eq(StatusCode::TOO_MANY_REQUESTS);
+	}
+
+	#[beet_core::test]
+	async fn table_store_sweeps_expired() {
+		let store = RateLimitStore::table(TableStore::temp());
+		let policy = RateLimit::token_bucket(1, Duration::from_millis(1));
+		store.acquire(&policy, "a").await.unwrap().xpect_none();
+		time_ext::sleep(Duration::from_millis(5)).await;
+		store.sweep().await.unwrap().xpect_eq(1);
+	}
+
+	#[beet_core::test]
+	async fn memory_store_is_capped() {
+		let store = RateLimitStore::default();
+		let policy = RateLimit::token_bucket(1, Duration::from_secs(60));
+		for client in 0..=RateLimitStore::MAX_MEMORY_COUNTERS {
+			store.acquire(&policy, &client.to_string()).await.unwrap();
+		}
+		let RateLimitStore::Memory { counters, .. } = &store else {
+			unreachable!()
+		};
+		let len = counters.lock().unwrap().len();
+		(len <= RateLimitStore::MAX_MEMORY_COUNTERS).xpect_true();
+		// the newest client is kept
+		counters
+			.lock()
+			.unwrap()
+			.contains_key(&RateLimitStore::MAX_MEMORY_COUNTERS.to_string())
+			.xpect_true();
+	}
+
+	#[beet_core::test]
+	async fn table_store_shares_counters() {
+		let table = TableStore::temp();
+		let policy = RateLimit::token_bucket(1, Duration::from_secs(60))
+			.keyed_by(RateLimitKey::Authorization);
+		let mut world = router_world();
+		// two routers sharing a table stand in for two tasks
+		let routers = [0, 1].map(|_| {
+			world
+				.spawn((
+					RateLimitMiddleware,
+					RateLimitStore::table(table.clone()),
+					policy.clone(),
+					Router::with_defaults(),
+					children![route::exchange("hello", Hello)],
+				))
+				.flush()
+		});
+		let request = || {
+			Request::get("hello").with_header_raw("authorization", "Bearer abc")
+		};
+		world
+			.entity_mut(routers[0])
+			.exchange(request())
+			.await
+			.status()
+			.xpect_eq(StatusCode::OK);
+		world
+			.entity_mut(routers[1])
+			.exchange(request())
+			.await
+			.status()
+			.xpect_eq(StatusCode::TOO_MANY_REQUESTS);
+	}
+}
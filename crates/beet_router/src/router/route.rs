@@ -9,6 +9,10 @@
 //!   [`ExchangeOverload`] adapting it to `Request -> Response` dispatch.
 //! - [`exchange_overload`]: that adapter alone, for a handler that mounts its
 //!   own path (the `#[action(route)]` macro's require site).
+//! - [`upload`]: a `POST` route writing multipart file uploads into a store.
+//! - [`login`], [`magic_link`], [`logout`]: the session routes, under the
+//!   `auth` feature.
+//! - [`openapi`]: the router's OpenAPI document, under the `json` feature.
 //! - [`fallback`]: the not-found sibling a router keeps last.
 
 use crate::prelude::*;
@@ -66,6 +70,56 @@ where
 	))
 }
 
+/// Creates a `POST` route writing the files of a `multipart/form-data` body
+/// (a browser `<form enctype="multipart/form-data">`) into the nearest
+/// self-or-ancestor [`BlobStore`], answering `201 Created` with the stored
+/// paths. Pair with a [`DirPath`] to scope the store to an upload directory.
+pub fn upload(path: &str) -> impl Bundle {
+	(exchange(path, UploadHandler), HttpMethod::Post)
+}
+
+/// Creates a `POST` route replicating a [`BlobSync`] client against the
+/// nearest self-or-ancestor [`BlobStore`]: it applies the pushed changes as
+/// conditional writes and answers with the conflicts and the server changes
+/// the client has not seen. Pair with a [`DirPath`] to sync a subdirectory.
+#[cfg(all(feature = "std", feature = "json"))]
+pub fn blob_sync(path: &str) -> impl Bundle {
+	(exchange(path, BlobSyncHandler), HttpMethod::Post)
+}
+
+/// Creates a `POST` route logging in with an `email` / `password` form
+/// against the nearest [`UserStore`], answering a `303` to the form's local
+/// `next` path (else `/`) with a session cookie, or `401`. Requires a
+/// [`SessionConfig`] and [`SessionStore`] alongside the [`UserStore`].
+#[cfg(feature = "auth")]
+pub fn login(path: &str) -> impl Bundle {
+	(exchange(path, LoginHandler), HttpMethod::Post)
+}
+
+/// Creates a passwordless login route: `POST` an `email` form to trigger a
+/// [`MagicLinkRequested`] for the app to deliver. The single-use link it
+/// carries opens a confirm page, whose form `POST`s the token back to log in.
+/// Answers the `email` `POST` with `202` whether or not the email is known.
+#[cfg(feature = "auth")]
+pub fn magic_link(path: &str) -> impl Bundle {
+	exchange(path, MagicLinkHandler)
+}
+
+/// Creates a `POST` route ending the request's session and clearing its
+/// cookie, answering a `303` to `/`.
+#[cfg(feature = "auth")]
+pub fn logout(path: &str) -> impl Bundle {
+	(exchange(path, LogoutHandler), HttpMethod::Post)
+}
+
+/// Creates a `GET` route serving an OpenAPI 3.1 document of every other route
+/// in the router, as YAML when the path ends in `.yaml` or the request asks
+/// for `?format=yaml`, else JSON. See [`OpenApiDoc`].
+#[cfg(all(feature = "std", feature = "json"))]
+pub fn openapi(path: &str) -> impl Bundle {
+	(exchange(path, OpenApiHandler), HttpMethod::Get)
+}
+
 /// Exchange control-flow that tries each child until one passes.
 /// Returns the first [`Pass`] response, or a 404 not-found response
 /// if no child matches. Errors are converted to a response.
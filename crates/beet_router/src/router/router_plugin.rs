@@ -40,6 +40,7 @@ impl Plugin for RouterPlugin {
 			.register_type::<NoCacheHeaders>()
 			.register_type::<CacheHeaders>()
 			.register_type::<CacheHeadersMiddleware>()
+			.register_type::<ConditionalMiddleware>()
 			.register_type::<CorsHandler>()
 			.register_type::<CorsConfig>()
 			.register_type::<HtmlStoreAction>()
@@ -211,6 +212,23 @@ impl Plugin for RouterPlugin {
 			// an `<AnalyticsConfig/>` is spawned, so nothing records until a site
 			// opts in with that on-switch.
 			app.register_type::<AnalyticsMiddleware>();
+			// per-client quotas, declarable in a router spread like the cache
+			// headers pair (`<Router {(RateLimitMiddleware, RateLimit{..})}>`).
+			app.register_type::<RateLimitMiddleware>()
+				.register_type::<RateLimit>();
+			// response compression, declarable in a router spread like
+			// `CacheHeadersMiddleware` (`<Router {(CompressionMiddleware, ..)}>`).
+			#[cfg(feature = "compression")]
+			app.register_type::<CompressionMiddleware>()
+				.register_type::<CompressionConfig>();
+			// sessions and their gates: `SessionMiddleware` in a router spread,
+			// `Authenticated` / `RequireRole` on the subtree they guard. The
+			// config and stores hold keys and tables, so are spawned from rust.
+			#[cfg(feature = "auth")]
+			app.register_type::<SessionMiddleware>()
+				.register_type::<Authenticated>()
+				.register_type::<RequireRole>()
+				.register_type::<RequireRoleMiddleware>();
 			#[cfg(feature = "json")]
 			app.add_plugins(analytics_plugin);
 		}
@@ -0,0 +1,96 @@
+use beet_core::prelude::*;
+use beet_net::prelude::*;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Runs a store's sweep of its expired entries at most once per interval,
+/// however many clones of the store or concurrent requests share it, as
+/// [`SessionMiddleware`](crate::prelude::SessionMiddleware) and
+/// [`RateLimitMiddleware`](crate::prelude::RateLimitMiddleware) do.
+#[derive(Debug, Default, Clone)]
+pub struct SweepSchedule {
+	/// The unix second the next sweep is due, shared by clones.
+	next_sweep: Arc<AtomicU64>,
+}
+
+impl SweepSchedule {
+	/// Claims the next sweep if `interval` has passed since the last, so of
+	/// many concurrent callers exactly one sweeps.
+	pub fn claim(&self, interval: Duration) -> bool {
+		let now = time_ext::now().as_secs();
+		let due = self.next_sweep.load(Ordering::Relaxed);
+		now >= due
+			&& self
+				.next_sweep
+				.compare_exchange(
+					due,
+					now + interval.as_secs(),
+					Ordering::Relaxed,
+					Ordering::Relaxed,
+				)
+				.is_ok()
+	}
+
+	/// Starts `sweep` as a task of `world` if this call [claims](Self::claim)
+	/// it, detached so a slow table never delays the calling request.
+	pub async fn run<Fut>(
+		&self,
+		world: &AsyncWorld,
+		interval: Duration,
+		sweep: impl 'static + Send + FnOnce() -> Fut,
+	) where
+		Fut: 'static + Send + Future<Output = Result<usize>>,
+	{
+		if self.claim(interval) {
+			world
+				.run_async(move |_| async move { sweep().await.map(|_| ()) })
+				.await;
+		}
+	}
+
+	/// Removes every row of `table` that `is_expired`, returning how many
+	/// were removed.
+	///
+	/// # Caution
+	/// Reads every row of the table.
+	pub async fn sweep_table<T: TableStoreRow>(
+		table: &Table<T>,
+		is_expired: impl Fn(&T) -> bool,
+	) -> Result<usize> {
+		let expired = table
+			.query_all(TableQuery::new().with_lossy())
+			.await?
+			.into_iter()
+			.filter(|row| is_expired(row))
+			.collect::<Vec<_>>();
+		for row in &expired {
+			table.remove(row.id()).await?;
+		}
+		expired.len().xok()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[beet_core::test]
+	async fn sweeps_once_per_interval() {
+		let schedule = SweepSchedule::default();
+		let interval = Duration::from_secs(60);
+		schedule.claim(interval).xpect_true();
+		// clones share the claim
+		schedule.clone().claim(interval).xpect_false();
+
+		let table = Table::<TableItem<u32>>::temp();
+		for data in 0..3 {
+			table.push(TableItem::new(data)).await.unwrap();
+		}
+		SweepSchedule::sweep_table(&table, |item| item.data > 0)
+			.await
+			.unwrap()
+			.xpect_eq(2);
+		table.list().await.unwrap().len().xpect_eq(1);
+	}
+}
@@ -68,7 +68,7 @@ pub(crate) async fn ServeBlobsHandler(
 		.get_params(STORE_PATH_PARAM)
 		.map(|segments| SmolPath::from_segments(segments))
 		.unwrap_or_else(|| SmolPath::from(cx.input.path()));
-	serve_blob(&store, &path)
+	serve_blob(&store, &path, &cx.input.headers)
 		.await
 		.map_err(|err| unhydrated_hint(&cx.input, err))
 }
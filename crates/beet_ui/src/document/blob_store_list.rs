@@ -25,8 +25,12 @@ use beet_net::prelude::*;
 pub struct BlobStoreList;
 
 impl BlobStoreList {
-	/// One reactive row per object in the entity's [`BlobStore`], rebuilt as it
-	/// changes.
+	/// The most rows listed, the first page of the store in key order, so a
+	/// bucket of a million objects renders a page rather than listing them all.
+	pub const MAX_ROWS: usize = 1000;
+
+	/// One reactive row per object in the entity's [`BlobStore`], up to
+	/// [`Self::MAX_ROWS`], rebuilt as it changes.
 	pub fn new(
 		build_item: impl 'static + Send + Sync + Fn(usize, &Value) -> OnSpawn,
 	) -> impl Bundle {
@@ -52,7 +56,11 @@ pub(super) fn refresh_blob_store_list(
 		let store = store.clone();
 		commands.entity(entity).run_local(async move |entity| {
 			// graceful empty, like ListBlobs
-			let paths = store.list().await.unwrap_or_default();
+			let paths = store
+				.list_prefix("", None, None, Some(BlobStoreList::MAX_ROWS))
+				.await
+				.map(|listing| listing.paths)
+				.unwrap_or_default();
 			// the store entity is the self-bound field: write its local Value
 			entity
 				.with_state::<FieldQuery, _>(move |subject, mut fields| {
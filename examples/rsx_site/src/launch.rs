@@ -13,8 +13,8 @@ use beet::prelude::*;
 pub fn run_codegen() -> Result { async_ext::block_on(route_codegen().export()) }
 
 /// The codegen pass: the typed `pages` collection, the markdown `content`
-/// collection, the `actions` server-action collection, plus the typed route tree
-/// and the client-action callers.
+/// collection, the `actions` server-action collection, plus the typed route tree,
+/// the client-action callers and their TypeScript twin, typed from `shared.rs`.
 fn route_codegen() -> RouteCodegen {
 	RouteCodegen::new()
 		.add_collection(RouteCollection::new(
@@ -32,6 +32,10 @@ fn route_codegen() -> RouteCodegen {
 		)
 		.with_route_tree(codegen("route_tree.rs"))
 		.with_client_actions(codegen("client_actions.rs"))
+		.with_ts_client(
+			TsClientFile::new(site_rel("src/codegen/client_actions.ts"))
+				.with_type_source(site_rel("src/shared.rs")),
+		)
 }
 
 /// An absolute path to a file relative to the `rsx_site` crate root.